@@ -0,0 +1,62 @@
+// Column alias map: reads inputs whose producer renamed columns
+//
+// The map file holds one `canonical = "renamed"` line per column, e.g.
+// `mean_qscore = "avg_qscore"`; blank lines, `#` comments and `[section]`
+// headers are skipped. A canonical name may be listed several times to
+// accept any of its renames.
+use std::error::Error;
+use std::fs;
+
+/// Renamed columns, as `(canonical name, name in the input)` pairs
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AliasMap {
+    aliases: Vec<(String, String)>,
+}
+
+impl AliasMap {
+    /// Parses a map file's contents
+    pub fn parse(text: &str) -> Result<Self, Box<dyn Error>> {
+        let mut aliases = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() || line.starts_with('[') {
+                continue;
+            }
+            let (canonical, renamed) = line
+                .split_once('=')
+                .map(|(c, r)| (c.trim(), r.trim().trim_matches('"').trim()))
+                .filter(|(c, r)| !c.is_empty() && !r.is_empty())
+                .ok_or_else(|| {
+                    format!(
+                        "Invalid alias on line {}: '{}' (expected column = \"renamed\")",
+                        number + 1,
+                        line
+                    )
+                })?;
+            aliases.push((canonical.to_string(), renamed.to_string()));
+        }
+        Ok(AliasMap { aliases })
+    }
+
+    /// Reads a map file
+    pub fn load(path: &str) -> Result<Self, Box<dyn Error>> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("Cannot read alias map {}: {}", path, e))?;
+        Self::parse(&text)
+    }
+
+    /// `headers` with renamed columns given their canonical names; a column
+    /// the input already has under its canonical name is left alone
+    pub fn apply(&self, headers: &csv::StringRecord) -> csv::StringRecord {
+        let present = |name: &str| headers.iter().any(|h| h.trim() == name);
+        headers
+            .iter()
+            .map(|header| {
+                self.aliases
+                    .iter()
+                    .find(|(canonical, renamed)| renamed == header.trim() && !present(canonical))
+                    .map_or(header, |(canonical, _)| canonical.as_str())
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,159 @@
+// Per-metric anomaly detectors for marking unusual values
+use std::error::Error;
+
+/// Parameters used when a detector is named without them
+const DEFAULT_Z: f64 = 3.0;
+const DEFAULT_IQR: f64 = 1.5;
+const DEFAULT_LAMBDA: f64 = 0.2;
+const DEFAULT_WIDTH: f64 = 3.0;
+
+/// Rule deciding which values of a metric are anomalous
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Detector {
+    ZScore { k: f64 }, // Further than k standard deviations from the mean
+    Iqr { k: f64 },    // Beyond k interquartile ranges outside the quartiles
+    Ewma { lambda: f64, width: f64 }, // EWMA outside its control limits
+    Threshold { low: f64, high: f64 }, // Outside fixed absolute bounds
+}
+
+impl Detector {
+    /// Parses `zscore[:K]`, `iqr[:K]`, `ewma[:LAMBDA[:L]]` or `threshold:LOW:HIGH`.
+    ///
+    /// Either threshold bound may be left empty, e.g. `threshold::2e9`.
+    pub fn parse(spec: &str) -> Result<Self, Box<dyn Error>> {
+        let mut parts = spec.split(':');
+        let kind = parts.next().unwrap_or_default().to_ascii_lowercase();
+        let params: Vec<&str> = parts.collect();
+        let number = |i: usize, default: f64| -> Result<f64, Box<dyn Error>> {
+            match params.get(i).map(|p| p.trim()) {
+                None | Some("") => Ok(default),
+                Some(p) => p
+                    .parse()
+                    .map_err(|_| format!("Detector '{}': '{}' is not a number", spec, p).into()),
+            }
+        };
+
+        let detector = match kind.as_str() {
+            "zscore" | "z" => Detector::ZScore {
+                k: number(0, DEFAULT_Z)?,
+            },
+            "iqr" => Detector::Iqr {
+                k: number(0, DEFAULT_IQR)?,
+            },
+            "ewma" => Detector::Ewma {
+                lambda: number(0, DEFAULT_LAMBDA)?,
+                width: number(1, DEFAULT_WIDTH)?,
+            },
+            "threshold" => Detector::Threshold {
+                low: number(0, f64::NEG_INFINITY)?,
+                high: number(1, f64::INFINITY)?,
+            },
+            _ => return Err(format!("Unknown anomaly detector '{}'", kind).into()),
+        };
+        if let Detector::Ewma { lambda, .. } = detector
+            && !(lambda > 0.0 && lambda <= 1.0)
+        {
+            return Err(format!("Detector '{}': lambda must be in (0, 1]", spec).into());
+        }
+        Ok(detector)
+    }
+
+    /// Short description for reports
+    pub fn describe(&self) -> String {
+        match self {
+            Detector::ZScore { k } => format!("z-score > {}", k),
+            Detector::Iqr { k } => format!("{} × IQR", k),
+            Detector::Ewma { lambda, width } => format!("EWMA λ={} L={}", lambda, width),
+            Detector::Threshold { low, high } => format!("outside {} to {}", low, high),
+        }
+    }
+
+    /// Whether each value is anomalous
+    pub fn flag(&self, values: &[f64]) -> Vec<bool> {
+        if values.is_empty() {
+            return Vec::new();
+        }
+        match *self {
+            Detector::ZScore { k } => {
+                let (mean, sd) = mean_sd(values);
+                values
+                    .iter()
+                    .map(|v| sd > 0.0 && ((v - mean) / sd).abs() > k)
+                    .collect()
+            }
+            Detector::Iqr { k } => {
+                let mut sorted = values.to_vec();
+                sorted.sort_by(f64::total_cmp);
+                let at = |p: f64| sorted[((sorted.len() - 1) as f64 * p) as usize];
+                let (q1, q3) = (at(0.25), at(0.75));
+                let fence = k * (q3 - q1);
+                values
+                    .iter()
+                    .map(|&v| v < q1 - fence || v > q3 + fence)
+                    .collect()
+            }
+            Detector::Ewma { lambda, width } => {
+                let chart = ewma(values, lambda, width);
+                chart
+                    .iter()
+                    .map(|p| p.value < p.lower || p.value > p.upper)
+                    .collect()
+            }
+            Detector::Threshold { low, high } => {
+                values.iter().map(|&v| v < low || v > high).collect()
+            }
+        }
+    }
+}
+
+/// One point of an EWMA control chart
+#[derive(Debug, Clone, Copy)]
+pub struct ControlPoint {
+    pub value: f64, // Exponentially weighted moving average
+    pub lower: f64, // Lower control limit
+    pub upper: f64, // Upper control limit
+}
+
+/// EWMA control chart around the overall mean.
+///
+/// The limits are `mean ± width · σ · sqrt(λ / (2 − λ) · (1 − (1 − λ)^(2t)))`,
+/// which widen over the first points towards their steady-state value.
+pub fn ewma(values: &[f64], lambda: f64, width: f64) -> Vec<ControlPoint> {
+    let (mean, sd) = mean_sd(values);
+    let mut z = mean;
+    let mut decay = 1.0;
+    values
+        .iter()
+        .map(|&x| {
+            z = lambda * x + (1.0 - lambda) * z;
+            decay *= (1.0 - lambda) * (1.0 - lambda);
+            let half = width * sd * (lambda / (2.0 - lambda) * (1.0 - decay)).sqrt();
+            ControlPoint {
+                value: z,
+                lower: mean - half,
+                upper: mean + half,
+            }
+        })
+        .collect()
+}
+
+/// Shewhart individuals chart: each value against fixed `mean ± width · σ` limits
+pub fn shewhart(values: &[f64], width: f64) -> Vec<ControlPoint> {
+    let (mean, sd) = mean_sd(values);
+    values
+        .iter()
+        .map(|&x| ControlPoint {
+            value: x,
+            lower: mean - width * sd,
+            upper: mean + width * sd,
+        })
+        .collect()
+}
+
+/// Mean and population standard deviation
+fn mean_sd(values: &[f64]) -> (f64, f64) {
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / n;
+    let var = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n;
+    (mean, var.sqrt())
+}
@@ -0,0 +1,50 @@
+// Broken y axes for series whose rare spikes dwarf the typical range
+use crate::quantile::{self, Method};
+
+/// Tukey fence multiplier marking values as spikes
+const FENCE: f64 = 3.0;
+/// Largest share of the full span the typical range may take for a break
+const MAX_TYPICAL_SHARE: f64 = 0.2;
+/// Share of the chart height given to the spike range above the break
+pub const UPPER_SHARE: f64 = 0.35;
+
+/// The two y ranges of a broken axis
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AxisBreak {
+    pub lower: (f64, f64), // Typical range, drawn below the break
+    pub upper: (f64, f64), // Spike range, drawn above the break
+}
+
+/// Finds a break when values above the far Tukey fence stretch the axis so
+/// much that the typical range would be squeezed into a thin band
+pub fn find(values: &[f64]) -> Option<AxisBreak> {
+    let finite: Vec<f64> = values.iter().copied().filter(|v| v.is_finite()).collect();
+    if finite.len() < 4 {
+        return None;
+    }
+    let q = quantile::quantiles(&finite, &[0.25, 0.75], Method::Exact);
+    let fence = q[1] + FENCE * (q[1] - q[0]);
+    let min = finite.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = finite.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let typical_max = finite
+        .iter()
+        .copied()
+        .filter(|&v| v <= fence)
+        .fold(f64::NEG_INFINITY, f64::max);
+    let spike_min = finite
+        .iter()
+        .copied()
+        .filter(|&v| v > fence)
+        .fold(f64::INFINITY, f64::min);
+    if !spike_min.is_finite() || typical_max - min > MAX_TYPICAL_SHARE * (max - min) {
+        return None;
+    }
+
+    // Small pads keep the extremes off the chart edges
+    let lower_pad = ((typical_max - min) * 0.05).max(f64::EPSILON);
+    let upper_pad = ((max - spike_min) * 0.05).max(lower_pad);
+    Some(AxisBreak {
+        lower: (min, typical_max + lower_pad),
+        upper: (spike_min - upper_pad, max),
+    })
+}
@@ -0,0 +1,240 @@
+// Barcode balance and cross-talk checks for multiplexed runs
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use plotters::style::full_palette::GREY_500;
+use std::error::Error;
+
+use crate::model::{Health, Thresholds};
+use crate::render::{self, Figure};
+
+/// Labels dorado uses for reads that were not assigned a barcode
+const UNCLASSIFIED: &[&str] = &["", "unclassified", "none"];
+/// Coefficient of variation of per-barcode yield that turns balance amber/red
+pub const BALANCE_CV: Thresholds = Thresholds {
+    warn: 0.3,
+    fail: 0.5,
+    higher_is_worse: true,
+};
+/// Unclassified read fraction that turns amber/red
+pub const UNCLASSIFIED_FRACTION: Thresholds = Thresholds {
+    warn: 0.1,
+    fail: 0.25,
+    higher_is_worse: true,
+};
+/// Barcodes below this share of the median barcode yield look like cross-talk
+const CROSS_TALK_SHARE: f64 = 0.01;
+
+/// Reads and bases assigned to one barcode
+#[derive(Debug, Clone)]
+pub struct BarcodeCount {
+    pub barcode: String, // Barcode arrangement name
+    pub reads: usize,    // Number of reads
+    pub bases: f64,      // Called bases
+}
+
+/// Balance statistics across the barcodes of a run
+#[derive(Debug, Clone)]
+pub struct BarcodeReport {
+    pub counts: Vec<BarcodeCount>,  // Classified barcodes, most bases first
+    pub unclassified_reads: usize,  // Reads without a barcode
+    pub cv: f64,                    // Coefficient of variation of bases per barcode
+    pub unclassified_fraction: f64, // Share of reads without a barcode
+    pub cross_talk: Vec<String>,    // Barcodes with implausibly low yield
+}
+
+impl BarcodeReport {
+    /// Replaces barcode names (which a sample sheet may set to sample aliases) by rank labels
+    pub fn redact(&mut self) {
+        for (rank, count) in self.counts.iter_mut().enumerate() {
+            let label = crate::redact::sample_label(rank);
+            for name in self.cross_talk.iter_mut().filter(|n| **n == count.barcode) {
+                *name = label.clone();
+            }
+            count.barcode = label;
+        }
+    }
+
+    /// Worst of the balance and unclassified-fraction checks
+    pub fn health(&self) -> Health {
+        BALANCE_CV
+            .classify(self.cv)
+            .max(UNCLASSIFIED_FRACTION.classify(self.unclassified_fraction))
+    }
+}
+
+/// Whether a barcode label means "no barcode"
+pub fn is_unclassified(barcode: &str) -> bool {
+    UNCLASSIFIED
+        .iter()
+        .any(|label| barcode.eq_ignore_ascii_case(label))
+}
+
+/// Computes barcode balance, or `None` when the run is not multiplexed
+pub fn analyze(barcodes: &[String], bases: &[f64]) -> Option<BarcodeReport> {
+    let mut counts: Vec<BarcodeCount> = Vec::new();
+    let mut unclassified_reads = 0;
+
+    for (barcode, &b) in barcodes.iter().zip(bases) {
+        if is_unclassified(barcode) {
+            unclassified_reads += 1;
+            continue;
+        }
+        match counts.iter_mut().find(|c| &c.barcode == barcode) {
+            Some(count) => {
+                count.reads += 1;
+                count.bases += b;
+            }
+            None => counts.push(BarcodeCount {
+                barcode: barcode.clone(),
+                reads: 1,
+                bases: b,
+            }),
+        }
+    }
+    if counts.is_empty() {
+        return None;
+    }
+    counts.sort_by(|a, b| b.bases.total_cmp(&a.bases));
+
+    let n = counts.len() as f64;
+    let mean = counts.iter().map(|c| c.bases).sum::<f64>() / n;
+    let variance = counts.iter().map(|c| (c.bases - mean).powi(2)).sum::<f64>() / n;
+    let cv = if mean > 0.0 {
+        variance.sqrt() / mean
+    } else {
+        0.0
+    };
+
+    // Median of the (descending) per-barcode yields
+    let median = counts[counts.len() / 2].bases;
+    let cross_talk = counts
+        .iter()
+        .filter(|c| c.bases < median * CROSS_TALK_SHARE)
+        .map(|c| c.barcode.clone())
+        .collect();
+
+    Some(BarcodeReport {
+        counts,
+        unclassified_reads,
+        cv,
+        unclassified_fraction: unclassified_reads as f64 / barcodes.len() as f64,
+        cross_talk,
+    })
+}
+
+/// Running share of unclassified reads up to each row
+pub fn cumulative_unclassified(barcodes: &[String]) -> Vec<f64> {
+    let mut unclassified = 0usize;
+    barcodes
+        .iter()
+        .enumerate()
+        .map(|(i, barcode)| {
+            if is_unclassified(barcode) {
+                unclassified += 1;
+            }
+            unclassified as f64 / (i + 1) as f64
+        })
+        .collect()
+}
+
+/// Prints the balance summary and cross-talk suspects to stdout
+pub fn print_report(report: &BarcodeReport) {
+    println!(
+        "Barcodes: {} classified, CV {:.3}, {} unclassified reads ({:.1}%) [{}]",
+        report.counts.len(),
+        report.cv,
+        report.unclassified_reads,
+        report.unclassified_fraction * 100.0,
+        report.health().label()
+    );
+    for count in &report.counts {
+        println!(
+            "  {}: {} reads, {} bases",
+            count.barcode, count.reads, count.bases
+        );
+    }
+    if !report.cross_talk.is_empty() {
+        println!(
+            "  Possible cross-talk or ligation issue: {}",
+            report.cross_talk.join(", ")
+        );
+    }
+}
+
+/// Draws a bar chart of bases per barcode, cross-talk suspects in red
+pub fn plot_balance(report: &BarcodeReport, output_path: &str) -> Result<(), Box<dyn Error>> {
+    let figure = BalanceFigure { report };
+    render::to_file(&figure, output_path)
+}
+
+/// Bases per barcode as bars
+struct BalanceFigure<'a> {
+    report: &'a BarcodeReport,
+}
+
+impl Figure for BalanceFigure<'_> {
+    fn size(&self) -> (u32, u32) {
+        (1200, 600)
+    }
+
+    fn draw<DB: DrawingBackend>(&self, root: &DrawingArea<DB, Shift>) -> Result<(), Box<dyn Error>>
+    where
+        DB::ErrorType: 'static,
+    {
+        let &BalanceFigure { report } = self;
+        root.fill(&GREY_500)?;
+
+        let max_bases = report
+            .counts
+            .iter()
+            .map(|c| c.bases)
+            .fold(0.0, f64::max)
+            .max(1.0);
+        let caption = format!(
+            "Barcode balance (CV {:.2}, unclassified {:.1}%)",
+            report.cv,
+            report.unclassified_fraction * 100.0
+        );
+        let mut chart = ChartBuilder::on(root)
+            .caption(caption, ("sans-serif", 20))
+            .margin(20)
+            .x_label_area_size(80)
+            .y_label_area_size(100)
+            .build_cartesian_2d(
+                (0..report.counts.len()).into_segmented(),
+                0.0..max_bases * 1.05,
+            )?;
+
+        chart
+            .configure_mesh()
+            .disable_x_mesh()
+            .x_labels(report.counts.len())
+            .x_label_formatter(&|x| match x {
+                SegmentValue::CenterOf(i) => report
+                    .counts
+                    .get(*i)
+                    .map(|c| c.barcode.clone())
+                    .unwrap_or_default(),
+                _ => String::new(),
+            })
+            .y_desc("Bases")
+            .draw()?;
+
+        chart.draw_series(report.counts.iter().enumerate().map(|(i, count)| {
+            let color = if report.cross_talk.contains(&count.barcode) {
+                Health::Fail.color()
+            } else {
+                GREEN
+            };
+            Rectangle::new(
+                [
+                    (SegmentValue::Exact(i), 0.0),
+                    (SegmentValue::Exact(i + 1), count.bases),
+                ],
+                color.filled(),
+            )
+        }))?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,498 @@
+// Expected-performance baselines per flow cell type
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use plotters::style::full_palette::GREY_500;
+use std::error::Error;
+use std::fs;
+
+use crate::model::Health;
+use crate::model::RunData;
+use crate::reduce;
+use crate::render::{self, Figure};
+
+/// Bases per gigabase
+const BASES_PER_GB: f64 = 1e9;
+/// Rows in the rolling Q-score window compared against the band
+const QSCORE_WINDOW: usize = 1000;
+/// Width of the elapsed-time bins a learned baseline is built on
+const LEARN_BIN_HOURS: f64 = 1.0;
+
+/// Elapsed hours of the built-in yield curves
+const HOURS: [f64; 6] = [0.0, 6.0, 12.0, 24.0, 48.0, 72.0];
+
+/// A shipped profile: cumulative Gb band at `HOURS` and a constant mean Q-score range
+struct Shipped {
+    name: &'static str,
+    yield_low: [f64; 6],
+    yield_high: [f64; 6],
+    qscore: (f64, f64),
+}
+
+/// Built-in profiles for the common flow cell and chemistry combinations
+const BUILT_IN: [Shipped; 4] = [
+    Shipped {
+        name: "minion-r10",
+        yield_low: [0.0, 2.0, 3.5, 6.0, 9.0, 11.0],
+        yield_high: [0.0, 6.0, 11.0, 18.0, 27.0, 32.0],
+        qscore: (12.0, 20.0),
+    },
+    Shipped {
+        name: "minion-r9",
+        yield_low: [0.0, 1.5, 2.5, 4.0, 6.0, 7.0],
+        yield_high: [0.0, 4.0, 7.0, 11.0, 16.0, 20.0],
+        qscore: (9.0, 14.0),
+    },
+    Shipped {
+        name: "promethion-r10",
+        yield_low: [0.0, 10.0, 18.0, 35.0, 60.0, 80.0],
+        yield_high: [0.0, 25.0, 45.0, 80.0, 130.0, 160.0],
+        qscore: (12.0, 20.0),
+    },
+    Shipped {
+        name: "promethion-r9",
+        yield_low: [0.0, 8.0, 14.0, 25.0, 45.0, 60.0],
+        yield_high: [0.0, 20.0, 35.0, 60.0, 95.0, 120.0],
+        qscore: (9.0, 14.0),
+    },
+];
+
+/// Expected band at one elapsed time
+#[derive(Debug, Clone, Copy)]
+pub struct BaselinePoint {
+    pub hours: f64,       // Elapsed time since the first batch
+    pub yield_low: f64,   // Lower edge of the expected cumulative yield (Gb)
+    pub yield_high: f64,  // Upper edge of the expected cumulative yield (Gb)
+    pub qscore_low: f64,  // Lower edge of the expected mean Q-score
+    pub qscore_high: f64, // Upper edge of the expected mean Q-score
+}
+
+/// Expected yield curve and Q-score band over the course of a run
+#[derive(Debug, Clone)]
+pub struct Baseline {
+    pub name: String,               // Profile or file name
+    pub points: Vec<BaselinePoint>, // Sorted by elapsed time
+}
+
+impl Baseline {
+    /// Looks up a shipped profile by name
+    pub fn built_in(name: &str) -> Option<Self> {
+        let shipped = BUILT_IN
+            .iter()
+            .find(|profile| profile.name.eq_ignore_ascii_case(name))?;
+        let points = HOURS
+            .iter()
+            .enumerate()
+            .map(|(i, &hours)| BaselinePoint {
+                hours,
+                yield_low: shipped.yield_low[i],
+                yield_high: shipped.yield_high[i],
+                qscore_low: shipped.qscore.0,
+                qscore_high: shipped.qscore.1,
+            })
+            .collect();
+        Some(Baseline {
+            name: shipped.name.to_string(),
+            points,
+        })
+    }
+
+    /// Names of the shipped profiles, for error messages
+    pub fn built_in_names() -> Vec<&'static str> {
+        BUILT_IN.iter().map(|profile| profile.name).collect()
+    }
+
+    /// Reads an override CSV with `hours,yield_low_gb,yield_high_gb,qscore_low,qscore_high`
+    pub fn from_csv(path: &str) -> Result<Self, Box<dyn Error>> {
+        let mut rdr = csv::Reader::from_path(path)?;
+        let mut points = Vec::new();
+        for result in rdr.records() {
+            let record = result?;
+            let value = |i: usize, name: &str| -> Result<f64, Box<dyn Error>> {
+                Ok(record
+                    .get(i)
+                    .ok_or_else(|| format!("Missing {}", name))?
+                    .trim()
+                    .parse()?)
+            };
+            points.push(BaselinePoint {
+                hours: value(0, "hours")?,
+                yield_low: value(1, "yield_low_gb")?,
+                yield_high: value(2, "yield_high_gb")?,
+                qscore_low: value(3, "qscore_low")?,
+                qscore_high: value(4, "qscore_high")?,
+            });
+        }
+        if points.is_empty() {
+            return Err(format!("Baseline {} has no rows", path).into());
+        }
+        points.sort_by(|a, b| a.hours.total_cmp(&b.hours));
+        Ok(Baseline {
+            name: path.to_string(),
+            points,
+        })
+    }
+
+    /// Band interpolated at `hours`, held flat beyond the first and last points
+    pub fn at(&self, hours: f64) -> BaselinePoint {
+        let after = self.points.partition_point(|p| p.hours <= hours);
+        if after == 0 {
+            return self.points[0];
+        }
+        if after == self.points.len() {
+            return self.points[after - 1];
+        }
+        let (a, b) = (self.points[after - 1], self.points[after]);
+        let f = (hours - a.hours) / (b.hours - a.hours);
+        let lerp = |x: f64, y: f64| x + (y - x) * f;
+        BaselinePoint {
+            hours,
+            yield_low: lerp(a.yield_low, b.yield_low),
+            yield_high: lerp(a.yield_high, b.yield_high),
+            qscore_low: lerp(a.qscore_low, b.qscore_low),
+            qscore_high: lerp(a.qscore_high, b.qscore_high),
+        }
+    }
+}
+
+/// Builds a baseline from previous runs: median ± IQR per elapsed-hour bin.
+///
+/// Each run contributes its cumulative yield at the end of every bin it
+/// covers and the mean Q-score within that bin; the band spans the first to
+/// third quartile across runs.
+pub fn learn(runs: &[RunData]) -> Result<Baseline, Box<dyn Error>> {
+    let mut bins: Vec<(Vec<f64>, Vec<f64>)> = Vec::new();
+    for run in runs {
+        let (hours, gigabases, _) = actual(run);
+        for (i, (&h, &gb)) in hours.iter().zip(&gigabases).enumerate() {
+            let bin = (h / LEARN_BIN_HOURS) as usize;
+            if bins.len() <= bin {
+                bins.resize(bin + 1, (Vec::new(), Vec::new()));
+            }
+            // Rows are time-sorted, so the last row of a bin holds its cumulative yield
+            let last_in_bin = hours
+                .get(i + 1)
+                .is_none_or(|&next| (next / LEARN_BIN_HOURS) as usize != bin);
+            if last_in_bin {
+                bins[bin].0.push(gb);
+            }
+        }
+        // Mean Q-score per bin, one value per run
+        let mut sums = vec![(0.0, 0usize); bins.len()];
+        for (&h, &q) in hours.iter().zip(&run.mean_qscore) {
+            let slot = &mut sums[(h / LEARN_BIN_HOURS) as usize];
+            slot.0 += q;
+            slot.1 += 1;
+        }
+        for (bin, (sum, count)) in sums.into_iter().enumerate() {
+            if count > 0 {
+                bins[bin].1.push(sum / count as f64);
+            }
+        }
+    }
+    if bins.is_empty() {
+        return Err("No runs to learn a baseline from".into());
+    }
+
+    let quartiles = |values: &mut Vec<f64>| {
+        values.sort_by(f64::total_cmp);
+        let at = |p: f64| values[((values.len() - 1) as f64 * p).round() as usize];
+        (at(0.25), at(0.75))
+    };
+    let mut points = Vec::with_capacity(bins.len() + 1);
+    for (bin, (mut yields, mut qscores)) in bins.into_iter().enumerate() {
+        if yields.is_empty() || qscores.is_empty() {
+            continue;
+        }
+        let (yield_low, yield_high) = quartiles(&mut yields);
+        let (qscore_low, qscore_high) = quartiles(&mut qscores);
+        if points.is_empty() {
+            points.push(BaselinePoint {
+                hours: 0.0,
+                yield_low: 0.0,
+                yield_high: 0.0,
+                qscore_low,
+                qscore_high,
+            });
+        }
+        points.push(BaselinePoint {
+            hours: (bin + 1) as f64 * LEARN_BIN_HOURS,
+            yield_low,
+            yield_high,
+            qscore_low,
+            qscore_high,
+        });
+    }
+    Ok(Baseline {
+        name: format!("learned from {} runs", runs.len()),
+        points,
+    })
+}
+
+/// Writes a baseline in the format `Baseline::from_csv` reads
+pub fn write_csv(path: &str, baseline: &Baseline) -> Result<(), Box<dyn Error>> {
+    let mut out = String::from("hours,yield_low_gb,yield_high_gb,qscore_low,qscore_high\n");
+    for p in &baseline.points {
+        out.push_str(&format!(
+            "{},{},{},{},{}\n",
+            p.hours, p.yield_low, p.yield_high, p.qscore_low, p.qscore_high
+        ));
+    }
+    fs::write(path, out)?;
+    Ok(())
+}
+
+/// Elapsed hours, cumulative Gb and rolling mean Q-score at every row
+fn actual(data: &RunData) -> (Vec<f64>, Vec<f64>, Vec<f64>) {
+    let start = data.time.first().copied().unwrap_or(0.0);
+    let hours = data.time.iter().map(|t| (t - start) / 3600.0).collect();
+    let mut total = 0.0;
+    let gigabases = data
+        .bases
+        .iter()
+        .map(|b| {
+            total += b;
+            total / BASES_PER_GB
+        })
+        .collect();
+    let qscore = reduce::rolling_mean(&data.mean_qscore, QSCORE_WINDOW);
+    (hours, gigabases, qscore)
+}
+
+/// Signed deviation from a band: 0 inside, relative distance to the nearest edge outside
+pub fn deviation(value: f64, low: f64, high: f64) -> f64 {
+    if value < low {
+        (value - low) / low.abs().max(f64::EPSILON)
+    } else if value > high {
+        (value - high) / high.abs().max(f64::EPSILON)
+    } else {
+        0.0
+    }
+}
+
+/// Describes a deviation for the report
+fn describe(deviation: f64) -> String {
+    if deviation < 0.0 {
+        format!("{:.0}% below the expected band", -deviation * 100.0)
+    } else if deviation > 0.0 {
+        format!("{:.0}% above the expected band", deviation * 100.0)
+    } else {
+        "within the expected band".to_string()
+    }
+}
+
+/// Share of rows whose yield and whose rolling Q-score fall outside the band
+pub fn outside_share(data: &RunData, baseline: &Baseline) -> (f64, f64) {
+    let (hours, gigabases, qscore) = actual(data);
+    let (mut yields, mut qscores) = (0usize, 0usize);
+    for ((&h, &gb), &q) in hours.iter().zip(&gigabases).zip(&qscore) {
+        let band = baseline.at(h);
+        yields += usize::from(deviation(gb, band.yield_low, band.yield_high) != 0.0);
+        qscores += usize::from(deviation(q, band.qscore_low, band.qscore_high) != 0.0);
+    }
+    let n = hours.len().max(1) as f64;
+    (yields as f64 / n, qscores as f64 / n)
+}
+
+/// Prints the latest yield and Q-score against the band, and how much of the run left it
+pub fn print_report(data: &RunData, baseline: &Baseline) {
+    let (hours, gigabases, qscore) = actual(data);
+    let (Some(&h), Some(&gb), Some(&q)) = (hours.last(), gigabases.last(), qscore.last()) else {
+        return;
+    };
+    let band = baseline.at(h);
+    println!("Baseline {} at {:.1} h:", baseline.name, h);
+    println!(
+        "  Yield {:.3} Gb (expected {:.3}–{:.3}): {}",
+        gb,
+        band.yield_low,
+        band.yield_high,
+        describe(deviation(gb, band.yield_low, band.yield_high))
+    );
+    println!(
+        "  Mean Q-score {:.2} (expected {:.1}–{:.1}): {}",
+        q,
+        band.qscore_low,
+        band.qscore_high,
+        describe(deviation(q, band.qscore_low, band.qscore_high))
+    );
+    let (yields, qscores) = outside_share(data, baseline);
+    println!(
+        "  Outside the band: yield {:.0}% of the run, Q-score {:.0}%",
+        yields * 100.0,
+        qscores * 100.0
+    );
+}
+
+/// Plots cumulative yield and rolling Q-score over the expected bands
+pub fn plot(data: &RunData, baseline: &Baseline, output_path: &str) -> Result<(), Box<dyn Error>> {
+    if data.is_empty() {
+        return Err("No records to compare against the baseline".into());
+    }
+    let figure = BaselineFigure { data, baseline };
+    render::to_file(&figure, output_path)
+}
+
+/// The run drawn over the expected baseline bands
+struct BaselineFigure<'a> {
+    data: &'a RunData,
+    baseline: &'a Baseline,
+}
+
+impl Figure for BaselineFigure<'_> {
+    fn size(&self) -> (u32, u32) {
+        (1600, 900)
+    }
+
+    fn draw<DB: DrawingBackend>(&self, root: &DrawingArea<DB, Shift>) -> Result<(), Box<dyn Error>>
+    where
+        DB::ErrorType: 'static,
+    {
+        let &BaselineFigure { data, baseline } = self;
+        let (hours, gigabases, qscore) = actual(data);
+        let Some(&max_hours) = hours.last() else {
+            return Err("No records to compare against the baseline".into());
+        };
+        let max_hours = max_hours.max(f64::EPSILON);
+        let bands: Vec<BaselinePoint> = (0..=100)
+            .map(|i| baseline.at(max_hours * i as f64 / 100.0))
+            .collect();
+
+        root.fill(&GREY_500)?;
+        let panels = root.split_evenly((2, 1));
+
+        type Edge = fn(&BaselinePoint) -> f64;
+        let series: [(&str, &[f64], Edge, Edge); 2] = [
+            (
+                "Cumulative Yield (Gb)",
+                &gigabases,
+                |p| p.yield_low,
+                |p| p.yield_high,
+            ),
+            ("Mean Q-score", &qscore, |p| p.qscore_low, |p| p.qscore_high),
+        ];
+        for (area, (title, values, low, high)) in panels.iter().zip(series) {
+            let top = values
+                .iter()
+                .copied()
+                .chain(bands.iter().map(high))
+                .fold(0.0, f64::max)
+                .max(f64::EPSILON);
+            let mut chart = ChartBuilder::on(area)
+                .caption(
+                    format!("{} vs {}", title, baseline.name),
+                    ("sans-serif", 20),
+                )
+                .margin(20)
+                .x_label_area_size(50)
+                .y_label_area_size(100)
+                .build_cartesian_2d(0.0..max_hours, 0.0..top * 1.05)?;
+            chart
+                .configure_mesh()
+                .x_labels(5)
+                .y_labels(5)
+                .x_desc("Elapsed Hours")
+                .y_desc(title)
+                .draw()?;
+
+            // Band as a closed polygon: lower edge forwards, upper edge backwards
+            let outline: Vec<(f64, f64)> = bands
+                .iter()
+                .map(|p| (p.hours, low(p)))
+                .chain(bands.iter().rev().map(|p| (p.hours, high(p))))
+                .collect();
+            chart.draw_series(std::iter::once(Polygon::new(
+                outline,
+                Health::Ok.color().mix(0.3).filled(),
+            )))?;
+            chart.draw_series(LineSeries::new(
+                hours.iter().copied().zip(values.iter().copied()),
+                &BLUE,
+            ))?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Two runs of four half-hourly batches, the second with twice the yield
+    fn runs() -> Vec<RunData> {
+        [(1e9, 10.0), (2e9, 14.0)]
+            .into_iter()
+            .map(|(bases, qscore)| RunData {
+                time: vec![0.0, 1800.0, 3600.0, 5400.0],
+                bases: vec![bases; 4],
+                mean_qscore: vec![qscore, qscore, qscore + 2.0, qscore + 2.0],
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn built_in_profiles() {
+        let baseline = Baseline::built_in("MinION-R10").unwrap();
+        assert_eq!(baseline.name, "minion-r10");
+        assert_eq!(baseline.points.len(), HOURS.len());
+        assert!(Baseline::built_in("flongle").is_none());
+        assert_eq!(Baseline::built_in_names().len(), BUILT_IN.len());
+    }
+
+    #[test]
+    fn bands_interpolate_and_hold_flat() {
+        let baseline = Baseline::built_in("minion-r10").unwrap();
+        let band = baseline.at(3.0);
+        assert_eq!((band.yield_low, band.yield_high), (1.0, 3.0));
+        assert_eq!(baseline.at(-1.0).yield_high, 0.0);
+        assert_eq!(baseline.at(100.0).yield_high, 32.0);
+    }
+
+    #[test]
+    fn deviations_are_relative_to_the_nearest_edge() {
+        assert_eq!(deviation(5.0, 4.0, 6.0), 0.0);
+        assert_eq!(deviation(2.0, 4.0, 6.0), -0.5);
+        assert_eq!(deviation(9.0, 4.0, 6.0), 0.5);
+    }
+
+    #[test]
+    fn learns_quartiles_per_hour() {
+        let baseline = learn(&runs()).unwrap();
+        let bands: Vec<(f64, f64, f64, f64, f64)> = baseline
+            .points
+            .iter()
+            .map(|p| {
+                (
+                    p.hours,
+                    p.yield_low,
+                    p.yield_high,
+                    p.qscore_low,
+                    p.qscore_high,
+                )
+            })
+            .collect();
+        assert_eq!(
+            bands,
+            [
+                (0.0, 0.0, 0.0, 10.0, 14.0),
+                (1.0, 2.0, 4.0, 10.0, 14.0),
+                (2.0, 4.0, 8.0, 12.0, 16.0)
+            ]
+        );
+        assert!(learn(&[]).is_err());
+    }
+
+    #[test]
+    fn learned_baselines_round_trip_through_csv() {
+        let path =
+            std::env::temp_dir().join(format!("as_parser-baseline-{}.csv", std::process::id()));
+        let path = path.to_string_lossy().to_string();
+        let learned = learn(&runs()).unwrap();
+        write_csv(&path, &learned).unwrap();
+        let read = Baseline::from_csv(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(read.points.len(), learned.points.len());
+        assert_eq!(read.at(1.5).yield_high, learned.at(1.5).yield_high);
+    }
+}
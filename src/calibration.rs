@@ -0,0 +1,274 @@
+// Q-score calibration: basecaller-predicted against alignment-observed accuracy
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use plotters::style::full_palette::GREY_500;
+use std::error::Error;
+
+use crate::model::{Health, RunData};
+use crate::render::{self, Figure};
+
+/// Time bins the run is divided into by default
+pub const DEFAULT_BINS: usize = 6;
+/// Change of the calibration offset (in Q) from the first time bin that is
+/// reported; larger steps usually follow a basecaller model change
+pub const MAX_SHIFT: f64 = 1.0;
+/// Empirical Q-scores are capped here, perfect alignments have no measurable error
+const MAX_QSCORE: f64 = 60.0;
+/// Reads a Q-score bin needs before it is drawn on a calibration curve
+const MIN_CURVE_READS: usize = 20;
+
+/// Error probability a Phred quality stands for
+pub fn error_rate(qscore: f64) -> f64 {
+    10f64.powf(-qscore / 10.0)
+}
+
+/// Phred quality of an error probability, capped at `MAX_QSCORE`
+pub fn phred(error: f64) -> f64 {
+    (-10.0 * error.max(error_rate(MAX_QSCORE)).log10()).min(MAX_QSCORE)
+}
+
+/// Pooled Q-score of a set of reads: the mean error probability, not the
+/// mean Q-score, which would overweight the best reads
+fn pooled(errors: &[f64]) -> f64 {
+    phred(errors.iter().sum::<f64>() / errors.len() as f64)
+}
+
+/// One point of a calibration curve
+#[derive(Debug, Clone, Copy)]
+pub struct CurvePoint {
+    pub predicted: f64, // Pooled Q-score the basecaller reported
+    pub observed: f64,  // Q-score implied by the alignment identity
+    pub reads: usize,   // Reads in this Q-score bin
+}
+
+/// Calibration of the reads in one stretch of the run
+#[derive(Debug, Clone)]
+pub struct TimeBin {
+    pub start: f64,             // Elapsed hours at the start of the bin
+    pub end: f64,               // Elapsed hours at the end of the bin
+    pub reads: usize,           // Reads with both a Q-score and an identity
+    pub predicted: f64,         // Pooled predicted Q-score
+    pub observed: f64,          // Pooled observed Q-score
+    pub curve: Vec<CurvePoint>, // Observed against predicted, per whole Q-score
+}
+
+impl TimeBin {
+    /// Predicted minus observed Q-score; positive when the basecaller is overconfident
+    pub fn offset(&self) -> f64 {
+        self.predicted - self.observed
+    }
+}
+
+/// Calibration over the run
+#[derive(Debug, Clone)]
+pub struct Calibration {
+    pub bins: Vec<TimeBin>,   // Time bins holding reads, in order
+    pub shift: Option<usize>, // First bin whose offset moved by more than `MAX_SHIFT`
+}
+
+/// Compares predicted and observed accuracy in `bins` equal stretches of the
+/// run; `None` without an `alignment_identity` column
+pub fn analyze(data: &RunData, bins: usize) -> Option<Calibration> {
+    if data.alignment_identity.len() != data.len() {
+        return None;
+    }
+    let (first, last) = data.time_range()?;
+    let bins = bins.max(1);
+    let width = (last - first) / bins as f64;
+    let index = |t: f64| {
+        if width > 0.0 {
+            (((t - first) / width) as usize).min(bins - 1)
+        } else {
+            0
+        }
+    };
+
+    // Error probabilities (predicted, observed) by time bin, then by whole Q-score
+    let mut grouped: Vec<Vec<(f64, f64, f64)>> = vec![Vec::new(); bins];
+    for ((&t, &q), &identity) in data
+        .time
+        .iter()
+        .zip(&data.mean_qscore)
+        .zip(&data.alignment_identity)
+    {
+        if q.is_finite() && identity.is_finite() {
+            grouped[index(t)].push((q, error_rate(q), 1.0 - identity.clamp(0.0, 1.0)));
+        }
+    }
+
+    let bins: Vec<TimeBin> = grouped
+        .into_iter()
+        .enumerate()
+        .filter(|(_, reads)| !reads.is_empty())
+        .map(|(i, mut reads)| {
+            let predicted: Vec<f64> = reads.iter().map(|r| r.1).collect();
+            let observed: Vec<f64> = reads.iter().map(|r| r.2).collect();
+            reads.sort_by(|a, b| a.0.total_cmp(&b.0));
+            let curve = reads
+                .chunk_by(|a, b| a.0.floor() == b.0.floor())
+                .filter(|group| group.len() >= MIN_CURVE_READS)
+                .map(|group| CurvePoint {
+                    predicted: pooled(&group.iter().map(|r| r.1).collect::<Vec<_>>()),
+                    observed: pooled(&group.iter().map(|r| r.2).collect::<Vec<_>>()),
+                    reads: group.len(),
+                })
+                .collect();
+            TimeBin {
+                start: width * i as f64 / 3600.0,
+                end: width * (i + 1) as f64 / 3600.0,
+                reads: reads.len(),
+                predicted: pooled(&predicted),
+                observed: pooled(&observed),
+                curve,
+            }
+        })
+        .collect();
+
+    let reference = bins.first()?.offset();
+    let shift = bins
+        .iter()
+        .position(|b| (b.offset() - reference).abs() > MAX_SHIFT);
+    Some(Calibration { bins, shift })
+}
+
+/// Prints the calibration per time bin and any shift in it
+pub fn print_report(calibration: &Calibration) {
+    println!("Q-score calibration (predicted vs alignment identity):");
+    for bin in &calibration.bins {
+        println!(
+            "  {:.1}–{:.1} h: predicted Q{:.1}, observed Q{:.1}, offset {:+.1} ({} reads)",
+            bin.start,
+            bin.end,
+            bin.predicted,
+            bin.observed,
+            bin.offset(),
+            bin.reads
+        );
+    }
+    if let Some(i) = calibration.shift {
+        let bin = &calibration.bins[i];
+        println!(
+            "  Calibration shifted by {:+.1} Q from {:.1} h; check for a basecaller model change",
+            bin.offset() - calibration.bins[0].offset(),
+            bin.start
+        );
+    }
+}
+
+/// Plots the calibration curve of each time bin and the offset over time
+pub fn plot(calibration: &Calibration, output_path: &str) -> Result<(), Box<dyn Error>> {
+    if calibration.bins.is_empty() {
+        return Err("No reads with an alignment identity to plot".into());
+    }
+    render::to_file(&CalibrationFigure { calibration }, output_path)
+}
+
+/// Colour of time bin `i` of `n`, from blue (early) to red (late)
+fn bin_color(i: usize, n: usize) -> RGBColor {
+    let f = if n > 1 {
+        i as f64 / (n - 1) as f64
+    } else {
+        0.0
+    };
+    RGBColor((255.0 * f) as u8, 60, (255.0 * (1.0 - f)) as u8)
+}
+
+/// Calibration curves next to the offset per time bin
+struct CalibrationFigure<'a> {
+    calibration: &'a Calibration,
+}
+
+impl Figure for CalibrationFigure<'_> {
+    fn size(&self) -> (u32, u32) {
+        (1800, 800)
+    }
+
+    fn draw<DB: DrawingBackend>(&self, root: &DrawingArea<DB, Shift>) -> Result<(), Box<dyn Error>>
+    where
+        DB::ErrorType: 'static,
+    {
+        let bins = &self.calibration.bins;
+        root.fill(&GREY_500)?;
+        let panels = root.split_evenly((1, 2));
+
+        // Observed against predicted on shared axes, the diagonal being perfect calibration
+        let top = bins
+            .iter()
+            .flat_map(|b| b.curve.iter().flat_map(|p| [p.predicted, p.observed]))
+            .chain(bins.iter().flat_map(|b| [b.predicted, b.observed]))
+            .fold(0.0, f64::max)
+            .ceil()
+            + 1.0;
+        let mut chart = ChartBuilder::on(&panels[0])
+            .caption("Q-score Calibration", ("sans-serif", 20))
+            .margin(20)
+            .x_label_area_size(50)
+            .y_label_area_size(80)
+            .build_cartesian_2d(0.0..top, 0.0..top)?;
+        chart
+            .configure_mesh()
+            .x_labels(6)
+            .y_labels(6)
+            .x_desc("Predicted Q-score")
+            .y_desc("Observed Q-score (alignment identity)")
+            .draw()?;
+        chart.draw_series(LineSeries::new([(0.0, 0.0), (top, top)], &BLACK))?;
+        for (i, bin) in bins.iter().enumerate() {
+            let color = bin_color(i, bins.len());
+            chart
+                .draw_series(LineSeries::new(
+                    bin.curve.iter().map(|p| (p.predicted, p.observed)),
+                    color.stroke_width(2),
+                ))?
+                .label(format!("{:.1}–{:.1} h", bin.start, bin.end))
+                .legend(move |(x, y)| PathElement::new([(x, y), (x + 20, y)], color));
+        }
+        chart
+            .configure_series_labels()
+            .position(SeriesLabelPosition::UpperLeft)
+            .background_style(WHITE.mix(0.8))
+            .border_style(BLACK)
+            .draw()?;
+
+        // Offset per bin against the band of tolerated drift from the first bin
+        let reference = bins[0].offset();
+        let (low, high) = bins.iter().fold(
+            (reference - MAX_SHIFT, reference + MAX_SHIFT),
+            |(low, high), b| (low.min(b.offset()), high.max(b.offset())),
+        );
+        let end = bins.last().map_or(1.0, |b| b.end).max(f64::EPSILON);
+        let mut chart = ChartBuilder::on(&panels[1])
+            .caption("Calibration Offset", ("sans-serif", 20))
+            .margin(20)
+            .x_label_area_size(50)
+            .y_label_area_size(80)
+            .build_cartesian_2d(0.0..end, low - 0.5..high + 0.5)?;
+        chart
+            .configure_mesh()
+            .x_labels(5)
+            .y_labels(6)
+            .x_desc("Elapsed Hours")
+            .y_desc("Predicted − observed Q-score")
+            .draw()?;
+        chart.draw_series(std::iter::once(Rectangle::new(
+            [(0.0, reference - MAX_SHIFT), (end, reference + MAX_SHIFT)],
+            Health::Ok.color().mix(0.3).filled(),
+        )))?;
+        let centers: Vec<(f64, f64)> = bins
+            .iter()
+            .map(|b| ((b.start + b.end) / 2.0, b.offset()))
+            .collect();
+        chart.draw_series(LineSeries::new(centers.iter().copied(), &BLACK))?;
+        chart.draw_series(bins.iter().zip(&centers).enumerate().map(|(i, (bin, &c))| {
+            let shifted = (bin.offset() - reference).abs() > MAX_SHIFT;
+            let color = if shifted {
+                Health::Fail.color()
+            } else {
+                bin_color(i, bins.len())
+            };
+            Circle::new(c, 6, color.filled())
+        }))?;
+
+        Ok(())
+    }
+}
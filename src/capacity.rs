@@ -0,0 +1,162 @@
+// Basecaller capacity model and batch-size / GPU-count what-if tables
+use std::error::Error;
+
+use crate::model::RunData;
+
+/// Nanoseconds per second, the unit of the basecaller timings
+const NANOS_PER_SECOND: f64 = 1e9;
+/// Expected latency the recommendation must stay under (the Time in Basecaller warn level)
+const LATENCY_BUDGET: f64 = 1.0;
+/// Highest utilisation considered safe; queues grow quickly above this
+const MAX_UTILISATION: f64 = 0.8;
+
+/// Linear basecaller service time per batch: `fixed + per_sample * samples` seconds
+#[derive(Debug, Clone, Copy)]
+pub struct CapacityModel {
+    pub fixed: f64,      // Per-batch overhead in seconds
+    pub per_sample: f64, // Seconds per signal sample
+    pub arrival: f64,    // Observed samples per second reaching the basecaller
+    pub slots: f64,      // Batches one GPU processes concurrently
+}
+
+impl CapacityModel {
+    /// Least-squares fit of time in basecaller against samples per batch.
+    ///
+    /// When batch sizes barely vary the slope is meaningless, so the model falls
+    /// back to a purely proportional one through the mean. The number of
+    /// concurrent batches per GPU comes from Little's law on the observed run.
+    pub fn fit(data: &RunData) -> Option<Self> {
+        let n = data.len() as f64;
+        let (start, end) = data.time_range()?;
+        let latency: Vec<f64> = data
+            .time_in_basecaller
+            .iter()
+            .map(|ns| ns / NANOS_PER_SECOND)
+            .collect();
+        let mean_x = data.samples.iter().sum::<f64>() / n;
+        let mean_y = latency.iter().sum::<f64>() / n;
+        if mean_x <= 0.0 {
+            return None;
+        }
+
+        let (mut sxy, mut sxx) = (0.0, 0.0);
+        for (&x, &y) in data.samples.iter().zip(&latency) {
+            sxy += (x - mean_x) * (y - mean_y);
+            sxx += (x - mean_x) * (x - mean_x);
+        }
+        let slope = sxy / sxx;
+        let (fixed, per_sample) =
+            if slope.is_finite() && slope > 0.0 && mean_y - slope * mean_x >= 0.0 {
+                (mean_y - slope * mean_x, slope)
+            } else {
+                (0.0, mean_y / mean_x)
+            };
+
+        let span = end - start;
+        let arrival = if span > 0.0 {
+            data.samples.iter().sum::<f64>() / span
+        } else {
+            0.0
+        };
+        // Batches in flight = arrival rate in batches × mean latency
+        let slots = (arrival / mean_x * mean_y).ceil().max(1.0);
+        Some(CapacityModel {
+            fixed,
+            per_sample,
+            arrival,
+            slots,
+        })
+    }
+
+    /// Predicted behaviour with `batch` samples per batch spread over `gpus` GPUs
+    pub fn scenario(&self, batch: f64, gpus: usize) -> Scenario {
+        let service = self.fixed + self.per_sample * batch;
+        let capacity = gpus as f64 * self.slots * batch / service;
+        let utilisation = self.arrival / capacity;
+        // Single-queue approximation: waiting grows as 1 / (1 - utilisation)
+        let latency = if utilisation < 1.0 {
+            service / (1.0 - utilisation)
+        } else {
+            f64::INFINITY
+        };
+        Scenario {
+            batch,
+            gpus,
+            latency,
+            capacity,
+            utilisation,
+        }
+    }
+}
+
+/// One row of the what-if table
+#[derive(Debug, Clone, Copy)]
+pub struct Scenario {
+    pub batch: f64,       // Samples per batch
+    pub gpus: usize,      // Number of GPUs
+    pub latency: f64,     // Expected seconds from submission to result
+    pub capacity: f64,    // Samples per second the configuration can sustain
+    pub utilisation: f64, // Observed load as a share of capacity
+}
+
+/// Parses a comma-separated list such as `2000,4000,8000`
+pub fn parse_list<T: std::str::FromStr>(list: &str) -> Result<Vec<T>, Box<dyn Error>> {
+    list.split(',')
+        .map(|item| {
+            item.trim()
+                .parse()
+                .map_err(|_| format!("Invalid list entry '{}'", item).into())
+        })
+        .collect()
+}
+
+/// Cheapest configuration meeting the latency budget: fewest GPUs, then lowest latency
+pub fn recommend(scenarios: &[Scenario]) -> Option<&Scenario> {
+    scenarios
+        .iter()
+        .filter(|s| s.latency <= LATENCY_BUDGET && s.utilisation <= MAX_UTILISATION)
+        .min_by(|a, b| a.gpus.cmp(&b.gpus).then(a.latency.total_cmp(&b.latency)))
+}
+
+/// Prints the fitted model and the what-if table, marking the recommendation
+pub fn print_report(model: &CapacityModel, scenarios: &[Scenario]) {
+    println!(
+        "Basecaller model: {:.3} s per batch + {:.3} µs per sample, {:.0} concurrent batches, load {:.0} samples/s",
+        model.fixed,
+        model.per_sample * 1e6,
+        model.slots,
+        model.arrival
+    );
+    println!(
+        "  {:>10} {:>5} {:>12} {:>16} {:>12}",
+        "Batch", "GPUs", "Latency (s)", "Capacity (S/s)", "Utilisation"
+    );
+    let best = recommend(scenarios);
+    for s in scenarios {
+        let mark = if best.is_some_and(|b| std::ptr::eq(b, s)) {
+            '*'
+        } else {
+            ' '
+        };
+        println!(
+            "{} {:>10.0} {:>5} {:>12.3} {:>16.0} {:>11.0}%",
+            mark,
+            s.batch,
+            s.gpus,
+            s.latency,
+            s.capacity,
+            s.utilisation * 100.0
+        );
+    }
+    match best {
+        Some(b) => println!(
+            "Recommended: {:.0} samples per batch on {} GPU(s)",
+            b.batch, b.gpus
+        ),
+        None => println!(
+            "No configuration keeps latency under {:.1} s at {:.0}% utilisation",
+            LATENCY_BUDGET,
+            MAX_UTILISATION * 100.0
+        ),
+    }
+}
@@ -0,0 +1,650 @@
+// Command-line interface: one subcommand per kind of output
+use clap::{Args, Parser, Subcommand};
+
+use as_parser::calibration;
+use as_parser::control::ChartKind;
+use as_parser::histogram::{Bandwidth, Binning};
+use as_parser::lengthclass::{self, LengthClasses};
+use as_parser::locale::Language;
+use as_parser::mode::Mode;
+use as_parser::model::{Clip, Profile, YRange};
+use as_parser::preset::Preset;
+use as_parser::quantile::Method;
+use as_parser::render::Format;
+use as_parser::retention::Period;
+use as_parser::rna::Library;
+use as_parser::robust::Center;
+use as_parser::sample::Sampling;
+use as_parser::smooth::Window;
+use as_parser::theme::{Palette, Theme};
+use as_parser::timestamp::TimeFormat;
+
+/// Analysis and plotting of adaptive sampling batch timings
+#[derive(Debug, Parser)]
+#[command(version)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+/// What to produce
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Render the metrics figure, plus any extra analyses requested
+    #[command(args_override_self = true)]
+    Plot(PlotArgs),
+    /// Print per-metric statistics without touching a drawing backend
+    #[command(args_override_self = true)]
+    Stats(StatsArgs),
+    /// Write a self-contained HTML report embedding every panel, the statistics and run metadata
+    #[command(args_override_self = true)]
+    Report(ReportArgs),
+    /// Follow every run under a data directory and keep its outputs current
+    #[command(args_override_self = true)]
+    Watch(WatchArgs),
+    /// Show sparklines and current values of one run in the terminal
+    #[command(args_override_self = true)]
+    Tui(TuiArgs),
+    /// Follow one run and expose its latest metrics on /metrics and as JSON under /runs/
+    #[command(args_override_self = true)]
+    Serve(ServeArgs),
+}
+
+impl Command {
+    /// Run-level flags every subcommand takes
+    pub fn run(&self) -> &RunArgs {
+        match self {
+            Command::Plot(args) => &args.run,
+            Command::Stats(args) => &args.run,
+            Command::Report(args) => &args.run,
+            Command::Watch(args) => &args.run,
+            Command::Tui(args) => &args.run,
+            Command::Serve(args) => &args.run,
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct PlotArgs {
+    /// Batch timings CSV or sequencing_summary.txt (path, URL or ssh:// location); several runs are
+    /// overlaid on the standard panels, aligned on elapsed time
+    #[arg(required = true, num_args = 1..)]
+    pub inputs: Vec<String>,
+    /// Figure to write; the extension picks the format unless --format is given
+    pub output: String,
+    /// Figure width in pixels
+    #[arg(long, default_value_t = as_parser::plot::FIGURE_SIZE.0)]
+    pub width: u32,
+    /// Figure height in pixels
+    #[arg(long, default_value_t = as_parser::plot::FIGURE_SIZE.1)]
+    pub height: u32,
+    /// Output format; added to an output path without extension
+    #[arg(long, value_parser = parse_format)]
+    pub format: Option<Format>,
+    /// Also write each panel as its own figure into this directory
+    #[arg(long, value_name = "DIR")]
+    pub split_panels: Option<String>,
+    /// Write the points each panel draws to a CSV next to its image
+    #[arg(long)]
+    pub panel_data: bool,
+    /// Keep following the input as it grows, re-rendering the standard panels
+    /// every SECS seconds when rows were appended (until Ctrl-C)
+    #[arg(
+        long,
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "30",
+        value_name = "SECS"
+    )]
+    pub watch: Option<u64>,
+    /// Panel spec replacing the standard layout: NAME, yield, rate:COL, cumulative:COL or hist:COL
+    #[arg(long = "panel", id = "panel", value_name = "SPEC")]
+    pub panels: Vec<String>,
+    #[command(flatten)]
+    pub figure: FigureArgs,
+    #[command(flatten)]
+    pub run: RunArgs,
+    #[command(flatten)]
+    pub analysis: AnalysisArgs,
+}
+
+#[derive(Debug, Args)]
+pub struct StatsArgs {
+    /// Batch timings CSV or sequencing_summary.txt (path, URL or ssh:// location)
+    pub input: String,
+    /// Run-level aggregates and throughput windows as JSON
+    #[arg(long, value_name = "JSON")]
+    pub json: Option<String>,
+    /// The same aggregates as long-format TSV
+    #[arg(long, value_name = "TSV")]
+    pub tsv: Option<String>,
+    /// Seconds per throughput window in the JSON/TSV output
+    #[arg(long, value_name = "SECS", default_value_t = as_parser::summary::DEFAULT_WINDOW)]
+    pub throughput_window: f64,
+    #[command(flatten)]
+    pub run: RunArgs,
+    #[command(flatten)]
+    pub analysis: AnalysisArgs,
+}
+
+#[derive(Debug, Args)]
+pub struct ReportArgs {
+    /// Batch timings CSV or sequencing_summary.txt (path, URL or ssh:// location)
+    pub input: String,
+    /// HTML report to write
+    pub output: String,
+    #[command(flatten)]
+    pub figure: FigureArgs,
+    #[command(flatten)]
+    pub run: RunArgs,
+    #[command(flatten)]
+    pub analysis: AnalysisArgs,
+}
+
+#[derive(Debug, Args)]
+pub struct WatchArgs {
+    /// Data directory whose runs are followed
+    pub dir: String,
+    /// Seconds between scans
+    #[arg(long, default_value_t = 60)]
+    pub interval: u64,
+    /// Scan once and exit
+    #[arg(long)]
+    pub once: bool,
+    /// Seconds between automatic snapshots of all runs
+    #[arg(long, value_name = "SECS")]
+    pub snapshot_every: Option<u64>,
+    /// Number of most recent snapshots to keep (the newest is always kept)
+    #[arg(long, value_name = "N")]
+    pub keep_last: Option<usize>,
+    /// Additionally keep the first snapshot of every hour or day
+    #[arg(long, value_parser = parse_period)]
+    pub keep_per: Option<Period>,
+    /// Write the process ID to this file while running
+    #[arg(long, value_name = "PATH")]
+    pub pid_file: Option<String>,
+    /// Prefix log lines with syslog priorities for the systemd journal
+    #[arg(long)]
+    pub journal: bool,
+    /// Batch timings CSV of a known-good run, drawn behind every run's panels and scored against
+    #[arg(long, value_name = "CSV")]
+    pub reference: Option<String>,
+    #[command(flatten)]
+    pub figure: FigureArgs,
+    #[command(flatten)]
+    pub run: RunArgs,
+}
+
+#[derive(Debug, Args)]
+pub struct TuiArgs {
+    /// Batch timings CSV (uncompressed, local or ssh://, when following)
+    pub input: String,
+    /// Keep following the input as it grows, redrawing every SECS seconds (until Ctrl-C)
+    #[arg(
+        long,
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "5",
+        value_name = "SECS"
+    )]
+    pub follow: Option<u64>,
+    /// Dashboard width in columns [default: $COLUMNS, else 100]
+    #[arg(long, value_name = "COLS")]
+    pub width: Option<usize>,
+    #[command(flatten)]
+    pub run: RunArgs,
+}
+
+#[derive(Debug, Args)]
+pub struct ServeArgs {
+    /// Batch timings CSV (uncompressed, local or ssh://), followed as it grows
+    pub input: String,
+    /// Address to serve /metrics and /runs/ on
+    #[arg(long, value_name = "ADDR", default_value = as_parser::serve::DEFAULT_LISTEN)]
+    pub listen: String,
+    /// File holding a token clients must send as `Authorization: Bearer <token>`
+    #[arg(long, value_name = "FILE")]
+    pub token_file: Option<String>,
+    /// Seconds between polls of the input
+    #[arg(long, value_name = "SECS", default_value_t = 15)]
+    pub interval: u64,
+    /// Seconds of recent batches behind the rate and average gauges
+    #[arg(long, value_name = "SECS", default_value_t = 300)]
+    pub window: u64,
+    /// Adaptive sampling decision CSV, re-read each poll for the unblock rate
+    #[arg(long, value_name = "CSV")]
+    pub decisions: Option<String>,
+    /// Signal sample rate in Hz, for decision latencies
+    #[arg(long, value_name = "HZ", default_value_t = as_parser::decisions::DEFAULT_SAMPLE_RATE)]
+    pub sample_rate: f64,
+    #[command(flatten)]
+    pub run: RunArgs,
+}
+
+/// Switches for the rendered figure
+#[derive(Debug, Args)]
+pub struct FigureArgs {
+    /// Draw a health strip beneath each panel
+    #[arg(long)]
+    pub summary_strip: bool,
+    /// Break the y axis of panels with rare extreme spikes
+    #[arg(long)]
+    pub axis_break: bool,
+    /// Render without captions and axis labels, writing a label legend instead
+    #[arg(long)]
+    pub no_text: bool,
+    /// Points each line is decimated to (LTTB); 0 draws every point [default: 2 per pixel of width]
+    #[arg(long, value_name = "N")]
+    pub max_points: Option<usize>,
+    /// Rolling overlay on every panel over the last N batches or a duration (30s, 5m, 1h)
+    #[arg(long, value_parser = parse_smooth_window, value_name = "N|DURATION")]
+    pub smooth: Option<Window>,
+    /// Statistic of the rolling overlay: mean, median or trimmed[:F]
+    #[arg(long, value_parser = parse_center, default_value = "mean", requires = "smooth")]
+    pub smooth_stat: Center,
+    /// Draw the raw series faintly beneath the rolling overlay
+    #[arg(long, requires = "smooth")]
+    pub dim_raw: bool,
+    /// Percentiles each panel's y range spans, LOW:HIGH, or none for min to max
+    #[arg(long, value_parser = parse_clip, default_value = "0.5:99.5", value_name = "LOW:HIGH")]
+    pub clip_percentiles: Clip,
+    /// Mark values beyond a panel's y range at its edge
+    #[arg(long)]
+    pub outlier_markers: bool,
+    /// Keep every local minimum and maximum and every flagged value when decimating, beyond --max-points if need be
+    #[arg(long)]
+    pub keep_extremes: bool,
+    /// Figure colours: grey, light (slides, print) or dark (dark-mode dashboards)
+    #[arg(long, value_parser = Theme::parse, default_value = "grey")]
+    pub theme: Theme,
+    /// Series colours: classic, okabe-ito or tableau
+    #[arg(long, value_parser = Palette::parse, default_value = "classic")]
+    pub palette: Palette,
+    /// Print resolution recorded in PNG figures, so they import at width/DPI inches
+    #[arg(long, value_name = "DPI", value_parser = clap::value_parser!(u32).range(1..))]
+    pub dpi: Option<u32>,
+}
+
+/// Settings every subcommand shares
+#[derive(Debug, Args)]
+pub struct RunArgs {
+    /// Typical-value statistic: mean, median or trimmed[:F]
+    #[arg(long = "stat", value_parser = parse_center, default_value = "mean")]
+    pub center: Center,
+    /// Language of titles, labels and the digest: en, de or fr
+    #[arg(long, value_parser = parse_language, default_value = "en")]
+    pub language: Language,
+    /// Expected samples per batch (the configured chunk size)
+    #[arg(long, value_name = "N")]
+    pub expected_samples: Option<f64>,
+    /// Relative tolerance around --expected-samples
+    #[arg(
+        long,
+        value_name = "F",
+        default_value_t = 0.1,
+        requires = "expected_samples"
+    )]
+    pub samples_tolerance: f64,
+    /// Replace input paths and barcodes by pseudonyms in shared outputs
+    #[arg(long)]
+    pub redact: bool,
+    /// Timestamp format of every input: auto, unix, unix-ms, iso8601 or minknow
+    #[arg(long, value_parser = parse_time_format, default_value = "auto")]
+    pub time_format: TimeFormat,
+    /// Column alias map for renamed columns: one `column = "renamed"` line each
+    #[arg(long, value_name = "FILE")]
+    pub aliases: Option<String>,
+    /// CSV of withdrawn or failed reads (read_id, optional reason) to leave out of every statistic
+    #[arg(long, value_name = "FILE")]
+    pub tombstones: Option<String>,
+    /// Worker threads for parsing several runs and computing statistics [default: usable CPUs,
+    /// within cgroup limits]
+    #[arg(long, value_name = "N")]
+    pub threads: Option<usize>,
+    /// TOML or YAML file of flags, inputs and outputs; flags given on the command line win
+    #[arg(long, value_name = "FILE")]
+    pub config: Option<String>,
+}
+
+/// Input handling and the optional analyses run on one CSV
+#[derive(Debug, Args)]
+pub struct AnalysisArgs {
+    /// Statistics mode: exact, approximate (P² quantiles over a row sample) or
+    /// streaming (every row in constant memory, for inputs larger than RAM;
+    /// without batch intervals, which need the rows in time order)
+    #[arg(long, value_parser = parse_mode)]
+    pub mode: Option<Mode>,
+    /// Keep a random subset of rows: a percentage (1%) or a row count
+    #[arg(long, value_parser = parse_sampling, value_name = "PCT%|ROWS")]
+    pub sample: Option<Sampling>,
+    /// Quantile method: exact or p2 (defaults follow --mode; streaming only has p2)
+    #[arg(long, value_parser = parse_quantiles)]
+    pub quantiles: Option<Method>,
+    /// Drop rows with negative counts or times, Q-scores outside 0–60 and
+    /// similar impossible values, and report them with out-of-order rows
+    #[arg(long)]
+    pub validate: bool,
+    /// Write the rows dropped by validation to this CSV (implies --validate)
+    #[arg(long, value_name = "CSV")]
+    pub quarantine: Option<String>,
+    /// Analysis preset: ultralong
+    #[arg(long, value_parser = parse_preset)]
+    pub preset: Option<Preset>,
+    /// Library type when the input names no kit: dna, cdna or rna
+    #[arg(long, value_parser = parse_library)]
+    pub library: Option<Library>,
+    /// Audience: operator, scientist or manager
+    #[arg(long, value_parser = parse_profile, default_value = "scientist")]
+    pub profile: Profile,
+    /// Panels to show and their order: samples, bases, qscore, package, basecaller,
+    /// interval, or any derived metric's name or title; `all` adds the rest
+    #[arg(long, value_delimiter = ',', value_name = "NAMES")]
+    pub panels: Vec<String>,
+    /// Log-scale y axis on the named panels (--log-y=bases,samples), or on every panel
+    #[arg(
+        long,
+        num_args = 0..=1,
+        require_equals = true,
+        value_delimiter = ',',
+        value_name = "NAMES"
+    )]
+    pub log_y: Option<Vec<String>>,
+    /// Fixed y range of one panel instead of the data's min and max: NAME=MIN:MAX
+    #[arg(long = "y-range", value_parser = parse_y_range, value_name = "SPEC")]
+    pub y_ranges: Vec<YRange>,
+    /// Add cumulative bases and cumulative reads panels
+    #[arg(long)]
+    pub cumulative_yield: bool,
+    /// Add bases/sec and reads/sec panels, each batch over the time since the previous one
+    #[arg(long)]
+    pub rates: bool,
+    /// Seconds without batches that count as a pause [default: 10x the usual batch interval, at least 60]
+    #[arg(long, value_name = "SECS")]
+    pub pause_gap: Option<f64>,
+    /// Aligned BAM of the run's reads (with MD tags), for a homopolymer error rate panel
+    #[arg(long, value_name = "BAM")]
+    pub bam: Option<String>,
+    /// Anomaly detector for one metric: METRIC=zscore|iqr|ewma|threshold[:PARAMS]
+    #[arg(long = "anomaly", value_name = "SPEC")]
+    pub anomalies: Vec<String>,
+    /// Rounding of one metric's reported values: METRIC=2dp|3sf (METRIC `all` for every metric)
+    #[arg(long = "precision", value_name = "SPEC")]
+    pub precisions: Vec<String>,
+    /// Facility QC rules file
+    #[arg(long, value_name = "FILE")]
+    pub rules: Option<String>,
+    /// YAML manifest of every file written
+    #[arg(long, value_name = "YAML")]
+    pub manifest: Option<String>,
+    /// HTML digest, with a thumbnail when a figure is rendered
+    #[arg(long, value_name = "HTML")]
+    pub digest: Option<String>,
+    /// Yield target in bases, for milestones and the health score
+    #[arg(long, value_name = "N")]
+    pub target_bases: Option<f64>,
+    /// Projected milestones as JSON
+    #[arg(long, value_name = "JSON", requires = "target_bases")]
+    pub milestones: Option<String>,
+    /// Projected milestones as an iCalendar file
+    #[arg(long, value_name = "ICS", requires = "target_bases")]
+    pub milestones_ics: Option<String>,
+    /// Flow cell cost, adds a cost-per-Gb panel
+    #[arg(long, value_name = "X")]
+    pub flowcell_cost: Option<f64>,
+    /// Compute cost per hour of run time
+    #[arg(
+        long,
+        value_name = "Y",
+        default_value_t = 0.0,
+        requires = "flowcell_cost"
+    )]
+    pub compute_cost_per_hour: f64,
+    /// Bar chart of bases per barcode
+    #[arg(long, value_name = "PNG")]
+    pub barcode_plot: Option<String>,
+    /// MinKNOW pore scan CSV
+    #[arg(long, value_name = "CSV")]
+    pub pore_scan: Option<String>,
+    /// Plot of the pore scans
+    #[arg(long, value_name = "PNG", requires = "pore_scan")]
+    pub pore_scan_plot: Option<String>,
+    /// Adaptive sampling decision CSV (MinKNOW adaptive_sampling or ReadFish)
+    #[arg(long, value_name = "CSV")]
+    pub decisions: Option<String>,
+    /// Plot of the unblock rate, decision latency and per-channel decisions
+    #[arg(long, value_name = "PNG", requires = "decisions")]
+    pub decision_plot: Option<String>,
+    /// Signal sample rate in Hz, for decision latencies
+    #[arg(long, value_name = "HZ", default_value_t = as_parser::decisions::DEFAULT_SAMPLE_RATE)]
+    pub sample_rate: f64,
+    /// Device telemetry CSV (ASIC temperature, bias voltage)
+    #[arg(long, value_name = "CSV")]
+    pub telemetry: Option<String>,
+    /// dorado server log; repeat for rotated logs
+    #[arg(long = "dorado-log", value_name = "LOG")]
+    pub dorado_logs: Vec<String>,
+    /// Post-incident timeline figure
+    #[arg(long, value_name = "PNG")]
+    pub forensic: Option<String>,
+    /// Add the composite run health score panel
+    #[arg(long)]
+    pub health_score: bool,
+    /// Health sub-score weights, e.g. yield=2,qscore=1
+    #[arg(long, value_name = "SPEC")]
+    pub health_weights: Option<String>,
+    /// Planned run length in hours, for the yield sub-score
+    #[arg(long, value_name = "H", default_value_t = 72.0)]
+    pub run_hours: f64,
+    /// Health verdict as JSON (implies --health-score)
+    #[arg(long, value_name = "JSON")]
+    pub verdict_json: Option<String>,
+    /// Built-in flow cell baseline
+    #[arg(long, value_name = "NAME", conflicts_with_all = ["baseline_file", "learn_baseline"])]
+    pub baseline: Option<String>,
+    /// Baseline from a CSV file
+    #[arg(long, value_name = "CSV", conflicts_with = "learn_baseline")]
+    pub baseline_file: Option<String>,
+    /// Learn the baseline from the runs under a directory
+    #[arg(long, value_name = "RUNS_DIR")]
+    pub learn_baseline: Option<String>,
+    /// Save the learned baseline
+    #[arg(long, value_name = "CSV", requires = "learn_baseline")]
+    pub baseline_out: Option<String>,
+    /// Plot of the run against the baseline bands
+    #[arg(long, value_name = "PNG")]
+    pub baseline_plot: Option<String>,
+    /// Batch timings CSV of a known-good run, drawn faded behind every panel and scored against
+    #[arg(long, value_name = "CSV")]
+    pub reference: Option<String>,
+    /// Histograms of the selected metrics
+    #[arg(long, value_name = "PNG")]
+    pub histogram: Option<String>,
+    /// Histogram binning: auto, linear, log or equal-count
+    #[arg(long, value_parser = parse_binning, default_value = "auto")]
+    pub histogram_bins: Binning,
+    /// Density overlay bandwidth: auto or a positive number
+    #[arg(long, value_parser = parse_bandwidth, value_name = "auto|BW")]
+    pub kde: Option<Bandwidth>,
+    /// Distribution of mean Q-scores per batch (per read for a sequencing summary)
+    #[arg(long, value_name = "PNG")]
+    pub qscore_histogram: Option<String>,
+    /// Bin width of the Q-score histogram
+    #[arg(long, value_parser = parse_bin_width, value_name = "Q", default_value = "0.5")]
+    pub qscore_bin_width: Binning,
+    /// Pass/fail Q-score, e.g. 9 or 10: a line on the Q-score histogram and
+    /// the pass split reconciled with --minknow-report
+    #[arg(long, value_name = "Q")]
+    pub qscore_threshold: Option<f64>,
+    /// MinKNOW's run report JSON, whose yield totals are reconciled with the computed ones
+    #[arg(long, value_name = "JSON")]
+    pub minknow_report: Option<String>,
+    /// Difference, in percent, beyond which a reconciled total is flagged
+    #[arg(long, value_name = "PCT", default_value_t = as_parser::reconcile::DEFAULT_TOLERANCE)]
+    pub reconcile_tolerance: f64,
+    /// Write the reconciliation table to this CSV
+    #[arg(long, value_name = "CSV", requires = "minknow_report")]
+    pub reconcile_out: Option<String>,
+    /// Control charts of latency and Q-score
+    #[arg(long, value_name = "PNG")]
+    pub control_chart: Option<String>,
+    /// Control chart type: ewma or shewhart
+    #[arg(long, value_parser = parse_chart_kind, default_value = "ewma")]
+    pub control_chart_type: ChartKind,
+    /// Q-score calibration curves against alignment identity
+    #[arg(long, value_name = "PNG")]
+    pub calibration_plot: Option<String>,
+    /// Time bins of the calibration check
+    #[arg(long, value_name = "N", default_value_t = calibration::DEFAULT_BINS)]
+    pub calibration_bins: usize,
+    /// Per-read statistics by read length class, split at these lengths
+    #[arg(
+        long,
+        value_parser = parse_length_classes,
+        value_name = "LENGTHS",
+        num_args = 0..=1,
+        default_missing_value = lengthclass::DEFAULT_CLASSES
+    )]
+    pub length_classes: Option<LengthClasses>,
+    /// Cumulative yield stacked by read length class (default classes without --length-classes)
+    #[arg(long, value_name = "PNG")]
+    pub length_class_plot: Option<String>,
+    /// Read IDs worth re-basecalling
+    #[arg(long, value_name = "TXT")]
+    pub rebasecall_reads: Option<String>,
+    /// Q-score below which reads are re-basecalled (default: the warning threshold)
+    #[arg(long, value_name = "Q", requires = "rebasecall_reads")]
+    pub rebasecall_qscore: Option<f64>,
+    /// Shell script with the re-basecalling commands
+    #[arg(long, value_name = "SH", requires = "rebasecall_reads")]
+    pub rebasecall_commands: Option<String>,
+    /// Read IDs matching every --filter
+    #[arg(long, value_name = "TXT")]
+    pub export_reads: Option<String>,
+    /// Read filter expression for --export-reads
+    #[arg(long = "filter", value_name = "EXPR", requires = "export_reads")]
+    pub filters: Vec<String>,
+    /// What-if adaptive sampling rule: NAME:FILTERS[,unblock=N]
+    #[arg(long = "simulate-rule", value_name = "SPEC")]
+    pub simulate_rules: Vec<String>,
+    /// Batch sizes for the basecaller capacity table
+    #[arg(long, value_name = "LIST")]
+    pub what_if_batch: Option<String>,
+    /// GPU counts for the capacity table
+    #[arg(
+        long,
+        value_name = "LIST",
+        default_value = "1",
+        requires = "what_if_batch"
+    )]
+    pub what_if_gpus: String,
+}
+
+fn parse_format(name: &str) -> Result<Format, String> {
+    Format::parse(name).ok_or_else(|| format!("Unknown format '{}' (png or svg)", name))
+}
+
+fn parse_period(name: &str) -> Result<Period, String> {
+    Period::parse(name).ok_or_else(|| format!("Unknown period '{}' (hour or day)", name))
+}
+
+fn parse_time_format(name: &str) -> Result<TimeFormat, String> {
+    TimeFormat::parse(name).ok_or_else(|| {
+        format!(
+            "Unknown time format '{}' (auto, unix, unix-ms, iso8601 or minknow)",
+            name
+        )
+    })
+}
+
+fn parse_smooth_window(spec: &str) -> Result<Window, String> {
+    Window::parse(spec).ok_or_else(|| {
+        format!(
+            "Invalid smoothing window '{}' (batches such as 100, or a duration such as 30s, 5m or 1h)",
+            spec
+        )
+    })
+}
+
+fn parse_center(name: &str) -> Result<Center, String> {
+    Center::parse(name)
+        .ok_or_else(|| format!("Unknown statistic '{}' (mean, median or trimmed[:F])", name))
+}
+
+fn parse_language(name: &str) -> Result<Language, String> {
+    Language::parse(name).ok_or_else(|| format!("Unsupported language '{}' (en, de or fr)", name))
+}
+
+fn parse_mode(name: &str) -> Result<Mode, String> {
+    Mode::parse(name).ok_or_else(|| {
+        let modes: Vec<&str> = Mode::ALL.iter().map(|m| m.label()).collect();
+        format!("Unknown mode '{}' ({})", name, modes.join(", "))
+    })
+}
+
+fn parse_sampling(value: &str) -> Result<Sampling, String> {
+    Sampling::parse(value).ok_or_else(|| format!("Invalid sample '{}' (e.g. 1% or 10000)", value))
+}
+
+fn parse_quantiles(name: &str) -> Result<Method, String> {
+    Method::parse(name).ok_or_else(|| format!("Unknown quantile method '{}' (exact or p2)", name))
+}
+
+fn parse_preset(name: &str) -> Result<Preset, String> {
+    Preset::parse(name).ok_or_else(|| format!("Unknown preset '{}' (ultralong)", name))
+}
+
+fn parse_library(name: &str) -> Result<Library, String> {
+    Library::parse(name).ok_or_else(|| format!("Unknown library '{}' (dna, cdna or rna)", name))
+}
+
+fn parse_profile(name: &str) -> Result<Profile, String> {
+    Profile::parse(name).map_err(|e| e.to_string())
+}
+
+fn parse_binning(name: &str) -> Result<Binning, String> {
+    Binning::parse(name).ok_or_else(|| {
+        format!(
+            "Unknown binning '{}' (auto, linear, log, equal-count or a bin width)",
+            name
+        )
+    })
+}
+
+fn parse_bin_width(value: &str) -> Result<Binning, String> {
+    match Binning::parse(value) {
+        Some(width @ Binning::Width(_)) => Ok(width),
+        _ => Err(format!("Invalid bin width '{}' (a positive number)", value)),
+    }
+}
+
+fn parse_bandwidth(value: &str) -> Result<Bandwidth, String> {
+    Bandwidth::parse(value).ok_or_else(|| {
+        format!(
+            "Invalid KDE bandwidth '{}' (auto or a positive number)",
+            value
+        )
+    })
+}
+
+fn parse_chart_kind(name: &str) -> Result<ChartKind, String> {
+    ChartKind::parse(name)
+        .ok_or_else(|| format!("Unknown control chart '{}' (ewma or shewhart)", name))
+}
+
+fn parse_clip(spec: &str) -> Result<Clip, String> {
+    Clip::parse(spec).ok_or_else(|| {
+        format!(
+            "Invalid clip percentiles '{}' (LOW:HIGH within 0 to 100, or none)",
+            spec
+        )
+    })
+}
+
+fn parse_y_range(spec: &str) -> Result<YRange, String> {
+    YRange::parse(spec)
+        .ok_or_else(|| format!("Invalid y range '{}' (NAME=MIN:MAX with MIN < MAX)", spec))
+}
+
+fn parse_length_classes(spec: &str) -> Result<LengthClasses, String> {
+    LengthClasses::parse(spec).map_err(|e| e.to_string())
+}
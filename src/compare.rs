@@ -0,0 +1,181 @@
+// Several runs overlaid on the standard panels, aligned on elapsed time
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use plotters::style::SizeDesc;
+use std::error::Error;
+
+use crate::decimate;
+use crate::model::{Field, RunData};
+use crate::plot::PlotOptions;
+use crate::render::{self, Figure};
+
+/// Height in pixels of each comparison panel
+const PANEL_HEIGHT: u32 = 400;
+/// Series colours, one per run in input order (Okabe–Ito, distinguishable with colour blindness)
+const RUN_COLORS: [RGBColor; 8] = [
+    RGBColor(0, 114, 178),
+    RGBColor(213, 94, 0),
+    RGBColor(0, 158, 115),
+    RGBColor(204, 121, 167),
+    RGBColor(230, 159, 0),
+    RGBColor(86, 180, 233),
+    RGBColor(240, 228, 66),
+    RGBColor(0, 0, 0),
+];
+
+/// One run of a comparison
+#[derive(Debug, Clone)]
+pub struct Run {
+    pub label: String, // Legend entry, e.g. the input's file name
+    pub data: RunData, // Parsed rows
+}
+
+/// Colour of the `i`-th run; more runs than colours reuse them in order
+pub fn run_color(i: usize) -> RGBColor {
+    RUN_COLORS[i % RUN_COLORS.len()]
+}
+
+/// Plots every field once, with one series per run over the hours elapsed
+/// since that run's first batch
+pub fn plot(
+    runs: &[Run],
+    fields: &[Field],
+    output_path: &str,
+    options: PlotOptions,
+) -> Result<(), Box<dyn Error>> {
+    if let Some(run) = runs.iter().find(|run| run.data.is_empty()) {
+        return Err(format!("Run {} has no records to compare", run.label).into());
+    }
+    render::to_file(
+        &Comparison {
+            runs,
+            fields,
+            options,
+        },
+        output_path,
+    )
+}
+
+/// Hours since the run's first batch, per row
+fn elapsed_hours(data: &RunData) -> Vec<f64> {
+    let first = data.time_range().map_or(0.0, |(first, _)| first);
+    data.time.iter().map(|t| (t - first) / 3600.0).collect()
+}
+
+/// Stacked panels, one per field, with a legend naming the runs
+struct Comparison<'a> {
+    runs: &'a [Run],
+    fields: &'a [Field],
+    options: PlotOptions,
+}
+
+impl Figure for Comparison<'_> {
+    fn size(&self) -> (u32, u32) {
+        (self.options.size.0, PANEL_HEIGHT * self.fields.len() as u32)
+    }
+
+    fn draw<DB: DrawingBackend>(&self, root: &DrawingArea<DB, Shift>) -> Result<(), Box<dyn Error>>
+    where
+        DB::ErrorType: 'static,
+    {
+        root.fill(&self.options.theme.background())?;
+        let foreground = self.options.theme.foreground();
+        let text = |size: u32| ("sans-serif", size).into_font().color(&foreground);
+        let hours: Vec<Vec<f64>> = self.runs.iter().map(|r| elapsed_hours(&r.data)).collect();
+        let max_hours = hours
+            .iter()
+            .filter_map(|h| h.last().copied())
+            .fold(0.0, f64::max)
+            .max(f64::EPSILON);
+
+        let panels = root.split_evenly((self.fields.len(), 1));
+        for (area, field) in panels.iter().zip(self.fields) {
+            // One y range for all runs, so the series are directly comparable
+            let axis = field.y_axis;
+            let (min_val, max_val) = self
+                .runs
+                .iter()
+                .map(|run| axis.bounds((field.column)(&run.data), self.options.clip))
+                .fold(
+                    (f64::INFINITY, f64::NEG_INFINITY),
+                    |(lo, hi), (min, max)| (lo.min(min), hi.max(max)),
+                );
+            if !min_val.is_finite() {
+                continue;
+            }
+            let max_val = if max_val > min_val {
+                max_val
+            } else {
+                min_val + 1.0
+            };
+
+            let mut builder = ChartBuilder::on(area);
+            if self.options.text {
+                builder.caption(self.options.language.tr(field.title), text(20));
+            }
+            let mut chart = builder
+                .margin(20)
+                .x_label_area_size(50)
+                .y_label_area_size(100)
+                .build_cartesian_2d(0.0..max_hours, min_val..max_val)?;
+            if self.options.text {
+                // plotters' own default label size, now that the labels are recoloured
+                let label_size = 12i32.percent().max(12).in_pixels(area) as u32;
+                let y_precision = field
+                    .precision
+                    .fixed_for(axis.value(min_val).abs().max(axis.value(max_val).abs()));
+                let y_label = |v: &f64| y_precision.format(axis.value(*v));
+                chart
+                    .configure_mesh()
+                    .x_labels(5)
+                    .y_labels(5)
+                    .y_label_formatter(&y_label)
+                    .x_label_style(text(label_size))
+                    .y_label_style(text(label_size))
+                    .axis_desc_style(text(label_size))
+                    .axis_style(foreground)
+                    .bold_line_style(foreground.mix(0.2))
+                    .light_line_style(foreground.mix(0.1))
+                    .x_desc("Elapsed Hours")
+                    .draw()?;
+            } else {
+                chart.configure_mesh().x_labels(0).y_labels(0).draw()?;
+            }
+
+            for (i, (run, times)) in self.runs.iter().zip(&hours).enumerate() {
+                let values = (field.column)(&run.data);
+                if values.len() != times.len() {
+                    continue;
+                }
+                let kept = match self.options.max_points {
+                    Some(max_points) if self.options.keep_extremes => {
+                        decimate::with_extremes(times, values, max_points, &[])
+                    }
+                    Some(max_points) => decimate::lttb(times, values, max_points),
+                    None => (0..times.len()).collect(),
+                };
+                let color = run_color(i);
+                chart
+                    .draw_series(LineSeries::new(
+                        kept.iter()
+                            .map(|&k| (times[k], axis.position(values[k])))
+                            .filter(|(_, p)| p.is_finite())
+                            .map(|(t, p)| (t, p.clamp(min_val, max_val))),
+                        color,
+                    ))?
+                    .label(run.label.as_str())
+                    .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color));
+            }
+            if self.options.text {
+                chart
+                    .configure_series_labels()
+                    .position(SeriesLabelPosition::UpperRight)
+                    .background_style(WHITE.mix(0.8))
+                    .border_style(BLACK)
+                    .draw()?;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,212 @@
+// Figures composed from panel specs given on the command line
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use plotters::style::SizeDesc;
+use std::error::Error;
+
+use crate::histogram::{self, Binning};
+use crate::model::Field;
+use crate::model::RunData;
+use crate::plot::PlotOptions;
+use crate::reduce;
+use crate::render::{self, Figure};
+
+/// Seconds summed into each point of a `rate:` panel
+const RATE_WINDOW: f64 = 60.0;
+/// Height in pixels of each composed panel
+const PANEL_HEIGHT: u32 = 400;
+
+/// One requested panel
+#[derive(Debug, Clone, PartialEq)]
+pub enum Panel {
+    Series(String),     // Field title or column name over time
+    Rate(String),       // Column summed over the preceding minute
+    Cumulative(String), // Running total of a column
+    Hist(String),       // Distribution of a column
+}
+
+impl Panel {
+    /// Parses `NAME`, `rate:COLUMN`, `cumulative:COLUMN`, `hist:COLUMN` or `yield`
+    pub fn parse(spec: &str) -> Result<Self, Box<dyn Error>> {
+        let spec = spec.trim();
+        let panel = match spec.split_once(':') {
+            Some(("rate", column)) => Panel::Rate(column.trim().to_string()),
+            Some(("cumulative", column)) => Panel::Cumulative(column.trim().to_string()),
+            Some(("hist", column)) => Panel::Hist(column.trim().to_string()),
+            Some((kind, _)) => {
+                return Err(
+                    format!("Unknown panel kind '{}' (rate, cumulative or hist)", kind).into(),
+                );
+            }
+            None if spec.eq_ignore_ascii_case("yield") => Panel::Cumulative("bases".into()),
+            None => Panel::Series(spec.to_string()),
+        };
+        Ok(panel)
+    }
+
+    /// Panel caption
+    fn title(&self) -> String {
+        match self {
+            Panel::Series(name) => name.clone(),
+            Panel::Rate(column) => format!("{} per minute", column),
+            Panel::Cumulative(column) if column == "bases" => "Cumulative yield".into(),
+            Panel::Cumulative(column) => format!("Cumulative {}", column),
+            Panel::Hist(column) => format!("{} distribution", column),
+        }
+    }
+}
+
+/// Column by name, with a readable error for unknown names
+fn column<'a>(data: &'a RunData, name: &str) -> Result<&'a [f64], Box<dyn Error>> {
+    data.column(name)
+        .filter(|v| !v.is_empty())
+        .ok_or_else(|| format!("No column '{}' for --panel", name).into())
+}
+
+/// Sum of `values` over the `RATE_WINDOW` seconds up to each row
+fn rate(times: &[f64], values: &[f64]) -> Vec<f64> {
+    let mut prefix = vec![0.0];
+    prefix.extend(values.iter().scan(0.0, |sum, &v| {
+        *sum += v;
+        Some(*sum)
+    }));
+    times
+        .iter()
+        .enumerate()
+        .map(|(i, &t)| prefix[i + 1] - prefix[times.partition_point(|&s| s <= t - RATE_WINDOW)])
+        .collect()
+}
+
+/// Renders exactly the requested panels, stacked, into one figure.
+///
+/// A series panel naming a plotted field (by title, any case) is drawn like
+/// in the standard figure, thresholds and strip included; other names are
+/// looked up as input columns.
+pub fn plot(
+    data: &RunData,
+    fields: &[Field],
+    panels: &[Panel],
+    output_path: &str,
+    options: PlotOptions,
+) -> Result<(), Box<dyn Error>> {
+    if data.is_empty() {
+        return Err("No records to plot".into());
+    }
+    let figure = Composed {
+        data,
+        fields,
+        panels,
+        options,
+    };
+    render::to_file(&figure, output_path)
+}
+
+/// Panels from command-line specs, stacked top to bottom
+struct Composed<'a> {
+    data: &'a RunData,
+    fields: &'a [Field],
+    panels: &'a [Panel],
+    options: PlotOptions,
+}
+
+impl Figure for Composed<'_> {
+    fn size(&self) -> (u32, u32) {
+        (self.options.size.0, PANEL_HEIGHT * self.panels.len() as u32)
+    }
+
+    fn draw<DB: DrawingBackend>(&self, root: &DrawingArea<DB, Shift>) -> Result<(), Box<dyn Error>>
+    where
+        DB::ErrorType: 'static,
+    {
+        draw(root, self.data, self.fields, self.panels, self.options)?;
+        crate::plot::draw_watermark(root, self.data, self.options)
+    }
+}
+
+/// Draws the panels top to bottom onto `root`
+fn draw<DB: DrawingBackend>(
+    root: &DrawingArea<DB, Shift>,
+    data: &RunData,
+    fields: &[Field],
+    panels: &[Panel],
+    options: PlotOptions,
+) -> Result<(), Box<dyn Error>>
+where
+    DB::ErrorType: 'static,
+{
+    root.fill(&options.theme.background())?;
+    let areas = root.split_evenly((panels.len(), 1));
+
+    for (area, panel) in areas.iter().zip(panels) {
+        let title = panel.title();
+        match panel {
+            Panel::Series(name) => {
+                match fields.iter().find(|f| f.title.eq_ignore_ascii_case(name)) {
+                    Some(field) => crate::plot::draw_panel(area, data, field, options)?,
+                    None => draw_series(area, &title, &data.time, column(data, name)?, options)?,
+                }
+            }
+            Panel::Rate(name) => {
+                let values = rate(&data.time, column(data, name)?);
+                draw_series(area, &title, &data.time, &values, options)?;
+            }
+            Panel::Cumulative(name) => {
+                let values = reduce::cumulative_sum(column(data, name)?);
+                draw_series(area, &title, &data.time, &values, options)?;
+            }
+            Panel::Hist(name) => {
+                histogram::draw(area, &title, column(data, name)?, Binning::Auto, None, None)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Draws a plain time series panel
+fn draw_series<DB: DrawingBackend>(
+    area: &DrawingArea<DB, Shift>,
+    title: &str,
+    times: &[f64],
+    values: &[f64],
+    options: PlotOptions,
+) -> Result<(), Box<dyn Error>>
+where
+    DB::ErrorType: 'static,
+{
+    let foreground = options.theme.foreground();
+    let text = |size: u32| ("sans-serif", size).into_font().color(&foreground);
+    // plotters' own default label size, now that the labels are recoloured
+    let label_size = 12i32.percent().max(12).in_pixels(area) as u32;
+    let (low, high) = values
+        .iter()
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |acc, &v| {
+            (acc.0.min(v), acc.1.max(v))
+        });
+    let pad = ((high - low) * 0.05).max(f64::EPSILON);
+    let (min_time, max_time) = (times[0], times[times.len() - 1]);
+
+    let mut chart = ChartBuilder::on(area)
+        .caption(title, text(20))
+        .margin(20)
+        .x_label_area_size(50)
+        .y_label_area_size(100)
+        .build_cartesian_2d(min_time..max_time, low - pad..high + pad)?;
+    chart
+        .configure_mesh()
+        .x_labels(5)
+        .y_labels(5)
+        .x_label_style(text(label_size))
+        .y_label_style(text(label_size))
+        .axis_desc_style(text(label_size))
+        .axis_style(foreground)
+        .bold_line_style(foreground.mix(0.2))
+        .light_line_style(foreground.mix(0.1))
+        .x_desc("Batch Time")
+        .y_desc(title)
+        .draw()?;
+    chart.draw_series(LineSeries::new(
+        times.iter().copied().zip(values.iter().copied()),
+        options.palette.raw(),
+    ))?;
+    Ok(())
+}
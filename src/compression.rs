@@ -0,0 +1,69 @@
+// Transparent decompression of gzip- and zstd-compressed inputs
+use std::error::Error;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+
+use flate2::read::MultiGzDecoder;
+
+/// Leading bytes of a gzip member
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+/// Leading bytes of a zstd frame
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// Compression of an input stream
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Compression {
+    None, // Plain text
+    Gzip, // `.gz`, possibly several concatenated members
+    Zstd, // `.zst`
+}
+
+impl Compression {
+    /// Compression implied by a file name's extension
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("gz") => Compression::Gzip,
+            Some(ext) if ext.eq_ignore_ascii_case("zst") => Compression::Zstd,
+            _ => Compression::None,
+        }
+    }
+
+    /// Compression identified by the first bytes of a stream
+    fn sniff(head: &[u8]) -> Self {
+        if head.starts_with(&GZIP_MAGIC) {
+            Compression::Gzip
+        } else if head.starts_with(&ZSTD_MAGIC) {
+            Compression::Zstd
+        } else {
+            Compression::None
+        }
+    }
+}
+
+/// Whether a file looks like a batch CSV: `.csv`, `.csv.gz` or `.csv.zst`
+pub fn is_csv(path: &Path) -> bool {
+    let stem = match Compression::from_path(path) {
+        Compression::None => path.to_path_buf(),
+        _ => path.with_extension(""),
+    };
+    stem.extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("csv"))
+}
+
+/// Wraps `reader` in a streaming decoder when it starts with gzip or zstd
+/// magic bytes; anything else passes through unchanged.
+///
+/// The magic bytes decide rather than the extension, so rotated logs that
+/// kept a `.csv` name still decode, and a `.gz` file that is really plain text
+/// is read as such.
+pub fn decompress<R: Read + Send + 'static>(
+    reader: R,
+) -> Result<Box<dyn Read + Send>, Box<dyn Error>> {
+    let mut reader = BufReader::new(reader);
+    let compression = Compression::sniff(reader.fill_buf()?);
+    Ok(match compression {
+        Compression::None => Box::new(reader),
+        Compression::Gzip => Box::new(MultiGzDecoder::new(reader)),
+        Compression::Zstd => Box::new(zstd::Decoder::with_buffer(reader)?),
+    })
+}
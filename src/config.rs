@@ -0,0 +1,488 @@
+// Configuration files: command-line flags kept in a TOML or YAML file
+//
+// Each key is a long flag name (`smooth = "5m"`, `theme: dark`), with `_`
+// and `-` interchangeable. `true` turns a switch on, `false` leaves it off,
+// and an array repeats the flag once per item. `input`, `inputs` and
+// `output` give the positional arguments. Keys under a `[plot]` table (TOML)
+// or a `plot:` mapping (YAML) only apply to that subcommand; keys outside
+// them are skipped by subcommands that do not take the flag. A key no
+// subcommand takes is an error, so typos are not silently ignored.
+//
+// The file's flags are placed in front of the command line's own, so a flag
+// given on the command line replaces the file's value; repeatable flags
+// (such as `--panel`) collect both. Positional arguments given on the
+// command line replace the file's (see `positionals`).
+use std::error::Error;
+use std::ffi::OsString;
+use std::fs;
+
+/// Flag that names the configuration file
+const FLAG: &str = "--config";
+/// Config keys standing for the input positional, whichever name it has
+const INPUT_KEYS: [&str; 2] = ["input", "inputs"];
+
+/// The arguments of one subcommand, as far as `expand` needs them
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Arguments {
+    pub subcommand: String,         // Subcommand name
+    pub flags: Vec<(String, bool)>, // Long flags, and whether each reads the next argument as its value
+    pub positionals: Vec<String>,   // Positional arguments, in order
+}
+
+impl Arguments {
+    /// Whether `--name` reads the next argument; `None` for unknown flags
+    fn flag(&self, name: &str) -> Option<bool> {
+        self.flags
+            .iter()
+            .find(|(flag, _)| flag == name)
+            .map(|&(_, value)| value)
+    }
+
+    /// Index of the positional a config key gives
+    fn slot(&self, key: &str) -> Option<usize> {
+        self.positionals
+            .iter()
+            .position(|p| p == key || (INPUT_KEYS.contains(&key) && INPUT_KEYS.contains(&&**p)))
+    }
+
+    /// Whether a config key names one of this subcommand's arguments
+    fn knows(&self, key: &str) -> bool {
+        self.flag(&key.replace('_', "-")).is_some() || self.slot(key).is_some()
+    }
+}
+
+/// One setting: the subcommand it is scoped to, its key and its values
+#[derive(Debug, Clone, PartialEq)]
+struct Entry {
+    section: Option<String>,
+    key: String,
+    values: Vec<String>,
+}
+
+/// `args` with the settings of the file named by `--config` merged in;
+/// unchanged without `--config`. `commands` describes every subcommand.
+pub fn expand(
+    args: Vec<OsString>,
+    commands: &[Arguments],
+) -> Result<Vec<OsString>, Box<dyn Error>> {
+    let Some(path) = config_path(&args) else {
+        return Ok(args);
+    };
+    let text = fs::read_to_string(&path)
+        .map_err(|e| format!("Cannot read config file {}: {}", path, e))?;
+    let yaml = path.ends_with(".yaml") || path.ends_with(".yml");
+    let entries = if yaml {
+        parse_yaml(&text)
+    } else {
+        parse_toml(&text)
+    }
+    .map_err(|e| format!("{}: {}", path, e))?;
+    merge(args, commands, &entries).map_err(|e| format!("{}: {}", path, e).into())
+}
+
+/// `args` with `entries` merged in: their flags right after the subcommand,
+/// then the command line's own arguments, then the positionals
+fn merge(
+    args: Vec<OsString>,
+    commands: &[Arguments],
+    entries: &[Entry],
+) -> Result<Vec<OsString>, String> {
+    // Every key is checked, not only those of this subcommand
+    for entry in entries {
+        match &entry.section {
+            Some(section) => {
+                let scoped = commands
+                    .iter()
+                    .find(|c| &c.subcommand == section)
+                    .ok_or_else(|| format!("unknown section '{}'", section))?;
+                if !scoped.knows(&entry.key) {
+                    return Err(format!(
+                        "unknown key '{}' in section '{}'",
+                        entry.key, section
+                    ));
+                }
+            }
+            None if !commands.iter().any(|c| c.knows(&entry.key)) => {
+                return Err(format!("unknown key '{}'", entry.key));
+            }
+            None => {}
+        }
+    }
+    let Some(command) = args
+        .get(1)
+        .and_then(|a| a.to_str())
+        .and_then(|name| commands.iter().find(|c| c.subcommand == name))
+    else {
+        return Ok(args);
+    };
+
+    let applicable: Vec<&Entry> = entries
+        .iter()
+        .filter(|e| e.section.as_deref().is_none_or(|s| s == command.subcommand))
+        .filter(|e| command.knows(&e.key))
+        .collect();
+    let mut expanded = args[..2].to_vec();
+    for entry in applicable.iter().filter(|e| command.slot(&e.key).is_none()) {
+        let flag = format!("--{}", entry.key.replace('_', "-"));
+        for value in &entry.values {
+            match value.as_str() {
+                "true" => expanded.push(flag.clone().into()),
+                "false" => {}
+                _ => expanded.push(format!("{}={}", flag, value).into()),
+            }
+        }
+    }
+    let mut configured = vec![Vec::new(); command.positionals.len()];
+    for entry in &applicable {
+        if let Some(slot) = command.slot(&entry.key) {
+            configured[slot].extend(entry.values.iter().map(OsString::from));
+        }
+    }
+
+    let (rest, given, separator) = split_positionals(&args[2..], command);
+    expanded.extend(rest);
+    if separator {
+        expanded.push("--".into());
+    }
+    expanded.extend(positionals(configured, given));
+    Ok(expanded)
+}
+
+/// The command line's arguments apart from its positionals, the positionals,
+/// and whether they followed a `--`
+fn split_positionals(
+    args: &[OsString],
+    command: &Arguments,
+) -> (Vec<OsString>, Vec<OsString>, bool) {
+    let (mut rest, mut given) = (Vec::new(), Vec::new());
+    let mut args = args.iter().peekable();
+    while let Some(arg) = args.next() {
+        let text = arg.to_string_lossy();
+        if text == "--" {
+            given.extend(args.cloned());
+            return (rest, given, true);
+        }
+        rest.push(arg.clone());
+        if let Some(name) = text.strip_prefix("--") {
+            // A value-taking flag reads the next argument, unless it looks like a flag
+            let reads_next = !name.contains('=') && command.flag(name).unwrap_or(false);
+            if reads_next
+                && let Some(value) = args.next_if(|v| !v.to_string_lossy().starts_with('-'))
+            {
+                rest.push(value.clone());
+            }
+        } else if !text.starts_with('-') || text == "-" {
+            given.push(rest.pop().unwrap_or_default());
+        }
+    }
+    (rest, given, false)
+}
+
+/// Positionals in order: `configured` holds the file's values of each,
+/// `given` the command line's, which replace them.
+///
+/// The command line fills the positionals the file lacks when it gives
+/// exactly that many, e.g. the input of a config naming only the output;
+/// otherwise it fills them from the first on.
+fn positionals(configured: Vec<Vec<OsString>>, given: Vec<OsString>) -> Vec<OsString> {
+    if given.is_empty() {
+        return configured.concat();
+    }
+    if given.len() >= configured.len() {
+        return given;
+    }
+    let missing = configured.iter().filter(|v| v.is_empty()).count();
+    let mut given = given.into_iter();
+    if given.len() == missing {
+        return configured
+            .into_iter()
+            .flat_map(|values| match values.is_empty() {
+                true => given.next().into_iter().collect(),
+                false => values,
+            })
+            .collect();
+    }
+    let leading = given.len();
+    given
+        .chain(configured.into_iter().skip(leading).flatten())
+        .collect()
+}
+
+/// Path given to `--config FILE` or `--config=FILE`
+fn config_path(args: &[OsString]) -> Option<String> {
+    let args: Vec<&str> = args.iter().filter_map(|a| a.to_str()).collect();
+    args.iter().enumerate().find_map(|(i, arg)| {
+        if *arg == FLAG {
+            args.get(i + 1).map(|p| p.to_string())
+        } else {
+            arg.strip_prefix(FLAG)?
+                .strip_prefix('=')
+                .map(str::to_string)
+        }
+    })
+}
+
+/// Parses `key = value` lines and `[section]` tables; values are strings,
+/// numbers, booleans or one-line arrays of them
+fn parse_toml(text: &str) -> Result<Vec<Entry>, String> {
+    let mut entries = Vec::new();
+    let mut section = None;
+    for (number, line) in text.lines().enumerate() {
+        let line = strip_comment(line).trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            section = Some(name.trim().to_string());
+            continue;
+        }
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| format!("line {}: expected key = value", number + 1))?;
+        entries.push(Entry {
+            section: section.clone(),
+            key: key.trim().to_string(),
+            values: values(value.trim()),
+        });
+    }
+    Ok(entries)
+}
+
+/// Parses `key: value` lines, `- item` lists under a key, and one level of
+/// nesting under a subcommand name
+fn parse_yaml(text: &str) -> Result<Vec<Entry>, String> {
+    let mut entries: Vec<Entry> = Vec::new();
+    let mut section: Option<(String, usize)> = None; // Name and indent of its keys' parent
+    for (number, raw) in text.lines().enumerate() {
+        let line = strip_comment(raw);
+        if line.trim().is_empty() || line.trim() == "---" {
+            continue;
+        }
+        let indent = line.len() - line.trim_start().len();
+        let line = line.trim();
+        if section.as_ref().is_some_and(|&(_, at)| indent <= at) {
+            section = None;
+        }
+
+        if let Some(item) = line.strip_prefix("- ") {
+            let entry = entries
+                .last_mut()
+                .ok_or_else(|| format!("line {}: list item without a key", number + 1))?;
+            entry.values.push(unquote(item.trim()));
+            continue;
+        }
+        let (key, value) = line
+            .split_once(':')
+            .ok_or_else(|| format!("line {}: expected key: value", number + 1))?;
+        let (key, value) = (key.trim().to_string(), value.trim());
+        if value.is_empty() && section.is_none() && !is_flag_list(text, number) {
+            section = Some((key, indent));
+            continue;
+        }
+        entries.push(Entry {
+            section: section.as_ref().map(|(name, _)| name.clone()),
+            key,
+            values: if value.is_empty() {
+                Vec::new()
+            } else {
+                values(value)
+            },
+        });
+    }
+    Ok(entries)
+}
+
+/// Whether the key on line `number` is followed by `- item` lines (a list)
+/// rather than nested keys (a subcommand section)
+fn is_flag_list(text: &str, number: usize) -> bool {
+    text.lines()
+        .skip(number + 1)
+        .map(strip_comment)
+        .find(|l| !l.trim().is_empty())
+        .is_some_and(|l| l.trim().starts_with("- "))
+}
+
+/// A scalar or a `[a, b]` array as a list of plain strings; commas inside
+/// quotes do not split items
+fn values(value: &str) -> Vec<String> {
+    let Some(items) = value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) else {
+        return vec![unquote(value)];
+    };
+    let mut values = Vec::new();
+    let (mut start, mut quote) = (0, None);
+    for (i, c) in items.char_indices() {
+        match (c, quote) {
+            ('"' | '\'', None) => quote = Some(c),
+            (c, Some(q)) if c == q => quote = None,
+            (',', None) => {
+                values.push(&items[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    values.push(&items[start..]);
+    values
+        .into_iter()
+        .map(|item| item.trim())
+        .filter(|item| !item.is_empty())
+        .map(unquote)
+        .collect()
+}
+
+fn unquote(value: &str) -> String {
+    value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .or_else(|| value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')))
+        .unwrap_or(value)
+        .to_string()
+}
+
+/// The line up to a `#` that is not inside a quoted string
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    for (i, c) in line.char_indices() {
+        match (c, quote) {
+            ('"' | '\'', None) => quote = Some(c),
+            (c, Some(q)) if c == q => quote = None,
+            ('#', None) => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commands() -> Vec<Arguments> {
+        let flags = |names: &[(&str, bool)]| {
+            names
+                .iter()
+                .map(|&(n, v)| (n.to_string(), v))
+                .chain([("config".to_string(), true)])
+                .collect()
+        };
+        vec![
+            Arguments {
+                subcommand: "plot".into(),
+                flags: flags(&[("theme", true), ("smooth", true), ("redact", false)]),
+                positionals: vec!["inputs".into(), "output".into()],
+            },
+            Arguments {
+                subcommand: "stats".into(),
+                flags: flags(&[("redact", false), ("json", true)]),
+                positionals: vec!["input".into()],
+            },
+        ]
+    }
+
+    fn expand_toml(config: &str, args: &[&str]) -> Result<Vec<String>, String> {
+        let args = std::iter::once("as_parser")
+            .chain(args.iter().copied())
+            .map(OsString::from)
+            .collect();
+        let entries = parse_toml(config)?;
+        Ok(merge(args, &commands(), &entries)?
+            .into_iter()
+            .skip(1)
+            .map(|a| a.to_string_lossy().into_owned())
+            .collect())
+    }
+
+    #[test]
+    fn toml_scalars_arrays_sections_and_booleans() {
+        let entries = parse_toml(
+            "# Defaults\ntheme = \"dark\" # trailing\nsmooth = 5\n\n[plot]\nredact = true\ninputs = ['a.csv', \"b, c.csv\"]\n",
+        )
+        .unwrap();
+        let entry = |key: &str| entries.iter().find(|e| e.key == key).unwrap();
+        assert_eq!(entry("theme").values, ["dark"]);
+        assert_eq!(entry("theme").section, None);
+        assert_eq!(entry("smooth").values, ["5"]);
+        assert_eq!(entry("redact").section.as_deref(), Some("plot"));
+        assert_eq!(entry("redact").values, ["true"]);
+        assert_eq!(entry("inputs").values, ["a.csv", "b, c.csv"]);
+        assert!(parse_toml("theme dark").is_err());
+    }
+
+    #[test]
+    fn yaml_scalars_lists_sections_and_quoting() {
+        let entries = parse_yaml(
+            "---\ntheme: 'dark # not a comment'\ninputs:\n  - a.csv\n  - \"b.csv\"\nplot:\n  smooth: [5, \"1,2\"]\n  redact: false\nlisten: 127.0.0.1:9184\n",
+        )
+        .unwrap();
+        let entry = |key: &str| entries.iter().find(|e| e.key == key).unwrap();
+        assert_eq!(entry("theme").values, ["dark # not a comment"]);
+        assert_eq!(entry("inputs").values, ["a.csv", "b.csv"]);
+        assert_eq!(entry("inputs").section, None);
+        assert_eq!(entry("smooth").section.as_deref(), Some("plot"));
+        assert_eq!(entry("smooth").values, ["5", "1,2"]);
+        assert_eq!(entry("redact").values, ["false"]);
+        assert_eq!(entry("listen").section, None);
+        assert_eq!(entry("listen").values, ["127.0.0.1:9184"]);
+        assert!(parse_yaml("- orphan").is_err());
+    }
+
+    #[test]
+    fn flags_go_before_the_command_line_and_booleans_toggle() {
+        let args = expand_toml(
+            "theme = \"dark\"\nredact = false\njson = \"s.json\"\n[plot]\nredact = true\n",
+            &["plot", "--theme", "light", "a.csv", "out.png"],
+        )
+        .unwrap();
+        assert_eq!(
+            args,
+            [
+                "plot",
+                "--theme=dark",
+                "--redact",
+                "--theme",
+                "light",
+                "a.csv",
+                "out.png"
+            ]
+        );
+    }
+
+    #[test]
+    fn config_output_with_command_line_input() {
+        let args = expand_toml(
+            "output = \"out.png\"\n",
+            &["plot", "--smooth", "3", "run.csv"],
+        );
+        assert_eq!(
+            args.unwrap(),
+            ["plot", "--smooth", "3", "run.csv", "out.png"]
+        );
+    }
+
+    #[test]
+    fn command_line_positionals_replace_config_input() {
+        let config = "input = \"config.csv\"\noutput = \"config.png\"\n";
+        let args = expand_toml(config, &["plot", "a.csv", "b.csv", "out.png"]).unwrap();
+        assert_eq!(args, ["plot", "a.csv", "b.csv", "out.png"]);
+        let args = expand_toml(config, &["plot", "run.csv"]).unwrap();
+        assert_eq!(args, ["plot", "run.csv", "config.png"]);
+        let args = expand_toml("input = \"config.csv\"\n", &["plot", "out.png"]).unwrap();
+        assert_eq!(args, ["plot", "config.csv", "out.png"]);
+        let args = expand_toml(config, &["stats", "--json", "s.json", "run.csv"]).unwrap();
+        assert_eq!(args, ["stats", "--json", "s.json", "run.csv"]);
+        let args = expand_toml(config, &["stats"]).unwrap();
+        assert_eq!(args, ["stats", "config.csv"]);
+    }
+
+    #[test]
+    fn unknown_keys_and_sections_are_errors() {
+        let err = expand_toml("smoth = 5\n", &["plot"]).unwrap_err();
+        assert!(err.contains("'smoth'"), "{}", err);
+        let err = expand_toml("[stats]\ntheme = \"dark\"\n", &["plot"]).unwrap_err();
+        assert!(err.contains("'theme'") && err.contains("stats"), "{}", err);
+        assert!(expand_toml("[plots]\ntheme = \"dark\"\n", &["plot"]).is_err());
+        // Known to another subcommand: skipped, not an error
+        let args = expand_toml("theme = \"dark\"\n", &["stats", "run.csv"]).unwrap();
+        assert_eq!(args, ["stats", "run.csv"]);
+    }
+}
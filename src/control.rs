@@ -0,0 +1,155 @@
+// Statistical process control charts for latency and Q-score
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use plotters::style::full_palette::GREY_500;
+use std::error::Error;
+
+use crate::anomaly::{self, ControlPoint};
+use crate::model::Health;
+use crate::model::RunData;
+use crate::render::{self, Figure};
+
+/// Control limit width in standard deviations
+const LIMIT_WIDTH: f64 = 3.0;
+/// EWMA smoothing weight of the newest value
+const EWMA_LAMBDA: f64 = 0.2;
+
+/// Selector of one column of the store
+type Column = fn(&RunData) -> &[f64];
+
+/// Metrics QA tracks on control charts
+const CHARTED: [(&str, Column); 3] = [
+    ("Time in Basecaller", |d| &d.time_in_basecaller),
+    ("Time to Package", |d| &d.time_to_package_and_send),
+    ("Mean Q-score", |d| &d.mean_qscore),
+];
+
+/// Kind of control chart
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChartKind {
+    Ewma,     // Exponentially weighted moving average, sensitive to small drifts
+    Shewhart, // Individual values against fixed 3σ limits
+}
+
+impl ChartKind {
+    /// Parses a `--control-chart-type` value
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "ewma" => Some(ChartKind::Ewma),
+            "shewhart" | "individuals" => Some(ChartKind::Shewhart),
+            _ => None,
+        }
+    }
+
+    /// Control statistic and limits for a column
+    fn points(self, values: &[f64]) -> Vec<ControlPoint> {
+        match self {
+            ChartKind::Ewma => anomaly::ewma(values, EWMA_LAMBDA, LIMIT_WIDTH),
+            ChartKind::Shewhart => anomaly::shewhart(values, LIMIT_WIDTH),
+        }
+    }
+}
+
+/// Number of out-of-control points per charted metric
+pub fn out_of_control(data: &RunData, kind: ChartKind) -> Vec<(&'static str, usize)> {
+    CHARTED
+        .iter()
+        .map(|&(title, column)| {
+            let count = kind
+                .points(column(data))
+                .iter()
+                .filter(|p| p.value < p.lower || p.value > p.upper)
+                .count();
+            (title, count)
+        })
+        .collect()
+}
+
+/// Draws one control chart per metric with its limits, marking out-of-control points
+pub fn plot(data: &RunData, kind: ChartKind, output_path: &str) -> Result<(), Box<dyn Error>> {
+    if data.is_empty() {
+        return Err("No records to chart".into());
+    }
+    let figure = ControlFigure { data, kind };
+    render::to_file(&figure, output_path)
+}
+
+/// Control charts of the charted metrics, stacked
+struct ControlFigure<'a> {
+    data: &'a RunData,
+    kind: ChartKind,
+}
+
+impl Figure for ControlFigure<'_> {
+    fn size(&self) -> (u32, u32) {
+        (1600, 1200)
+    }
+
+    fn draw<DB: DrawingBackend>(&self, root: &DrawingArea<DB, Shift>) -> Result<(), Box<dyn Error>>
+    where
+        DB::ErrorType: 'static,
+    {
+        let &ControlFigure { data, kind } = self;
+        let (Some(&min_time), Some(&max_time)) = (data.time.first(), data.time.last()) else {
+            return Err("No records to chart".into());
+        };
+
+        root.fill(&GREY_500)?;
+        let panels = root.split_evenly((CHARTED.len(), 1));
+
+        for (area, &(title, column)) in panels.iter().zip(&CHARTED) {
+            let points = kind.points(column(data));
+            let (low, high) = points
+                .iter()
+                .fold((f64::INFINITY, f64::NEG_INFINITY), |acc, p| {
+                    (
+                        acc.0.min(p.value).min(p.lower),
+                        acc.1.max(p.value).max(p.upper),
+                    )
+                });
+            let pad = ((high - low) * 0.05).max(f64::EPSILON);
+            let caption = match kind {
+                ChartKind::Ewma => format!("{} (EWMA)", title),
+                ChartKind::Shewhart => format!("{} (Shewhart)", title),
+            };
+
+            let mut chart = ChartBuilder::on(area)
+                .caption(caption, ("sans-serif", 20))
+                .margin(20)
+                .x_label_area_size(50)
+                .y_label_area_size(100)
+                .build_cartesian_2d(min_time..max_time, low - pad..high + pad)?;
+            chart
+                .configure_mesh()
+                .x_labels(5)
+                .y_labels(5)
+                .x_desc("Batch Time")
+                .y_desc(title)
+                .draw()?;
+
+            let series = |f: fn(&ControlPoint) -> f64| {
+                data.time
+                    .iter()
+                    .zip(&points)
+                    .map(move |(&t, p)| (t, f(p)))
+                    .collect::<Vec<_>>()
+            };
+            chart.draw_series(LineSeries::new(series(|p| p.upper), Health::Fail.color()))?;
+            chart.draw_series(LineSeries::new(series(|p| p.lower), Health::Fail.color()))?;
+            chart.draw_series(LineSeries::new(
+                series(|p| (p.upper + p.lower) / 2.0),
+                &BLACK,
+            ))?;
+            chart.draw_series(LineSeries::new(series(|p| p.value), &GREEN))?;
+            chart.draw_series(
+                data.time
+                    .iter()
+                    .zip(&points)
+                    .filter(|(_, p)| p.value < p.lower || p.value > p.upper)
+                    .map(|(&t, p)| Circle::new((t, p.value), 4, Health::Fail.color().filled())),
+            )?;
+        }
+
+        Ok(())
+    }
+}
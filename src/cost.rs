@@ -0,0 +1,51 @@
+// Cost-per-gigabase estimation for facility accounting
+
+/// Bases per gigabase
+const BASES_PER_GB: f64 = 1e9;
+
+/// Fixed and running costs of a run, in any single currency
+#[derive(Debug, Clone, Copy)]
+pub struct CostModel {
+    pub flowcell: f64,         // One-off flow cell (and library) cost
+    pub compute_per_hour: f64, // Sequencer/GPU running cost per hour
+}
+
+impl CostModel {
+    /// Total cost after `elapsed` seconds of sequencing
+    pub fn cost_at(&self, elapsed: f64) -> f64 {
+        self.flowcell + self.compute_per_hour * elapsed.max(0.0) / 3600.0
+    }
+
+    /// Cost per Gb for the given cumulative yield, infinite before any bases
+    pub fn cost_per_gb(&self, elapsed: f64, bases: f64) -> f64 {
+        if bases > 0.0 {
+            self.cost_at(elapsed) / (bases / BASES_PER_GB)
+        } else {
+            f64::INFINITY
+        }
+    }
+
+    /// Cumulative cost per Gb at every row, as a plottable column.
+    ///
+    /// Rows before the first base is called repeat the first finite value so
+    /// the column never holds infinities.
+    pub fn cumulative_cost_per_gb(&self, times: &[f64], bases: &[f64]) -> Vec<f64> {
+        let start = times.first().copied().unwrap_or(0.0);
+        let mut total = 0.0;
+        let mut column: Vec<f64> = times
+            .iter()
+            .zip(bases)
+            .map(|(&t, &b)| {
+                total += b;
+                self.cost_per_gb(t - start, total)
+            })
+            .collect();
+
+        if let Some(first) = column.iter().copied().find(|v| v.is_finite()) {
+            for value in column.iter_mut().take_while(|v| !v.is_finite()) {
+                *value = first;
+            }
+        }
+        column
+    }
+}
@@ -0,0 +1,740 @@
+// Daemon mode: watch a data directory and keep per-run outputs up to date
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::io::BufRead;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::alias::AliasMap;
+use crate::compression;
+use crate::digest::escape_html;
+use crate::golden;
+use crate::lock::DirLock;
+use crate::model::{RunData, SampleExpectation};
+use crate::plot::PlotOptions;
+use crate::retention::{self, Retention};
+use crate::schema::Schema;
+use crate::service::{self, Logger, PidFile};
+use crate::shutdown;
+use crate::timestamp::TimeFormat;
+use crate::tombstone::Tombstones;
+
+/// Directory created inside each run folder for the generated outputs; under
+/// `--redact`, created in the watched directory instead, holding one folder
+/// per run named by its pseudonym
+const OUTPUT_DIR: &str = "as_parser";
+/// File name of the run index written into the watched directory
+const INDEX_FILE: &str = "as_parser_runs.json";
+/// File name of the HTML run selector written next to the index
+const INDEX_PAGE: &str = "as_parser_runs.html";
+/// Columns of the last CSV processed, kept in each run's output directory
+const SCHEMA_FILE: &str = "schema.txt";
+/// How deep below a run folder the batch CSV is searched for
+const MAX_DEPTH: usize = 3;
+/// Directory inside the watched directory that receives snapshots
+const SNAPSHOT_DIR: &str = "as_parser_snapshots";
+/// Creating this file in the watched directory requests a snapshot
+const SNAPSHOT_TRIGGER: &str = "as_parser.snapshot";
+/// A run counts as active while its CSV changed within this window
+const ACTIVE_WINDOW: Duration = Duration::from_secs(3600);
+
+/// Settings of the watch loop
+pub struct WatchConfig {
+    pub root: PathBuf,        // Top-level data directory holding one folder per run
+    pub interval: Duration,   // Pause between scans
+    pub once: bool,           // Scan a single time and exit
+    pub options: PlotOptions, // Rendering options for every run
+    pub samples: Option<SampleExpectation>, // Optional samples-per-batch validation
+    pub redact: bool,         // Keep input paths and run names out of digests and indexes
+    pub time_format: TimeFormat, // Format of the batch times
+    pub aliases: AliasMap,    // Renamed columns to read under their usual names
+    pub tombstones: Tombstones, // Withdrawn reads left out of every run
+    pub reference: Option<RunData>, // Golden run every run is drawn over and scored against
+    pub snapshot_every: Option<Duration>, // Take snapshots on this cadence too
+    pub retention: Retention, // Which snapshots to keep
+    pub pid_file: Option<PathBuf>, // Holds the daemon's process ID while it runs
+    pub log: Logger,          // Where progress and failures are written
+}
+
+/// Latest known state of one run, as written to the index
+#[derive(Debug, Clone)]
+struct RunEntry {
+    name: String,          // Run folder name
+    input: PathBuf,        // Batch CSV being followed
+    modified: SystemTime,  // Modification time of the CSV when last processed
+    records: usize,        // Number of parsed records
+    verdict: &'static str, // Overall check outcome
+    plot: PathBuf,         // Rendered figure
+    digest: PathBuf,       // HTML digest
+    schema: String,        // Fingerprint of the CSV's columns
+}
+
+/// Scans `config.root` for runs and re-renders those whose CSV changed.
+///
+/// Each immediate subdirectory is a run; its first CSV (depth-first, at most
+/// `MAX_DEPTH` levels down) is the batch log. Failures on one run are
+/// reported and do not stop the others.
+///
+/// Between scans, typing `s` and Enter or creating `SNAPSHOT_TRIGGER` in the
+/// watched directory captures all runs into a timestamped snapshot folder.
+/// Snapshots are also taken every `snapshot_every`, and after each one the
+/// folders the retention policy no longer keeps are pruned.
+///
+/// SIGINT or SIGTERM stops the loop after the run being rendered, with the
+/// index brought up to date and a final snapshot written. Outputs are
+/// written under a temporary name and renamed into place, so a stop never
+/// leaves a truncated figure, digest or index behind.
+///
+/// Under systemd (`Type=notify`) readiness is signalled after the first
+/// scan and each scan's outcome is published as the service status.
+pub fn run(config: &WatchConfig) -> Result<(), Box<dyn Error>> {
+    let log = config.log;
+    let _pid_file = config
+        .pid_file
+        .as_deref()
+        .map(PidFile::create)
+        .transpose()?;
+    let mut runs: HashMap<PathBuf, RunEntry> = HashMap::new();
+    let mut requests = (!config.once).then(snapshot_requests);
+    let mut last_snapshot = SystemTime::now();
+    let mut ready = false;
+    if !config.once {
+        shutdown::install();
+        log.info(&format!(
+            "Type s and Enter, or create {}, to snapshot all runs",
+            config.root.join(SNAPSHOT_TRIGGER).display()
+        ));
+    }
+
+    loop {
+        for (name, input) in discover_runs(&config.root)? {
+            if shutdown::requested() {
+                break;
+            }
+            // A run's CSV may be moved or deleted between discovery and here
+            let modified = match fs::metadata(&input).and_then(|m| m.modified()) {
+                Ok(modified) => modified,
+                Err(e) => {
+                    log.warn(&format!("Skipping run {}: {}", name, e));
+                    continue;
+                }
+            };
+            let up_to_date = runs
+                .get(&input)
+                .is_some_and(|entry| entry.modified == modified);
+            if up_to_date {
+                continue;
+            }
+
+            if !runs.contains_key(&input) {
+                log.info(&format!("New run detected: {} ({})", name, input.display()));
+            }
+            let out_dir = if config.redact {
+                config
+                    .root
+                    .join(OUTPUT_DIR)
+                    .join(output_name(&name, &input, true))
+            } else {
+                config.root.join(&name).join(OUTPUT_DIR)
+            };
+            match process_run(&name, &input, modified, config, &out_dir) {
+                Ok(entry) => {
+                    log.info(&format!(
+                        "Updated run {} ({} records, {})",
+                        name, entry.records, entry.verdict
+                    ));
+                    runs.insert(input, entry);
+                }
+                Err(e) => log.warn(&format!("Run {}: {}", name, e)),
+            }
+        }
+
+        write_index(&config.root.join(INDEX_FILE), &runs, config.redact)?;
+        write_index_page(&config.root.join(INDEX_PAGE), &runs, config.redact)?;
+        service::notify(&format!("STATUS=Following {} runs", runs.len()));
+        if !ready {
+            service::notify("READY=1");
+            ready = true;
+        }
+
+        if config.once {
+            return Ok(());
+        }
+
+        // Wait for the next scan, serving snapshot requests in the meantime
+        let deadline = SystemTime::now() + config.interval;
+        loop {
+            if shutdown::requested() {
+                return shut_down(config, &runs);
+            }
+            let trigger = config.root.join(SNAPSHOT_TRIGGER);
+            // Removing the file is the check, so it cannot vanish in between
+            let mut requested = fs::remove_file(&trigger).is_ok();
+            requested |= config.snapshot_every.is_some_and(|every| {
+                last_snapshot
+                    .elapsed()
+                    .is_ok_and(|elapsed| elapsed >= every)
+            });
+            let left = deadline
+                .duration_since(SystemTime::now())
+                .unwrap_or_default();
+            if left.is_zero() && !requested {
+                break;
+            }
+            // Poll for the trigger file at least once a second
+            let wait = left.min(Duration::from_secs(1));
+            match &requests {
+                Some(rx) if !requested => match rx.recv_timeout(wait) {
+                    Ok(()) => requested = true,
+                    Err(RecvTimeoutError::Timeout) => {}
+                    // Stdin closed (e.g. under a service manager)
+                    Err(RecvTimeoutError::Disconnected) => requests = None,
+                },
+                None if !requested => std::thread::sleep(wait),
+                _ => {}
+            }
+            if requested {
+                last_snapshot = SystemTime::now();
+                match snapshot(config, &runs) {
+                    Ok(dir) => log.info(&format!("Snapshot saved to {}", dir.display())),
+                    Err(e) => log.warn(&format!("Snapshot failed: {}", e)),
+                }
+                match retention::prune(&config.root.join(SNAPSHOT_DIR), &config.retention) {
+                    Ok(0) => {}
+                    Ok(pruned) => log.info(&format!("Pruned {} old snapshots", pruned)),
+                    Err(e) => log.warn(&format!("Pruning snapshots failed: {}", e)),
+                }
+            }
+        }
+    }
+}
+
+/// Takes the final snapshot after SIGINT/SIGTERM
+fn shut_down(
+    config: &WatchConfig,
+    runs: &HashMap<PathBuf, RunEntry>,
+) -> Result<(), Box<dyn Error>> {
+    service::notify("STOPPING=1");
+    config
+        .log
+        .info("Shutdown requested, taking a final snapshot");
+    if !runs.is_empty() {
+        let dir = snapshot(config, runs)?;
+        config
+            .log
+            .info(&format!("Snapshot saved to {}", dir.display()));
+        retention::prune(&config.root.join(SNAPSHOT_DIR), &config.retention)?;
+    }
+    Ok(())
+}
+
+/// Writes `contents` to a temporary file next to `path`, then renames it
+/// over `path`, so readers never see a partly written file
+fn write_replacing(path: &Path, contents: &str) -> Result<(), Box<dyn Error>> {
+    let partial = partial_path(path);
+    fs::write(&partial, contents)?;
+    fs::rename(&partial, path)?;
+    Ok(())
+}
+
+/// Temporary name `path` is written under: `metrics.png` becomes
+/// `metrics.partial.png`, keeping the extension renderers pick formats by
+pub fn partial_path(path: &Path) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    match path.extension() {
+        Some(ext) => path.with_file_name(format!("{}.partial.{}", stem, ext.to_string_lossy())),
+        None => path.with_file_name(format!("{}.partial", stem)),
+    }
+}
+
+/// Forwards every `s`/`snapshot` line typed on stdin as a snapshot request
+fn snapshot_requests() -> Receiver<()> {
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        for line in std::io::stdin().lock().lines() {
+            let Ok(line) = line else { break };
+            if matches!(line.trim(), "s" | "snapshot") && tx.send(()).is_err() {
+                break;
+            }
+        }
+    });
+    rx
+}
+
+/// Re-renders every known run into `SNAPSHOT_DIR/<UTC time>/<run>` with its
+/// own index, leaving the live outputs untouched; returns the folder. A run
+/// that fails is logged and left out; only a snapshot of no run fails.
+fn snapshot(
+    config: &WatchConfig,
+    runs: &HashMap<PathBuf, RunEntry>,
+) -> Result<PathBuf, Box<dyn Error>> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs_f64();
+    let dir = config
+        .root
+        .join(SNAPSHOT_DIR)
+        .join(crate::projection::iso8601_basic(now));
+
+    let mut captured = HashMap::new();
+    let mut failed = Vec::new();
+    for (input, entry) in runs {
+        let out_dir = dir.join(output_name(&entry.name, input, config.redact));
+        let result = fs::metadata(input)
+            .and_then(|m| m.modified())
+            .map_err(Into::into)
+            .and_then(|modified| process_run(&entry.name, input, modified, config, &out_dir));
+        match result {
+            Ok(snapshot) => {
+                captured.insert(input.clone(), snapshot);
+            }
+            Err(e) => {
+                config
+                    .log
+                    .warn(&format!("Snapshot of run {}: {}", entry.name, e));
+                failed.push(entry.name.as_str());
+            }
+        }
+    }
+    if captured.is_empty() && !failed.is_empty() {
+        return Err(format!("no run could be captured ({})", failed.join(", ")).into());
+    }
+    write_index(&dir.join(INDEX_FILE), &captured, config.redact)?;
+    write_index_page(&dir.join(INDEX_PAGE), &captured, config.redact)?;
+    Ok(dir)
+}
+
+/// Lists `(run name, batch CSV)` for every run folder under `root`
+pub fn discover_runs(root: &Path) -> Result<Vec<(String, PathBuf)>, Box<dyn Error>> {
+    let mut runs = Vec::new();
+    for entry in fs::read_dir(root)? {
+        let path = entry?.path();
+        if !path.is_dir() || path.ends_with(SNAPSHOT_DIR) || path.ends_with(OUTPUT_DIR) {
+            continue;
+        }
+        if let Some(csv) = find_csv(&path, MAX_DEPTH)? {
+            let name = path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            runs.push((name, csv));
+        }
+    }
+    runs.sort();
+    Ok(runs)
+}
+
+/// Folder name of a run's outputs: its pseudonym when redacting, so that
+/// paths in the indexes do not carry the run folder's name
+fn output_name(name: &str, input: &Path, redact: bool) -> String {
+    if redact {
+        crate::redact::pseudonym(&input.to_string_lossy())
+    } else {
+        name.to_string()
+    }
+}
+
+/// Finds the first (possibly compressed) CSV below `dir`, skipping our own output folders
+fn find_csv(dir: &Path, depth: usize) -> Result<Option<PathBuf>, Box<dyn Error>> {
+    let mut entries: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .collect();
+    entries.sort();
+
+    if let Some(csv) = entries
+        .iter()
+        .find(|p| p.is_file() && compression::is_csv(p))
+    {
+        return Ok(Some(csv.clone()));
+    }
+    if depth == 0 {
+        return Ok(None);
+    }
+    for sub in entries
+        .iter()
+        .filter(|p| p.is_dir() && !p.ends_with(OUTPUT_DIR))
+    {
+        if let Some(csv) = find_csv(sub, depth - 1)? {
+            return Ok(Some(csv));
+        }
+    }
+    Ok(None)
+}
+
+/// Renders the figure and digest of one run into `out_dir`, holding its lock
+/// so a concurrent invocation (cron next to a manual run) cannot interleave
+fn process_run(
+    name: &str,
+    input: &Path,
+    modified: SystemTime,
+    config: &WatchConfig,
+    out_dir: &Path,
+) -> Result<RunEntry, Box<dyn Error>> {
+    fs::create_dir_all(out_dir)?;
+    let _lock = DirLock::acquire(out_dir)?;
+
+    let input_str = input.to_string_lossy();
+    let mut data = crate::parser::parse_csv(
+        &input_str,
+        &crate::parser::ParseOptions {
+            time_format: config.time_format,
+            aliases: config.aliases.clone(),
+            tombstones: config.tombstones.clone(),
+            ..Default::default()
+        },
+    )?;
+    let fields = crate::model::plot_fields(config.samples);
+    let samples_flagged = config
+        .samples
+        .is_some_and(|s| !crate::model::check_samples_per_batch(&data, s).is_empty());
+
+    // A later file of the run with other columns is parsed as well as it
+    // can be, but its panels may have lost data
+    let schema_path = out_dir.join(SCHEMA_FILE);
+    let drift = Schema::load(&schema_path).and_then(|previous| previous.drift(&data.schema));
+    if let Some(drift) = &drift {
+        config.log.warn(&format!(
+            "Run {}: INPUT SCHEMA CHANGED ({}), check the panels of this run",
+            name,
+            drift.describe()
+        ));
+    }
+    data.schema.save(&schema_path)?;
+    data.pauses = crate::pauses::detect(&data, None);
+
+    let deviations = match &config.reference {
+        Some(reference) => {
+            let aligned = golden::align(reference, &data);
+            let deviations = golden::deviations(&data, &aligned, &fields, config.options.center);
+            data.reference = Some(Box::new(aligned));
+            deviations
+        }
+        None => Vec::new(),
+    };
+    let strayed = deviations
+        .iter()
+        .any(|d| d.health() != crate::model::Health::Ok);
+    if strayed && let Some(worst) = golden::worst(&deviations) {
+        config.log.warn(&format!(
+            "Run {}: strays from the reference run ({})",
+            name, worst
+        ));
+    }
+
+    // Rendered under a temporary name; a text-free figure's label legend
+    // is named after it and moves along
+    let plot = out_dir.join("metrics.png");
+    let partial = partial_path(&plot);
+    let partial_str = partial.to_string_lossy().to_string();
+    for artifact in crate::plot::plot_multi_series(&data, &fields, &partial_str, config.options)? {
+        let target = artifact
+            .path
+            .replacen(&partial_str, &plot.to_string_lossy(), 1);
+        fs::rename(&artifact.path, target)?;
+    }
+
+    let thumbnail = crate::plot::render_thumbnail(&data, &fields, config.options)?;
+    let shown_input = if config.redact {
+        crate::redact::pseudonym(&input_str)
+    } else {
+        input_str.to_string()
+    };
+    let mut digest = crate::digest::build_digest(
+        &shown_input,
+        &data,
+        &fields,
+        if samples_flagged || drift.is_some() || strayed {
+            crate::model::Health::Warn
+        } else {
+            crate::model::Health::Ok
+        },
+        &crate::mode::provenance(
+            crate::mode::Mode::Exact,
+            crate::quantile::Method::Exact,
+            None,
+            data.len(),
+        ),
+        config.options.center,
+        config.options.language,
+        Some(thumbnail),
+        Vec::new(),
+    );
+    if let Some(drift) = &drift {
+        digest.metadata.push(("Schema drift", drift.describe()));
+    }
+    if let Some(worst) = golden::worst(&deviations) {
+        digest.metadata.push(("Reference deviation", worst));
+    }
+    let digest_path = out_dir.join("metrics.html");
+    let partial = partial_path(&digest_path);
+    crate::digest::write_digest(&partial.to_string_lossy(), &digest)?;
+    fs::rename(&partial, &digest_path)?;
+
+    Ok(RunEntry {
+        name: name.to_string(),
+        input: input.to_path_buf(),
+        modified,
+        records: data.len(),
+        verdict: digest.verdict,
+        plot,
+        digest: digest_path,
+        schema: data.schema.fingerprint(),
+    })
+}
+
+/// Writes the JSON index of all known runs, flagging the active ones
+fn write_index(
+    path: &Path,
+    runs: &HashMap<PathBuf, RunEntry>,
+    redact: bool,
+) -> Result<(), Box<dyn Error>> {
+    let now = SystemTime::now();
+    let base = path.parent().unwrap_or(Path::new(""));
+    // Redacted indexes hold no absolute paths
+    let shown = |target: &Path| {
+        if redact {
+            relative_path(target, base)
+        } else {
+            target.to_string_lossy().into_owned()
+        }
+    };
+    let mut entries: Vec<&RunEntry> = runs.values().collect();
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let lines: Vec<String> = entries
+        .iter()
+        .map(|run| {
+            let active = is_active(run, now);
+            let modified = run
+                .modified
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0);
+            format!(
+                "    {{\"name\": {}, \"input\": {}, \"records\": {}, \"verdict\": \"{}\", \"active\": {}, \"last_modified\": {}, \"plot\": {}, \"digest\": {}, \"schema\": \"{}\"}}",
+                json_string(&shown_name(run, redact)),
+                json_string(&shown_input(run, redact)),
+                run.records,
+                run.verdict,
+                active,
+                modified,
+                json_string(&shown(&run.plot)),
+                json_string(&shown(&run.digest)),
+                run.schema
+            )
+        })
+        .collect();
+
+    write_replacing(
+        path,
+        &format!("{{\n  \"runs\": [\n{}\n  ]\n}}\n", lines.join(",\n")),
+    )?;
+    Ok(())
+}
+
+/// Whether the run's CSV changed within `ACTIVE_WINDOW` of `now`
+fn is_active(run: &RunEntry, now: SystemTime) -> bool {
+    now.duration_since(run.modified)
+        .map(|age| age <= ACTIVE_WINDOW)
+        .unwrap_or(true)
+}
+
+/// Writes the HTML run selector: active runs first, then archived ones, each
+/// linking to its digest and figure by paths relative to the page, so the
+/// watched directory can be served as-is by any static web server
+fn write_index_page(
+    path: &Path,
+    runs: &HashMap<PathBuf, RunEntry>,
+    redact: bool,
+) -> Result<(), Box<dyn Error>> {
+    let now = SystemTime::now();
+    let base = path.parent().unwrap_or(Path::new(""));
+    let link = |target: &Path| escape_html(&relative_path(target, base));
+    let mut entries: Vec<&RunEntry> = runs.values().collect();
+    entries.sort_by(|a, b| b.modified.cmp(&a.modified).then(a.name.cmp(&b.name)));
+
+    let mut html = String::from(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta http-equiv=\"refresh\" content=\"60\">\n<title>Runs</title>\n\
+         <style>body{font-family:sans-serif;max-width:960px;margin:auto}\
+         table{border-collapse:collapse}td,th{padding:2px 8px;text-align:left}\
+         .OK{color:#2ea043}.WARN{color:#ffb000}.FAIL{color:#dc322f}</style>\n\
+         </head>\n<body>\n",
+    );
+    for (heading, active) in [("Active runs", true), ("Archived runs", false)] {
+        let group: Vec<&&RunEntry> = entries
+            .iter()
+            .filter(|run| is_active(run, now) == active)
+            .collect();
+        if group.is_empty() {
+            continue;
+        }
+        html.push_str(&format!(
+            "<h2>{}</h2>\n<table>\n<tr><th>Run</th><th>Records</th><th>Verdict</th>\
+             <th>Last update (UTC)</th><th>Figure</th></tr>\n",
+            heading
+        ));
+        for run in group {
+            let modified = run
+                .modified
+                .duration_since(UNIX_EPOCH)
+                .map_or(0.0, |d| d.as_secs_f64());
+            html.push_str(&format!(
+                "<tr><td><a href=\"{0}\">{1}</a></td><td>{2}</td><td class=\"{3}\">{3}</td>\
+                 <td>{4}</td><td><a href=\"{5}\">plot</a></td></tr>\n",
+                link(&run.digest),
+                escape_html(&shown_name(run, redact)),
+                run.records,
+                run.verdict,
+                crate::projection::iso8601_basic(modified),
+                link(&run.plot)
+            ));
+        }
+        html.push_str("</table>\n");
+    }
+    if entries.is_empty() {
+        html.push_str("<p>No runs found yet.</p>\n");
+    }
+    html.push_str("</body>\n</html>\n");
+    write_replacing(path, &html)?;
+    Ok(())
+}
+
+/// `target` relative to `base` with `/` separators; only its file name when
+/// it lies outside `base`
+fn relative_path(target: &Path, base: &Path) -> String {
+    match target.strip_prefix(base) {
+        Ok(relative) => relative.to_string_lossy().replace('\\', "/"),
+        Err(_) => crate::redact::file_name(&target.to_string_lossy()),
+    }
+}
+
+/// Run name shown in indexes: its pseudonym when redacting, since run
+/// folders are often named after the sample
+fn shown_name(run: &RunEntry, redact: bool) -> String {
+    output_name(&run.name, &run.input, redact)
+}
+
+/// Input path shown in indexes: its pseudonym when redacting
+fn shown_input(run: &RunEntry, redact: bool) -> String {
+    if redact {
+        crate::redact::pseudonym(&run.input.to_string_lossy())
+    } else {
+        run.input.to_string_lossy().into_owned()
+    }
+}
+
+/// Encodes a string as a JSON string literal
+fn json_string(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("as_parser-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// A run processed into `out_dir`, as `process_run` records it
+    fn entry(root: &Path, name: &str, out_dir: &Path) -> RunEntry {
+        RunEntry {
+            name: name.to_string(),
+            input: root.join(name).join("as_timings.csv"),
+            modified: SystemTime::now(),
+            records: 42,
+            verdict: "OK",
+            plot: out_dir.join("metrics.png"),
+            digest: out_dir.join("metrics.html"),
+            schema: "0123456789abcdef".to_string(),
+        }
+    }
+
+    #[test]
+    fn redacted_indexes_name_no_run_folder() {
+        let root = scratch("index-redacted");
+        let input = root.join("PATIENT_1234_run").join("as_timings.csv");
+        let pseudonym = output_name("PATIENT_1234_run", &input, true);
+        assert!(pseudonym.starts_with("run-"));
+        let out_dir = root.join(OUTPUT_DIR).join(&pseudonym);
+        let runs = HashMap::from([(input, entry(&root, "PATIENT_1234_run", &out_dir))]);
+
+        write_index(&root.join(INDEX_FILE), &runs, true).unwrap();
+        write_index_page(&root.join(INDEX_PAGE), &runs, true).unwrap();
+        let json = fs::read_to_string(root.join(INDEX_FILE)).unwrap();
+        let html = fs::read_to_string(root.join(INDEX_PAGE)).unwrap();
+        for text in [&json, &html] {
+            assert!(!text.contains("PATIENT"), "{}", text);
+            assert!(!text.contains(&*root.to_string_lossy()), "{}", text);
+        }
+        let plot = format!("\"plot\": \"as_parser/{}/metrics.png\"", pseudonym);
+        assert!(json.contains(&plot), "{}", json);
+        assert!(html.contains(&format!("href=\"as_parser/{}/metrics.html\"", pseudonym)));
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn plain_indexes_keep_names_and_paths() {
+        let root = scratch("index-plain");
+        let out_dir = root.join("run_a").join(OUTPUT_DIR);
+        let run = entry(&root, "run_a", &out_dir);
+        let runs = HashMap::from([(run.input.clone(), run.clone())]);
+
+        write_index(&root.join(INDEX_FILE), &runs, false).unwrap();
+        write_index_page(&root.join(INDEX_PAGE), &runs, false).unwrap();
+        let json = fs::read_to_string(root.join(INDEX_FILE)).unwrap();
+        assert!(json.contains(&json_string(&run.plot.to_string_lossy())));
+        assert!(json.contains("\"name\": \"run_a\", \"input\": "));
+        assert!(json.contains("\"records\": 42, \"verdict\": \"OK\", \"active\": true"));
+        let html = fs::read_to_string(root.join(INDEX_PAGE)).unwrap();
+        assert!(html.contains("<h2>Active runs</h2>"));
+        assert!(html.contains("<a href=\"run_a/as_parser/metrics.html\">run_a</a>"));
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn discovers_runs_but_not_outputs() {
+        let root = scratch("discover");
+        for dir in [
+            "run_b/fastq_pass",
+            "run_a",
+            OUTPUT_DIR,
+            SNAPSHOT_DIR,
+            "empty",
+        ] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        fs::write(root.join("run_a/as_timings.csv"), "").unwrap();
+        fs::write(root.join("run_b/fastq_pass/batches.csv.gz"), "").unwrap();
+        fs::write(root.join(OUTPUT_DIR).join("stray.csv"), "").unwrap();
+        let runs = discover_runs(&root).unwrap();
+        let names: Vec<&str> = runs.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["run_a", "run_b"]);
+        assert!(runs[1].1.ends_with("fastq_pass/batches.csv.gz"));
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn json_strings_are_escaped() {
+        assert_eq!(json_string("a\"b\\c\n"), "\"a\\\"b\\\\c\\u000a\"");
+        assert_eq!(
+            partial_path(Path::new("out/metrics.png")),
+            Path::new("out/metrics.partial.png")
+        );
+    }
+}
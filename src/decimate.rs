@@ -0,0 +1,150 @@
+// Decimation of long series before drawing: Largest-Triangle-Three-Buckets
+//
+// A figure a couple of thousand pixels wide cannot show more than a few points
+// per pixel column, yet a run can hold millions of batches. LTTB keeps the
+// first and last point and, from each of the equally sized buckets between
+// them, the point spanning the largest triangle with the previously kept point
+// and the mean of the next bucket. Peaks and troughs therefore survive while
+// flat stretches collapse, and the line looks as it would with every point.
+//
+// LTTB still picks one point per bucket, so a narrow spike next to a larger
+// swing can be dropped. `with_extremes` adds each bucket's lowest and highest
+// point and any rows that must be shown, at the cost of exceeding the budget.
+use std::ops::Range;
+
+/// Points drawn per horizontal pixel of the canvas unless `--max-points` says otherwise
+pub const POINTS_PER_PIXEL: usize = 2;
+
+/// Fewest points LTTB works with: the two ends and one bucket between them
+const MIN_POINTS: usize = 3;
+
+/// Default point budget of a series on a canvas `width` pixels wide
+pub fn default_max_points(width: u32) -> usize {
+    width as usize * POINTS_PER_PIXEL
+}
+
+/// Indices of the rows LTTB keeps when reducing `values` to at most
+/// `max_points` points, in ascending order; every index when the series is
+/// already short enough
+pub fn lttb(times: &[f64], values: &[f64], max_points: usize) -> Vec<usize> {
+    let n = times.len().min(values.len());
+    if max_points >= n || max_points < MIN_POINTS {
+        return (0..n).collect();
+    }
+
+    let buckets = buckets(n, max_points);
+    let mut kept = Vec::with_capacity(max_points);
+    kept.push(0);
+    let mut anchor = 0;
+    for (b, bucket) in buckets.iter().enumerate() {
+        let (start, end) = (bucket.start, bucket.end);
+
+        // Mean of the next bucket, or the last point after the final bucket
+        let (next_start, next_end) = match buckets.get(b + 1) {
+            Some(next) => (next.start, next.end.max(next.start + 1)),
+            None => (end, (end + 1).min(n)),
+        };
+        let count = (next_end - next_start) as f64;
+        let (mean_t, mean_v) = (next_start..next_end).fold((0.0, 0.0), |(t, v), i| {
+            (t + times[i] / count, v + values[i] / count)
+        });
+
+        let (at, av) = (times[anchor], values[anchor]);
+        let mut best = (start, f64::NEG_INFINITY);
+        for i in start..end.max(start + 1) {
+            let area = ((at - mean_t) * (values[i] - av) - (at - times[i]) * (mean_v - av)).abs();
+            if area > best.1 {
+                best = (i, area);
+            }
+        }
+        kept.push(best.0);
+        anchor = best.0;
+    }
+    kept.push(n - 1);
+    kept.dedup();
+    kept
+}
+
+/// Rows between the two ends, split into the `max_points - 2` equal buckets
+/// LTTB picks one row from each of
+fn buckets(n: usize, max_points: usize) -> Vec<Range<usize>> {
+    let count = max_points - 2;
+    let every = (n - 2) as f64 / count as f64;
+    let start = |b: usize| ((b as f64 * every) as usize + 1).min(n - 1);
+    (0..count).map(|b| start(b)..start(b + 1)).collect()
+}
+
+/// `lttb`'s rows plus, from each bucket, the rows with its lowest and
+/// highest value, and every row in `always` (e.g. flagged anomalies); the
+/// result may hold more than `max_points` rows
+pub fn with_extremes(
+    times: &[f64],
+    values: &[f64],
+    max_points: usize,
+    always: &[usize],
+) -> Vec<usize> {
+    let n = times.len().min(values.len());
+    let mut kept = lttb(times, values, max_points);
+    if kept.len() < n {
+        for bucket in buckets(n, max_points) {
+            let finite = || bucket.clone().filter(|&i| values[i].is_finite());
+            kept.extend(finite().min_by(|&a, &b| values[a].total_cmp(&values[b])));
+            kept.extend(finite().max_by(|&a, &b| values[a].total_cmp(&values[b])));
+        }
+    }
+    kept.extend(always.iter().copied().filter(|&i| i < n));
+    kept.sort_unstable();
+    kept.dedup();
+    kept
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_series_are_kept_whole() {
+        let times = [0.0, 1.0, 2.0, 3.0];
+        assert_eq!(lttb(&times, &times, 10), [0, 1, 2, 3]);
+        // Budgets below three points leave nothing to choose from
+        assert_eq!(lttb(&times, &times, 2), [0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn lttb_keeps_ends_and_peaks_within_budget() {
+        let times: Vec<f64> = (0..1000).map(f64::from).collect();
+        let mut values = vec![1.0; 1000];
+        values[437] = 50.0;
+        values[712] = -50.0;
+        let kept = lttb(&times, &values, 20);
+        assert!(kept.len() <= 20);
+        assert_eq!(kept.first(), Some(&0));
+        assert_eq!(kept.last(), Some(&999));
+        assert!(kept.windows(2).all(|w| w[0] < w[1]));
+        assert!(kept.contains(&437) && kept.contains(&712), "{:?}", kept);
+    }
+
+    #[test]
+    fn buckets_cover_the_interior() {
+        let buckets = buckets(102, 12);
+        assert_eq!(buckets.len(), 10);
+        assert_eq!(buckets[0].start, 1);
+        assert_eq!(buckets[9].end, 101);
+        assert!(buckets.windows(2).all(|w| w[0].end == w[1].start));
+    }
+
+    #[test]
+    fn extremes_and_forced_rows_are_added() {
+        let times: Vec<f64> = (0..100).map(f64::from).collect();
+        let values: Vec<f64> = (0..100).map(|i| ((i * 37) % 11) as f64).collect();
+        let kept = with_extremes(&times, &values, 10, &[42, 500]);
+        assert!(kept.contains(&42) && !kept.contains(&500));
+        for bucket in buckets(100, 10) {
+            let max = bucket.clone().map(|i| values[i]).fold(f64::MIN, f64::max);
+            assert!(
+                kept.iter()
+                    .any(|&i| bucket.contains(&i) && values[i] == max)
+            );
+        }
+    }
+}
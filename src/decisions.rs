@@ -0,0 +1,360 @@
+// Adaptive sampling decision logs (MinKNOW adaptive_sampling CSV, ReadFish)
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use plotters::style::full_palette::GREY_500;
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+
+use crate::render::{self, Figure};
+use crate::robust::Center;
+use crate::timestamp::{TimeFormat, TimeParser};
+use crate::tombstone::Tombstones;
+
+/// Signal sample rate of R10.4.1 flow cells, for latencies unless `--sample-rate` says otherwise
+pub const DEFAULT_SAMPLE_RATE: f64 = 5000.0;
+/// Time bins of the unblock-rate and latency panels
+const TIME_BINS: usize = 100;
+
+/// What the adaptive sampling client decided for a read
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DecisionKind {
+    StopReceiving, // Read kept, the client stopped receiving its signal
+    Unblock,       // Read ejected from the pore
+    NoDecision,    // No decision before the read ended
+}
+
+impl DecisionKind {
+    /// Every kind, in report order
+    pub const ALL: [DecisionKind; 3] = [
+        DecisionKind::StopReceiving,
+        DecisionKind::Unblock,
+        DecisionKind::NoDecision,
+    ];
+
+    /// Parses a decision as MinKNOW and ReadFish write it
+    pub fn parse(text: &str) -> Option<Self> {
+        match text.trim().to_ascii_lowercase().as_str() {
+            "stop_receiving" => Some(DecisionKind::StopReceiving),
+            "unblock" => Some(DecisionKind::Unblock),
+            "no_decision" => Some(DecisionKind::NoDecision),
+            _ => None,
+        }
+    }
+
+    /// Name as written in the log
+    pub fn label(self) -> &'static str {
+        match self {
+            DecisionKind::StopReceiving => "stop_receiving",
+            DecisionKind::Unblock => "unblock",
+            DecisionKind::NoDecision => "no_decision",
+        }
+    }
+
+    /// Colour in the channel composition panel
+    pub fn color(self) -> RGBColor {
+        match self {
+            DecisionKind::StopReceiving => RGBColor(46, 160, 67),
+            DecisionKind::Unblock => RGBColor(220, 50, 47),
+            DecisionKind::NoDecision => RGBColor(108, 113, 196),
+        }
+    }
+}
+
+/// One logged decision
+#[derive(Debug, Clone, Copy)]
+pub struct Decision {
+    pub time: f64,            // Decision time (the batch it was made in)
+    pub channel: u32,         // Channel of the read
+    pub kind: DecisionKind,   // What was decided
+    pub samples: Option<f64>, // Signal samples seen when deciding (num_samples)
+}
+
+/// Parses a decision CSV, in time order, with the number of decisions left
+/// out because their read is in `tombstones`.
+///
+/// Needs `channel`, `decision` and a `batch_time` (MinKNOW) or `time`
+/// (ReadFish) column in `time_format`; `num_samples` gives the latency and
+/// `read_id` matches decisions to tombstones.
+pub fn parse(
+    path: &str,
+    time_format: TimeFormat,
+    tombstones: &Tombstones,
+) -> Result<(Vec<Decision>, usize), Box<dyn Error>> {
+    let mut rdr = csv::Reader::from_path(path)?;
+    let headers = rdr.headers()?.clone();
+    let column = |name: &str| headers.iter().position(|h| h.trim() == name);
+    let time_column = column("batch_time")
+        .or_else(|| column("time"))
+        .ok_or("Decision CSV has no batch_time or time column")?;
+    let channel_column = column("channel").ok_or("Decision CSV has no channel column")?;
+    let decision_column = column("decision").ok_or("Decision CSV has no decision column")?;
+    let samples_column = column("num_samples");
+    let read_column = column("read_id").filter(|_| !tombstones.is_empty());
+
+    let mut times = TimeParser::new(time_format);
+    let mut decisions = Vec::new();
+    let mut withdrawn = 0;
+    for result in rdr.records() {
+        let record = result?;
+        let line = record.position().map_or(0, |p| p.line());
+        let field = |index: usize| record.get(index).unwrap_or("").trim();
+        if read_column.is_some_and(|c| tombstones.reason(field(c)).is_some()) {
+            withdrawn += 1;
+            continue;
+        }
+        let time = times.read(field(time_column))?;
+        let channel = field(channel_column).parse().map_err(|_| {
+            format!(
+                "Invalid channel '{}' on line {}",
+                field(channel_column),
+                line
+            )
+        })?;
+        let kind = DecisionKind::parse(field(decision_column)).ok_or_else(|| {
+            format!(
+                "Unknown decision '{}' on line {} (stop_receiving, unblock or no_decision)",
+                field(decision_column),
+                line
+            )
+        })?;
+        let samples = samples_column.and_then(|c| field(c).parse().ok());
+        decisions.push(Decision {
+            time,
+            channel,
+            kind,
+            samples,
+        });
+    }
+    decisions.sort_by(|a, b| a.time.total_cmp(&b.time));
+    Ok((decisions, withdrawn))
+}
+
+/// Decision totals of a run
+#[derive(Debug, Clone)]
+pub struct DecisionReport {
+    pub counts: [usize; 3], // Decisions of each kind, in `DecisionKind::ALL` order
+    pub unblock_rate: f64,  // Share of decisions that were unblocks
+    pub median_latency: Option<f64>, // Median seconds of signal before deciding
+    pub channels: usize,    // Channels with at least one decision
+    pub withdrawn: usize,   // Decisions left out because their read was tombstoned
+}
+
+/// Seconds of signal seen before each decision, where the log has sample counts
+fn latencies(decisions: &[Decision], sample_rate: f64) -> Vec<(f64, f64)> {
+    decisions
+        .iter()
+        .filter_map(|d| d.samples.map(|s| (d.time, s / sample_rate)))
+        .collect()
+}
+
+/// Totals the decisions, `withdrawn` more having been left out as tombstoned;
+/// latencies convert sample counts at `sample_rate` Hz
+pub fn analyze(decisions: &[Decision], withdrawn: usize, sample_rate: f64) -> DecisionReport {
+    let mut counts = [0; 3];
+    let mut channels = BTreeSet::new();
+    for d in decisions {
+        counts[d.kind as usize] += 1;
+        channels.insert(d.channel);
+    }
+    let latency: Vec<f64> = latencies(decisions, sample_rate)
+        .into_iter()
+        .map(|(_, l)| l)
+        .collect();
+    DecisionReport {
+        counts,
+        unblock_rate: if decisions.is_empty() {
+            f64::NAN
+        } else {
+            counts[DecisionKind::Unblock as usize] as f64 / decisions.len() as f64
+        },
+        median_latency: (!latency.is_empty()).then(|| Center::Median.of(&latency)),
+        channels: channels.len(),
+        withdrawn,
+    }
+}
+
+/// Prints the decision totals
+pub fn print_report(report: &DecisionReport) {
+    let total: usize = report.counts.iter().sum();
+    println!(
+        "Adaptive sampling decisions: {} on {} channels",
+        total, report.channels
+    );
+    for (kind, count) in DecisionKind::ALL.iter().zip(report.counts) {
+        println!(
+            "  {}: {} ({:.1}%)",
+            kind.label(),
+            count,
+            100.0 * count as f64 / total.max(1) as f64
+        );
+    }
+    println!("  Unblock rate: {:.3}", report.unblock_rate);
+    if let Some(latency) = report.median_latency {
+        println!("  Median decision latency: {:.3} s", latency);
+    }
+    if report.withdrawn > 0 {
+        println!(
+            "  Left out: {} decisions on tombstoned reads",
+            report.withdrawn
+        );
+    }
+}
+
+/// Plots the unblock rate and decision latency over time, and each
+/// channel's decision composition
+pub fn plot(
+    decisions: &[Decision],
+    sample_rate: f64,
+    output_path: &str,
+) -> Result<(), Box<dyn Error>> {
+    if decisions.is_empty() {
+        return Err("No decisions to plot".into());
+    }
+    render::to_file(
+        &DecisionFigure {
+            decisions,
+            sample_rate,
+        },
+        output_path,
+    )
+}
+
+/// Time bin of each `(time, value)` point, as `(bin centre, values)`
+fn bin_by_time(points: &[(f64, f64)], first: f64, last: f64) -> Vec<(f64, Vec<f64>)> {
+    let width = ((last - first) / TIME_BINS as f64).max(f64::EPSILON);
+    let mut bins = vec![Vec::new(); TIME_BINS];
+    for &(t, v) in points {
+        bins[(((t - first) / width) as usize).min(TIME_BINS - 1)].push(v);
+    }
+    bins.into_iter()
+        .enumerate()
+        .filter(|(_, values)| !values.is_empty())
+        .map(|(i, values)| (first + (i as f64 + 0.5) * width, values))
+        .collect()
+}
+
+/// Unblock rate, latency and channel composition as three stacked panels
+struct DecisionFigure<'a> {
+    decisions: &'a [Decision],
+    sample_rate: f64,
+}
+
+impl Figure for DecisionFigure<'_> {
+    fn size(&self) -> (u32, u32) {
+        (1600, 1350)
+    }
+
+    fn draw<DB: DrawingBackend>(&self, root: &DrawingArea<DB, Shift>) -> Result<(), Box<dyn Error>>
+    where
+        DB::ErrorType: 'static,
+    {
+        let &DecisionFigure {
+            decisions,
+            sample_rate,
+        } = self;
+        let (Some(first), Some(last)) = (decisions.first(), decisions.last()) else {
+            return Err("No decisions to plot".into());
+        };
+        // Pad a single batch so the time axis has a non-empty range
+        let (first, last) = (first.time, last.time.max(first.time + 1.0));
+
+        root.fill(&GREY_500)?;
+        let panels = root.split_evenly((3, 1));
+
+        // Share of unblocks per time bin
+        let unblocks: Vec<(f64, f64)> = decisions
+            .iter()
+            .map(|d| (d.time, f64::from(u8::from(d.kind == DecisionKind::Unblock))))
+            .collect();
+        let rate: Vec<(f64, f64)> = bin_by_time(&unblocks, first, last)
+            .into_iter()
+            .map(|(t, values)| (t, Center::Mean.of(&values)))
+            .collect();
+        let mut chart = ChartBuilder::on(&panels[0])
+            .caption("Unblock Rate", ("sans-serif", 20))
+            .margin(20)
+            .x_label_area_size(50)
+            .y_label_area_size(100)
+            .build_cartesian_2d(first..last, 0.0..1.0)?;
+        chart
+            .configure_mesh()
+            .x_labels(5)
+            .y_labels(5)
+            .x_desc("Batch Time")
+            .y_desc("Unblocked Share")
+            .draw()?;
+        chart.draw_series(LineSeries::new(
+            rate,
+            DecisionKind::Unblock.color().stroke_width(2),
+        ))?;
+
+        // Median seconds of signal before deciding, per time bin
+        let latency: Vec<(f64, f64)> = bin_by_time(&latencies(decisions, sample_rate), first, last)
+            .into_iter()
+            .map(|(t, values)| (t, Center::Median.of(&values)))
+            .collect();
+        let max_latency = latency.iter().map(|p| p.1).fold(0.0, f64::max).max(0.1);
+        let mut chart = ChartBuilder::on(&panels[1])
+            .caption("Decision Latency (median)", ("sans-serif", 20))
+            .margin(20)
+            .x_label_area_size(50)
+            .y_label_area_size(100)
+            .build_cartesian_2d(first..last, 0.0..max_latency * 1.05)?;
+        chart
+            .configure_mesh()
+            .x_labels(5)
+            .y_labels(5)
+            .x_desc("Batch Time")
+            .y_desc("Seconds of Signal")
+            .draw()?;
+        chart.draw_series(LineSeries::new(latency, &BLUE))?;
+
+        // Decision shares per channel, stacked in `DecisionKind::ALL` order
+        let mut per_channel: BTreeMap<u32, [usize; 3]> = BTreeMap::new();
+        for d in decisions {
+            per_channel.entry(d.channel).or_default()[d.kind as usize] += 1;
+        }
+        let max_channel = per_channel.keys().last().copied().unwrap_or(0);
+        let mut chart = ChartBuilder::on(&panels[2])
+            .caption("Decisions per Channel", ("sans-serif", 20))
+            .margin(20)
+            .x_label_area_size(50)
+            .y_label_area_size(100)
+            .build_cartesian_2d(0.0..max_channel as f64 + 1.0, 0.0..1.0)?;
+        chart
+            .configure_mesh()
+            .x_labels(8)
+            .y_labels(5)
+            .x_desc("Channel")
+            .y_desc("Share of Decisions")
+            .draw()?;
+        for (k, kind) in DecisionKind::ALL.into_iter().enumerate() {
+            let color = kind.color();
+            chart
+                .draw_series(per_channel.iter().map(|(&channel, counts)| {
+                    let total = counts.iter().sum::<usize>().max(1) as f64;
+                    let below: usize = counts[..k].iter().sum();
+                    let x = channel as f64;
+                    Rectangle::new(
+                        [
+                            (x - 0.5, below as f64 / total),
+                            (x + 0.5, (below + counts[k]) as f64 / total),
+                        ],
+                        color.filled(),
+                    )
+                }))?
+                .label(kind.label())
+                .legend(move |(x, y)| {
+                    Rectangle::new([(x, y - 5), (x + 15, y + 5)], color.filled())
+                });
+        }
+        chart
+            .configure_series_labels()
+            .position(SeriesLabelPosition::UpperRight)
+            .background_style(WHITE.mix(0.8))
+            .border_style(BLACK)
+            .draw()?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,269 @@
+// Single-file HTML digest meant to be emailed at the end of a run, and the
+// full report with every panel embedded
+use std::collections::HashSet;
+use std::error::Error;
+use std::fs;
+
+use crate::locale::Language;
+use crate::model::{Field, FieldSummary, Health, RunData};
+use crate::plot::{FIGURE_TITLE, PanelImage};
+use crate::precision::Precision;
+use crate::{locale, robust, threads};
+
+/// One metric line of the digest table
+#[derive(Debug, Clone)]
+pub struct DigestRow {
+    pub metric: String,               // Metric name
+    pub center: f64,                  // Typical value over the run
+    pub min: f64,                     // Smallest value
+    pub max: f64,                     // Largest value
+    pub precision: Precision,         // Rounding of the three values
+    pub status: Option<&'static str>, // Check outcome label, if the metric is checked
+}
+
+/// Everything shown in the digest
+#[derive(Debug, Clone)]
+pub struct Digest {
+    pub title: String,                         // Heading, also used as the page title
+    pub input: String,                         // Input file the numbers were computed from
+    pub center: &'static str,                  // Column header of the typical-value statistic
+    pub language: Language,                    // Language of the fixed labels
+    pub records: usize,                        // Number of parsed records
+    pub span_seconds: f64,                     // Covered batch time span
+    pub provenance: String, // How the statistics were computed (mode, rows, quantiles)
+    pub verdict: &'static str, // Overall pass/fail label
+    pub rows: Vec<DigestRow>, // Per-metric numbers
+    pub metadata: Vec<(&'static str, String)>, // Run metadata: (label, value)
+    pub thumbnail: Option<Vec<u8>>, // PNG thumbnail of the figure, if one was rendered
+    pub panels: Vec<PanelImage>, // Full-size PNG of each panel, empty in plain digests
+}
+
+/// Writes the digest as one self-contained HTML file (no external resources)
+pub fn write_digest(path: &str, digest: &Digest) -> Result<(), Box<dyn Error>> {
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    html.push_str(&format!("<title>{}</title>\n", escape_html(&digest.title)));
+    html.push_str(
+        "<style>body{font-family:sans-serif;max-width:720px;margin:auto}\
+         table{border-collapse:collapse}td,th{padding:2px 8px;text-align:right}\
+         td:first-child,th:first-child{text-align:left}\
+         .OK{color:#2ea043}.WARN{color:#ffb000}.FAIL{color:#dc322f}</style>\n",
+    );
+    html.push_str("</head>\n<body>\n");
+    html.push_str(&format!("<h1>{}</h1>\n", escape_html(&digest.title)));
+    let tr = |english| digest.language.tr(english);
+    html.push_str(&format!(
+        "<p>{}: <code>{}</code><br>{}: {}<br>{}: {:.1} s<br>{}: {}</p>\n",
+        tr("Input"),
+        escape_html(&digest.input),
+        tr("Records"),
+        digest.records,
+        tr("Time span"),
+        digest.span_seconds,
+        tr("Statistics"),
+        escape_html(&digest.provenance)
+    ));
+    if !digest.metadata.is_empty() {
+        html.push_str(&format!("<h2>{}</h2>\n<table>\n", tr("Run")));
+        for (label, value) in &digest.metadata {
+            html.push_str(&format!(
+                "<tr><td>{}</td><td>{}</td></tr>\n",
+                tr(label),
+                escape_html(value)
+            ));
+        }
+        html.push_str("</table>\n");
+    }
+    html.push_str(&format!(
+        "<h2>{}: <span class=\"{1}\">{1}</span></h2>\n",
+        tr("Verdict"),
+        digest.verdict
+    ));
+
+    html.push_str(&format!(
+        "<table>\n<tr><th>{}</th><th>{}</th><th>{}</th><th>{}</th><th>{}</th></tr>\n",
+        tr("Metric"),
+        tr(digest.center),
+        tr("Min"),
+        tr("Max"),
+        tr("Status")
+    ));
+    for row in &digest.rows {
+        let status = row.status.unwrap_or("");
+        html.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td class=\"{}\">{}</td></tr>\n",
+            escape_html(&row.metric),
+            row.precision.format(row.center),
+            row.precision.format(row.min),
+            row.precision.format(row.max),
+            status,
+            status
+        ));
+    }
+    html.push_str("</table>\n");
+
+    if let Some(png) = &digest.thumbnail {
+        html.push_str(&format!(
+            "<p><img alt=\"{}\" src=\"data:image/png;base64,{}\"></p>\n",
+            escape_html(&digest.title),
+            base64(png)
+        ));
+    }
+
+    if !digest.panels.is_empty() {
+        html.push_str(&format!("<h2>{}</h2>\n", tr("Panels")));
+        for panel in &digest.panels {
+            html.push_str(&format!(
+                "<h3>{0}</h3>\n<p><img alt=\"{0}\" style=\"max-width:100%\" \
+                 src=\"data:image/png;base64,{1}\"></p>\n",
+                escape_html(&panel.title),
+                base64(&panel.png)
+            ));
+        }
+    }
+
+    html.push_str("</body>\n</html>\n");
+    fs::write(path, html)?;
+    Ok(())
+}
+
+/// Standard (RFC 4648) base64 encoding with padding
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 0x3F) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// Escapes the characters that are significant in HTML text and attributes
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Run metadata shown above the verdict: kit, channels, batch time bounds and
+/// whatever the parser dropped or sampled
+fn run_metadata(data: &RunData) -> Vec<(&'static str, String)> {
+    let mut metadata = Vec::new();
+    if let Some(kit) = &data.kit {
+        metadata.push(("Kit", kit.clone()));
+    }
+    if !data.channel.is_empty() {
+        let channels: HashSet<u32> = data.channel.iter().copied().collect();
+        metadata.push(("Channels", channels.len().to_string()));
+    }
+    if let Some((first, last)) = data.time_range() {
+        metadata.push(("First batch", format!("{:.3}", first)));
+        metadata.push(("Last batch", format!("{:.3}", last)));
+    }
+    if let Some(info) = data.sample {
+        metadata.push(("Sampling", info.describe()));
+    }
+    if let Some(report) = &data.validation {
+        metadata.push((
+            "Validation",
+            format!(
+                "{} rows checked, {} invalid, {} suspect",
+                report.checked, report.invalid, report.suspect
+            ),
+        ));
+    }
+    if let Some(report) = &data.tombstones {
+        metadata.push(("Tombstones", report.describe()));
+    }
+    if !data.schema.columns.is_empty() {
+        metadata.push((
+            "Schema",
+            format!(
+                "{} ({} columns)",
+                data.schema.fingerprint(),
+                data.schema.columns.len()
+            ),
+        ));
+    }
+    if !data.events.is_empty() {
+        let events: usize = data.events.iter().map(|e| e.count).sum();
+        metadata.push(("Server log events", events.to_string()));
+    }
+    metadata
+}
+
+/// Collects the numbers and checks shown in the HTML digest.
+///
+/// The verdict is the worst state of any checked field or of `checks`, the
+/// combined outcome of the validations and QC rules run outside the table.
+/// `panels` are embedded at full size after the thumbnail (the report
+/// subcommand); plain digests pass none to stay small enough to email.
+#[allow(clippy::too_many_arguments)]
+pub fn build_digest(
+    input: &str,
+    data: &RunData,
+    fields: &[Field],
+    checks: Health,
+    provenance: &str,
+    center: robust::Center,
+    language: locale::Language,
+    thumbnail: Option<Vec<u8>>,
+    panels: Vec<PanelImage>,
+) -> Digest {
+    // One fused pass per field, shared by the table and the verdict
+    let summaries: Vec<FieldSummary> = threads::map(fields, |f| FieldSummary::new(data, f, center));
+
+    let rows: Vec<DigestRow> = fields
+        .iter()
+        .zip(&summaries)
+        .map(|(field, summary)| DigestRow {
+            metric: language.tr(field.title).to_string(),
+            center: summary.center,
+            min: summary.min,
+            max: summary.max,
+            precision: field.precision,
+            status: field.thresholds.map(|t| t.classify(summary.center).label()),
+        })
+        .collect();
+
+    let verdict = fields
+        .iter()
+        .zip(&summaries)
+        .filter_map(|(field, summary)| field.thresholds.map(|t| t.classify(summary.center)))
+        .max()
+        .unwrap_or(Health::Ok)
+        .max(checks);
+
+    let span_seconds = match data.time_range() {
+        Some((first, last)) => last - first,
+        None => 0.0,
+    };
+
+    Digest {
+        title: language.tr(FIGURE_TITLE).to_string(),
+        input: input.to_string(),
+        center: center.header(),
+        language,
+        records: data.len(),
+        span_seconds,
+        provenance: provenance.to_string(),
+        verdict: verdict.label(),
+        rows,
+        metadata: run_metadata(data),
+        thumbnail,
+        panels,
+    }
+}
@@ -0,0 +1,179 @@
+// Per-read predicates for exporting read-ID lists
+use std::error::Error;
+
+use crate::model::RunData;
+
+/// Comparison operators, longest first so `>=` is not read as `>`
+const OPERATORS: &[(&str, Op)] = &[
+    (">=", Op::Ge),
+    ("<=", Op::Le),
+    ("!=", Op::Ne),
+    (">", Op::Gt),
+    ("<", Op::Lt),
+    ("=", Op::Eq),
+];
+
+/// Comparison applied between a read's value and the filter value
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Gt,
+    Ge,
+    Lt,
+    Le,
+    Eq,
+    Ne,
+}
+
+/// Per-read field a filter can test
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Target {
+    Bases,
+    Samples,
+    Qscore,
+    Channel,
+    Time,
+    Barcode,
+}
+
+/// A single `field<op>value` condition, e.g. `mean_qscore<10` or `barcode=barcode01`
+#[derive(Debug, Clone)]
+pub struct Predicate {
+    target: Target,
+    op: Op,
+    number: f64,  // Value for numeric fields
+    text: String, // Value for the barcode
+}
+
+impl Predicate {
+    /// Parses an expression such as `bases>=1000`, `channel=12` or `barcode!=unclassified`.
+    ///
+    /// `length` is accepted for `bases` and `qscore` for `mean_qscore`.
+    pub fn parse(expr: &str) -> Result<Self, Box<dyn Error>> {
+        let (at, symbol, op) = OPERATORS
+            .iter()
+            .filter_map(|&(symbol, op)| expr.find(symbol).map(|at| (at, symbol, op)))
+            .min_by_key(|&(at, symbol, _)| (at, usize::MAX - symbol.len()))
+            .ok_or_else(|| format!("Filter '{}' has no comparison operator", expr))?;
+        let name = expr[..at].trim();
+        let value = expr[at + symbol.len()..].trim();
+
+        let target = match name {
+            "bases" | "length" => Target::Bases,
+            "samples" => Target::Samples,
+            "mean_qscore" | "qscore" => Target::Qscore,
+            "channel" => Target::Channel,
+            "time" | "batch_time" => Target::Time,
+            "barcode" | "barcode_arrangement" => Target::Barcode,
+            _ => return Err(format!("Unknown filter field '{}'", name).into()),
+        };
+
+        if target == Target::Barcode {
+            if !matches!(op, Op::Eq | Op::Ne) {
+                return Err(format!("Filter '{}': barcode only supports = and !=", expr).into());
+            }
+            return Ok(Predicate {
+                target,
+                op,
+                number: f64::NAN,
+                text: value.to_string(),
+            });
+        }
+
+        Ok(Predicate {
+            target,
+            op,
+            number: value
+                .parse()
+                .map_err(|_| format!("Filter '{}': '{}' is not a number", expr, value))?,
+            text: String::new(),
+        })
+    }
+
+    /// Whether row `i` of the store satisfies the condition
+    pub fn matches(&self, data: &RunData, i: usize) -> bool {
+        let value = match self.target {
+            Target::Bases => data.bases[i],
+            Target::Samples => data.samples[i],
+            Target::Qscore => data.mean_qscore[i],
+            Target::Channel => data.channel[i] as f64,
+            Target::Time => data.time[i],
+            Target::Barcode => {
+                let equal = data.barcode[i] == self.text;
+                return if self.op == Op::Eq { equal } else { !equal };
+            }
+        };
+        match self.op {
+            Op::Gt => value > self.number,
+            Op::Ge => value >= self.number,
+            Op::Lt => value < self.number,
+            Op::Le => value <= self.number,
+            Op::Eq => value == self.number,
+            Op::Ne => value != self.number,
+        }
+    }
+}
+
+/// Read IDs of all rows that satisfy every predicate
+pub fn select_reads(data: &RunData, predicates: &[Predicate]) -> Vec<String> {
+    (0..data.len())
+        .filter(|&i| predicates.iter().all(|p| p.matches(data, i)))
+        .map(|i| data.read_id[i].clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run() -> RunData {
+        RunData {
+            read_id: ["a", "b", "c", "d"].map(String::from).to_vec(),
+            time: vec![10.0, 20.0, 30.0, 40.0],
+            channel: vec![1, 12, 12, 500],
+            samples: vec![4000.0, 8000.0, 12000.0, 2000.0],
+            bases: vec![500.0, 1000.0, 1500.0, 250.0],
+            mean_qscore: vec![7.5, 9.0, 12.0, 15.0],
+            barcode: ["barcode01", "barcode02", "barcode01", "unclassified"]
+                .map(String::from)
+                .to_vec(),
+            ..Default::default()
+        }
+    }
+
+    fn select(expressions: &[&str]) -> Vec<String> {
+        let predicates: Vec<Predicate> = expressions
+            .iter()
+            .map(|e| Predicate::parse(e).unwrap())
+            .collect();
+        select_reads(&run(), &predicates)
+    }
+
+    #[test]
+    fn numeric_predicates() {
+        assert_eq!(select(&["bases>=1000"]), ["b", "c"]);
+        assert_eq!(select(&["length > 1000"]), ["c"]);
+        assert_eq!(select(&["qscore<9"]), ["a"]);
+        assert_eq!(select(&["mean_qscore<=9"]), ["a", "b"]);
+        assert_eq!(select(&["channel=12"]), ["b", "c"]);
+        assert_eq!(select(&["channel!=12"]), ["a", "d"]);
+        assert_eq!(select(&["samples>3000", "batch_time<30"]), ["a", "b"]);
+        assert_eq!(select(&[]), ["a", "b", "c", "d"]);
+    }
+
+    #[test]
+    fn barcode_predicates() {
+        assert_eq!(select(&["barcode=barcode01"]), ["a", "c"]);
+        assert_eq!(
+            select(&["barcode_arrangement!=unclassified"]),
+            ["a", "b", "c"]
+        );
+        assert!(Predicate::parse("barcode>barcode01").is_err());
+    }
+
+    #[test]
+    fn rejects_malformed_predicates() {
+        assert!(Predicate::parse("bases").is_err());
+        assert!(Predicate::parse("width>3").is_err());
+        assert!(Predicate::parse("bases>many").is_err());
+    }
+}
@@ -0,0 +1,296 @@
+// Post-incident timeline of restarts, data gaps and error bursts
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use plotters::style::full_palette::GREY_500;
+use std::error::Error;
+
+use crate::model::RunData;
+use crate::render::{self, Figure};
+use crate::robust::Center;
+use crate::serverlog::{self, EventKind, LogEvent};
+
+/// A gap is at least this many typical batch intervals long...
+const GAP_FACTOR: f64 = 10.0;
+/// ...and never shorter than this many seconds
+const MIN_GAP: f64 = 60.0;
+/// Error events within one error window that make a burst
+const BURST_ERRORS: usize = 5;
+
+/// Kind of reconstructed incident
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IncidentKind {
+    Restart,    // Server start after the first one
+    Gap,        // No batches for far longer than usual
+    ErrorBurst, // Dense run of CUDA errors or batch retries
+}
+
+impl IncidentKind {
+    fn label(self) -> &'static str {
+        match self {
+            IncidentKind::Restart => "restart",
+            IncidentKind::Gap => "gap",
+            IncidentKind::ErrorBurst => "error burst",
+        }
+    }
+
+    fn color(self) -> RGBColor {
+        match self {
+            IncidentKind::Restart => RGBColor(0, 0, 0),
+            IncidentKind::Gap => RGBColor(108, 113, 196),
+            IncidentKind::ErrorBurst => RGBColor(220, 50, 47),
+        }
+    }
+}
+
+/// One incident on the timeline
+#[derive(Debug, Clone)]
+pub struct Incident {
+    pub kind: IncidentKind, // What happened
+    pub start: f64,         // Unix seconds
+    pub end: f64,           // Equal to `start` for instantaneous incidents
+    pub count: usize,       // Events involved (restarts and bursts)
+}
+
+/// Longest pause between batches that is not a gap: `GAP_FACTOR` times
+/// the median batch interval, and at least `MIN_GAP` seconds
+pub fn gap_limit(data: &RunData) -> f64 {
+    let typical = match data.batch_interval.get(1..) {
+        Some(intervals) if !intervals.is_empty() => Center::Median.of(intervals),
+        _ => 0.0,
+    };
+    (typical * GAP_FACTOR).max(MIN_GAP)
+}
+
+/// Reconstructs restarts, gaps and error bursts from batch rows and log events
+pub fn reconstruct(data: &RunData) -> Vec<Incident> {
+    let mut incidents = Vec::new();
+
+    // Every server start but the first is a restart
+    incidents.extend(
+        data.events
+            .iter()
+            .filter(|e| e.kind == EventKind::Start)
+            .skip(1)
+            .map(|e| Incident {
+                kind: IncidentKind::Restart,
+                start: e.time,
+                end: e.time,
+                count: 1,
+            }),
+    );
+
+    // Gaps relative to the run's usual cadence
+    if data.len() > 1 {
+        let limit = gap_limit(data);
+        incidents.extend(
+            data.time
+                .windows(2)
+                .filter(|w| w[1] - w[0] > limit)
+                .map(|w| Incident {
+                    kind: IncidentKind::Gap,
+                    start: w[0],
+                    end: w[1],
+                    count: 0,
+                }),
+        );
+    }
+
+    // Chains of errors no further apart than the error window; a collapsed
+    // event counts all of its repeats
+    let errors: Vec<&LogEvent> = data.events.iter().filter(|e| e.kind.is_error()).collect();
+    let mut first = 0;
+    let mut count = 0;
+    for i in 0..=errors.len() {
+        if i == errors.len()
+            || (i > 0 && errors[i].time - errors[i - 1].last > serverlog::ERROR_WINDOW)
+        {
+            if count >= BURST_ERRORS {
+                incidents.push(Incident {
+                    kind: IncidentKind::ErrorBurst,
+                    start: errors[first].time,
+                    end: errors[i - 1].last,
+                    count,
+                });
+            }
+            first = i;
+            count = 0;
+        }
+        if let Some(error) = errors.get(i) {
+            count += error.count;
+        }
+    }
+
+    incidents.sort_by(|a, b| a.start.total_cmp(&b.start));
+    incidents
+}
+
+/// Prints the incidents in time order
+pub fn print_report(incidents: &[Incident]) {
+    println!("Forensic timeline: {} incidents", incidents.len());
+    for i in incidents {
+        match i.kind {
+            IncidentKind::Restart => println!("  {:.0}: restart", i.start),
+            IncidentKind::Gap => println!(
+                "  {:.0}: gap of {:.0} s without batches",
+                i.start,
+                i.end - i.start
+            ),
+            IncidentKind::ErrorBurst => println!(
+                "  {:.0}: error burst of {} events over {:.0} s",
+                i.start,
+                i.count,
+                i.end - i.start
+            ),
+        }
+    }
+}
+
+/// Batches per minute at each row, counting the preceding 60 s
+fn batch_rate(times: &[f64]) -> Vec<f64> {
+    times
+        .iter()
+        .enumerate()
+        .map(|(i, &t)| (i + 1 - times.partition_point(|&s| s <= t - 60.0)) as f64)
+        .collect()
+}
+
+/// Draws aligned evidence tracks above an incident track.
+///
+/// Tracks are the batch rate, basecaller latency and, when loaded, the server
+/// error rate and ASIC temperature; log events are marked on every track.
+pub fn plot(
+    data: &RunData,
+    incidents: &[Incident],
+    output_path: &str,
+) -> Result<(), Box<dyn Error>> {
+    if data.is_empty() {
+        return Err("No records for the forensic timeline".into());
+    }
+    let figure = ForensicFigure { data, incidents };
+    render::to_file(&figure, output_path)
+}
+
+/// Evidence tracks over the incident track
+struct ForensicFigure<'a> {
+    data: &'a RunData,
+    incidents: &'a [Incident],
+}
+
+impl Figure for ForensicFigure<'_> {
+    fn size(&self) -> (u32, u32) {
+        let tracks = 2
+            + usize::from(!self.data.server_errors.is_empty())
+            + usize::from(!self.data.asic_temperature.is_empty());
+        (1600, 300 * (tracks as u32 + 1))
+    }
+
+    fn draw<DB: DrawingBackend>(&self, root: &DrawingArea<DB, Shift>) -> Result<(), Box<dyn Error>>
+    where
+        DB::ErrorType: 'static,
+    {
+        let &ForensicFigure { data, incidents } = self;
+        // The window covers the batches and any log lines around them
+        let (Some(&first), Some(&last)) = (data.time.first(), data.time.last()) else {
+            return Err("No records for the forensic timeline".into());
+        };
+        let min_time = data.events.iter().map(|e| e.time).fold(first, f64::min);
+        let max_time = data.events.iter().map(|e| e.time).fold(last, f64::max);
+
+        let rate = batch_rate(&data.time);
+        let mut tracks: Vec<(&str, &[f64])> = vec![
+            ("Batches/min", &rate),
+            ("Time in Basecaller", &data.time_in_basecaller),
+        ];
+        if !data.server_errors.is_empty() {
+            tracks.push(("Server Errors/min", &data.server_errors));
+        }
+        if !data.asic_temperature.is_empty() {
+            tracks.push(("ASIC Temperature", &data.asic_temperature));
+        }
+
+        root.fill(&GREY_500)?;
+        let panels = root.split_evenly((tracks.len() + 1, 1));
+
+        for (area, &(title, values)) in panels.iter().zip(&tracks) {
+            let (low, high) = values
+                .iter()
+                .fold((f64::INFINITY, f64::NEG_INFINITY), |acc, &v| {
+                    (acc.0.min(v), acc.1.max(v))
+                });
+            let pad = ((high - low) * 0.05).max(f64::EPSILON);
+            let mut chart = ChartBuilder::on(area)
+                .caption(title, ("sans-serif", 20))
+                .margin(20)
+                .x_label_area_size(50)
+                .y_label_area_size(100)
+                .build_cartesian_2d(min_time..max_time, low - pad..high + pad)?;
+            chart
+                .configure_mesh()
+                .x_labels(5)
+                .y_labels(5)
+                .x_desc("Batch Time")
+                .y_desc(title)
+                .draw()?;
+
+            // Incidents shade the evidence behind the data
+            chart.draw_series(incidents.iter().filter(|i| i.end > i.start).map(|i| {
+                Rectangle::new(
+                    [(i.start, low - pad), (i.end, high + pad)],
+                    i.kind.color().mix(0.2).filled(),
+                )
+            }))?;
+            chart.draw_series(LineSeries::new(
+                data.time.iter().copied().zip(values.iter().copied()),
+                &GREEN,
+            ))?;
+            chart.draw_series(data.events.iter().map(|e| {
+                PathElement::new(
+                    vec![(e.time, low - pad), (e.time, high + pad)],
+                    e.kind.color().stroke_width(1),
+                )
+            }))?;
+        }
+
+        // Incident track: spans for gaps and bursts, ticks for restarts
+        let area = panels.last().ok_or("No incident track")?;
+        let mut chart = ChartBuilder::on(area)
+            .caption("Incidents", ("sans-serif", 20))
+            .margin(20)
+            .x_label_area_size(50)
+            .y_label_area_size(100)
+            .build_cartesian_2d(min_time..max_time, 0.0..3.0)?;
+        chart
+            .configure_mesh()
+            .x_labels(5)
+            .y_labels(0)
+            .disable_y_mesh()
+            .x_desc("Batch Time")
+            .draw()?;
+
+        let kinds = [
+            IncidentKind::Restart,
+            IncidentKind::Gap,
+            IncidentKind::ErrorBurst,
+        ];
+        for (row, kind) in kinds.into_iter().enumerate() {
+            let (bottom, top) = (row as f64 + 0.2, row as f64 + 0.8);
+            chart
+                .draw_series(incidents.iter().filter(|i| i.kind == kind).map(|i| {
+                    // Instantaneous incidents still get a visible width
+                    let end = i.end.max(i.start + (max_time - min_time) / 500.0);
+                    Rectangle::new([(i.start, bottom), (end, top)], kind.color().filled())
+                }))?
+                .label(kind.label())
+                .legend(move |(x, y)| {
+                    Rectangle::new([(x, y - 5), (x + 10, y + 5)], kind.color().filled())
+                });
+        }
+        chart
+            .configure_series_labels()
+            .background_style(WHITE.mix(0.8))
+            .border_style(BLACK)
+            .draw()?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,130 @@
+// Golden run: a known-good reference run overlaid on and scored against new runs
+//
+// The reference is another run's batch log. It is aligned on elapsed time,
+// so its first batch is drawn at the new run's first batch.
+use crate::model::{Field, Health, RunData, Thresholds};
+use crate::robust::Center;
+
+/// Width of the elapsed-time bins compared between the runs
+const BIN_SECONDS: f64 = 3600.0;
+/// Mean relative deviation, in percent, at which a metric is flagged
+pub const DEVIATION_THRESHOLDS: Thresholds = Thresholds {
+    warn: 10.0,
+    fail: 25.0,
+    higher_is_worse: true,
+};
+
+/// How far one metric strayed from the reference
+#[derive(Debug, Clone, PartialEq)]
+pub struct Deviation {
+    pub title: &'static str, // Panel title of the metric
+    pub run: f64,            // Typical value of the run
+    pub reference: f64,      // Typical value of the reference over the same hours
+    pub score: f64,          // Mean relative deviation across the hourly bins, in percent
+}
+
+impl Deviation {
+    /// Health of the score against `DEVIATION_THRESHOLDS`
+    pub fn health(&self) -> Health {
+        DEVIATION_THRESHOLDS.classify(self.score)
+    }
+}
+
+/// `reference` with its batch times moved onto `data`'s clock
+pub fn align(reference: &RunData, data: &RunData) -> RunData {
+    let mut aligned = reference.clone();
+    if let (Some((first, _)), Some((run_first, _))) = (reference.time_range(), data.time_range()) {
+        for t in &mut aligned.time {
+            *t += run_first - first;
+        }
+    }
+    aligned
+}
+
+/// Scores each field both runs have values for, over the hours both cover;
+/// `reference` must already be aligned
+pub fn deviations(
+    data: &RunData,
+    reference: &RunData,
+    fields: &[Field],
+    center: Center,
+) -> Vec<Deviation> {
+    let (Some((first, last)), Some((_, reference_last))) =
+        (data.time_range(), reference.time_range())
+    else {
+        return Vec::new();
+    };
+    let end = last.min(reference_last);
+    let bins = (((end - first) / BIN_SECONDS).ceil() as usize).max(1);
+    let binned = |run: &RunData, values: &[f64]| -> Vec<Vec<f64>> {
+        let mut binned = vec![Vec::new(); bins];
+        for (&t, &v) in run.time.iter().zip(values) {
+            if t <= end && v.is_finite() {
+                binned[(((t - first) / BIN_SECONDS) as usize).min(bins - 1)].push(v);
+            }
+        }
+        binned
+    };
+
+    fields
+        .iter()
+        .filter_map(|field| {
+            let values = (field.column)(data);
+            let reference_values = (field.column)(reference);
+            if values.len() != data.len() || reference_values.len() != reference.len() {
+                return None; // Derived for one run only
+            }
+            let run_bins = binned(data, values);
+            let reference_bins = binned(reference, reference_values);
+            let scores: Vec<f64> = run_bins
+                .iter()
+                .zip(&reference_bins)
+                .filter(|(a, b)| !a.is_empty() && !b.is_empty())
+                .map(|(a, b)| relative(center.of(a), center.of(b)))
+                .filter(|s| s.is_finite())
+                .collect();
+            if scores.is_empty() {
+                return None;
+            }
+            let overlap = |bins: &[Vec<f64>]| center.of(&bins.concat());
+            Some(Deviation {
+                title: field.title,
+                run: overlap(&run_bins),
+                reference: overlap(&reference_bins),
+                score: scores.iter().sum::<f64>() / scores.len() as f64,
+            })
+        })
+        .collect()
+}
+
+/// Absolute difference relative to the reference value, in percent
+fn relative(value: f64, reference: f64) -> f64 {
+    (value - reference).abs() / reference.abs().max(f64::EPSILON) * 100.0
+}
+
+/// Worst deviation, as `title score%` for digests and logs
+pub fn worst(deviations: &[Deviation]) -> Option<String> {
+    deviations
+        .iter()
+        .max_by(|a, b| a.score.total_cmp(&b.score))
+        .map(|d| format!("{} {:.1}%", d.title, d.score))
+}
+
+/// Prints every metric's deviation from the reference run
+pub fn print_report(name: &str, deviations: &[Deviation]) {
+    println!("Deviation from reference run {}:", name);
+    if deviations.is_empty() {
+        println!("  No metric overlaps the reference");
+        return;
+    }
+    for d in deviations {
+        println!(
+            "  {:<28} {:>12.3} vs {:>12.3}  {:>6.1}%  {}",
+            d.title,
+            d.run,
+            d.reference,
+            d.score,
+            d.health().label()
+        );
+    }
+}
@@ -0,0 +1,190 @@
+// Composite 0–100 run health score from weighted sub-scores
+use std::error::Error;
+use std::fs;
+
+use crate::model::RunData;
+use crate::model::{Health, QSCORE_THRESHOLDS, Thresholds};
+use crate::porescan::PoreScan;
+use crate::precision::Precision;
+
+/// Rows in the rolling window of the Q-score and latency sub-scores
+const WINDOW: usize = 1000;
+/// Basecaller latency service level objective in nanoseconds (1 s)
+const LATENCY_SLO: f64 = 1e9;
+/// Score below which the composite turns amber/red
+pub const HEALTH_SCORE: Thresholds = Thresholds {
+    warn: 70.0,
+    fail: 50.0,
+    higher_is_worse: false,
+};
+
+/// Relative weight of each sub-score; missing sub-scores are left out and the rest renormalised
+#[derive(Debug, Clone, Copy)]
+pub struct Weights {
+    pub yield_: f64,  // Cumulative yield against the expected pace
+    pub qscore: f64,  // Rolling mean Q-score against its thresholds
+    pub latency: f64, // Share of recent batches within the latency SLO
+    pub pores: f64,   // Available pores relative to the first pore scan
+}
+
+impl Default for Weights {
+    fn default() -> Self {
+        Weights {
+            yield_: 0.3,
+            qscore: 0.3,
+            latency: 0.2,
+            pores: 0.2,
+        }
+    }
+}
+
+impl Weights {
+    /// Parses `yield=W,qscore=W,latency=W,pores=W`; unnamed weights keep their default
+    pub fn parse(spec: &str) -> Result<Self, Box<dyn Error>> {
+        let mut weights = Weights::default();
+        for item in spec.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            let (name, value) = item
+                .split_once('=')
+                .ok_or_else(|| format!("Weight '{}' must look like name=value", item))?;
+            let value: f64 = value.trim().parse()?;
+            match name.trim() {
+                "yield" => weights.yield_ = value,
+                "qscore" => weights.qscore = value,
+                "latency" => weights.latency = value,
+                "pores" => weights.pores = value,
+                other => return Err(format!("Unknown health weight '{}'", other).into()),
+            }
+        }
+        Ok(weights)
+    }
+}
+
+/// What the yield sub-score is measured against
+#[derive(Debug, Clone, Copy)]
+pub struct YieldExpectation {
+    pub target_bases: f64, // Bases expected by the end of the run
+    pub run_seconds: f64,  // Planned run length
+}
+
+/// Sub-scores and composite at every row, each 0–100 (NaN where not available)
+#[derive(Debug, Clone, Default)]
+pub struct HealthScores {
+    pub yield_: Vec<f64>,    // Pace of the cumulative yield
+    pub qscore: Vec<f64>,    // Rolling Q-score
+    pub latency: Vec<f64>,   // Rolling latency SLO compliance
+    pub pores: Vec<f64>,     // Pore retention
+    pub composite: Vec<f64>, // Weighted combination
+}
+
+/// Linear score: 100 at the warn level (or better), 0 at the fail level (or worse)
+fn threshold_score(value: f64, t: Thresholds) -> f64 {
+    (100.0 * (value - t.fail) / (t.warn - t.fail)).clamp(0.0, 100.0)
+}
+
+/// Computes all sub-scores and the weighted composite per row of the time-sorted store
+pub fn score(
+    data: &RunData,
+    weights: Weights,
+    expectation: Option<YieldExpectation>,
+    scans: &[PoreScan],
+) -> HealthScores {
+    let n = data.len();
+    let start = data.time.first().copied().unwrap_or(0.0);
+
+    let mut total = 0.0;
+    let yield_ = match expectation {
+        Some(e) if e.target_bases > 0.0 && e.run_seconds > 0.0 => data
+            .time
+            .iter()
+            .zip(&data.bases)
+            .map(|(&t, &b)| {
+                total += b;
+                let expected = e.target_bases * (t - start) / e.run_seconds;
+                if expected > 0.0 {
+                    (100.0 * total / expected).min(100.0)
+                } else {
+                    100.0
+                }
+            })
+            .collect(),
+        _ => vec![f64::NAN; n],
+    };
+
+    let qscore = crate::reduce::rolling_mean(&data.mean_qscore, WINDOW)
+        .into_iter()
+        .map(|q| threshold_score(q, QSCORE_THRESHOLDS))
+        .collect();
+
+    let within: Vec<f64> = data
+        .time_in_basecaller
+        .iter()
+        .map(|&ns| f64::from(u8::from(ns <= LATENCY_SLO)))
+        .collect();
+    let latency = crate::reduce::rolling_mean(&within, WINDOW)
+        .into_iter()
+        .map(|share| 100.0 * share)
+        .collect();
+
+    let pores = match scans.first() {
+        Some(first) if first.available > 0 => data
+            .time
+            .iter()
+            .map(|&t| {
+                let latest = scans.iter().rev().find(|s| s.time <= t).unwrap_or(first);
+                (100.0 * latest.available as f64 / first.available as f64).min(100.0)
+            })
+            .collect(),
+        _ => vec![f64::NAN; n],
+    };
+
+    let mut scores = HealthScores {
+        yield_,
+        qscore,
+        latency,
+        pores,
+        composite: Vec::with_capacity(n),
+    };
+    for i in 0..n {
+        let parts = [
+            (scores.yield_[i], weights.yield_),
+            (scores.qscore[i], weights.qscore),
+            (scores.latency[i], weights.latency),
+            (scores.pores[i], weights.pores),
+        ];
+        let (sum, weight) = parts
+            .iter()
+            .filter(|(s, w)| s.is_finite() && *w > 0.0)
+            .fold((0.0, 0.0), |acc, &(s, w)| (acc.0 + s * w, acc.1 + w));
+        scores
+            .composite
+            .push(if weight > 0.0 { sum / weight } else { f64::NAN });
+    }
+    scores
+}
+
+/// Writes the final scores and verdict as JSON, rounded to `precision`
+pub fn write_verdict(
+    path: &str,
+    scores: &HealthScores,
+    precision: Precision,
+) -> Result<(), Box<dyn Error>> {
+    let last = |column: &[f64]| match column.last() {
+        Some(&v) if v.is_finite() => precision.format(v),
+        _ => "null".to_string(),
+    };
+    let verdict = match scores.composite.last() {
+        Some(&s) if s.is_finite() => HEALTH_SCORE.classify(s),
+        _ => Health::Fail,
+    };
+    let json = format!(
+        "{{\n  \"score\": {},\n  \"verdict\": \"{}\",\n  \"subscores\": {{\"yield\": {}, \"qscore\": {}, \"latency\": {}, \"pores\": {}}}\n}}\n",
+        last(&scores.composite),
+        verdict.label(),
+        last(&scores.yield_),
+        last(&scores.qscore),
+        last(&scores.latency),
+        last(&scores.pores)
+    );
+    fs::write(path, json)?;
+    Ok(())
+}
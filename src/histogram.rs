@@ -0,0 +1,514 @@
+// Distribution histograms with linear, log-spaced, equal-count, fixed-width or automatic bins
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use plotters::style::full_palette::GREY_500;
+use std::error::Error;
+
+use crate::model::Field;
+use crate::model::RunData;
+use crate::render::{self, Figure};
+use crate::robust::Center;
+use crate::seqsummary;
+
+/// Bin count for the fixed-count binnings
+const BINS: usize = 30;
+/// Upper limit for Freedman–Diaconis, which explodes on very wide tails
+const MAX_AUTO_BINS: usize = 200;
+/// Upper limit for fixed-width bins; narrower widths are widened to fit
+const MAX_WIDTH_BINS: usize = 10_000;
+/// Points at which a density overlay is evaluated
+const KDE_POINTS: usize = 200;
+
+/// How bin edges are placed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Binning {
+    Auto,       // Linear bins, width from the Freedman–Diaconis rule
+    Linear,     // `BINS` equal-width bins
+    Log,        // `BINS` log-spaced bins on a log axis, for heavy tails
+    EqualCount, // `BINS` bins holding the same number of values, drawn as density
+    Width(f64), // Bins of this width, on multiples of it
+}
+
+impl Binning {
+    /// Parses a `--histogram-bins` value: a binning name or a bin width
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "auto" | "fd" => Some(Binning::Auto),
+            "linear" => Some(Binning::Linear),
+            "log" => Some(Binning::Log),
+            "equal-count" | "quantile" => Some(Binning::EqualCount),
+            width => width
+                .parse()
+                .ok()
+                .filter(|&w: &f64| w > 0.0 && w.is_finite())
+                .map(Binning::Width),
+        }
+    }
+}
+
+/// Kernel bandwidth of the density overlay
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Bandwidth {
+    Silverman,  // 0.9 · min(σ, IQR / 1.34) · n^(-1/5)
+    Fixed(f64), // In data units (log units with log bins)
+}
+
+impl Bandwidth {
+    /// Parses a `--kde` value: `auto`/`silverman` or a positive number
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "auto" | "silverman" => Some(Bandwidth::Silverman),
+            number => number
+                .parse()
+                .ok()
+                .filter(|&b: &f64| b > 0.0)
+                .map(Bandwidth::Fixed),
+        }
+    }
+
+    /// Resolves the bandwidth for sorted values
+    fn resolve(self, sorted: &[f64]) -> f64 {
+        match self {
+            Bandwidth::Fixed(b) => b,
+            Bandwidth::Silverman => {
+                let n = sorted.len() as f64;
+                let mean = sorted.iter().sum::<f64>() / n;
+                let sd = (sorted.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n).sqrt();
+                let at = |p: f64| sorted[((sorted.len() - 1) as f64 * p) as usize];
+                let iqr = (at(0.75) - at(0.25)) / 1.34;
+                let spread = if iqr > 0.0 { sd.min(iqr) } else { sd };
+                (0.9 * spread * n.powf(-0.2)).max(f64::EPSILON)
+            }
+        }
+    }
+}
+
+/// Gaussian kernel density of sorted values at each point of `at`
+pub fn kde(sorted: &[f64], bandwidth: Bandwidth, at: &[f64]) -> Vec<f64> {
+    let h = bandwidth.resolve(sorted);
+    let norm = 1.0 / (sorted.len() as f64 * h * (2.0 * std::f64::consts::PI).sqrt());
+    at.iter()
+        .map(|&x| {
+            // Values beyond 5 bandwidths contribute nothing visible
+            let lo = sorted.partition_point(|&v| v < x - 5.0 * h);
+            let hi = sorted.partition_point(|&v| v <= x + 5.0 * h);
+            sorted[lo..hi]
+                .iter()
+                .map(|&v| (-0.5 * ((x - v) / h).powi(2)).exp())
+                .sum::<f64>()
+                * norm
+        })
+        .collect()
+}
+
+/// Density overlay scaled to the histogram's bar heights
+fn overlay(
+    sorted: &[f64],
+    edges: &[f64],
+    binning: Binning,
+    bandwidth: Bandwidth,
+) -> Vec<(f64, f64)> {
+    let n = sorted.len() as f64;
+    let (lo, hi) = (edges[0], edges[edges.len() - 1]);
+    if binning == Binning::Log {
+        // Estimate in log space, where the bins are equally wide
+        let logs: Vec<f64> = sorted
+            .iter()
+            .filter(|&&v| v > 0.0)
+            .map(|v| v.ln())
+            .collect();
+        let (a, b) = (lo.ln(), hi.ln());
+        let at: Vec<f64> = (0..KDE_POINTS)
+            .map(|i| a + (b - a) * i as f64 / (KDE_POINTS - 1) as f64)
+            .collect();
+        let width = (b - a) / (edges.len() - 1) as f64;
+        return kde(&logs, bandwidth, &at)
+            .into_iter()
+            .zip(at)
+            .map(|(d, x)| (x.exp(), d * logs.len() as f64 * width))
+            .collect();
+    }
+
+    let at: Vec<f64> = (0..KDE_POINTS)
+        .map(|i| lo + (hi - lo) * i as f64 / (KDE_POINTS - 1) as f64)
+        .collect();
+    // Counts per bin for equal-width bins, counts per unit for density bars
+    let scale = match binning {
+        Binning::EqualCount => n,
+        _ => n * (hi - lo) / (edges.len() - 1) as f64,
+    };
+    kde(sorted, bandwidth, &at)
+        .into_iter()
+        .zip(at)
+        .map(|(d, x)| (x, d * scale))
+        .collect()
+}
+
+/// Bin edges for already sorted, finite values (at least two edges)
+pub fn edges(sorted: &[f64], binning: Binning) -> Vec<f64> {
+    let (min, max) = (sorted[0], sorted[sorted.len() - 1]);
+    if max <= min {
+        return vec![min - 0.5, max + 0.5];
+    }
+    let linear = |bins: usize| -> Vec<f64> {
+        (0..=bins)
+            .map(|i| min + (max - min) * i as f64 / bins as f64)
+            .collect()
+    };
+
+    match binning {
+        Binning::Linear => linear(BINS),
+        Binning::Width(width) => {
+            let width = width.max((max - min) / MAX_WIDTH_BINS as f64);
+            let start = (min / width).floor() * width;
+            let bins = ((max - start) / width).floor() as usize + 1;
+            (0..=bins).map(|i| start + width * i as f64).collect()
+        }
+        Binning::Auto => {
+            // Freedman–Diaconis: width = 2 · IQR · n^(-1/3)
+            let at = |p: f64| sorted[((sorted.len() - 1) as f64 * p) as usize];
+            let width = 2.0 * (at(0.75) - at(0.25)) / (sorted.len() as f64).cbrt();
+            let bins = if width > 0.0 {
+                (((max - min) / width).ceil() as usize).clamp(1, MAX_AUTO_BINS)
+            } else {
+                BINS
+            };
+            linear(bins)
+        }
+        Binning::Log => {
+            // Log bins need a positive lower edge; zeros land in the first bin
+            let low = sorted
+                .iter()
+                .copied()
+                .find(|&v| v > 0.0)
+                .unwrap_or(1.0)
+                .min(max);
+            let (a, b) = (low.ln(), max.ln().max(low.ln() + f64::EPSILON));
+            (0..=BINS)
+                .map(|i| (a + (b - a) * i as f64 / BINS as f64).exp())
+                .collect()
+        }
+        Binning::EqualCount => {
+            let mut edges: Vec<f64> = (0..=BINS)
+                .map(|i| sorted[(sorted.len() - 1) * i / BINS])
+                .collect();
+            // Repeated values collapse edges; drop bins too narrow to draw
+            let tiny = (max - min) * 1e-9;
+            edges.dedup_by(|b, a| *b - *a <= tiny);
+            if edges.len() < 2 {
+                edges = vec![min, max];
+            }
+            edges
+        }
+    }
+}
+
+/// Number of values in each bin; values outside the edges go to the end bins
+pub fn counts(values: &[f64], edges: &[f64]) -> Vec<usize> {
+    let bins = edges.len() - 1;
+    let mut counts = vec![0; bins];
+    for &v in values.iter().filter(|v| v.is_finite()) {
+        let bin = edges.partition_point(|&e| e <= v).saturating_sub(1);
+        counts[bin.min(bins - 1)] += 1;
+    }
+    counts
+}
+
+/// Draws one histogram per field into a two-column grid, optionally with a density overlay
+pub fn plot(
+    data: &RunData,
+    fields: &[Field],
+    binning: Binning,
+    kde: Option<Bandwidth>,
+    output_path: &str,
+) -> Result<(), Box<dyn Error>> {
+    let figure = HistogramFigure {
+        data,
+        fields,
+        binning,
+        kde,
+    };
+    render::to_file(&figure, output_path)
+}
+
+/// Histogram grid of the given fields, two per row
+struct HistogramFigure<'a> {
+    data: &'a RunData,
+    fields: &'a [Field],
+    binning: Binning,
+    kde: Option<Bandwidth>,
+}
+
+impl Figure for HistogramFigure<'_> {
+    fn size(&self) -> (u32, u32) {
+        (1600, 450 * self.fields.len().div_ceil(2).max(1) as u32)
+    }
+
+    fn draw<DB: DrawingBackend>(&self, root: &DrawingArea<DB, Shift>) -> Result<(), Box<dyn Error>>
+    where
+        DB::ErrorType: 'static,
+    {
+        let &HistogramFigure {
+            data,
+            fields,
+            binning,
+            kde,
+        } = self;
+        let rows = fields.len().div_ceil(2).max(1);
+        root.fill(&GREY_500)?;
+        let panels = root.split_evenly((rows, 2));
+
+        for (area, field) in panels.iter().zip(fields) {
+            draw(area, field.title, (field.column)(data), binning, kde, None)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Draws one histogram (with optional density overlay) of `values` onto
+/// `area`, with a vertical line at `threshold` if given
+pub fn draw<DB: DrawingBackend>(
+    area: &DrawingArea<DB, Shift>,
+    title: &str,
+    values: &[f64],
+    binning: Binning,
+    kde: Option<Bandwidth>,
+    threshold: Option<f64>,
+) -> Result<(), Box<dyn Error>>
+where
+    DB::ErrorType: 'static,
+{
+    let mut sorted: Vec<f64> = values.iter().copied().filter(|v| v.is_finite()).collect();
+    if sorted.is_empty() {
+        return Ok(());
+    }
+    sorted.sort_by(f64::total_cmp);
+    let edges = edges(&sorted, binning);
+    let counts = counts(&sorted, &edges);
+
+    // Unequal bins are compared by area, so draw density instead of counts
+    let heights: Vec<f64> = counts
+        .iter()
+        .zip(edges.windows(2))
+        .map(|(&c, e)| match binning {
+            Binning::EqualCount => c as f64 / (e[1] - e[0]),
+            _ => c as f64,
+        })
+        .collect();
+    let curve = kde
+        .map(|b| overlay(&sorted, &edges, binning, b))
+        .unwrap_or_default();
+    let top = heights
+        .iter()
+        .chain(curve.iter().map(|p| &p.1))
+        .copied()
+        .fold(0.0, f64::max)
+        .max(1e-12)
+        * 1.05;
+    let (lo, hi) = (edges[0], edges[edges.len() - 1]);
+    let y_desc = if binning == Binning::EqualCount {
+        "Density"
+    } else {
+        "Count"
+    };
+    let marker = threshold
+        .filter(|t| (lo..=hi).contains(t))
+        .map(|t| [(t, 0.0), (t, top)])
+        .into_iter()
+        .flatten();
+    let bars = edges
+        .windows(2)
+        .zip(&heights)
+        .map(|(e, &h)| Rectangle::new([(e[0], 0.0), (e[1], h)], BLUE.mix(0.6).filled()));
+
+    let mut builder = ChartBuilder::on(area);
+    builder
+        .caption(title, ("sans-serif", 20))
+        .margin(20)
+        .x_label_area_size(50)
+        .y_label_area_size(100);
+    if binning == Binning::Log {
+        let mut chart = builder.build_cartesian_2d((lo..hi).log_scale(), 0.0..top)?;
+        chart
+            .configure_mesh()
+            .x_labels(5)
+            .y_labels(5)
+            .x_desc(title)
+            .y_desc(y_desc)
+            .draw()?;
+        chart.draw_series(bars)?;
+        chart.draw_series(LineSeries::new(curve.iter().copied(), RED.stroke_width(2)))?;
+        chart.draw_series(LineSeries::new(marker, BLACK.stroke_width(2)))?;
+    } else {
+        let mut chart = builder.build_cartesian_2d(lo..hi, 0.0..top)?;
+        chart
+            .configure_mesh()
+            .x_labels(5)
+            .y_labels(5)
+            .x_desc(title)
+            .y_desc(y_desc)
+            .draw()?;
+        chart.draw_series(bars)?;
+        chart.draw_series(LineSeries::new(curve.iter().copied(), RED.stroke_width(2)))?;
+        chart.draw_series(LineSeries::new(marker, BLACK.stroke_width(2)))?;
+    }
+    Ok(())
+}
+
+/// Mean Q-score of every batch (the rows sharing a batch time), or of every
+/// read when the input is a sequencing summary, with what the values are of
+pub fn qscores(data: &RunData) -> (Vec<f64>, &'static str) {
+    let summary = data
+        .schema
+        .columns
+        .iter()
+        .any(|c| c == seqsummary::MARKER_COLUMN);
+    if summary {
+        return (data.mean_qscore.clone(), "reads");
+    }
+    let mut means = Vec::new();
+    let mut row = 0;
+    while row < data.len() {
+        let time = data.time[row];
+        let end = row + data.time[row..].partition_point(|&t| t == time);
+        means.push(Center::Mean.of(&data.mean_qscore[row..end]));
+        row = end;
+    }
+    (means, "batches")
+}
+
+/// Share of `values` at or above `threshold`
+pub fn passing(values: &[f64], threshold: f64) -> f64 {
+    let finite = values.iter().filter(|v| v.is_finite());
+    let n = finite.clone().count();
+    finite.filter(|&&v| v >= threshold).count() as f64 / n.max(1) as f64
+}
+
+/// Draws the distribution of mean Q-scores (see `qscores`), with the
+/// pass/fail `threshold` marked and its pass share in the title
+pub fn plot_qscores(
+    data: &RunData,
+    binning: Binning,
+    kde: Option<Bandwidth>,
+    threshold: Option<f64>,
+    output_path: &str,
+) -> Result<(), Box<dyn Error>> {
+    let (values, unit) = qscores(data);
+    let mut title = format!("Mean Q-score of {} {}", values.len(), unit);
+    if let Some(q) = threshold {
+        title.push_str(&format!(
+            ", {:.1}% at or above Q{}",
+            100.0 * passing(&values, q),
+            q
+        ));
+    }
+    let figure = QscoreFigure {
+        values: &values,
+        title,
+        binning,
+        kde,
+        threshold,
+    };
+    render::to_file(&figure, output_path)
+}
+
+/// One histogram of mean Q-scores
+struct QscoreFigure<'a> {
+    values: &'a [f64],
+    title: String,
+    binning: Binning,
+    kde: Option<Bandwidth>,
+    threshold: Option<f64>,
+}
+
+impl Figure for QscoreFigure<'_> {
+    fn size(&self) -> (u32, u32) {
+        (1600, 600)
+    }
+
+    fn draw<DB: DrawingBackend>(&self, root: &DrawingArea<DB, Shift>) -> Result<(), Box<dyn Error>>
+    where
+        DB::ErrorType: 'static,
+    {
+        root.fill(&GREY_500)?;
+        draw(
+            root,
+            &self.title,
+            self.values,
+            self.binning,
+            self.kde,
+            self.threshold,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn binning_and_bandwidth_names() {
+        assert_eq!(Binning::parse("FD"), Some(Binning::Auto));
+        assert_eq!(Binning::parse("quantile"), Some(Binning::EqualCount));
+        assert_eq!(Binning::parse("0.5"), Some(Binning::Width(0.5)));
+        assert_eq!(Binning::parse("-1"), None);
+        assert_eq!(Bandwidth::parse("auto"), Some(Bandwidth::Silverman));
+        assert_eq!(Bandwidth::parse("2"), Some(Bandwidth::Fixed(2.0)));
+        assert_eq!(Bandwidth::parse("0"), None);
+    }
+
+    #[test]
+    fn edges_span_the_values() {
+        let sorted: Vec<f64> = (1..=9).map(f64::from).collect();
+        let linear = edges(&sorted, Binning::Linear);
+        assert_eq!(linear.len(), BINS + 1);
+        assert_eq!((linear[0], linear[BINS]), (1.0, 9.0));
+        // Fixed widths start on a multiple of the width
+        assert_eq!(
+            edges(&sorted, Binning::Width(2.0)),
+            [0.0, 2.0, 4.0, 6.0, 8.0, 10.0]
+        );
+        let log = edges(&sorted, Binning::Log);
+        assert!((log[0] - 1.0).abs() < 1e-12 && (log[BINS] - 9.0).abs() < 1e-9);
+        assert!(log.windows(2).all(|w| w[0] < w[1]));
+        // A single value still gets one bin around it
+        assert_eq!(edges(&[3.0, 3.0], Binning::Auto), [2.5, 3.5]);
+    }
+
+    #[test]
+    fn equal_count_edges_drop_repeated_values() {
+        let mut sorted = vec![1.0; 90];
+        sorted.extend((0..10).map(|i| 2.0 + f64::from(i)));
+        let edges = edges(&sorted, Binning::EqualCount);
+        assert!(edges.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!((edges[0], edges[edges.len() - 1]), (1.0, 11.0));
+    }
+
+    #[test]
+    fn counts_clamp_to_the_end_bins() {
+        let edges = [0.0, 1.0, 2.0, 3.0];
+        let values = [-5.0, 0.0, 0.5, 1.0, 2.9, 3.0, 7.0, f64::NAN];
+        assert_eq!(counts(&values, &edges), [3, 1, 3]);
+    }
+
+    #[test]
+    fn kde_is_a_density() {
+        let sorted: Vec<f64> = (0..100).map(|i| f64::from(i) / 10.0).collect();
+        let at: Vec<f64> = (0..=400).map(|i| -10.0 + f64::from(i) * 0.075).collect();
+        let area: f64 = kde(&sorted, Bandwidth::Silverman, &at).iter().sum::<f64>() * 0.075;
+        assert!((area - 1.0).abs() < 1e-3, "area {}", area);
+    }
+
+    #[test]
+    fn qscores_average_each_batch() {
+        let data = RunData {
+            time: vec![1.0, 1.0, 2.0, 3.0, 3.0, 3.0],
+            mean_qscore: vec![8.0, 10.0, 12.0, 6.0, 9.0, 12.0],
+            ..Default::default()
+        };
+        assert_eq!(qscores(&data), (vec![9.0, 12.0, 9.0], "batches"));
+        assert_eq!(passing(&[8.0, 9.0, 10.0, f64::NAN], 9.0), 2.0 / 3.0);
+        assert_eq!(passing(&[], 9.0), 0.0);
+    }
+}
@@ -0,0 +1,442 @@
+// Homopolymer error proxy from an aligned BAM
+//
+// Systematic basecalling drift shows first in homopolymers, whose lengths
+// the basecaller mis-counts. Each alignment's reference is rebuilt from its
+// CIGAR and MD tag (no FASTA is needed), and the mismatched, inserted and
+// deleted bases inside or next to reference homopolymers are counted. Reads
+// are matched to batch rows by read ID.
+//
+// Only what this needs of the BAM format is read: BGZF blocks are gzip
+// members, so a multi-member gzip decoder yields the raw BAM stream.
+use flate2::read::MultiGzDecoder;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufReader, ErrorKind, Read};
+
+use crate::model::{RunData, Thresholds};
+
+/// Shortest run of one base that counts as a homopolymer
+pub const MIN_LENGTH: usize = 4;
+/// Number of most recent aligned reads the rolling error rate is computed over
+pub const WINDOW: usize = 1000;
+/// Homopolymer error rate that turns the panel amber/red
+pub const ERROR_RATE: Thresholds = Thresholds {
+    warn: 0.05,
+    fail: 0.10,
+    higher_is_worse: true,
+};
+
+/// BAM magic bytes
+const MAGIC: &[u8; 4] = b"BAM\x01";
+/// Largest uncompressed BGZF block
+const BGZF_BLOCK_SIZE: usize = 64 * 1024;
+/// Largest alignment record read; records span BGZF blocks, and long reads
+/// need many, but a corrupt size must not allocate gigabytes
+const MAX_RECORD_SIZE: usize = 1024 * BGZF_BLOCK_SIZE;
+/// Bytes of a record before its read name
+const FIXED_FIELDS: usize = 32;
+/// Flags of alignments not counted: unmapped, secondary, supplementary
+const SKIPPED_FLAGS: u16 = 0x4 | 0x100 | 0x800;
+/// 4-bit base codes of the BAM sequence encoding
+const BASES: &[u8; 16] = b"=ACMGRSVTWYHKDBN";
+
+/// Homopolymer bases and the errors among them for one read
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ReadErrors {
+    pub bases: u64,  // Reference bases inside homopolymers
+    pub errors: u64, // Mismatched, inserted or deleted bases in or next to them
+}
+
+/// Counts homopolymer errors of every primary alignment with an MD tag, by read name
+pub fn parse(path: &str) -> Result<HashMap<String, ReadErrors>, Box<dyn Error>> {
+    let file = File::open(path).map_err(|e| format!("Cannot open BAM {}: {}", path, e))?;
+    let mut bam = BufReader::new(MultiGzDecoder::new(BufReader::new(file)));
+
+    let mut magic = [0; 4];
+    bam.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(format!("{} is not a BAM file", path).into());
+    }
+    let header_length = read_u32(&mut bam)? as usize;
+    skip(&mut bam, header_length)?;
+    for _ in 0..read_u32(&mut bam)? {
+        let name_length = read_u32(&mut bam)? as usize;
+        skip(&mut bam, name_length + 4)?;
+    }
+
+    let (reads, skipped) = alignments(&mut bam).map_err(|e| format!("{}: {}", path, e))?;
+    if reads.is_empty() && skipped > 0 {
+        return Err(format!(
+            "No usable alignments in {} (primary alignments with MD tags are needed)",
+            path
+        )
+        .into());
+    }
+    Ok(reads)
+}
+
+/// Homopolymer errors by read name of the alignment records up to the end of
+/// `bam`, and the number of records not counted
+fn alignments(bam: &mut impl Read) -> Result<(HashMap<String, ReadErrors>, usize), Box<dyn Error>> {
+    let mut reads = HashMap::new();
+    let mut skipped = 0usize;
+    loop {
+        let block_size = match read_u32(bam) {
+            Ok(size) => size as usize,
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e.into()),
+        };
+        if !(FIXED_FIELDS..=MAX_RECORD_SIZE).contains(&block_size) {
+            return Err(format!("Invalid BAM record size {}", block_size).into());
+        }
+        let mut record = vec![0; block_size];
+        bam.read_exact(&mut record)?;
+        match alignment(&record)? {
+            Some((name, errors)) => {
+                let total: &mut ReadErrors = reads.entry(name).or_default();
+                total.bases += errors.bases;
+                total.errors += errors.errors;
+            }
+            None => skipped += 1,
+        }
+    }
+    Ok((reads, skipped))
+}
+
+/// Read name and homopolymer errors of one BAM record; `None` for records
+/// that are not counted
+fn alignment(record: &[u8]) -> Result<Option<(String, ReadErrors)>, Box<dyn Error>> {
+    let field = |at: usize, len: usize| {
+        record
+            .get(at..at + len)
+            .ok_or("Truncated BAM record")
+            .map(|b| b.iter().rev().fold(0u32, |v, &byte| v << 8 | byte as u32))
+    };
+    let name_length = field(8, 1)? as usize;
+    let cigar_ops = field(12, 2)? as usize;
+    let flag = field(14, 2)? as u16;
+    let seq_length = field(16, 4)? as usize;
+    if flag & SKIPPED_FLAGS != 0 {
+        return Ok(None);
+    }
+
+    let name_at = FIXED_FIELDS;
+    let cigar_at = name_at + name_length;
+    let seq_at = cigar_at + 4 * cigar_ops;
+    let qual_at = seq_at + seq_length.div_ceil(2);
+    let tags_at = qual_at + seq_length;
+    if tags_at > record.len() {
+        return Err(format!(
+            "Truncated BAM record: {} bytes, its fields need {}",
+            record.len(),
+            tags_at
+        )
+        .into());
+    }
+    let name = record[name_at..cigar_at]
+        .split(|&b| b == 0)
+        .next()
+        .unwrap_or_default();
+    let name = String::from_utf8_lossy(name).to_string();
+    let cigar = (0..cigar_ops)
+        .map(|i| field(cigar_at + 4 * i, 4).map(|op| (op & 0xf, op as usize >> 4)))
+        .collect::<Result<Vec<_>, _>>()?;
+    let packed = &record[seq_at..qual_at];
+    let read: Vec<u8> = (0..seq_length)
+        .map(|i| BASES[(packed[i / 2] >> (4 * (1 - i % 2)) & 0xf) as usize])
+        .collect();
+    let Some(md) = md_tag(&record[tags_at..]) else {
+        return Ok(None);
+    };
+    Ok(count_errors(&read, &cigar, md).map(|errors| (name, errors)))
+}
+
+/// Value of the MD tag among a record's tags, if present
+fn md_tag(mut tags: &[u8]) -> Option<&str> {
+    while tags.len() >= 3 {
+        let (tag, kind) = (&tags[..2], tags[2]);
+        tags = &tags[3..];
+        let size = match kind {
+            b'A' | b'c' | b'C' => 1,
+            b's' | b'S' => 2,
+            b'i' | b'I' | b'f' => 4,
+            b'Z' | b'H' => {
+                let end = tags.iter().position(|&b| b == 0)?;
+                if tag == b"MD" {
+                    return std::str::from_utf8(&tags[..end]).ok();
+                }
+                end + 1
+            }
+            b'B' => {
+                let width = match tags.first()? {
+                    b'c' | b'C' => 1,
+                    b's' | b'S' => 2,
+                    _ => 4,
+                };
+                let count = u32::from_le_bytes(tags.get(1..5)?.try_into().ok()?) as usize;
+                5 + width * count
+            }
+            _ => return None,
+        };
+        tags = tags.get(size..)?;
+    }
+    None
+}
+
+/// What the alignment says about one reference position or gap
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Event {
+    Match,
+    Mismatch,
+    Deleted,
+}
+
+/// Rebuilds the aligned reference from the read, CIGAR and MD tag and counts
+/// the errors in and next to its homopolymers; `None` when CIGAR and MD
+/// disagree or the alignment skips reference (spliced reads)
+fn count_errors(read: &[u8], cigar: &[(u32, usize)], md: &str) -> Option<ReadErrors> {
+    let md = md_events(md)?;
+    let mut reference = Vec::with_capacity(md.len()); // (base, event)
+    let mut insertions = Vec::new(); // (reference offset, inserted bases)
+    let (mut query, mut next_md) = (0usize, 0usize);
+    for &(op, length) in cigar {
+        match op {
+            // M, =, X: aligned bases; the MD tag tells matches from mismatches
+            0 | 7 | 8 => {
+                for _ in 0..length {
+                    let (base, event) = *md.get(next_md)?;
+                    let base = match event {
+                        Event::Match => *read.get(query)?,
+                        _ => base,
+                    };
+                    if event == Event::Deleted {
+                        return None;
+                    }
+                    reference.push((base, event));
+                    query += 1;
+                    next_md += 1;
+                }
+            }
+            1 => {
+                insertions.push((reference.len(), length));
+                query += length;
+            }
+            2 => {
+                for _ in 0..length {
+                    let entry = *md.get(next_md)?;
+                    if entry.1 != Event::Deleted {
+                        return None;
+                    }
+                    reference.push(entry);
+                    next_md += 1;
+                }
+            }
+            4 => query += length,
+            5 | 6 => {}
+            _ => return None, // N: spliced
+        }
+    }
+    if next_md != md.len() {
+        return None;
+    }
+
+    // Homopolymer membership of every reference position
+    let bases: Vec<u8> = reference.iter().map(|&(b, _)| b).collect();
+    let mut in_run = vec![false; bases.len()];
+    let mut start = 0;
+    while start < bases.len() {
+        let end = start
+            + bases[start..]
+                .iter()
+                .take_while(|&&b| b == bases[start])
+                .count();
+        if end - start >= MIN_LENGTH && bases[start] != b'N' {
+            in_run[start..end].fill(true);
+        }
+        start = end;
+    }
+
+    let mut errors = ReadErrors {
+        bases: in_run.iter().filter(|&&h| h).count() as u64,
+        ..Default::default()
+    };
+    errors.errors += reference
+        .iter()
+        .zip(&in_run)
+        .filter(|&(&(_, event), &h)| h && event != Event::Match)
+        .count() as u64;
+    // An insertion sits between two reference positions; it counts when
+    // either of them is in a homopolymer
+    errors.errors += insertions
+        .iter()
+        .filter(|&&(at, _)| {
+            (at > 0 && in_run.get(at - 1).copied().unwrap_or(false))
+                || in_run.get(at).copied().unwrap_or(false)
+        })
+        .map(|&(_, length)| length as u64)
+        .sum::<u64>();
+    Some(errors)
+}
+
+/// Expands an MD tag into one entry per reference position: the reference
+/// base for mismatches and deletions (a placeholder for matches)
+fn md_events(md: &str) -> Option<Vec<(u8, Event)>> {
+    let mut events = Vec::new();
+    let mut number = 0usize;
+    let mut deletion = false;
+    for byte in md.bytes() {
+        match byte {
+            b'0'..=b'9' => {
+                number = number * 10 + (byte - b'0') as usize;
+                deletion = false;
+            }
+            b'^' => {
+                events.extend(std::iter::repeat_n((b'=', Event::Match), number));
+                number = 0;
+                deletion = true;
+            }
+            b'A'..=b'Z' | b'a'..=b'z' => {
+                events.extend(std::iter::repeat_n((b'=', Event::Match), number));
+                number = 0;
+                let event = if deletion {
+                    Event::Deleted
+                } else {
+                    Event::Mismatch
+                };
+                events.push((byte.to_ascii_uppercase(), event));
+            }
+            _ => return None,
+        }
+    }
+    events.extend(std::iter::repeat_n((b'=', Event::Match), number));
+    Some(events)
+}
+
+/// Share of homopolymer bases in error over the last `window` aligned reads,
+/// at every row; rows before the first aligned read are NaN
+pub fn rolling_error_rate(
+    data: &RunData,
+    reads: &HashMap<String, ReadErrors>,
+    window: usize,
+) -> Vec<f64> {
+    let mut recent: std::collections::VecDeque<ReadErrors> = Default::default();
+    let mut total = ReadErrors::default();
+    data.read_id
+        .iter()
+        .map(|id| {
+            if let Some(&read) = reads.get(id).filter(|r| r.bases > 0) {
+                recent.push_back(read);
+                total.bases += read.bases;
+                total.errors += read.errors;
+                if recent.len() > window {
+                    let old = recent.pop_front().unwrap_or_default();
+                    total.bases -= old.bases;
+                    total.errors -= old.errors;
+                }
+            }
+            if total.bases == 0 {
+                f64::NAN
+            } else {
+                total.errors as f64 / total.bases as f64
+            }
+        })
+        .collect()
+}
+
+fn read_u32(reader: &mut impl Read) -> std::io::Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn skip(reader: &mut impl Read, length: usize) -> std::io::Result<()> {
+    std::io::copy(&mut reader.take(length as u64), &mut std::io::sink())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    /// A BAM alignment record, without its leading block size
+    fn record(name: &str, flag: u16, seq: &[u8], cigar: &[(u32, u32)], md: &str) -> Vec<u8> {
+        let mut record = vec![0; 8]; // refID, pos
+        record.push(name.len() as u8 + 1);
+        record.extend([0; 3]); // mapq, bin
+        record.extend((cigar.len() as u16).to_le_bytes());
+        record.extend(flag.to_le_bytes());
+        record.extend((seq.len() as u32).to_le_bytes());
+        record.extend([0; 12]); // next refID, next pos, tlen
+        record.extend(name.bytes().chain([0]));
+        for &(op, length) in cigar {
+            record.extend((length << 4 | op).to_le_bytes());
+        }
+        let code = |b: &u8| BASES.iter().position(|c| c == b).unwrap() as u8;
+        for pair in seq.chunks(2) {
+            record.push(code(&pair[0]) << 4 | pair.get(1).map_or(0, code));
+        }
+        record.extend(std::iter::repeat_n(30, seq.len()));
+        record.extend(b"MDZ".iter().chain(md.as_bytes()).chain([&0]));
+        record
+    }
+
+    fn stream(records: &[Vec<u8>]) -> Vec<u8> {
+        records
+            .iter()
+            .flat_map(|r| (r.len() as u32).to_le_bytes().into_iter().chain(r.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn counts_errors_in_homopolymers() {
+        // Reference CAAAAG; the read has T in the homopolymer and an insertion after it
+        let aligned = record("r1", 0, b"CAATAGG", &[(0, 5), (1, 1), (0, 1)], "3A2");
+        let (name, errors) = alignment(&aligned).unwrap().unwrap();
+        assert_eq!(name, "r1");
+        assert_eq!(
+            errors,
+            ReadErrors {
+                bases: 4,
+                errors: 2
+            }
+        );
+
+        let unmapped = record("r2", 0x4, b"CAAAAG", &[(0, 6)], "6");
+        let bam = stream(&[aligned, unmapped]);
+        let (reads, skipped) = alignments(&mut Cursor::new(bam)).unwrap();
+        assert_eq!(reads.len(), 1);
+        assert_eq!(reads["r1"].errors, 2);
+        assert_eq!(skipped, 1);
+    }
+
+    #[test]
+    fn truncated_records_are_errors() {
+        let aligned = record("r1", 0, b"CAAAAG", &[(0, 6)], "6");
+        // Cut inside the sequence, and inside the fixed fields
+        assert!(alignment(&aligned[..36]).is_err());
+        assert!(alignment(&aligned[..20]).is_err());
+        // A record ending early in the stream
+        let mut bam = stream(&[aligned]);
+        bam.truncate(bam.len() - 4);
+        assert!(alignments(&mut Cursor::new(bam)).is_err());
+    }
+
+    #[test]
+    fn oversize_records_are_errors() {
+        let mut bam = ((MAX_RECORD_SIZE + 1) as u32).to_le_bytes().to_vec();
+        bam.extend([0; 64]);
+        let err = alignments(&mut Cursor::new(bam)).unwrap_err();
+        assert!(err.to_string().contains("record size"), "{}", err);
+    }
+
+    #[test]
+    fn md_tag_expands_to_reference_events() {
+        let events = md_events("2A0^GT1").unwrap();
+        let kinds: Vec<Event> = events.iter().map(|&(_, e)| e).collect();
+        use Event::*;
+        assert_eq!(kinds, [Match, Match, Mismatch, Deleted, Deleted, Match]);
+        assert_eq!(events[3].0, b'G');
+    }
+}
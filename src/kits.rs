@@ -0,0 +1,135 @@
+// Registry of known sequencing kits and the columns and metrics each should produce
+use crate::barcode;
+use crate::model::RunData;
+
+/// Something a run on a given kit is expected to provide
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Expectation {
+    Column(&'static str), // A per-read column with at least one value
+    Barcodes,             // Reads assigned to barcodes
+}
+
+impl Expectation {
+    /// Why the run does not meet the expectation, `None` when it does
+    pub fn check(self, data: &RunData) -> Option<String> {
+        match self {
+            Expectation::Column(name) => match data.column(name) {
+                Some(values) if values.len() == data.len() => values
+                    .iter()
+                    .all(|v| !v.is_finite())
+                    .then(|| format!("column '{}' holds no values", name)),
+                _ => Some(format!("column '{}' is missing", name)),
+            },
+            Expectation::Barcodes => data
+                .barcode
+                .iter()
+                .all(|b| barcode::is_unclassified(b))
+                .then(|| "no read is assigned to a barcode".to_string()),
+        }
+    }
+}
+
+/// A family of kits sharing a name prefix
+#[derive(Debug, Clone, Copy)]
+pub struct KitFamily {
+    pub prefix: &'static str,            // Kit name prefix, e.g. `SQK-NBD`
+    pub description: &'static str,       // What the kits are for
+    pub expects: &'static [Expectation], // What runs on them should provide
+}
+
+/// Kit families checked for expected columns, most specific prefix first
+pub const KITS: &[KitFamily] = &[
+    KitFamily {
+        prefix: "SQK-RNA",
+        description: "direct RNA",
+        expects: &[Expectation::Column("poly_tail_length")],
+    },
+    KitFamily {
+        prefix: "SQK-PCB",
+        description: "barcoded PCR-cDNA",
+        expects: &[
+            Expectation::Column("poly_tail_length"),
+            Expectation::Column("strand"),
+            Expectation::Barcodes,
+        ],
+    },
+    KitFamily {
+        prefix: "SQK-PCS",
+        description: "PCR-cDNA",
+        expects: &[
+            Expectation::Column("poly_tail_length"),
+            Expectation::Column("strand"),
+        ],
+    },
+    KitFamily {
+        prefix: "SQK-DCS",
+        description: "direct cDNA",
+        expects: &[
+            Expectation::Column("poly_tail_length"),
+            Expectation::Column("strand"),
+        ],
+    },
+    KitFamily {
+        prefix: "SQK-NBD",
+        description: "native barcoding",
+        expects: &[Expectation::Barcodes],
+    },
+    KitFamily {
+        prefix: "SQK-RBK",
+        description: "rapid barcoding",
+        expects: &[Expectation::Barcodes],
+    },
+    KitFamily {
+        prefix: "SQK-RPB",
+        description: "rapid PCR barcoding",
+        expects: &[Expectation::Barcodes],
+    },
+    KitFamily {
+        prefix: "SQK-LSK",
+        description: "ligation sequencing",
+        expects: &[],
+    },
+    KitFamily {
+        prefix: "SQK-RAD",
+        description: "rapid sequencing",
+        expects: &[],
+    },
+    KitFamily {
+        prefix: "SQK-ULK",
+        description: "ultra-long",
+        expects: &[],
+    },
+];
+
+/// Registry entry of a kit name, matched case-insensitively by prefix
+pub fn lookup(kit: &str) -> Option<&'static KitFamily> {
+    let kit = kit.trim().to_ascii_uppercase();
+    KITS.iter().find(|family| kit.starts_with(family.prefix))
+}
+
+/// Prints a warning to stderr for each expectation of the run's kit that the
+/// input does not meet; returns the number of warnings
+pub fn warn_missing(data: &RunData) -> usize {
+    let Some(kit) = data.kit.as_deref() else {
+        return 0;
+    };
+    let Some(family) = lookup(kit) else {
+        eprintln!(
+            "Kit {} is not in the kit registry; expected columns not checked",
+            kit
+        );
+        return 0;
+    };
+    let problems: Vec<String> = family
+        .expects
+        .iter()
+        .filter_map(|expectation| expectation.check(data))
+        .collect();
+    for problem in &problems {
+        eprintln!(
+            "Warning: {} is a {} kit, but {}; check the basecaller configuration",
+            kit, family.description, problem
+        );
+    }
+    problems.len()
+}
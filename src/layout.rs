@@ -0,0 +1,136 @@
+// Measurement-based placement of captions, axis descriptions and tick labels
+use plotters::coord::Shift;
+use plotters::data::float::FloatPrettyPrinter;
+use plotters::prelude::*;
+use std::error::Error;
+
+use crate::precision::Precision;
+
+/// Largest caption font size, used whenever the caption fits
+const CAPTION_SIZE: u32 = 20;
+/// Axis description and x tick label font size
+const DESC_SIZE: u32 = 20;
+/// Y tick label font size
+pub const TICK_SIZE: u32 = 14;
+/// Smallest font size text is shrunk to before it gets truncated
+const MIN_SIZE: u32 = 9;
+/// Gap in pixels between tick labels, descriptions and the area edge
+const PAD: u32 = 8;
+/// Outer margin around each chart, as used by the panels
+pub const MARGIN: u32 = 20;
+
+/// Font sizes, texts and label area sizes that fit a panel
+#[derive(Debug, Clone)]
+pub struct PanelLayout {
+    pub caption: String,   // Caption, truncated with an ellipsis if needed
+    pub caption_size: u32, // Caption font size
+    pub y_desc: String,    // Y axis description, truncated if needed
+    pub desc_size: u32,    // Axis description font size
+    pub x_label_area: u32, // Height reserved below the chart
+    pub y_label_area: u32, // Width reserved left of the chart
+    pub right_margin: u32, // Margin keeping the last x tick label inside
+}
+
+/// Formats an x tick label exactly like plotters' default f64 formatter
+fn tick_label(value: f64) -> String {
+    FloatPrettyPrinter {
+        allow_scientific: false,
+        min_decimal: 1,
+        max_decimal: 5,
+    }
+    .print(value)
+}
+
+/// Measured width and height of `text` at `size`
+fn measure<DB: DrawingBackend>(
+    area: &DrawingArea<DB, Shift>,
+    text: &str,
+    size: u32,
+) -> Result<(u32, u32), Box<dyn Error>>
+where
+    DB::ErrorType: 'static,
+{
+    Ok(area.estimate_text_size(text, &("sans-serif", size).into_text_style(area))?)
+}
+
+/// Largest size in `MIN_SIZE..=start` at which `text` fits `width`, and the
+/// text itself, cut down with an ellipsis when even `MIN_SIZE` is too wide
+fn fit<DB: DrawingBackend>(
+    area: &DrawingArea<DB, Shift>,
+    text: &str,
+    start: u32,
+    width: u32,
+) -> Result<(String, u32), Box<dyn Error>>
+where
+    DB::ErrorType: 'static,
+{
+    for size in (MIN_SIZE..=start).rev() {
+        if measure(area, text, size)?.0 <= width {
+            return Ok((text.to_string(), size));
+        }
+    }
+    let mut chars: Vec<char> = text.chars().collect();
+    while !chars.is_empty() {
+        chars.pop();
+        let cut = format!("{}…", chars.iter().collect::<String>().trim_end());
+        if measure(area, &cut, MIN_SIZE)?.0 <= width {
+            return Ok((cut, MIN_SIZE));
+        }
+    }
+    Ok((String::new(), MIN_SIZE))
+}
+
+/// Lays out a panel drawn on `area` whose axes span `x_range` and `y_range`.
+///
+/// The caption and y description shrink (then truncate) to fit the panel's
+/// width and height, and the label areas grow to the widest tick label, so
+/// nothing is clipped at small sizes or with large numbers. Y tick labels
+/// are measured as they will be drawn, rounded to `y_precision`.
+pub fn panel<DB: DrawingBackend>(
+    area: &DrawingArea<DB, Shift>,
+    caption: &str,
+    y_desc: &str,
+    x_range: (f64, f64),
+    y_range: (f64, f64),
+    y_precision: Precision,
+) -> Result<PanelLayout, Box<dyn Error>>
+where
+    DB::ErrorType: 'static,
+{
+    let (width, height) = area.dim_in_pixel();
+    let (caption, caption_size) = fit(
+        area,
+        caption,
+        CAPTION_SIZE,
+        width.saturating_sub(2 * MARGIN),
+    )?;
+
+    // X axis: one row of tick labels plus the description; a label on the
+    // right edge is centred on it, so half of it needs room in the margin
+    let (x_tick_width, x_tick_height) = measure(area, &tick_label(x_range.1), DESC_SIZE)?;
+    let right_margin = MARGIN.max(x_tick_width / 2 + PAD);
+    let x_label_area = x_tick_height + measure(area, "Batch Time", DESC_SIZE)?.1 + PAD;
+
+    // Y axis description runs along the chart's height
+    let chart_height = height.saturating_sub(2 * MARGIN + x_label_area + caption_size + PAD);
+    let (y_desc, desc_size) = fit(area, y_desc, DESC_SIZE, chart_height)?;
+
+    // Widest tick label; the extremes and a midpoint bound the label lengths
+    let (low, high) = y_range;
+    let y_precision = y_precision.fixed_for(low.abs().max(high.abs()));
+    let mut tick_width = 0;
+    for value in [low, high, (low + high) / 2.0] {
+        tick_width = tick_width.max(measure(area, &y_precision.format(value), TICK_SIZE)?.0);
+    }
+    let y_label_area = tick_width + measure(area, &y_desc, desc_size)?.1 + 3 * PAD;
+
+    Ok(PanelLayout {
+        caption,
+        caption_size,
+        y_desc,
+        desc_size,
+        x_label_area,
+        y_label_area,
+        right_margin,
+    })
+}
@@ -0,0 +1,323 @@
+// Per-read statistics split by read length class
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use plotters::style::full_palette::GREY_500;
+use std::error::Error;
+
+use crate::model::RunData;
+use crate::precision::Precision;
+use crate::render::{self, Figure};
+use crate::robust::Center;
+
+/// Class boundaries used when `--length-classes` is given without a value
+pub const DEFAULT_CLASSES: &str = "1kb,10kb";
+
+/// Per-read columns summarized for each class: (column, title, precision)
+const PER_READ: [(&str, &str, Precision); 7] = [
+    ("bases", "Bases", Precision::Decimals(0)),
+    ("samples", "Samples", Precision::Decimals(0)),
+    ("mean_qscore", "Q-score", Precision::Decimals(2)),
+    (
+        "time_to_package_and_send",
+        "Time to Package",
+        Precision::DEFAULT,
+    ),
+    (
+        "time_in_basecaller",
+        "Time in Basecaller",
+        Precision::DEFAULT,
+    ),
+    ("trimmed_bases", "Trimmed Bases", Precision::Decimals(0)),
+    (
+        "alignment_identity",
+        "Alignment Identity",
+        Precision::Decimals(3),
+    ),
+];
+
+/// Read length classes, split at ascending boundaries (in bases)
+#[derive(Debug, Clone, PartialEq)]
+pub struct LengthClasses {
+    bounds: Vec<f64>, // Each boundary starts a new class
+}
+
+impl LengthClasses {
+    /// Parses comma-separated boundaries such as `1kb,10kb` or `500,5000`
+    pub fn parse(spec: &str) -> Result<Self, Box<dyn Error>> {
+        let bounds = spec
+            .split(',')
+            .map(|bound| {
+                parse_length(bound).ok_or_else(|| {
+                    format!(
+                        "Invalid read length '{}' (e.g. 500, 1kb or 1.5Mb)",
+                        bound.trim()
+                    )
+                })
+            })
+            .collect::<Result<Vec<f64>, _>>()?;
+        if bounds.windows(2).any(|w| w[0] >= w[1]) {
+            return Err(format!("Length class boundaries '{}' must be ascending", spec).into());
+        }
+        Ok(LengthClasses { bounds })
+    }
+
+    /// Number of classes, one more than the boundaries
+    pub fn count(&self) -> usize {
+        self.bounds.len() + 1
+    }
+
+    /// Class index of a read with `bases` called bases
+    pub fn classify(&self, bases: f64) -> usize {
+        self.bounds.partition_point(|&bound| bound <= bases)
+    }
+
+    /// Labels of the classes in order, e.g. `<1kb`, `1kb–10kb`, `≥10kb`
+    pub fn labels(&self) -> Vec<String> {
+        let mut labels = Vec::with_capacity(self.count());
+        for (i, &bound) in self.bounds.iter().enumerate() {
+            match i {
+                0 => labels.push(format!("<{}", format_length(bound))),
+                _ => labels.push(format!(
+                    "{}–{}",
+                    format_length(self.bounds[i - 1]),
+                    format_length(bound)
+                )),
+            }
+        }
+        match self.bounds.last() {
+            Some(&last) => labels.push(format!("≥{}", format_length(last))),
+            None => labels.push("All reads".to_string()),
+        }
+        labels
+    }
+}
+
+/// Parses a read length with an optional `b`, `kb`, `Mb` or `Gb` unit
+fn parse_length(text: &str) -> Option<f64> {
+    let text = text.trim().to_ascii_lowercase();
+    let (number, scale) = match text.strip_suffix('b') {
+        Some(rest) => match rest.strip_suffix(['k', 'm', 'g']) {
+            Some(number) if rest.ends_with('k') => (number, 1e3),
+            Some(number) if rest.ends_with('m') => (number, 1e6),
+            Some(number) => (number, 1e9),
+            None => (rest, 1.0),
+        },
+        None => (text.as_str(), 1.0),
+    };
+    number
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .map(|n| n * scale)
+        .filter(|&n| n.is_finite() && n > 0.0)
+}
+
+/// Read length with the largest unit that keeps it at least 1
+fn format_length(bases: f64) -> String {
+    match bases {
+        b if b >= 1e6 => format!("{}Mb", b / 1e6),
+        b if b >= 1e3 => format!("{}kb", b / 1e3),
+        b => format!("{}b", b),
+    }
+}
+
+/// Reads, yield and typical per-read metrics of one length class
+#[derive(Debug, Clone)]
+pub struct ClassSummary {
+    pub label: String,                        // Class label, e.g. `≥10kb`
+    pub reads: usize,                         // Reads in the class
+    pub bases: f64,                           // Called bases of those reads
+    pub yield_share: f64,                     // Share of the run's bases
+    pub metrics: Vec<(&'static str, String)>, // Formatted typical value per metric
+}
+
+/// Summarizes each length class with `center`; metrics whose column the
+/// input lacks are left out
+pub fn analyze(data: &RunData, classes: &LengthClasses, center: Center) -> Vec<ClassSummary> {
+    let mut members: Vec<Vec<usize>> = vec![Vec::new(); classes.count()];
+    for (i, &bases) in data.bases.iter().enumerate() {
+        members[classes.classify(bases)].push(i);
+    }
+    let total: f64 = data.bases.iter().sum();
+
+    classes
+        .labels()
+        .into_iter()
+        .zip(&members)
+        .map(|(label, rows)| {
+            let bases = rows.iter().fold(0.0, |sum, &i| sum + data.bases[i]);
+            let metrics = PER_READ
+                .iter()
+                .filter_map(|&(name, title, precision)| {
+                    let column = data.column(name).filter(|c| c.len() == data.len())?;
+                    let values: Vec<f64> = rows.iter().map(|&i| column[i]).collect();
+                    let typical = center.of(&values);
+                    typical
+                        .is_finite()
+                        .then(|| (title, precision.format(typical)))
+                })
+                .collect();
+            ClassSummary {
+                label,
+                reads: rows.len(),
+                bases,
+                yield_share: if total > 0.0 { bases / total } else { 0.0 },
+                metrics,
+            }
+        })
+        .collect()
+}
+
+/// Prints one line per length class
+pub fn print_report(summaries: &[ClassSummary], center: Center) {
+    println!("Read length classes ({} per read):", center.label());
+    for class in summaries {
+        let metrics: Vec<String> = class
+            .metrics
+            .iter()
+            .map(|(title, value)| format!("{} {}", title, value))
+            .collect();
+        let separator = if metrics.is_empty() { "" } else { "; " };
+        println!(
+            "  {}: {} reads, {:.3} Gb ({:.1}% of yield){}{}",
+            class.label,
+            class.reads,
+            class.bases / 1e9,
+            class.yield_share * 100.0,
+            separator,
+            metrics.join(", ")
+        );
+    }
+}
+
+/// Plots the cumulative yield stacked by length class, absolute and as shares
+pub fn plot(
+    data: &RunData,
+    classes: &LengthClasses,
+    output_path: &str,
+) -> Result<(), Box<dyn Error>> {
+    if data.is_empty() {
+        return Err("No records to split by read length".into());
+    }
+    render::to_file(&LengthClassFigure { data, classes }, output_path)
+}
+
+/// Colour of class `i` of `n`, from light (short) to dark (long)
+fn class_color(i: usize, n: usize) -> RGBColor {
+    let f = if n > 1 {
+        i as f64 / (n - 1) as f64
+    } else {
+        1.0
+    };
+    RGBColor(
+        (180.0 * (1.0 - f)) as u8,
+        (200.0 - 120.0 * f) as u8,
+        (255.0 - 100.0 * f) as u8,
+    )
+}
+
+/// Stacked cumulative yield of the length classes
+struct LengthClassFigure<'a> {
+    data: &'a RunData,
+    classes: &'a LengthClasses,
+}
+
+impl Figure for LengthClassFigure<'_> {
+    fn size(&self) -> (u32, u32) {
+        (1600, 1000)
+    }
+
+    fn draw<DB: DrawingBackend>(&self, root: &DrawingArea<DB, Shift>) -> Result<(), Box<dyn Error>>
+    where
+        DB::ErrorType: 'static,
+    {
+        let &LengthClassFigure { data, classes } = self;
+        let Some((first, _)) = data.time_range() else {
+            return Err("No records to split by read length".into());
+        };
+        let hours: Vec<f64> = data.time.iter().map(|t| (t - first) / 3600.0).collect();
+        let max_hours = hours.last().copied().unwrap_or(0.0).max(f64::EPSILON);
+
+        // stacks[k][i]: Gb of classes 0..=k up to row i, so each layer sits on the one below
+        let n = classes.count();
+        let mut totals = vec![0.0; n];
+        let mut stacks = vec![Vec::with_capacity(data.len()); n];
+        for &bases in &data.bases {
+            totals[classes.classify(bases)] += bases / 1e9;
+            let mut running = 0.0;
+            for (stack, total) in stacks.iter_mut().zip(&totals) {
+                running += total;
+                stack.push(running);
+            }
+        }
+        let overall = stacks[n - 1].clone();
+        let shares: Vec<Vec<f64>> = stacks
+            .iter()
+            .map(|stack| {
+                stack
+                    .iter()
+                    .zip(&overall)
+                    .map(|(s, o)| if *o > 0.0 { 100.0 * s / o } else { 0.0 })
+                    .collect()
+            })
+            .collect();
+
+        root.fill(&GREY_500)?;
+        let panels = root.split_evenly((2, 1));
+        let labels = classes.labels();
+        let top = overall.last().copied().unwrap_or(0.0).max(f64::EPSILON);
+        let layouts = [
+            (
+                "Cumulative Yield by Read Length",
+                "Yield (Gb)",
+                &stacks,
+                top * 1.05,
+            ),
+            (
+                "Share of Yield by Read Length",
+                "Share of Yield (%)",
+                &shares,
+                100.0,
+            ),
+        ];
+
+        for (area, (caption, y_desc, layers, y_max)) in panels.iter().zip(layouts) {
+            let mut chart = ChartBuilder::on(area)
+                .caption(caption, ("sans-serif", 20))
+                .margin(20)
+                .x_label_area_size(50)
+                .y_label_area_size(100)
+                .build_cartesian_2d(0.0..max_hours, 0.0..y_max)?;
+            chart
+                .configure_mesh()
+                .x_labels(5)
+                .y_labels(5)
+                .x_desc("Elapsed Hours")
+                .y_desc(y_desc)
+                .draw()?;
+
+            // Longest class first, the shorter ones painted over its lower part
+            for k in (0..n).rev() {
+                let color = class_color(k, n);
+                chart
+                    .draw_series(AreaSeries::new(
+                        hours.iter().copied().zip(layers[k].iter().copied()),
+                        0.0,
+                        color.filled(),
+                    ))?
+                    .label(labels[k].as_str())
+                    .legend(move |(x, y)| {
+                        Rectangle::new([(x, y - 5), (x + 15, y + 5)], color.filled())
+                    });
+            }
+            chart
+                .configure_series_labels()
+                .position(SeriesLabelPosition::UpperLeft)
+                .background_style(WHITE.mix(0.8))
+                .border_style(BLACK)
+                .draw()?;
+        }
+
+        Ok(())
+    }
+}
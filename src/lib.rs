@@ -0,0 +1,78 @@
+// Parsing, analysis and plotting of adaptive sampling batch timings.
+//
+// The `as_parser` binary is a thin command-line front end over this crate;
+// other tools can call `parser::parse_csv` and `plot::plot_multi_series`
+// directly instead of shelling out to it.
+pub mod alias;
+pub mod anomaly;
+pub mod axisbreak;
+pub mod barcode;
+pub mod baseline;
+pub mod calibration;
+pub mod capacity;
+pub mod compare;
+pub mod compose;
+pub mod compression;
+pub mod config;
+pub mod control;
+pub mod cost;
+pub mod daemon;
+pub mod decimate;
+pub mod decisions;
+pub mod digest;
+pub mod filter;
+pub mod forensic;
+pub mod golden;
+pub mod health;
+pub mod histogram;
+pub mod homopolymer;
+pub mod kits;
+pub mod layout;
+pub mod lengthclass;
+pub mod live;
+pub mod locale;
+pub mod lock;
+pub mod manifest;
+pub mod metadata;
+pub mod mode;
+pub mod model;
+pub mod parser;
+pub mod pauses;
+pub mod plot;
+pub mod porescan;
+pub mod precision;
+pub mod preset;
+pub mod projection;
+pub mod quantile;
+pub mod rates;
+pub mod rebasecall;
+pub mod reconcile;
+pub mod redact;
+pub mod reduce;
+pub mod remote;
+pub mod render;
+pub mod retention;
+pub mod rna;
+pub mod robust;
+pub mod rules;
+pub mod sample;
+pub mod schema;
+pub mod seqsummary;
+pub mod serve;
+pub mod serverlog;
+pub mod service;
+pub mod shutdown;
+pub mod simulate;
+pub mod smooth;
+pub mod splitting;
+pub mod stream;
+pub mod summary;
+pub mod telemetry;
+pub mod theme;
+pub mod threads;
+pub mod timestamp;
+pub mod tombstone;
+pub mod trimming;
+pub mod tui;
+pub mod ultralong;
+pub mod validate;
@@ -0,0 +1,138 @@
+// Live plot mode: tail a growing batch CSV and re-render its figure
+//
+// Only the rows appended since the last poll are parsed. A file that
+// shrinks (rotated or rewritten) is read again from the start. Besides local
+// files, `ssh://` inputs can be followed: each poll fetches the new bytes
+// over ssh. Compressed inputs cannot be followed.
+use std::error::Error;
+use std::fs::File;
+use std::io::{Cursor, Read, Seek, SeekFrom};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use crate::compression::Compression;
+use crate::model::RunData;
+use crate::parser::{self, ParseOptions};
+use crate::{remote, shutdown};
+
+/// How often the wait between polls checks for a shutdown request
+const SHUTDOWN_POLL: Duration = Duration::from_millis(200);
+
+/// Whether `input` can be followed as it grows: an uncompressed CSV, local
+/// or on another host over ssh
+pub fn can_follow(input: &str) -> bool {
+    (!remote::is_remote(input) || remote::is_ssh(input))
+        && Compression::from_path(std::path::Path::new(input)) == Compression::None
+}
+
+/// Read position in a CSV that is still being written
+pub struct Tail {
+    path: PathBuf,          // The followed CSV
+    offset: u64,            // Bytes consumed so far (whole lines only)
+    header: Option<String>, // Header line, once read
+}
+
+/// Lines a poll found
+pub enum Appended {
+    Nothing,         // The file did not grow by a whole line
+    Rows(String),    // New rows, as a CSV with the header line
+    Restart(String), // The file shrank; all of its rows
+}
+
+impl Tail {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Tail {
+            path: path.into(),
+            offset: 0,
+            header: None,
+        }
+    }
+
+    /// Reads the whole lines written since the last poll; a trailing line
+    /// without its newline is left for the next one
+    pub fn poll(&mut self) -> Result<Appended, Box<dyn Error>> {
+        let (size, bytes) = self.read()?;
+        let restart = size < self.offset;
+        if restart {
+            self.offset = 0;
+            self.header = None;
+        }
+        let Some(end) = bytes.iter().rposition(|&b| b == b'\n') else {
+            return Ok(Appended::Nothing);
+        };
+        self.offset += end as u64 + 1;
+        let mut text = String::from_utf8(bytes[..=end].to_vec())?;
+
+        if self.header.is_none() {
+            // `text` ends with a newline, so the header line has one
+            let line_end = text.find('\n').unwrap_or_default();
+            self.header = Some(text[..line_end].trim_end_matches('\r').to_string());
+            text.drain(..=line_end);
+        }
+        if text.trim().is_empty() && !restart {
+            return Ok(Appended::Nothing);
+        }
+        let csv = format!("{}\n{}", self.header.as_deref().unwrap_or_default(), text);
+        Ok(if restart {
+            Appended::Restart(csv)
+        } else {
+            Appended::Rows(csv)
+        })
+    }
+
+    /// The file's size and its bytes from the offset on, or from the start
+    /// when it is smaller than the offset
+    fn read(&self) -> Result<(u64, Vec<u8>), Box<dyn Error>> {
+        let input = self.path.to_string_lossy();
+        if remote::is_ssh(&input) {
+            return remote::read_ssh_from(&input, self.offset);
+        }
+        let mut file = File::open(&self.path)?;
+        let size = file.metadata()?.len();
+        file.seek(SeekFrom::Start(if size < self.offset {
+            0
+        } else {
+            self.offset
+        }))?;
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)?;
+        Ok((size, bytes))
+    }
+}
+
+/// Follows `input` until SIGINT/SIGTERM, calling `render` with all rows so
+/// far whenever a poll every `interval` found new ones
+pub fn follow(
+    input: &str,
+    options: &ParseOptions,
+    interval: Duration,
+    mut render: impl FnMut(&RunData) -> Result<(), Box<dyn Error>>,
+) -> Result<(), Box<dyn Error>> {
+    shutdown::install();
+    let mut tail = Tail::new(input);
+    let mut data = RunData::default();
+    while !shutdown::requested() {
+        let next_poll = Instant::now() + interval;
+        let rows = match tail.poll()? {
+            Appended::Nothing => None,
+            Appended::Rows(csv) => Some(csv),
+            Appended::Restart(csv) => {
+                println!("{} shrank, reading it again", input);
+                data = RunData::default();
+                Some(csv)
+            }
+        };
+        if let Some(csv) = rows {
+            let rows = parser::parse_csv_reader(Cursor::new(csv), options)?;
+            if data.is_empty() {
+                data.schema = rows.schema.clone();
+            }
+            data.append(rows);
+            render(&data)?;
+        }
+        while !shutdown::requested() && Instant::now() < next_poll {
+            std::thread::sleep(SHUTDOWN_POLL.min(next_poll - Instant::now()));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,146 @@
+// Localisation of the figure, label legend and digest text
+//
+// Strings are looked up by their English text, so call sites stay readable
+// and anything without an entry falls back to English. To add a language:
+//   1. add a variant to `Language` and its name(s) to `Language::parse`,
+//   2. bump `LANGUAGES` and give the variant the next column index,
+//   3. append the translation to every row of `TEXTS` (the compiler rejects
+//      rows that are missing a column).
+
+/// Number of supported languages, i.e. columns in `TEXTS`
+const LANGUAGES: usize = 3;
+
+/// Language the user-facing text is written in
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Language {
+    #[default]
+    English, // Column 0, also the lookup key
+    German, // Column 1
+    French, // Column 2
+}
+
+impl Language {
+    /// Parses a `--language` value (ISO 639-1 code or English name)
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "en" | "english" => Some(Language::English),
+            "de" | "german" => Some(Language::German),
+            "fr" | "french" => Some(Language::French),
+            _ => None,
+        }
+    }
+
+    /// Column of this language in `TEXTS`
+    fn column(self) -> usize {
+        match self {
+            Language::English => 0,
+            Language::German => 1,
+            Language::French => 2,
+        }
+    }
+
+    /// Translates an English string, returning it unchanged when there is no entry
+    pub fn tr(self, english: &'static str) -> &'static str {
+        TEXTS
+            .iter()
+            .find(|row| row[0] == english)
+            .map_or(english, |row| row[self.column()])
+    }
+}
+
+/// Every translated string: English, German, French
+const TEXTS: &[[&str; LANGUAGES]] = &[
+    // Figure
+    [
+        "Adaptive sampling batch metrics",
+        "Batch-Metriken des Adaptive Sampling",
+        "Métriques des lots d'échantillonnage adaptatif",
+    ],
+    ["Batch Time", "Batch-Zeit", "Heure du lot"],
+    // Panel titles
+    ["Samples", "Signalwerte", "Échantillons"],
+    ["Bases", "Basen", "Bases"],
+    ["Mean Q-score", "Mittlerer Q-Score", "Q-score moyen"],
+    ["Time to Package", "Verpackungszeit", "Temps d'empaquetage"],
+    [
+        "Time in Basecaller",
+        "Zeit im Basecaller",
+        "Temps dans le basecaller",
+    ],
+    ["Batch Interval", "Batch-Intervall", "Intervalle entre lots"],
+    ["Cumulative Bases", "Kumulierte Basen", "Bases cumulées"],
+    ["Cumulative Reads", "Kumulierte Reads", "Lectures cumulées"],
+    ["Cost per Gb", "Kosten pro Gb", "Coût par Gb"],
+    ["Bases/sec", "Basen/s", "Bases/s"],
+    ["Reads/sec", "Reads/s", "Lectures/s"],
+    [
+        "Homopolymer Error Rate",
+        "Homopolymer-Fehlerrate",
+        "Taux d'erreur des homopolymères",
+    ],
+    [
+        "Unclassified Fraction",
+        "Anteil unklassifiziert",
+        "Fraction non classée",
+    ],
+    [
+        "Split Read Rate",
+        "Anteil geteilter Reads",
+        "Taux de reads scindés",
+    ],
+    ["Trimmed Bases", "Getrimmte Basen", "Bases coupées"],
+    [
+        "Adapter Detection Rate",
+        "Adapter-Erkennungsrate",
+        "Taux de détection d'adaptateurs",
+    ],
+    ["ASIC Temperature", "ASIC-Temperatur", "Température ASIC"],
+    ["Bias Voltage", "Bias-Spannung", "Tension de polarisation"],
+    [
+        "Run Health Score",
+        "Run-Gesundheitswert",
+        "Score de santé du run",
+    ],
+    [
+        "Server Errors/min",
+        "Serverfehler/min",
+        "Erreurs serveur/min",
+    ],
+    // Label legend
+    [
+        "x axis (all panels)",
+        "x-Achse (alle Panels)",
+        "axe x (tous les panneaux)",
+    ],
+    ["Panel", "Panel", "Panneau"],
+    ["top to bottom", "von oben nach unten", "de haut en bas"],
+    ["y axis", "y-Achse", "axe y"],
+    ["to", "bis", "à"],
+    // Digest
+    ["Input", "Eingabe", "Entrée"],
+    ["Records", "Datensätze", "Enregistrements"],
+    ["Time span", "Zeitspanne", "Durée"],
+    ["Statistics", "Statistik", "Statistiques"],
+    ["Verdict", "Bewertung", "Verdict"],
+    ["Metric", "Metrik", "Métrique"],
+    ["Mean", "Mittelwert", "Moyenne"],
+    ["Median", "Median", "Médiane"],
+    ["Trimmed mean", "Getrimmtes Mittel", "Moyenne tronquée"],
+    ["Min", "Min", "Min"],
+    ["Max", "Max", "Max"],
+    ["Status", "Status", "Statut"],
+    // Report
+    ["Run", "Lauf", "Run"],
+    ["Kit", "Kit", "Kit"],
+    ["Channels", "Kanäle", "Canaux"],
+    ["First batch", "Erster Batch", "Premier lot"],
+    ["Last batch", "Letzter Batch", "Dernier lot"],
+    ["Sampling", "Stichprobe", "Échantillonnage"],
+    ["Validation", "Validierung", "Validation"],
+    [
+        "Server log events",
+        "Server-Log-Ereignisse",
+        "Événements du journal serveur",
+    ],
+    ["Panels", "Panels", "Panneaux"],
+];
@@ -0,0 +1,131 @@
+// Per-run lockfiles: one process at a time writes a run's output directory
+//
+// A lockfile holds its owner's process ID. Whether that process is still
+// alive is only checked on Linux (through /proc); elsewhere a lock left by a
+// crashed process is never taken over and has to be removed by hand.
+use std::error::Error;
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+/// Lockfile created inside a locked directory
+pub const LOCK_FILE: &str = "as_parser.lock";
+
+/// Exclusive hold on a directory, released when dropped
+pub struct DirLock {
+    path: PathBuf, // The lockfile, holding the owner's process ID
+}
+
+impl DirLock {
+    /// Locks `dir`, failing when another live process holds it.
+    ///
+    /// A lock left behind by a process that is gone (crashed, killed) is
+    /// taken over; where liveness cannot be checked (not Linux), it is kept.
+    pub fn acquire(dir: &Path) -> Result<Self, Box<dyn Error>> {
+        let path = dir.join(LOCK_FILE);
+        let id = std::process::id();
+        for _ in 0..2 {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(mut file) => {
+                    writeln!(file, "{}", id)?;
+                    return Ok(DirLock { path });
+                }
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                    let owner = read_owner(&path);
+                    match owner {
+                        Some(pid) if is_gone(pid) => {
+                            // Replace the stale lock in one step: a complete
+                            // lockfile is renamed over it. Of processes taking
+                            // it over at once the last rename wins, and the
+                            // others find it held by the winner.
+                            let temp = dir.join(format!("{}.{}", LOCK_FILE, id));
+                            fs::write(&temp, format!("{}\n", id))?;
+                            if let Err(e) = fs::rename(&temp, &path) {
+                                let _ = fs::remove_file(&temp);
+                                return Err(format!(
+                                    "Cannot take over lock {}: {}",
+                                    path.display(),
+                                    e
+                                )
+                                .into());
+                            }
+                            if read_owner(&path) == Some(id) {
+                                return Ok(DirLock { path });
+                            }
+                        }
+                        _ => {
+                            return Err(format!(
+                                "{} is already locked by {} (remove {} if no other as_parser is running)",
+                                dir.display(),
+                                owner.map_or("another process".to_string(), |pid| format!(
+                                    "process {}",
+                                    pid
+                                )),
+                                path.display()
+                            )
+                            .into());
+                        }
+                    }
+                }
+                Err(e) => {
+                    return Err(format!("Cannot create lock {}: {}", path.display(), e).into());
+                }
+            }
+        }
+        Err(format!("{} is already locked", dir.display()).into())
+    }
+}
+
+impl Drop for DirLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Process ID held by a lockfile
+fn read_owner(path: &Path) -> Option<u32> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+/// Whether process `pid` certainly no longer exists; always false except
+/// on Linux, where it is looked up in /proc
+fn is_gone(pid: u32) -> bool {
+    cfg!(target_os = "linux") && !Path::new("/proc").join(pid.to_string()).exists()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("as_parser-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn held_lock_is_refused_and_released_on_drop() {
+        let dir = scratch("lock-held");
+        let lock = DirLock::acquire(&dir).unwrap();
+        let err = DirLock::acquire(&dir).err().unwrap();
+        assert!(err.to_string().contains("already locked"), "{}", err);
+        drop(lock);
+        assert!(!dir.join(LOCK_FILE).exists());
+        DirLock::acquire(&dir).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn stale_lock_is_taken_over() {
+        let dir = scratch("lock-stale");
+        // Above the largest PID Linux hands out
+        fs::write(dir.join(LOCK_FILE), "4194305\n").unwrap();
+        let lock = DirLock::acquire(&dir).unwrap();
+        assert_eq!(read_owner(&dir.join(LOCK_FILE)), Some(std::process::id()));
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        drop(lock);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
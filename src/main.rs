@@ -1,168 +1,1556 @@
-// Import all necessary types and traits from plotters
-use plotters::prelude::*;
-use plotters::style::full_palette::GREY_500;
-// For error handling
+mod cli;
+
 use std::error::Error;
-// For working with file paths
+use std::io::{IsTerminal, Write};
 use std::path::Path;
 
-/// A struct representing one row of the CSV input
-#[derive(Debug)]
-struct Record {
-    time: f64,                     // Batch time (Unix timestamp, float)
-    samples: f64,                  // Number of samples
-    bases: f64,                    // Number of basecalls
-    mean_qscore: f64,              // Average Q-score
-    time_to_package_and_send: f64, // Time taken to package and send
-    time_in_basecaller: f64,       // Time spent in basecalling
-}
-
-/// Reads the CSV file and parses it into a vector of `Record`s
-fn parse_csv<P: AsRef<Path>>(csv_path: P) -> Result<Vec<Record>, Box<dyn Error>> {
-    // Open the CSV reader from the given file path
-    let mut rdr = csv::Reader::from_path(csv_path)?;
-    let mut data = Vec::new();
-
-    // Iterate through each record (row) in the CSV
-    for result in rdr.records() {
-        let record = result?; // Handle CSV parsing errors
-
-        // Parse relevant fields into f64 and construct a Record
-        let r = Record {
-            time: record.get(2).ok_or("Missing batch_time")?.parse()?,
-            samples: record.get(3).ok_or("Missing samples")?.parse()?,
-            bases: record.get(4).ok_or("Missing bases")?.parse()?,
-            mean_qscore: record.get(6).ok_or("Missing mean_qscore")?.parse()?,
-            time_to_package_and_send: record
-                .get(7)
-                .ok_or("Missing time_to_package_and_send")?
-                .parse()?,
-            time_in_basecaller: record.get(8).ok_or("Missing time_in_basecaller")?.parse()?,
+use as_parser::alias::AliasMap;
+use as_parser::cost::CostModel;
+use as_parser::digest::build_digest;
+use as_parser::manifest::{self, Artifact};
+use as_parser::model::{
+    Field, FieldSummary, Health, Profile, QSCORE_THRESHOLDS, RunData, SampleExpectation,
+    Thresholds, check_samples_per_batch, plot_fields, select_fields, set_y_axes,
+};
+use as_parser::parser::{ParseOptions, parse_csv, stream_csv};
+use as_parser::plot::{
+    FIGURE_SIZE, PlotOptions, fonts_available, panel_file_name, plot_multi_series, render_panels,
+    render_thumbnail, write_panel_data, write_split_panels,
+};
+use as_parser::precision::Precision;
+use as_parser::preset::Preset;
+use as_parser::tombstone::Tombstones;
+use as_parser::*;
+use clap::{CommandFactory, Parser};
+use cli::{
+    AnalysisArgs, Cli, Command, FigureArgs, PlotArgs, ReportArgs, RunArgs, ServeArgs, StatsArgs,
+    TuiArgs, WatchArgs,
+};
+
+/// Prints the batches flagged by `check_samples_per_batch` to stderr
+fn report_samples_check(data: &RunData, expectation: SampleExpectation, flagged: &[usize]) {
+    // Only list the first few; a misconfigured server flags nearly every batch
+    const MAX_LISTED: usize = 10;
+
+    eprintln!(
+        "Samples per batch: {} of {} batches outside {} ± {:.1}%",
+        flagged.len(),
+        data.len(),
+        expectation.expected,
+        expectation.tolerance * 100.0
+    );
+    for &i in flagged.iter().take(MAX_LISTED) {
+        eprintln!(
+            "  batch time {:.3}: {} samples",
+            data.time[i], data.samples[i]
+        );
+    }
+    if flagged.len() > MAX_LISTED {
+        eprintln!("  ... and {} more", flagged.len() - MAX_LISTED);
+    }
+}
+
+/// Prints the run cost so far and, with a projected run end, the final cost
+fn report_costs(data: &RunData, model: CostModel, milestones: &[projection::Milestone]) {
+    let Some((start, end)) = data.time_range() else {
+        return;
+    };
+    let bases = reduce::summarize(&data.bases).sum;
+    println!(
+        "Cost so far: {:.2} ({:.2} per Gb)",
+        model.cost_at(end - start),
+        model.cost_per_gb(end - start, bases)
+    );
+
+    if let Some(run_end) = milestones.iter().find(|m| m.name == "run_end") {
+        println!(
+            "Projected cost at run end: {:.2}",
+            model.cost_at(run_end.time - start)
+        );
+    }
+}
+
+/// Prints per-field statistics and threshold checks to stdout.
+///
+/// `provenance` says how the numbers were computed (see `mode::provenance`).
+/// Fields with thresholds are classified by their typical value (mean,
+/// median or trimmed mean); the others are reported without a health state.
+fn print_report(
+    data: &RunData,
+    fields: &[Field],
+    method: quantile::Method,
+    center: robust::Center,
+    provenance: &str,
+) {
+    println!("Records: {}", data.rows());
+    println!("Statistics: {}", provenance);
+    if let Some((first, last)) = data.time_range() {
+        println!(
+            "Batch time: {:.3} to {:.3} ({:.1} s)",
+            first,
+            last,
+            last - first
+        );
+    } else {
+        return;
+    }
+
+    // Fields are summarized in parallel and printed in order
+    let summaries = threads::map(fields, |field| {
+        let summary = FieldSummary::new(data, field, center);
+        let tail = match data.streamed(field) {
+            Some(stats) => stats.tails(),
+            None => quantile::quantiles((field.column)(data), &quantile::REPORTED, method),
+        };
+        let flagged = field
+            .anomaly
+            .map(|detector| detector.flag((field.column)(data)));
+        (summary, tail, flagged)
+    });
+    for (field, (summary, tail, flagged)) in fields.iter().zip(summaries) {
+        let check = match field.thresholds {
+            Some(t) => format!(" [{}]", t.classify(summary.center).label()),
+            None => String::new(),
         };
+        let fmt = |v| field.precision.format(v);
+        println!(
+            "{}: {} {}, {} {}, min {}, max {}, p95 {}, p99 {}{}",
+            field.title,
+            center.label(),
+            fmt(summary.center),
+            center.spread_label(),
+            fmt(summary.spread),
+            fmt(summary.min),
+            fmt(summary.max),
+            fmt(tail[0]),
+            fmt(tail[1]),
+            check
+        );
+        if let (Some(detector), Some(flagged)) = (field.anomaly, flagged) {
+            println!(
+                "  {} anomalous values ({})",
+                flagged.iter().filter(|&&f| f).count(),
+                detector.describe()
+            );
+        }
+    }
+    pauses::print_report(&data.pauses);
+}
+
+/// What an analysis run writes besides the extras requested with flags
+enum Output<'a> {
+    Plot(&'a PlotArgs, String), // Figure, with the output path resolved against --format
+    Stats(&'a StatsArgs),       // Statistics on stdout, plus JSON/TSV aggregates
+    Report(&'a ReportArgs),     // HTML report with the thumbnail and every panel
+}
 
-        // Push the parsed record into the data vector
-        data.push(r);
+/// Rendering switches from the command line; operators always get the health strips
+fn plot_options(
+    figure: &FigureArgs,
+    run: &RunArgs,
+    profile: Profile,
+    size: (u32, u32),
+) -> PlotOptions {
+    // Fall back to text-free rendering rather than failing mid-plot without fonts
+    let text = !figure.no_text && fonts_available();
+    if !text && !figure.no_text {
+        eprintln!("No usable font found, rendering without text");
     }
+    PlotOptions {
+        summary_strip: figure.summary_strip || profile == Profile::Operator,
+        text,
+        center: run.center,
+        language: run.language,
+        axis_break: figure.axis_break,
+        size,
+        max_points: match figure.max_points {
+            Some(0) => None,
+            Some(max_points) => Some(max_points),
+            None => Some(decimate::default_max_points(size.0)),
+        },
+        smoothing: figure.smooth.map(|window| smooth::Smoothing {
+            window,
+            center: figure.smooth_stat,
+            dim_raw: figure.dim_raw,
+        }),
+        clip: figure.clip_percentiles,
+        outlier_markers: figure.outlier_markers,
+        keep_extremes: figure.keep_extremes,
+        theme: figure.theme,
+        palette: figure.palette,
+        dpi: figure.dpi,
+    }
+}
+
+/// Column aliases from `--aliases`, none without it
+fn alias_map(run: &RunArgs) -> Result<AliasMap, Box<dyn Error>> {
+    Ok(match &run.aliases {
+        Some(path) => AliasMap::load(path)?,
+        None => AliasMap::default(),
+    })
+}
 
-    // Sort records chronologically by time
-    data.sort_by(|a, b| a.time.partial_cmp(&b.time).unwrap());
+/// Withdrawn reads to leave out, empty without a tombstone list
+fn tombstones(run: &RunArgs) -> Result<Tombstones, Box<dyn Error>> {
+    Ok(match &run.tombstones {
+        Some(path) => Tombstones::load(path)?,
+        None => Tombstones::default(),
+    })
+}
 
-    Ok(data) // Return the parsed and sorted data
+/// Samples-per-batch validation against the configured chunk size, if requested
+fn sample_expectation(run: &RunArgs) -> Option<SampleExpectation> {
+    run.expected_samples.map(|expected| SampleExpectation {
+        expected,
+        tolerance: run.samples_tolerance,
+    })
 }
 
-/// Plots a set of subplots showing different variables over time
-fn plot_multi_series(data: &[Record], output_path: &str) -> Result<(), Box<dyn Error>> {
-    // Create an SVG drawing area (1000px wide, 1200px tall)
-    let root = BitMapBackend::new(output_path, (2200, 1800)).into_drawing_area();
-    root.fill(&GREY_500)?; // Fill the background with white
+/// Every subcommand's arguments, for merging in a config file
+fn config_arguments() -> Vec<config::Arguments> {
+    Cli::command()
+        .get_subcommands()
+        .map(|command| config::Arguments {
+            subcommand: command.get_name().to_string(),
+            flags: command
+                .get_arguments()
+                .filter_map(|a| {
+                    let reads_next = a.get_action().takes_values() && !a.is_require_equals_set();
+                    Some((a.get_long()?.to_string(), reads_next))
+                })
+                .collect(),
+            positionals: command
+                .get_positionals()
+                .map(|a| a.get_id().to_string())
+                .collect(),
+        })
+        .collect()
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args = config::expand(std::env::args_os().collect(), &config_arguments())?;
+    let cli = Cli::parse_from(args);
+    threads::configure(cli.command.run().threads);
+    match &cli.command {
+        Command::Plot(args) => {
+            let output = match args.format {
+                Some(format) => format.apply(&args.output)?,
+                None => args.output.clone(),
+            };
+            match args.inputs.as_slice() {
+                [input] if args.watch.is_some() => live_plot(input, args, &output),
+                [input] => analyze(input, &args.run, &args.analysis, Output::Plot(args, output)),
+                inputs => compare_runs(inputs, args, &output),
+            }
+        }
+        Command::Stats(args) => {
+            analyze(&args.input, &args.run, &args.analysis, Output::Stats(args))
+        }
+        Command::Report(args) => {
+            analyze(&args.input, &args.run, &args.analysis, Output::Report(args))
+        }
+        Command::Watch(args) => watch(args),
+        Command::Tui(args) => dashboard(args),
+        Command::Serve(args) => serve_metrics(args),
+    }
+}
+
+/// Whether `field` has a finite value to show; an empty input has none
+fn has_values(data: &RunData, field: &Field) -> bool {
+    (field.column)(data).iter().any(|v| v.is_finite())
+}
+
+/// Comparison mode: the standard panels with one series per input run
+fn compare_runs(inputs: &[String], args: &PlotArgs, output: &str) -> Result<(), Box<dyn Error>> {
+    if !args.panels.is_empty() || args.split_panels.is_some() {
+        return Err("--panel and --split-panels need a single input".into());
+    }
+    let parse_options = ParseOptions {
+        time_format: args.run.time_format,
+        aliases: alias_map(&args.run)?,
+        tombstones: tombstones(&args.run)?,
+        ..Default::default()
+    };
+    // Runs are parsed in parallel; errors cross threads as text
+    let runs = threads::map(inputs, |input| {
+        // Legends name runs by file name, or by pseudonym when redacting
+        let label = if args.run.redact {
+            redact::pseudonym(input)
+        } else {
+            Path::new(input)
+                .file_stem()
+                .map_or(input.clone(), |s| s.to_string_lossy().to_string())
+        };
+        Ok(compare::Run {
+            label,
+            data: parse_csv(input, &parse_options).map_err(|e| e.to_string())?,
+        })
+    })
+    .into_iter()
+    .collect::<Result<Vec<_>, String>>()?;
+
+    let profile = args.analysis.profile;
+    let fields: Vec<Field> = plot_fields(sample_expectation(&args.run))
+        .into_iter()
+        .filter(|field| {
+            profile.includes(field) && runs.iter().any(|run| has_values(&run.data, field))
+        })
+        .collect();
+    let mut fields = select_fields(fields, &args.analysis.panels)?;
+    set_y_axes(
+        &mut fields,
+        args.analysis.log_y.as_deref(),
+        &args.analysis.y_ranges,
+    )?;
+    let options = plot_options(&args.figure, &args.run, profile, (args.width, args.height));
+    compare::plot(&runs, &fields, output, options)?;
+    println!("Comparison of {} runs saved to {}", runs.len(), output);
+    Ok(())
+}
+
+/// Live mode: re-renders the standard panels of one growing CSV, writing
+/// under a temporary name so a viewer never shows a partial image
+fn live_plot(input: &str, args: &PlotArgs, output: &str) -> Result<(), Box<dyn Error>> {
+    if !live::can_follow(input) {
+        return Err("--watch follows an uncompressed CSV, local or ssh://".into());
+    }
+    if args.inputs.len() > 1 || !args.panels.is_empty() || args.split_panels.is_some() {
+        return Err("--watch plots a single input with the standard panels".into());
+    }
+    let parse_options = ParseOptions {
+        time_format: args.run.time_format,
+        aliases: alias_map(&args.run)?,
+        tombstones: tombstones(&args.run)?,
+        ..Default::default()
+    };
+    let profile = args.analysis.profile;
+    let options = plot_options(&args.figure, &args.run, profile, (args.width, args.height));
+    let interval = std::time::Duration::from_secs(args.watch.unwrap_or_default().max(1));
+    let partial = daemon::partial_path(Path::new(output));
+    let partial_str = partial.to_string_lossy().to_string();
+    println!("Following {} (Ctrl-C to stop)", input);
+
+    live::follow(input, &parse_options, interval, |data| {
+        let fields: Vec<Field> = plot_fields(sample_expectation(&args.run))
+            .into_iter()
+            .filter(|field| profile.includes(field) && has_values(data, field))
+            .collect();
+        let mut fields = select_fields(fields, &args.analysis.panels)?;
+        set_y_axes(
+            &mut fields,
+            args.analysis.log_y.as_deref(),
+            &args.analysis.y_ranges,
+        )?;
+        for artifact in plot_multi_series(data, &fields, &partial_str, options)? {
+            std::fs::rename(
+                &artifact.path,
+                artifact.path.replacen(&partial_str, output, 1),
+            )?;
+        }
+        println!("Plot of {} rows saved to {}", data.len(), output);
+        Ok(())
+    })
+}
+
+/// Terminal dashboard of one run, redrawn in place while following it
+fn dashboard(args: &TuiArgs) -> Result<(), Box<dyn Error>> {
+    let width = args
+        .width
+        .or_else(|| std::env::var("COLUMNS").ok()?.parse().ok())
+        .unwrap_or(100);
+    let color = std::io::stdout().is_terminal();
+    let title = if args.run.redact {
+        redact::pseudonym(&args.input)
+    } else {
+        args.input.clone()
+    };
+    let parse_options = ParseOptions {
+        time_format: args.run.time_format,
+        aliases: alias_map(&args.run)?,
+        tombstones: tombstones(&args.run)?,
+        ..Default::default()
+    };
+    let Some(secs) = args.follow else {
+        let data = parse_csv(&args.input, &parse_options)?;
+        print!("{}", tui::render(&data, &title, width, color));
+        return Ok(());
+    };
+    if !live::can_follow(&args.input) {
+        return Err("--follow needs an uncompressed CSV, local or ssh://".into());
+    }
+    let interval = std::time::Duration::from_secs(secs.max(1));
+    live::follow(&args.input, &parse_options, interval, |data| {
+        // Clear the screen and home the cursor before each redraw
+        print!("\x1b[2J\x1b[H{}", tui::render(data, &title, width, color));
+        std::io::stdout().flush()?;
+        Ok(())
+    })
+}
+
+/// Prometheus exporter of one run, refreshed each time its CSV grows
+fn serve_metrics(args: &ServeArgs) -> Result<(), Box<dyn Error>> {
+    if !live::can_follow(&args.input) {
+        return Err("serve needs an uncompressed CSV, local or ssh://".into());
+    }
+    let run = if args.run.redact {
+        redact::pseudonym(&args.input)
+    } else {
+        args.input.clone()
+    };
+    let parse_options = ParseOptions {
+        time_format: args.run.time_format,
+        aliases: alias_map(&args.run)?,
+        tombstones: tombstones(&args.run)?,
+        ..Default::default()
+    };
+    let window = args.window.max(1) as f64;
+    let shared = serve::Shared::default();
+    shared.lock().map_err(|_| "Metrics lock poisoned")?.metrics =
+        serve::exposition(&Default::default(), &run, window, None);
+    let token = match &args.token_file {
+        Some(path) => {
+            let token = std::fs::read_to_string(path)
+                .map_err(|e| format!("Cannot read token file {}: {}", path, e))?;
+            let token = token.trim();
+            if token.is_empty() {
+                return Err(format!("Token file {} is empty", path).into());
+            }
+            Some(token.to_string())
+        }
+        None => None,
+    };
+    let guarded = if token.is_some() {
+        " (bearer token required)"
+    } else {
+        ""
+    };
+    let id = serve::run_id(&args.input, args.run.redact);
+    serve::spawn(&args.listen, id.clone(), shared.clone(), token)?;
+    println!(
+        "Serving metrics of {} on http://{}/metrics and JSON under http://{}/runs/{}/{}",
+        run, args.listen, args.listen, id, guarded
+    );
+
+    let interval = std::time::Duration::from_secs(args.interval.max(1));
+    live::follow(&args.input, &parse_options, interval, |data| {
+        // The decision log grows alongside the batch log; a partly written
+        // one is skipped until the next poll
+        let report = args.decisions.as_deref().and_then(|path| {
+            decisions::parse(path, args.run.time_format, &parse_options.tombstones)
+                .map_err(|e| eprintln!("Skipping decisions {}: {}", path, e))
+                .ok()
+                .map(|(d, withdrawn)| decisions::analyze(&d, withdrawn, args.sample_rate))
+        });
+        let published = serve::Published {
+            metrics: serve::exposition(data, &run, window, report.as_ref()),
+            data: std::sync::Arc::new(data.clone()),
+        };
+        *shared.lock().map_err(|_| "Metrics lock poisoned")? = published;
+        Ok(())
+    })
+}
 
-    // Divide the root area into 5 stacked horizontal panels
-    let split = root.split_evenly((5, 1));
+/// Daemon mode: follows every run under a data directory instead of one CSV
+fn watch(args: &WatchArgs) -> Result<(), Box<dyn Error>> {
+    let config = daemon::WatchConfig {
+        root: args.dir.clone().into(),
+        interval: std::time::Duration::from_secs(args.interval),
+        once: args.once,
+        options: plot_options(&args.figure, &args.run, Profile::Scientist, FIGURE_SIZE),
+        samples: sample_expectation(&args.run),
+        redact: args.run.redact,
+        time_format: args.run.time_format,
+        aliases: alias_map(&args.run)?,
+        tombstones: tombstones(&args.run)?,
+        reference: match args.reference.as_deref() {
+            Some(path) => Some(parse_csv(
+                path,
+                &ParseOptions {
+                    time_format: args.run.time_format,
+                    aliases: alias_map(&args.run)?,
+                    ..Default::default()
+                },
+            )?),
+            None => None,
+        },
+        snapshot_every: args.snapshot_every.map(std::time::Duration::from_secs),
+        retention: retention::Retention {
+            keep_last: args.keep_last,
+            per: args.keep_per,
+        },
+        pid_file: args.pid_file.clone().map(Into::into),
+        log: service::Logger {
+            journal: args.journal,
+        },
+    };
+    daemon::run(&config)
+}
 
-    // List of fields to plot: (label, accessor function)
-    // The accessor functions are boxed closures that extract a f64 value from a `Record`
-    let fields: Vec<(&str, Box<dyn Fn(&Record) -> f64>)> = vec![
-        ("Samples", Box::new(|r: &Record| r.samples)),
-        ("Bases", Box::new(|r: &Record| r.bases)),
-        ("Mean Q-score", Box::new(|r: &Record| r.mean_qscore)),
+/// Options that need the individual rows, which `--mode streaming` does not keep
+fn unstreamable(run: &RunArgs, analysis: &AnalysisArgs) -> Vec<&'static str> {
+    [
+        ("--stat other than mean", run.center != robust::Center::Mean),
+        ("--expected-samples", run.expected_samples.is_some()),
+        ("--preset", analysis.preset.is_some()),
+        (
+            "--quantiles exact",
+            analysis.quantiles == Some(quantile::Method::Exact),
+        ),
+        ("--anomaly", !analysis.anomalies.is_empty()),
+        ("--cumulative-yield", analysis.cumulative_yield),
+        ("--rates", analysis.rates),
+        ("--bam", analysis.bam.is_some()),
+        ("--rules", analysis.rules.is_some()),
+        ("--target-bases", analysis.target_bases.is_some()),
+        ("--flowcell-cost", analysis.flowcell_cost.is_some()),
+        ("--barcode-plot", analysis.barcode_plot.is_some()),
+        ("--forensic", analysis.forensic.is_some()),
+        (
+            "--health-score",
+            analysis.health_score || analysis.verdict_json.is_some(),
+        ),
         (
-            "Time to Package",
-            Box::new(|r: &Record| r.time_to_package_and_send),
+            "--baseline",
+            analysis.baseline.is_some()
+                || analysis.baseline_file.is_some()
+                || analysis.learn_baseline.is_some(),
         ),
+        ("--reference", analysis.reference.is_some()),
+        ("--histogram", analysis.histogram.is_some()),
+        ("--qscore-histogram", analysis.qscore_histogram.is_some()),
+        ("--minknow-report", analysis.minknow_report.is_some()),
+        ("--control-chart", analysis.control_chart.is_some()),
+        ("--calibration-plot", analysis.calibration_plot.is_some()),
         (
-            "Time in Basecaller",
-            Box::new(|r: &Record| r.time_in_basecaller),
+            "--length-classes",
+            analysis.length_classes.is_some() || analysis.length_class_plot.is_some(),
         ),
-    ];
+        ("--rebasecall-reads", analysis.rebasecall_reads.is_some()),
+        ("--export-reads", analysis.export_reads.is_some()),
+        ("--simulate-rule", !analysis.simulate_rules.is_empty()),
+        ("--what-if-batch", analysis.what_if_batch.is_some()),
+    ]
+    .into_iter()
+    .filter(|&(_, set)| set)
+    .map(|(flag, _)| flag)
+    .collect()
+}
+
+/// Results of the optional analyses behind extra panels, reported after the figure
+struct Extras {
+    costs: Option<CostModel>,                 // Cost model, with --flowcell-cost
+    barcodes: Option<barcode::BarcodeReport>, // Balance of a multiplexed run
+    library: Option<rna::Library>,            // Transcript library, when the run is one
+    ultralong: bool,                          // Judged as an ultra-long library
+    scans: Vec<porescan::PoreScan>,           // Pore scans, with --pore-scan
+    health_scores: Option<health::HealthScores>, // Composite health, for the panel and verdict
+}
+
+/// A parsed run with its panels and checks, as the outputs of `analyze` see it
+struct Analyzed<'a> {
+    input: &'a str,              // Input path, shown as its pseudonym when redacting
+    run: &'a RunArgs,            // Run-level flags
+    analysis: &'a AnalysisArgs,  // Requested analyses
+    data: RunData,               // Parsed batches with their derived columns
+    fields: Vec<Field>,          // Panels, in figure order
+    provenance: String,          // How the statistics were computed
+    quantiles: quantile::Method, // How percentiles were computed
+    checks: Health,              // Worst outcome of the rules and validations
+    deviations: Vec<golden::Deviation>, // Distance from the golden run
+    tombstones: Tombstones,      // Withdrawn reads, also left out of the decisions
+}
 
-    // Iterate over each subplot panel and corresponding data field
-    for (i, (title, accessor)) in fields.iter().enumerate() {
-        let area = &split[i]; // Current subplot drawing area
+/// Parses one CSV, runs the requested analyses and writes `output` plus any extras
+fn analyze(
+    input_csv: &str,
+    run: &RunArgs,
+    analysis: &AnalysisArgs,
+    output: Output,
+) -> Result<(), Box<dyn Error>> {
+    let mode = statistics_mode(run, analysis)?;
+    let quantiles = analysis.quantiles.unwrap_or(mode.quantiles());
+    let (mut data, parse_options) = load_run(input_csv, run, analysis, mode)?;
+    let provenance = mode::provenance(mode, quantiles, data.sample, data.rows());
 
-        // Draw border around the subplot area
-        let x_range = area.get_pixel_range().0.clone();
-        let y_range = area.get_pixel_range().1.clone();
+    let mut fields = plot_fields(sample_expectation(run));
+    let extras = add_panels(&mut data, &mut fields, run, analysis)?;
+    customize_fields(&mut fields, analysis)?;
 
-        let x0 = x_range.start;
-        let x1 = x_range.end;
-        let y0 = y_range.start;
-        let y1 = y_range.end;
+    // Columns the input cannot provide (the batch timings of a sequencing
+    // summary) hold only NaN and get no panel
+    let fields: Vec<Field> = fields
+        .into_iter()
+        .filter(|field| analysis.profile.includes(field) && has_values(&data, field))
+        .collect();
+    if fields.is_empty() {
+        return Err(format!("{} has no batches with values to analyze", input_csv).into());
+    }
+    let mut fields = select_fields(fields, &analysis.panels)?;
+    set_y_axes(&mut fields, analysis.log_y.as_deref(), &analysis.y_ranges)?;
+
+    let (checks, deviations) = run_checks(&data, &fields, run, analysis)?;
+    let analyzed = Analyzed {
+        input: input_csv,
+        run,
+        analysis,
+        data,
+        fields,
+        provenance,
+        quantiles,
+        checks,
+        deviations,
+        tombstones: parse_options.tombstones,
+    };
+
+    let mut artifacts = Vec::new();
+    if let Some(path) = analysis.quarantine.as_deref() {
+        artifacts.push(Artifact::new("quarantine", path));
+    }
+    write_output(&analyzed, &output, &mut artifacts)?;
+    report_yield(&analyzed, &extras, &mut artifacts)?;
+    report_distributions(&analyzed, &extras, &mut artifacts)?;
+    report_quality(&analyzed, &extras, &mut artifacts)?;
+    report_reads(&analyzed, &mut artifacts)?;
 
-        area.draw(&Rectangle::new(
-            [(x0, y0), (x1 - 1, y1 - 1)],
-            BLACK.stroke_width(2),
-        ))?;
+    // List everything written so downstream steps need no hard-coded filenames
+    if let Some(manifest_path) = analysis.manifest.as_deref() {
+        manifest::write_manifest(
+            manifest_path,
+            input_csv,
+            &analyzed.provenance,
+            &artifacts,
+            run.redact,
+        )?;
+        println!("Manifest saved to {}", manifest_path);
+    }
+    Ok(())
+}
 
-        // Determine min/max time for x-axis bounds
-        let _min_time = data.first().unwrap().time;
-        let _max_time = data.last().unwrap().time;
+/// Statistics mode; a row sample on its own implies approximate statistics
+fn statistics_mode(run: &RunArgs, analysis: &AnalysisArgs) -> Result<mode::Mode, Box<dyn Error>> {
+    let mode = match analysis.mode {
+        Some(mode) => mode,
+        None if analysis.sample.is_some() => mode::Mode::Approximate,
+        None => mode::Mode::Exact,
+    };
+    if mode != mode::Mode::Approximate && analysis.sample.is_some() {
+        return Err(format!("--sample cannot be combined with --mode {}", mode.label()).into());
+    }
+    if mode == mode::Mode::Streaming {
+        let unsupported = unstreamable(run, analysis);
+        if !unsupported.is_empty() {
+            return Err(format!(
+                "--mode streaming keeps no rows and cannot be combined with {}",
+                unsupported.join(", ")
+            )
+            .into());
+        }
+    }
+    Ok(mode)
+}
 
-        // Determine min/max time for x-axis bounds
-        let min_time = data.first().unwrap().time;
-        let max_time = data.last().unwrap().time;
+/// Parses the input in `mode`, with the golden run aligned to it when given
+fn load_run(
+    input_csv: &str,
+    run: &RunArgs,
+    analysis: &AnalysisArgs,
+    mode: mode::Mode,
+) -> Result<(RunData, ParseOptions), Box<dyn Error>> {
+    let parse_options = ParseOptions {
+        sampling: analysis.sample.or(mode.sampling()),
+        time_format: run.time_format,
+        validate: analysis.validate,
+        quarantine: analysis.quarantine.clone(),
+        aliases: alias_map(run)?,
+        tombstones: tombstones(run)?,
+    };
+    let mut data = match mode {
+        mode::Mode::Streaming => stream_csv(input_csv, &parse_options)?,
+        _ => parse_csv(input_csv, &parse_options)?,
+    };
+    if let Some(info) = data.sample {
+        println!("{}", info.describe());
+    }
+    if let Some(report) = &data.validation {
+        report.print();
+    }
+    if let Some(report) = &data.tombstones {
+        report.print();
+    }
+    // Catch runs whose basecaller output does not match the kit early
+    kits::warn_missing(&data);
+    data.pauses = pauses::detect(&data, analysis.pause_gap);
+
+    // The golden run, on this run's clock, behind every panel
+    if let Some(path) = analysis.reference.as_deref() {
+        let reference = parse_csv(
+            path,
+            &ParseOptions {
+                time_format: run.time_format,
+                aliases: parse_options.aliases.clone(),
+                ..Default::default()
+            },
+        )?;
+        data.reference = Some(Box::new(golden::align(&reference, &data)));
+    }
+    Ok((data, parse_options))
+}
+
+/// Derives the columns of the requested analyses and appends their panels
+fn add_panels(
+    data: &mut RunData,
+    fields: &mut Vec<Field>,
+    run: &RunArgs,
+    analysis: &AnalysisArgs,
+) -> Result<Extras, Box<dyn Error>> {
+    // Yield so far, in bases and in reads (one row per read)
+    if analysis.cumulative_yield {
+        data.cumulative_bases = reduce::cumulative_sum(&data.bases);
+        data.cumulative_reads = (1..=data.len()).map(|n| n as f64).collect();
+        fields.extend([
+            Field::derived(
+                "Cumulative Bases",
+                |d| &d.cumulative_bases,
+                Precision::Decimals(0),
+            ),
+            Field::derived(
+                "Cumulative Reads",
+                |d| &d.cumulative_reads,
+                Precision::Decimals(0),
+            ),
+        ]);
+    }
+
+    // Cost accounting adds a cumulative cost-efficiency panel
+    let costs = analysis.flowcell_cost.map(|flowcell| CostModel {
+        flowcell,
+        compute_per_hour: analysis.compute_cost_per_hour,
+    });
+    if let Some(model) = costs {
+        data.cost_per_gb = model.cumulative_cost_per_gb(&data.time, &data.bases);
+        fields.push(Field::derived(
+            "Cost per Gb",
+            |d| &d.cost_per_gb,
+            Precision::Decimals(2),
+        ));
+    }
+
+    // Multiplexed runs get a balance check and an unclassified-fraction panel
+    let mut barcodes = barcode::analyze(&data.barcode, &data.bases);
+    if run.redact
+        && let Some(report) = &mut barcodes
+    {
+        report.redact();
+    }
+    if barcodes.is_some() {
+        data.unclassified_fraction = barcode::cumulative_unclassified(&data.barcode);
+        fields.push(
+            Field::derived(
+                "Unclassified Fraction",
+                |d| &d.unclassified_fraction,
+                Precision::Decimals(3),
+            )
+            .with_thresholds(barcode::UNCLASSIFIED_FRACTION),
+        );
+    }
+
+    add_read_panels(data, fields, analysis)?;
+    let library = add_library_panels(data, fields, analysis);
+
+    // Ultra-long libraries are judged by how their read lengths hold up over the run
+    let ultralong = analysis.preset == Some(Preset::Ultralong);
+    if ultralong {
+        data.read_n50 = ultralong::windowed_n50(&data.bases, ultralong::WINDOW);
+        data.n50_retention = ultralong::n50_retention(&data.bases, &data.read_n50);
+        data.ultralong_fraction = ultralong::cumulative_ultralong_fraction(&data.bases);
+        fields.extend([
+            Field::derived("Read N50", |d| &d.read_n50, Precision::Decimals(0)),
+            Field::derived(
+                "N50 Retention",
+                |d| &d.n50_retention,
+                Precision::Decimals(2),
+            )
+            .with_thresholds(ultralong::N50_RETENTION),
+            Field::derived(
+                "Ultra-long Yield Fraction",
+                |d| &d.ultralong_fraction,
+                Precision::Decimals(3),
+            )
+            .with_thresholds(ultralong::ULTRALONG_YIELD),
+        ]);
+    }
+
+    add_device_panels(data, fields, run, analysis)?;
+
+    // Pore scans feed both the attrition sub-score and their own report
+    let scans = match analysis.pore_scan.as_deref() {
+        Some(scan_path) => {
+            let mut scans = porescan::parse(scan_path, run.time_format)?;
+            porescan::attach_yield(&mut scans, &data.time, &data.bases);
+            scans
+        }
+        None => Vec::new(),
+    };
+
+    // Composite run health score, as a panel and as a final verdict
+    let mut health_scores = None;
+    if analysis.health_score || analysis.verdict_json.is_some() {
+        let weights = match analysis.health_weights.as_deref() {
+            Some(spec) => health::Weights::parse(spec)?,
+            None => health::Weights::default(),
+        };
+        let expectation = analysis
+            .target_bases
+            .map(|target_bases| health::YieldExpectation {
+                target_bases,
+                run_seconds: analysis.run_hours * 3600.0,
+            });
+        let scores = health::score(data, weights, expectation, &scans);
+        data.health_score = scores.composite.clone();
+        health_scores = Some(scores);
+        fields.push(
+            Field::derived(
+                "Run Health Score",
+                |d| &d.health_score,
+                Precision::Decimals(1),
+            )
+            .with_thresholds(health::HEALTH_SCORE),
+        );
+    }
+
+    Ok(Extras {
+        costs,
+        barcodes,
+        library,
+        ultralong,
+        scans,
+        health_scores,
+    })
+}
+
+/// Panels of per-read columns: splitting, trimming and homopolymer errors
+fn add_read_panels(
+    data: &mut RunData,
+    fields: &mut Vec<Field>,
+    analysis: &AnalysisArgs,
+) -> Result<(), Box<dyn Error>> {
+    // Read splitting is only tracked when the input carries parent read IDs
+    if !data.split.is_empty() {
+        data.split_rate = reduce::rolling_mean(&data.split, splitting::WINDOW);
+        fields.push(
+            Field::derived("Split Read Rate", |d| &d.split_rate, Precision::Decimals(3))
+                .with_thresholds(splitting::SPLIT_RATE),
+        );
+    }
 
-        // Determine min/max value for y-axis bounds using the accessor
-        let min_val = data
+    // Trimming statistics from dorado, when written
+    if !data.trimmed_bases.is_empty() {
+        fields.push(Field::derived(
+            "Trimmed Bases",
+            |d| &d.trimmed_bases,
+            Precision::Decimals(0),
+        ));
+    }
+    if !data.adapter_detected.is_empty() {
+        data.adapter_rate = reduce::rolling_mean(&data.adapter_detected, trimming::WINDOW);
+        fields.push(
+            Field::derived(
+                "Adapter Detection Rate",
+                |d| &d.adapter_rate,
+                Precision::Decimals(3),
+            )
+            .with_thresholds(trimming::ADAPTER_DETECTION),
+        );
+    }
+
+    // Homopolymer mis-counts as a proxy for basecalling error drift
+    if let Some(path) = analysis.bam.as_deref() {
+        let reads = homopolymer::parse(path)?;
+        let matched = data
+            .read_id
             .iter()
-            .map(|r| accessor(r))
-            .fold(f64::INFINITY, f64::min);
-        let max_val = data
+            .filter(|id| reads.contains_key(*id))
+            .count();
+        println!(
+            "{} aligned reads in {}, {} of them in the batch log",
+            reads.len(),
+            path,
+            matched
+        );
+        data.homopolymer_error_rate =
+            homopolymer::rolling_error_rate(data, &reads, homopolymer::WINDOW);
+        fields.push(
+            Field::derived(
+                "Homopolymer Error Rate",
+                |d| &d.homopolymer_error_rate,
+                Precision::Decimals(4),
+            )
+            .with_thresholds(homopolymer::ERROR_RATE),
+        );
+    }
+    Ok(())
+}
+
+/// Poly(A) and strand panels of a transcript library; the kit named in the
+/// input decides, then --library, then the mere presence of the columns
+fn add_library_panels(
+    data: &mut RunData,
+    fields: &mut Vec<Field>,
+    analysis: &AnalysisArgs,
+) -> Option<rna::Library> {
+    let library = match (&data.kit, analysis.library) {
+        (Some(kit), _) => Some(rna::Library::from_kit(kit)),
+        (None, Some(library)) => Some(library),
+        (None, None) if !data.poly_tail_length.is_empty() || !data.strand.is_empty() => {
+            Some(rna::Library::Cdna)
+        }
+        (None, None) => None,
+    }
+    .filter(|library| library.is_transcript())?;
+    if !data.poly_tail_length.is_empty() {
+        data.polya_length = rna::rolling_tail_length(&data.poly_tail_length, rna::WINDOW);
+        fields.push(Field::derived(
+            "Poly(A) Length",
+            |d| &d.polya_length,
+            Precision::Decimals(0),
+        ));
+    }
+    if !data.strand.is_empty() {
+        data.strand_balance = reduce::rolling_mean(&data.strand, rna::WINDOW);
+        fields.push(Field::derived(
+            "Forward Strand Share",
+            |d| &d.strand_balance,
+            Precision::Decimals(3),
+        ));
+    }
+    Some(library)
+}
+
+/// Panels from the sequencing device and basecall server: telemetry, server
+/// log errors and throughput rates
+fn add_device_panels(
+    data: &mut RunData,
+    fields: &mut Vec<Field>,
+    run: &RunArgs,
+    analysis: &AnalysisArgs,
+) -> Result<(), Box<dyn Error>> {
+    // Device telemetry panels, resampled onto the batch rows so they line up
+    if let Some(path) = analysis.telemetry.as_deref() {
+        let device = telemetry::parse(path, run.time_format)?;
+        if let Some(values) = &device.temperature {
+            data.asic_temperature = telemetry::resample(&device.time, values, &data.time);
+            fields.push(Field::derived(
+                "ASIC Temperature",
+                |d| &d.asic_temperature,
+                Precision::Decimals(1),
+            ));
+        }
+        if let Some(values) = &device.bias_voltage {
+            data.bias_voltage = telemetry::resample(&device.time, values, &data.time);
+            fields.push(Field::derived(
+                "Bias Voltage",
+                |d| &d.bias_voltage,
+                Precision::DEFAULT,
+            ));
+        }
+    }
+
+    // dorado server logs: events marked on every panel plus an error-rate panel
+    if !analysis.dorado_logs.is_empty() {
+        let mut events = Vec::new();
+        for path in &analysis.dorado_logs {
+            events.extend(serverlog::parse(path, run.time_format)?);
+        }
+        events.sort_by(|a, b| a.time.total_cmp(&b.time));
+        // The error rate counts every line; the timeline gets one event per
+        // run of repeats
+        data.server_errors = serverlog::error_rate(&events, &data.time);
+        data.events = serverlog::collapse(events);
+        serverlog::print_report(&data.events);
+        fields.push(
+            Field::derived(
+                "Server Errors/min",
+                |d| &d.server_errors,
+                Precision::Decimals(1),
+            )
+            .with_thresholds(Thresholds {
+                warn: 1.0,
+                fail: 5.0,
+                higher_is_worse: true,
+            }),
+        );
+    }
+
+    // Throughput as rates; after the server logs, whose restarts break them
+    if analysis.rates {
+        (data.bases_per_second, data.reads_per_second) = rates::per_second(data);
+        fields.extend([
+            Field::derived("Bases/sec", |d| &d.bases_per_second, Precision::Decimals(1)),
+            Field::derived("Reads/sec", |d| &d.reads_per_second, Precision::Decimals(2)),
+        ]);
+    }
+    Ok(())
+}
+
+/// Applies the per-metric anomaly detectors and rounding of the command line
+fn customize_fields(fields: &mut [Field], analysis: &AnalysisArgs) -> Result<(), Box<dyn Error>> {
+    // Per-metric anomaly detectors, given as `Metric Title=detector`
+    for spec in &analysis.anomalies {
+        let (title, detector) = spec
+            .split_once('=')
+            .ok_or_else(|| format!("Anomaly '{}' must look like METRIC=DETECTOR", spec))?;
+        let field = fields
+            .iter_mut()
+            .find(|f| f.title.eq_ignore_ascii_case(title.trim()))
+            .ok_or_else(|| format!("No metric named '{}'", title))?;
+        field.anomaly = Some(anomaly::Detector::parse(detector.trim())?);
+    }
+
+    // Per-metric rounding, given as `Metric Title=2dp|3sf` (`all` for every metric)
+    for spec in &analysis.precisions {
+        let (title, precision) = spec.split_once('=').ok_or_else(|| {
+            format!(
+                "Precision '{}' must look like METRIC=2dp or METRIC=3sf",
+                spec
+            )
+        })?;
+        let precision = Precision::parse(precision)?;
+        let title = title.trim();
+        let mut matched = fields
+            .iter_mut()
+            .filter(|f| title.eq_ignore_ascii_case("all") || f.title.eq_ignore_ascii_case(title))
+            .peekable();
+        if matched.peek().is_none() {
+            return Err(format!("No metric named '{}'", title).into());
+        }
+        matched.for_each(|field| field.precision = precision);
+    }
+    Ok(())
+}
+
+/// Runs the samples-per-batch check, the facility rules and the golden run
+/// comparison; returns their worst outcome and the golden run deviations
+fn run_checks(
+    data: &RunData,
+    fields: &[Field],
+    run: &RunArgs,
+    analysis: &AnalysisArgs,
+) -> Result<(Health, Vec<golden::Deviation>), Box<dyn Error>> {
+    let mut samples_flagged = 0;
+    if let Some(expectation) = sample_expectation(run) {
+        let flagged = check_samples_per_batch(data, expectation);
+        report_samples_check(data, expectation, &flagged);
+        samples_flagged = flagged.len();
+    }
+
+    // Facility QC rules from a rules file
+    let rule_outcomes = match analysis.rules.as_deref() {
+        Some(path) => rules::evaluate(data, &rules::load(path)?)?,
+        None => Vec::new(),
+    };
+    rules::print_report(&rule_outcomes);
+
+    // Distance from the golden run over the hours both runs cover
+    let deviations = match (analysis.reference.as_deref(), data.reference.as_deref()) {
+        (Some(path), Some(reference)) => {
+            let deviations = golden::deviations(data, reference, fields, run.center);
+            let name = if run.redact {
+                redact::pseudonym(path)
+            } else {
+                path.to_string()
+            };
+            golden::print_report(&name, &deviations);
+            deviations
+        }
+        _ => Vec::new(),
+    };
+
+    // A failed samples-per-batch validation or a run straying from the
+    // golden run counts as a warning
+    let strayed = deviations.iter().any(|d| d.health() != Health::Ok);
+    let checks = rule_outcomes
+        .iter()
+        .map(|o| o.health)
+        .chain((samples_flagged > 0 || strayed).then_some(Health::Warn))
+        .max()
+        .unwrap_or(Health::Ok);
+    Ok((checks, deviations))
+}
+
+/// Writes what the subcommand produces: the statistics report, the figure or
+/// the HTML report, plus any digests
+fn write_output(
+    a: &Analyzed,
+    output: &Output,
+    artifacts: &mut Vec<Artifact>,
+) -> Result<(), Box<dyn Error>> {
+    let (data, fields, run) = (&a.data, &a.fields, a.run);
+
+    // Stats only report; plots and reports render the figure (the latter as a thumbnail)
+    let mut options = None;
+    let mut digest_paths: Vec<&str> = a.analysis.digest.iter().map(String::as_str).collect();
+    match output {
+        Output::Stats(args) => {
+            print_report(data, fields, a.quantiles, run.center, &a.provenance);
+            if args.json.is_some() || args.tsv.is_some() {
+                let aggregates = summary::summarize(data, args.throughput_window);
+                if let Some(path) = args.json.as_deref() {
+                    summary::write_json(path, &aggregates)?;
+                    artifacts.push(Artifact::new("stats_json", path));
+                    println!("Aggregate statistics saved to {}", path);
+                }
+                if let Some(path) = args.tsv.as_deref() {
+                    summary::write_tsv(path, &aggregates)?;
+                    artifacts.push(Artifact::new("stats_tsv", path));
+                    println!("Aggregate statistics saved to {}", path);
+                }
+            }
+        }
+        Output::Plot(args, output_path) => {
+            options = Some(write_plot(a, args, output_path, artifacts)?);
+        }
+        Output::Report(args) => {
+            options = Some(plot_options(
+                &args.figure,
+                run,
+                a.analysis.profile,
+                FIGURE_SIZE,
+            ));
+            digest_paths.insert(0, &args.output);
+        }
+    }
+
+    // Small emailable summary; the thumbnail is only rendered along with a
+    // figure, and only the report embeds the panels at full size
+    let report_path = match output {
+        Output::Report(args) => Some(args.output.as_str()),
+        _ => None,
+    };
+    for digest_path in digest_paths {
+        let thumbnail = match options {
+            Some(plot_options) => Some(render_thumbnail(data, fields, plot_options)?),
+            None => None,
+        };
+        let panels = match options {
+            Some(plot_options) if report_path == Some(digest_path) => {
+                render_panels(data, fields, plot_options)?
+            }
+            _ => Vec::new(),
+        };
+        // Shared digests name the run by pseudonym instead of its path
+        let shown_input = if run.redact {
+            redact::pseudonym(a.input)
+        } else {
+            a.input.to_string()
+        };
+        let mut digest = build_digest(
+            &shown_input,
+            data,
+            fields,
+            a.checks,
+            &a.provenance,
+            run.center,
+            run.language,
+            thumbnail,
+            panels,
+        );
+        if let Some(worst) = golden::worst(&a.deviations) {
+            digest.metadata.push(("Reference deviation", worst));
+        }
+        digest::write_digest(digest_path, &digest)?;
+        if report_path == Some(digest_path) {
+            artifacts.push(Artifact::new("report", digest_path));
+            println!("Report saved to {}", digest_path);
+        } else {
+            artifacts.push(Artifact::new("digest", digest_path));
+            println!("Digest saved to {}", digest_path);
+        }
+    }
+    Ok(())
+}
+
+/// Renders the figure, plus any single panels and panel data; returns the
+/// options it was drawn with, for the digest thumbnails
+fn write_plot(
+    a: &Analyzed,
+    args: &PlotArgs,
+    output_path: &str,
+    artifacts: &mut Vec<Artifact>,
+) -> Result<PlotOptions, Box<dyn Error>> {
+    let (data, fields) = (&a.data, &a.fields);
+    let plot_options = plot_options(
+        &args.figure,
+        a.run,
+        a.analysis.profile,
+        (args.width, args.height),
+    );
+
+    // Generate the subplot visualization and save to file; explicit
+    // `--panel` specs replace the standard layout
+    let panels = args
+        .panels
+        .iter()
+        .map(|spec| compose::Panel::parse(spec))
+        .collect::<Result<Vec<_>, _>>()?;
+    if panels.is_empty() {
+        artifacts.extend(plot_multi_series(data, fields, output_path, plot_options)?);
+    } else if args.panel_data {
+        return Err("--panel-data needs the standard panels, not --panel".into());
+    } else {
+        compose::plot(data, fields, &panels, output_path, plot_options)?;
+        artifacts.push(Artifact::new("plot", output_path));
+    }
+    println!("Plot saved to {}", output_path);
+
+    // Single panels for slides and papers, next to the combined figure
+    if let Some(dir) = &args.split_panels {
+        let panels = write_split_panels(data, fields, dir, output_path, plot_options)?;
+        println!("{} panels saved to {}", panels.len(), dir);
+        artifacts.extend(panels);
+    }
+
+    // The plotted points of every panel, next to the combined figure
+    // and next to each single panel
+    if args.panel_data {
+        let stem = Path::new(output_path).with_extension("");
+        let mut written = 0;
+        for field in fields {
+            let mut paths = vec![format!(
+                "{}.{}.csv",
+                stem.to_string_lossy(),
+                panel_file_name(field)
+            )];
+            if let Some(dir) = &args.split_panels {
+                let path = Path::new(dir).join(format!("{}.csv", panel_file_name(field)));
+                paths.push(path.to_string_lossy().to_string());
+            }
+            for path in paths {
+                write_panel_data(&path, data, field, plot_options)?;
+                artifacts.push(Artifact::new("panel_data", path));
+                written += 1;
+            }
+        }
+        println!("{} panel data files saved next to the figures", written);
+    }
+    Ok(plot_options)
+}
+
+/// Yield projections, costs and barcode balance
+fn report_yield(
+    a: &Analyzed,
+    extras: &Extras,
+    artifacts: &mut Vec<Artifact>,
+) -> Result<(), Box<dyn Error>> {
+    let (data, analysis) = (&a.data, a.analysis);
+
+    // Projected milestones for operators, from the yield so far
+    let mut milestones = Vec::new();
+    if let Some(target) = analysis.target_bases {
+        milestones = projection::project_milestones(&data.time, &data.bases, target);
+
+        if let Some(json_path) = analysis.milestones.as_deref() {
+            projection::write_json(json_path, &milestones)?;
+            artifacts.push(Artifact::new("milestones", json_path));
+            println!("Milestones saved to {}", json_path);
+        }
+        if let Some(ics_path) = analysis.milestones_ics.as_deref() {
+            projection::write_ics(ics_path, &milestones)?;
+            artifacts.push(Artifact::new("calendar", ics_path));
+            println!("Calendar saved to {}", ics_path);
+        }
+    }
+
+    if let Some(model) = extras.costs {
+        report_costs(data, model, &milestones);
+    }
+
+    if let Some(report) = &extras.barcodes {
+        barcode::print_report(report);
+        if let Some(path) = analysis.barcode_plot.as_deref() {
+            barcode::plot_balance(report, path)?;
+            artifacts.push(Artifact::new("barcode_plot", path));
+            println!("Barcode plot saved to {}", path);
+        }
+    }
+    Ok(())
+}
+
+/// Accuracy calibration, library reports, read length classes and histograms
+fn report_distributions(
+    a: &Analyzed,
+    extras: &Extras,
+    artifacts: &mut Vec<Artifact>,
+) -> Result<(), Box<dyn Error>> {
+    let (data, analysis, center) = (&a.data, a.analysis, a.run.center);
+
+    // Predicted against measured accuracy, which drifts apart after a model change
+    match calibration::analyze(data, analysis.calibration_bins) {
+        Some(calibration) => {
+            calibration::print_report(&calibration);
+            if let Some(path) = analysis.calibration_plot.as_deref() {
+                calibration::plot(&calibration, path)?;
+                artifacts.push(Artifact::new("calibration_plot", path));
+                println!("Calibration plot saved to {}", path);
+            }
+        }
+        None if analysis.calibration_plot.is_some() => {
+            return Err("--calibration-plot needs an alignment_identity column".into());
+        }
+        None => {}
+    }
+
+    if let Some(library) = extras.library {
+        rna::print_report(data, library);
+    }
+
+    if extras.ultralong
+        && let Some(report) = ultralong::analyze(data)
+    {
+        ultralong::print_report(&report);
+    }
+
+    // Ultra-long protocols judge the run by its long fraction
+    let length_classes = match &analysis.length_classes {
+        Some(classes) => Some(classes.clone()),
+        None if extras.ultralong => Some(lengthclass::LengthClasses::parse(
+            ultralong::LENGTH_CLASSES,
+        )?),
+        None if analysis.length_class_plot.is_some() => Some(lengthclass::LengthClasses::parse(
+            lengthclass::DEFAULT_CLASSES,
+        )?),
+        None => None,
+    };
+    if let Some(classes) = &length_classes {
+        lengthclass::print_report(&lengthclass::analyze(data, classes, center), center);
+        if let Some(path) = analysis.length_class_plot.as_deref() {
+            lengthclass::plot(data, classes, path)?;
+            artifacts.push(Artifact::new("length_class_plot", path));
+            println!("Length class plot saved to {}", path);
+        }
+    }
+
+    // Distributions of the selected metrics, where time series hide the tails
+    if let Some(path) = analysis.histogram.as_deref() {
+        histogram::plot(data, &a.fields, analysis.histogram_bins, analysis.kde, path)?;
+        artifacts.push(Artifact::new("histogram", path));
+        println!("Histograms saved to {}", path);
+    }
+    if let Some(path) = analysis.qscore_histogram.as_deref() {
+        histogram::plot_qscores(
+            data,
+            analysis.qscore_bin_width,
+            analysis.kde,
+            analysis.qscore_threshold,
+            path,
+        )?;
+        artifacts.push(Artifact::new("qscore_histogram", path));
+        println!("Q-score histogram saved to {}", path);
+    }
+    Ok(())
+}
+
+/// The run against outside references: MinKNOW's report, process control
+/// limits, the flow cell baseline and pore scans, plus the health verdict
+fn report_quality(
+    a: &Analyzed,
+    extras: &Extras,
+    artifacts: &mut Vec<Artifact>,
+) -> Result<(), Box<dyn Error>> {
+    let (data, analysis) = (&a.data, a.analysis);
+
+    // Headline totals against MinKNOW's own report, for validation records
+    if let Some(report) = analysis.minknow_report.as_deref() {
+        let lines = reconcile::reconcile(report, data, analysis.qscore_threshold)?;
+        let tolerance = analysis.reconcile_tolerance;
+        reconcile::print_report(&lines, tolerance, data.tombstones.as_ref());
+        if let Some(path) = analysis.reconcile_out.as_deref() {
+            reconcile::write_csv(&lines, tolerance, path)?;
+            artifacts.push(Artifact::new("reconciliation", path));
+            println!("Reconciliation saved to {}", path);
+        }
+    }
+
+    // Post-incident timeline from the batches, telemetry and server logs
+    if let Some(path) = analysis.forensic.as_deref() {
+        let incidents = forensic::reconstruct(data);
+        forensic::print_report(&incidents);
+        forensic::plot(data, &incidents, path)?;
+        artifacts.push(Artifact::new("forensic_timeline", path));
+        println!("Forensic timeline saved to {}", path);
+    }
+
+    // Process control view of latency and Q-score for production QA
+    if let Some(path) = analysis.control_chart.as_deref() {
+        let kind = analysis.control_chart_type;
+        control::plot(data, kind, path)?;
+        artifacts.push(Artifact::new("control_chart", path));
+        println!("Control chart saved to {}", path);
+        for (title, count) in control::out_of_control(data, kind) {
+            if count > 0 {
+                println!("  {}: {} out-of-control points", title, count);
+            }
+        }
+    }
+
+    // Actual yield and Q-score in the context of the flow cell's expected performance
+    if let Some(baseline) = load_baseline(a.run, analysis, artifacts)? {
+        baseline::print_report(data, &baseline);
+        if let Some(path) = analysis.baseline_plot.as_deref() {
+            baseline::plot(data, &baseline, path)?;
+            artifacts.push(Artifact::new("baseline_plot", path));
+            println!("Baseline plot saved to {}", path);
+        }
+    }
+
+    if let (Some(path), Some(scores)) = (analysis.verdict_json.as_deref(), &extras.health_scores) {
+        let precision = a
+            .fields
             .iter()
-            .map(|r| accessor(r))
-            .fold(f64::NEG_INFINITY, f64::max);
-
-        // Create a chart for the current subplot
-        // Draw border around the subplot area
-        let mut chart = ChartBuilder::on(area)
-            .caption(*title, ("sans-serif", 20)) // Title
-            .margin(20) // Outer margin
-            .x_label_area_size(50) // Space for x-axis labels
-            .y_label_area_size(100) // Space for y-axis labels
-            .build_cartesian_2d(min_time..max_time, min_val..max_val)?; // Axes ranges
-
-        // Draw chart axes and grid
-        chart
-            .configure_mesh()
-            .x_labels(5)
-            .y_labels(5)
-            // .disable_mesh() // Disable inner grid lines for cleaner look
-            .x_desc("Batch Time")
-            // Adjust label font size
-            .x_label_style(("sans-serif", 20))
-            .y_desc(*title)
-            .draw()?;
-
-        // Plot the data as a line series
-        chart.draw_series(LineSeries::new(
-            data.iter().map(|r| (r.time, accessor(r))),
-            &GREEN, // Line color
-        ))?;
+            .find(|f| f.title == "Run Health Score")
+            .map_or(Precision::Decimals(1), |f| f.precision);
+        health::write_verdict(path, scores, precision)?;
+        artifacts.push(Artifact::new("verdict", path));
+        println!("Health verdict saved to {}", path);
     }
 
+    // Pore scans put the yield in relation to the pores still available
+    if analysis.pore_scan.as_deref().is_some() {
+        porescan::print_report(&extras.scans);
+        if let Some(path) = analysis.pore_scan_plot.as_deref() {
+            porescan::plot(&extras.scans, path)?;
+            artifacts.push(Artifact::new("pore_scan_plot", path));
+            println!("Pore scan plot saved to {}", path);
+        }
+    }
     Ok(())
 }
 
-use std::env;
+/// The flow cell baseline: learned from a directory of runs, read from a
+/// file or built in
+fn load_baseline(
+    run: &RunArgs,
+    analysis: &AnalysisArgs,
+    artifacts: &mut Vec<Artifact>,
+) -> Result<Option<baseline::Baseline>, Box<dyn Error>> {
+    Ok(
+        match (
+            analysis.learn_baseline.as_deref(),
+            analysis.baseline_file.as_deref(),
+            analysis.baseline.as_deref(),
+        ) {
+            (Some(dir), _, _) => {
+                let mut runs = Vec::new();
+                for (name, csv) in daemon::discover_runs(Path::new(dir))? {
+                    let options = ParseOptions {
+                        time_format: run.time_format,
+                        aliases: alias_map(run)?,
+                        ..Default::default()
+                    };
+                    match parse_csv(&csv.to_string_lossy(), &options) {
+                        Ok(run) => runs.push(run),
+                        Err(e) => eprintln!("Skipping run {}: {}", name, e),
+                    }
+                }
+                let learned = baseline::learn(&runs)?;
+                if let Some(path) = analysis.baseline_out.as_deref() {
+                    baseline::write_csv(path, &learned)?;
+                    artifacts.push(Artifact::new("baseline", path));
+                    println!("Learned baseline saved to {}", path);
+                }
+                Some(learned)
+            }
+            (None, Some(path), _) => Some(baseline::Baseline::from_csv(path)?),
+            (None, None, Some(name)) => {
+                Some(baseline::Baseline::built_in(name).ok_or_else(|| {
+                    format!(
+                        "Unknown baseline '{}' (one of {})",
+                        name,
+                        baseline::Baseline::built_in_names().join(", ")
+                    )
+                })?)
+            }
+            (None, None, None) => None,
+        },
+    )
+}
 
-fn main() -> Result<(), Box<dyn Error>> {
-    // Collect command-line arguments
-    let args: Vec<String> = env::args().collect();
+/// Per-read follow-ups: adaptive sampling decisions, read-ID lists, rule
+/// simulations and basecaller tuning
+fn report_reads(a: &Analyzed, artifacts: &mut Vec<Artifact>) -> Result<(), Box<dyn Error>> {
+    let (data, analysis) = (&a.data, a.analysis);
 
-    // Expecting two arguments: the CSV path and output PNG path
-    if args.len() != 3 {
-        eprintln!("Usage: {} <input_csv> <output_png>", args[0]);
-        std::process::exit(1);
+    // What the adaptive sampling client did with the reads it saw
+    if let Some(path) = analysis.decisions.as_deref() {
+        let (decisions, withdrawn) = decisions::parse(path, a.run.time_format, &a.tombstones)?;
+        decisions::print_report(&decisions::analyze(
+            &decisions,
+            withdrawn,
+            analysis.sample_rate,
+        ));
+        if let Some(plot_path) = analysis.decision_plot.as_deref() {
+            decisions::plot(&decisions, analysis.sample_rate, plot_path)?;
+            artifacts.push(Artifact::new("decision_plot", plot_path));
+            println!("Decision plot saved to {}", plot_path);
+        }
     }
 
-    let input_csv = &args[1];
-    let output_png = &args[2];
+    // Reads from poor stretches or channels, for a targeted higher-accuracy pass
+    if let Some(ids_path) = analysis.rebasecall_reads.as_deref() {
+        let min_qscore = analysis.rebasecall_qscore.unwrap_or(QSCORE_THRESHOLDS.warn);
+        let candidates = rebasecall::find_candidates(data, min_qscore);
+        rebasecall::write_read_ids(ids_path, &candidates.read_ids)?;
+        artifacts.push(Artifact::new("read_ids", ids_path));
+        println!(
+            "{} reads from {} windows and {} channels below Q{} saved to {}",
+            candidates.read_ids.len(),
+            candidates.windows.len(),
+            candidates.channels.len(),
+            min_qscore,
+            ids_path
+        );
 
-    // Load and parse CSV data from file
-    let data = parse_csv(input_csv)?;
+        if let Some(script_path) = analysis.rebasecall_commands.as_deref() {
+            rebasecall::write_commands(script_path, ids_path)?;
+            artifacts.push(Artifact::new("commands", script_path));
+            println!("Re-basecalling commands saved to {}", script_path);
+        }
+    }
 
-    // Generate the subplot visualization and save to file
-    plot_multi_series(&data, output_png)?;
+    // Read-ID list for `samtools view -N` / `pod5 filter --ids`, all filters must hold
+    if let Some(ids_path) = analysis.export_reads.as_deref() {
+        let predicates = analysis
+            .filters
+            .iter()
+            .map(|expr| filter::Predicate::parse(expr))
+            .collect::<Result<Vec<_>, _>>()?;
+        let read_ids = filter::select_reads(data, &predicates);
+        rebasecall::write_read_ids(ids_path, &read_ids)?;
+        artifacts.push(Artifact::new("read_ids", ids_path));
+        println!("{} reads saved to {}", read_ids.len(), ids_path);
+    }
+
+    // What-if replay of the reads under alternative adaptive sampling rules
+    let rules = analysis
+        .simulate_rules
+        .iter()
+        .map(|spec| simulate::Rule::parse(spec))
+        .collect::<Result<Vec<_>, _>>()?;
+    if !rules.is_empty() {
+        let outcomes: Vec<_> = rules.iter().map(|r| simulate::simulate(data, r)).collect();
+        simulate::print_report(data, &outcomes);
+    }
 
-    println!("Plot saved to {}", output_png);
+    // Basecaller tuning table from a capacity model fitted to the timings
+    if let Some(batches) = analysis.what_if_batch.as_deref() {
+        let batches: Vec<f64> = capacity::parse_list(batches)?;
+        let gpus: Vec<usize> = capacity::parse_list(&analysis.what_if_gpus)?;
+        let model = capacity::CapacityModel::fit(data)
+            .ok_or("Not enough data to fit the basecaller model")?;
+        let scenarios: Vec<_> = gpus
+            .iter()
+            .flat_map(|&g| batches.iter().map(move |&b| model.scenario(b, g)))
+            .collect();
+        capacity::print_report(&model, &scenarios);
+    }
     Ok(())
 }
@@ -0,0 +1,71 @@
+// Manifest of generated artifacts for downstream workflow steps
+use sha2::{Digest, Sha256};
+use std::error::Error;
+use std::fs;
+
+/// A file written by the tool
+#[derive(Debug, Clone)]
+pub struct Artifact {
+    pub kind: &'static str, // Artifact type, e.g. "plot" or "label_legend"
+    pub path: String,       // Path as written
+}
+
+impl Artifact {
+    pub fn new(kind: &'static str, path: impl Into<String>) -> Self {
+        Artifact {
+            kind,
+            path: path.into(),
+        }
+    }
+}
+
+/// Writes a YAML manifest listing each artifact with its type, path, size and SHA-256.
+///
+/// `statistics` records how the reported numbers were computed, so exact and
+/// approximate runs can be told apart later.
+///
+/// With `redact`, the input is replaced by a pseudonym and artifacts are
+/// listed by file name only.
+pub fn write_manifest(
+    path: &str,
+    input: &str,
+    statistics: &str,
+    artifacts: &[Artifact],
+    redact: bool,
+) -> Result<(), Box<dyn Error>> {
+    let mut out = String::new();
+    let input = if redact {
+        crate::redact::pseudonym(input)
+    } else {
+        input.to_string()
+    };
+    out.push_str(&format!("input: {}\n", quote(&input)));
+    out.push_str(&format!("statistics: {}\n", quote(statistics)));
+    out.push_str("artifacts:\n");
+
+    for artifact in artifacts {
+        let bytes = fs::read(&artifact.path)?;
+        out.push_str(&format!("  - type: {}\n", artifact.kind));
+        let shown = if redact {
+            crate::redact::file_name(&artifact.path)
+        } else {
+            artifact.path.clone()
+        };
+        out.push_str(&format!("    path: {}\n", quote(&shown)));
+        out.push_str(&format!("    bytes: {}\n", bytes.len()));
+        out.push_str(&format!("    sha256: {}\n", hex(&Sha256::digest(&bytes))));
+    }
+
+    fs::write(path, out)?;
+    Ok(())
+}
+
+/// Double-quotes a YAML scalar so paths with special characters stay strings
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Lower-case hex encoding of a digest
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
@@ -0,0 +1,128 @@
+// Embedding of descriptive metadata into rendered figures
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+/// PNG file signature that precedes the first chunk
+const PNG_SIGNATURE: [u8; 8] = [137, 80, 78, 71, 13, 10, 26, 10];
+/// Length of an inch in metres, for the pHYs chunk's pixels per metre
+const INCHES_PER_METRE: f64 = 0.0254;
+
+/// Title and description attached to a saved figure
+#[derive(Debug, Clone)]
+pub struct FigureMetadata {
+    pub title: String,       // Short figure title
+    pub description: String, // Longer alt-text style summary
+    pub dpi: Option<u32>,    // Print resolution, recorded in PNG files only
+}
+
+/// Embeds the metadata into the saved figure, choosing the format by extension
+pub fn embed<P: AsRef<Path>>(path: P, meta: &FigureMetadata) -> Result<(), Box<dyn Error>> {
+    let path = path.as_ref();
+    let is_svg = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("svg"));
+    if is_svg {
+        embed_svg(path, meta)
+    } else {
+        embed_png(path, meta)
+    }
+}
+
+/// Inserts `<title>` and `<desc>` elements right after the opening `<svg>` tag
+fn embed_svg(path: &Path, meta: &FigureMetadata) -> Result<(), Box<dyn Error>> {
+    let svg = fs::read_to_string(path)?;
+    let open = svg.find("<svg").ok_or("Missing <svg> element")?;
+    let end = svg[open..].find('>').ok_or("Unterminated <svg> element")? + open + 1;
+
+    let elements = format!(
+        "\n<title>{}</title>\n<desc>{}</desc>",
+        escape_xml(&meta.title),
+        escape_xml(&meta.description)
+    );
+
+    let mut out = String::with_capacity(svg.len() + elements.len());
+    out.push_str(&svg[..end]);
+    out.push_str(&elements);
+    out.push_str(&svg[end..]);
+    fs::write(path, out)?;
+    Ok(())
+}
+
+/// Inserts `Title` and `Description` tEXt chunks, and a pHYs chunk with the
+/// resolution when there is one, right after the IHDR chunk
+fn embed_png(path: &Path, meta: &FigureMetadata) -> Result<(), Box<dyn Error>> {
+    let png = fs::read(path)?;
+    if png.len() < 8 || png[..8] != PNG_SIGNATURE {
+        return Err(format!("{} is not a PNG file", path.display()).into());
+    }
+
+    // IHDR is always the first chunk: 4 length + 4 type + data + 4 CRC
+    let ihdr_len = u32::from_be_bytes(png[8..12].try_into()?) as usize;
+    let insert_at = 8 + 12 + ihdr_len;
+    if png.len() < insert_at {
+        return Err(format!("{} has a truncated IHDR chunk", path.display()).into());
+    }
+
+    let mut out = Vec::with_capacity(png.len() + meta.description.len() + 64);
+    out.extend_from_slice(&png[..insert_at]);
+    write_text_chunk(&mut out, "Title", &meta.title);
+    write_text_chunk(&mut out, "Description", &meta.description);
+    if let Some(dpi) = meta.dpi {
+        write_resolution_chunk(&mut out, dpi);
+    }
+    out.extend_from_slice(&png[insert_at..]);
+    fs::write(path, out)?;
+    Ok(())
+}
+
+/// Appends a tEXt chunk (keyword, NUL separator, Latin-1 text) to `out`
+fn write_text_chunk(out: &mut Vec<u8>, keyword: &str, text: &str) {
+    let mut body = Vec::with_capacity(4 + keyword.len() + 1 + text.len());
+    body.extend_from_slice(b"tEXt");
+    body.extend_from_slice(keyword.as_bytes());
+    body.push(0);
+    // tEXt is Latin-1; replace anything outside it rather than mis-encode
+    body.extend(
+        text.chars()
+            .map(|c| if (c as u32) < 256 { c as u8 } else { b'?' }),
+    );
+
+    let data_len = (body.len() - 4) as u32;
+    out.extend_from_slice(&data_len.to_be_bytes());
+    out.extend_from_slice(&body);
+    out.extend_from_slice(&crc32(&body).to_be_bytes());
+}
+
+/// Appends a pHYs chunk: the same pixels per metre on both axes
+fn write_resolution_chunk(out: &mut Vec<u8>, dpi: u32) {
+    let per_metre = (dpi as f64 / INCHES_PER_METRE).round() as u32;
+    let mut body = Vec::with_capacity(13);
+    body.extend_from_slice(b"pHYs");
+    body.extend_from_slice(&per_metre.to_be_bytes());
+    body.extend_from_slice(&per_metre.to_be_bytes());
+    body.push(1); // Unit: metre
+    out.extend_from_slice(&9u32.to_be_bytes());
+    out.extend_from_slice(&body);
+    out.extend_from_slice(&crc32(&body).to_be_bytes());
+}
+
+/// CRC-32 (ISO 3309) over chunk type and data, as required by the PNG spec
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+/// Escapes the characters that are significant in XML text content
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
@@ -0,0 +1,67 @@
+// Choice between exact statistics and fast approximate one-pass statistics
+use crate::quantile::Method;
+use crate::sample::{SampleInfo, Sampling};
+
+/// Rows an approximate run keeps when no `--sample` is given
+const APPROXIMATE_ROWS: usize = 100_000;
+
+/// How statistics are computed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mode {
+    Exact,       // Every row, materialised, with exact quantiles
+    Approximate, // One pass over a row sample with streaming (P²) quantiles
+    Streaming,   // Every row, never materialised: running statistics and an envelope
+}
+
+impl Mode {
+    /// Every mode, in help order
+    pub const ALL: [Mode; 3] = [Mode::Exact, Mode::Approximate, Mode::Streaming];
+
+    /// Parses a `--mode` value
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "exact" => Some(Mode::Exact),
+            "approximate" | "approx" | "fast" => Some(Mode::Approximate),
+            "streaming" | "stream" => Some(Mode::Streaming),
+            _ => None,
+        }
+    }
+
+    /// Lower-case name, as recorded in reports
+    pub fn label(self) -> &'static str {
+        match self {
+            Mode::Exact => "exact",
+            Mode::Approximate => "approximate",
+            Mode::Streaming => "streaming",
+        }
+    }
+
+    /// Quantile method used unless `--quantiles` overrides it
+    pub fn quantiles(self) -> Method {
+        match self {
+            Mode::Exact => Method::Exact,
+            Mode::Approximate | Mode::Streaming => Method::P2,
+        }
+    }
+
+    /// Row sampling used unless `--sample` overrides it
+    pub fn sampling(self) -> Option<Sampling> {
+        match self {
+            Mode::Exact | Mode::Streaming => None,
+            Mode::Approximate => Some(Sampling::Rows(APPROXIMATE_ROWS)),
+        }
+    }
+}
+
+/// Provenance line for reports, e.g. `approximate, 100000 of 2500000 rows, P² quantiles`
+pub fn provenance(mode: Mode, method: Method, sample: Option<SampleInfo>, rows: usize) -> String {
+    let rows = match sample {
+        Some(info) => format!("{} of {} rows", info.kept, info.total),
+        None => format!("all {} rows", rows),
+    };
+    let quantiles = match method {
+        Method::Exact => "exact quantiles",
+        Method::P2 => "P² quantiles",
+    };
+    format!("{}, {}, {}", mode.label(), rows, quantiles)
+}
@@ -0,0 +1,787 @@
+// Run data model: columnar rows, metric fields and their health thresholds
+use plotters::style::RGBColor;
+use std::error::Error;
+
+use crate::pauses::Pause;
+use crate::precision::Precision;
+use crate::reduce::{self, ColumnSummary};
+use crate::sample::SampleInfo;
+use crate::schema::Schema;
+use crate::serverlog::LogEvent;
+use crate::stream::{self, RunningStats, StreamStats};
+use crate::tombstone::TombstoneReport;
+use crate::validate::ValidationReport;
+use crate::{anomaly, quantile, robust};
+
+/// Numeric column names understood by `RunData::column`
+pub const COLUMNS: [&str; 25] = [
+    "batch_time",
+    "samples",
+    "bases",
+    "mean_qscore",
+    "time_to_package_and_send",
+    "time_in_basecaller",
+    "batch_interval",
+    "trimmed_bases",
+    "adapter_detected",
+    "alignment_identity",
+    "poly_tail_length",
+    "polya_length",
+    "strand",
+    "strand_balance",
+    "read_n50",
+    "n50_retention",
+    "ultralong_fraction",
+    "asic_temperature",
+    "bias_voltage",
+    "health_score",
+    "server_errors",
+    "cumulative_bases",
+    "cumulative_reads",
+    "bases_per_second",
+    "reads_per_second",
+];
+
+/// One run's parsed rows, stored column by column (structure of arrays).
+///
+/// Every column has the same length and row `i` is the `i`-th element of each,
+/// so per-field scans walk one contiguous slice instead of whole records.
+/// Optional and derived columns stay empty until filled. Columns are public
+/// for the analyses in this crate; embedding tools should prefer `column`,
+/// `columns` and `series`, which hide that distinction.
+#[derive(Debug, Default, Clone)]
+pub struct RunData {
+    pub channel: Vec<u32>,                    // Channel the read came from
+    pub read_id: Vec<String>,                 // Read UUID
+    pub time: Vec<f64>,                       // Batch time (Unix timestamp, float)
+    pub samples: Vec<f64>,                    // Number of samples
+    pub bases: Vec<f64>,                      // Number of basecalls
+    pub mean_qscore: Vec<f64>,                // Average Q-score
+    pub time_to_package_and_send: Vec<f64>,   // Time taken to package and send
+    pub time_in_basecaller: Vec<f64>,         // Time spent in basecalling
+    pub batch_interval: Vec<f64>,             // Seconds since the previous row's batch time
+    pub cost_per_gb: Vec<f64>,                // Cumulative cost per Gb (empty without a cost model)
+    pub barcode: Vec<String>,                 // Barcode arrangement (empty when unclassified)
+    pub unclassified_fraction: Vec<f64>,      // Running share of unclassified reads
+    pub split: Vec<f64>, // 1 for split read products (empty without parent IDs)
+    pub split_rate: Vec<f64>, // Rolling share of split reads
+    pub trimmed_bases: Vec<f64>, // Bases trimmed as adapter/primer (optional)
+    pub adapter_detected: Vec<f64>, // 1 when an adapter was found (optional)
+    pub adapter_rate: Vec<f64>, // Rolling adapter detection rate
+    pub homopolymer_error_rate: Vec<f64>, // Rolling homopolymer error share from an aligned BAM
+    pub alignment_identity: Vec<f64>, // Aligned identity, 0–1 (optional)
+    pub poly_tail_length: Vec<f64>, // Poly(A) estimate, NaN without one (optional)
+    pub polya_length: Vec<f64>, // Rolling mean poly(A) length
+    pub strand: Vec<f64>, // 1 for forward-strand reads (optional)
+    pub strand_balance: Vec<f64>, // Rolling share of forward-strand reads
+    pub kit: Option<String>, // Sequencing kit named in the input
+    pub read_n50: Vec<f64>, // Read N50 of each row's block of reads
+    pub n50_retention: Vec<f64>, // Block N50 relative to the run's start
+    pub ultralong_fraction: Vec<f64>, // Running share of bases from ultra-long reads
+    pub asic_temperature: Vec<f64>, // Device temperature held at each row's time
+    pub bias_voltage: Vec<f64>, // Bias voltage held at each row's time
+    pub health_score: Vec<f64>, // Composite 0–100 run health score
+    pub server_errors: Vec<f64>, // Server log errors in the preceding minute
+    pub cumulative_bases: Vec<f64>, // Bases called up to each row
+    pub cumulative_reads: Vec<f64>, // Reads up to each row
+    pub bases_per_second: Vec<f64>, // Bases of the row's batch over the time since the previous one
+    pub reads_per_second: Vec<f64>, // Reads of the row's batch over the time since the previous one
+    pub events: Vec<LogEvent>, // Server log events (empty without logs)
+    pub pauses: Vec<Pause>, // Stretches without batches, shaded on the panels
+    pub sample: Option<SampleInfo>, // Set when only a random subset was parsed
+    pub validation: Option<ValidationReport>, // Set when rows were validated while parsing
+    pub tombstones: Option<TombstoneReport>, // Set when tombstoned reads were left out
+    pub stream: Option<StreamStats>, // Statistics over all rows when streamed
+    pub schema: Schema,  // Columns of the input
+    pub reference: Option<Box<RunData>>, // Golden run drawn behind the panels, aligned to this run
+}
+
+impl RunData {
+    /// Number of rows
+    pub fn len(&self) -> usize {
+        self.time.len()
+    }
+
+    /// Number of input rows; more than `len` when only an envelope of a
+    /// streamed input is held
+    pub fn rows(&self) -> usize {
+        self.stream.as_ref().map_or(self.len(), |s| s.rows)
+    }
+
+    /// Whether no rows were parsed
+    pub fn is_empty(&self) -> bool {
+        self.time.is_empty()
+    }
+
+    /// Names of the numeric columns holding a value for every row
+    pub fn columns(&self) -> Vec<&'static str> {
+        COLUMNS
+            .into_iter()
+            .filter(|name| self.column(name).is_some_and(|c| c.len() == self.len()))
+            .collect()
+    }
+
+    /// `(batch time, value)` pairs of a complete numeric column
+    pub fn series(&self, name: &str) -> Option<Vec<(f64, f64)>> {
+        let values = self.column(name)?;
+        if values.len() != self.len() {
+            return None;
+        }
+        Some(
+            self.time
+                .iter()
+                .copied()
+                .zip(values.iter().copied())
+                .collect(),
+        )
+    }
+
+    /// Numeric column by its input (CSV) name, or a derived column's name
+    pub fn column(&self, name: &str) -> Option<&[f64]> {
+        let values: &[f64] = match name {
+            "samples" => &self.samples,
+            "bases" => &self.bases,
+            "mean_qscore" => &self.mean_qscore,
+            "time_to_package_and_send" => &self.time_to_package_and_send,
+            "time_in_basecaller" => &self.time_in_basecaller,
+            "batch_interval" => &self.batch_interval,
+            "batch_time" => &self.time,
+            "trimmed_bases" => &self.trimmed_bases,
+            "adapter_detected" => &self.adapter_detected,
+            "alignment_identity" => &self.alignment_identity,
+            "poly_tail_length" => &self.poly_tail_length,
+            "polya_length" => &self.polya_length,
+            "strand" => &self.strand,
+            "strand_balance" => &self.strand_balance,
+            "read_n50" => &self.read_n50,
+            "n50_retention" => &self.n50_retention,
+            "ultralong_fraction" => &self.ultralong_fraction,
+            "asic_temperature" => &self.asic_temperature,
+            "bias_voltage" => &self.bias_voltage,
+            "health_score" => &self.health_score,
+            "server_errors" => &self.server_errors,
+            "cumulative_bases" => &self.cumulative_bases,
+            "cumulative_reads" => &self.cumulative_reads,
+            "bases_per_second" => &self.bases_per_second,
+            "reads_per_second" => &self.reads_per_second,
+            _ => return None,
+        };
+        Some(values)
+    }
+
+    /// Running statistics of the column `field` selects, when the rows were
+    /// streamed; the store then only holds an envelope of the values
+    pub fn streamed(&self, field: &Field) -> Option<&RunningStats> {
+        let stats = self.stream.as_ref()?;
+        let values = (field.column)(self);
+        let name = stream::STREAMED
+            .into_iter()
+            .find(|name| self.column(name).is_some_and(|c| std::ptr::eq(c, values)))?;
+        stats.column(name)
+    }
+
+    /// First and last batch time, assuming the store is sorted
+    pub fn time_range(&self) -> Option<(f64, f64)> {
+        match (self.time.first(), self.time.last()) {
+            (Some(&first), Some(&last)) => Some((first, last)),
+            _ => None,
+        }
+    }
+
+    /// Sorts all columns chronologically and recomputes the batch intervals
+    pub fn sort_by_time(&mut self) {
+        let mut order: Vec<usize> = (0..self.len()).collect();
+        order.sort_by(|&a, &b| self.time[a].total_cmp(&self.time[b]));
+
+        for column in [
+            &mut self.time,
+            &mut self.samples,
+            &mut self.bases,
+            &mut self.mean_qscore,
+            &mut self.time_to_package_and_send,
+            &mut self.time_in_basecaller,
+            &mut self.split,
+            &mut self.trimmed_bases,
+            &mut self.adapter_detected,
+            &mut self.alignment_identity,
+            &mut self.poly_tail_length,
+            &mut self.strand,
+        ] {
+            // Optional columns stay empty when the input lacks them
+            if !column.is_empty() {
+                *column = order.iter().map(|&i| column[i]).collect();
+            }
+        }
+        self.channel = order.iter().map(|&i| self.channel[i]).collect();
+        self.read_id = order.iter().map(|&i| self.read_id[i].clone()).collect();
+        self.barcode = order.iter().map(|&i| self.barcode[i].clone()).collect();
+
+        // The first row has no predecessor and keeps 0
+        self.batch_interval = std::iter::once(0.0)
+            .chain(self.time.windows(2).map(|w| w[1] - w[0]))
+            .take(self.len())
+            .collect();
+    }
+
+    /// Adds the parsed rows of `rows` (rows appended to the same input) and
+    /// restores time order. Derived columns are not carried over; they are
+    /// recomputed over the whole run.
+    pub fn append(&mut self, rows: RunData) {
+        for (column, more) in [
+            (&mut self.time, rows.time),
+            (&mut self.samples, rows.samples),
+            (&mut self.bases, rows.bases),
+            (&mut self.mean_qscore, rows.mean_qscore),
+            (
+                &mut self.time_to_package_and_send,
+                rows.time_to_package_and_send,
+            ),
+            (&mut self.time_in_basecaller, rows.time_in_basecaller),
+            (&mut self.split, rows.split),
+            (&mut self.trimmed_bases, rows.trimmed_bases),
+            (&mut self.adapter_detected, rows.adapter_detected),
+            (&mut self.alignment_identity, rows.alignment_identity),
+            (&mut self.poly_tail_length, rows.poly_tail_length),
+            (&mut self.strand, rows.strand),
+        ] {
+            column.extend(more);
+        }
+        self.channel.extend(rows.channel);
+        self.read_id.extend(rows.read_id);
+        self.barcode.extend(rows.barcode);
+        if self.kit.is_none() {
+            self.kit = rows.kit;
+        }
+        match (&mut self.tombstones, rows.tombstones) {
+            (Some(report), Some(more)) => report.merge(more),
+            (None, more) => self.tombstones = more,
+            _ => {}
+        }
+        self.sort_by_time();
+    }
+}
+
+/// Health state of a value (or a bin of values) relative to its thresholds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Health {
+    Ok,   // Within the expected range
+    Warn, // Past the warning threshold
+    Fail, // Past the failure threshold
+}
+
+impl Health {
+    /// Traffic-light colour used when drawing this state
+    pub fn color(self) -> RGBColor {
+        match self {
+            Health::Ok => RGBColor(46, 160, 67),
+            Health::Warn => RGBColor(255, 176, 0),
+            Health::Fail => RGBColor(220, 50, 47),
+        }
+    }
+
+    /// Upper-case label used in text reports
+    pub fn label(self) -> &'static str {
+        match self {
+            Health::Ok => "OK",
+            Health::Warn => "WARN",
+            Health::Fail => "FAIL",
+        }
+    }
+}
+
+/// Warning/failure limits for a plotted field
+#[derive(Debug, Clone, Copy)]
+pub struct Thresholds {
+    pub warn: f64,             // Value at which the field turns amber
+    pub fail: f64,             // Value at which the field turns red
+    pub higher_is_worse: bool, // Whether exceeding (true) or falling below (false) is bad
+}
+
+impl Thresholds {
+    /// Classifies a single value against the limits
+    pub fn classify(&self, value: f64) -> Health {
+        let (past_fail, past_warn) = if self.higher_is_worse {
+            (value >= self.fail, value >= self.warn)
+        } else {
+            (value <= self.fail, value <= self.warn)
+        };
+        if past_fail {
+            Health::Fail
+        } else if past_warn {
+            Health::Warn
+        } else {
+            Health::Ok
+        }
+    }
+}
+
+/// Q10 is the usual pass threshold, Q7 the old R9 floor
+pub const QSCORE_THRESHOLDS: Thresholds = Thresholds {
+    warn: 10.0,
+    fail: 7.0,
+    higher_is_worse: false,
+};
+
+/// Relative bin-to-bin change limits used when a field has no thresholds
+pub const RATE_OF_CHANGE: Thresholds = Thresholds {
+    warn: 0.10,
+    fail: 0.25,
+    higher_is_worse: true,
+};
+
+/// A plotted field: panel title, column selector and optional health thresholds
+pub struct Field {
+    pub title: &'static str,
+    pub column: fn(&RunData) -> &[f64],
+    pub thresholds: Option<Thresholds>,
+    pub expected_range: Option<(f64, f64)>, // Values outside are marked on the panel
+    pub anomaly: Option<anomaly::Detector>, // Values it flags are marked on the panel
+    pub precision: Precision,               // Rounding of the values in reports and on the y axis
+    pub y_axis: YAxis,                      // Scale and bounds of the panel's y axis
+}
+
+impl Field {
+    /// A panel of a derived column, without thresholds, expected range or detector
+    pub fn derived(
+        title: &'static str,
+        column: fn(&RunData) -> &[f64],
+        precision: Precision,
+    ) -> Field {
+        Field {
+            title,
+            column,
+            thresholds: None,
+            expected_range: None,
+            anomaly: None,
+            precision,
+            y_axis: YAxis::AUTO,
+        }
+    }
+
+    /// The same panel, judged against `thresholds`
+    pub fn with_thresholds(self, thresholds: Thresholds) -> Field {
+        Field {
+            thresholds: Some(thresholds),
+            ..self
+        }
+    }
+}
+
+/// Scaling of a panel's y axis
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct YAxis {
+    pub log: bool,                 // Log10 scale; values at or below zero are not drawn
+    pub range: Option<(f64, f64)>, // Fixed bounds instead of the data's min and max
+}
+
+impl YAxis {
+    /// Linear axis spanning the data
+    pub const AUTO: YAxis = YAxis {
+        log: false,
+        range: None,
+    };
+
+    /// Axis position of a value (its log10 on a log axis, NaN where undefined)
+    pub fn position(self, value: f64) -> f64 {
+        match self.log {
+            true if value > 0.0 => value.log10(),
+            true => f64::NAN,
+            false => value,
+        }
+    }
+
+    /// Value at an axis position, for tick labels
+    pub fn value(self, position: f64) -> f64 {
+        if self.log {
+            10f64.powf(position)
+        } else {
+            position
+        }
+    }
+
+    /// Axis bounds, as positions: the fixed range, or the `clip` percentiles
+    /// of the values that have a position (infinite when none has)
+    pub fn bounds(self, values: &[f64], clip: Clip) -> (f64, f64) {
+        if let Some((min, max)) = self.range {
+            return (self.position(min), self.position(max));
+        }
+        let positions: Vec<f64> = values
+            .iter()
+            .map(|&v| self.position(v))
+            .filter(|p| p.is_finite())
+            .collect();
+        if positions.is_empty() || clip == Clip::NONE {
+            return positions
+                .iter()
+                .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), &p| {
+                    (lo.min(p), hi.max(p))
+                });
+        }
+        let bounds = quantile::quantiles(
+            &positions,
+            &[clip.low / 100.0, clip.high / 100.0],
+            quantile::Method::Exact,
+        );
+        (bounds[0], bounds[1])
+    }
+}
+
+/// Percentiles a panel's automatic y range spans, so a single corrupted
+/// batch does not flatten the rest of the series
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Clip {
+    pub low: f64,  // Lower percentile, 0 to 100
+    pub high: f64, // Upper percentile, above `low`
+}
+
+impl Clip {
+    /// Default range: the middle 99% of the values
+    pub const DEFAULT: Clip = Clip {
+        low: 0.5,
+        high: 99.5,
+    };
+    /// No clipping: the range spans min to max
+    pub const NONE: Clip = Clip {
+        low: 0.0,
+        high: 100.0,
+    };
+
+    /// Parses `LOW:HIGH` percentiles, or `none`
+    pub fn parse(spec: &str) -> Option<Self> {
+        if spec.trim().eq_ignore_ascii_case("none") {
+            return Some(Clip::NONE);
+        }
+        let (low, high) = spec.split_once(':')?;
+        let (low, high): (f64, f64) = (low.trim().parse().ok()?, high.trim().parse().ok()?);
+        (0.0 <= low && low < high && high <= 100.0).then_some(Clip { low, high })
+    }
+}
+
+/// Fixed y bounds for one panel, from `--y-range NAME=MIN:MAX`
+#[derive(Debug, Clone, PartialEq)]
+pub struct YRange {
+    pub panel: String, // Short panel name or title
+    pub min: f64,      // Lower bound
+    pub max: f64,      // Upper bound
+}
+
+impl YRange {
+    /// Parses `NAME=MIN:MAX` with MIN below MAX
+    pub fn parse(spec: &str) -> Option<Self> {
+        let (panel, range) = spec.split_once('=')?;
+        let (min, max) = range.split_once(':')?;
+        let (min, max): (f64, f64) = (min.trim().parse().ok()?, max.trim().parse().ok()?);
+        (!panel.trim().is_empty() && min < max).then(|| YRange {
+            panel: panel.trim().to_string(),
+            min,
+            max,
+        })
+    }
+}
+
+/// Configured samples-per-batch expectation (chunk size) with relative tolerance
+#[derive(Debug, Clone, Copy)]
+pub struct SampleExpectation {
+    pub expected: f64,  // Configured samples per batch
+    pub tolerance: f64, // Allowed relative deviation, e.g. 0.1 for ±10%
+}
+
+impl SampleExpectation {
+    /// Inclusive range of accepted samples-per-batch values
+    pub fn range(&self) -> (f64, f64) {
+        let slack = self.expected * self.tolerance;
+        (self.expected - slack, self.expected + slack)
+    }
+}
+
+/// Audience a report is tailored to, selected with `--profile`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Profile {
+    Operator,  // Run health and alerts
+    Scientist, // Every metric (the default)
+    Manager,   // Yield and cost
+}
+
+impl Profile {
+    /// Parses the `--profile` value
+    pub fn parse(name: &str) -> Result<Self, Box<dyn Error>> {
+        match name {
+            "operator" => Ok(Profile::Operator),
+            "scientist" => Ok(Profile::Scientist),
+            "manager" => Ok(Profile::Manager),
+            _ => Err(format!(
+                "Unknown profile '{}' (expected operator, scientist or manager)",
+                name
+            )
+            .into()),
+        }
+    }
+
+    /// Whether a field is part of this profile's reports
+    pub fn includes(self, field: &Field) -> bool {
+        match self {
+            Profile::Operator => field.thresholds.is_some() || field.title == "Batch Interval",
+            Profile::Scientist => true,
+            Profile::Manager => matches!(
+                field.title,
+                "Samples" | "Bases" | "Cost per Gb" | "Cumulative Bases" | "Cumulative Reads"
+            ),
+        }
+    }
+}
+
+/// Returns the fields plotted by `plot_multi_series`, top to bottom
+pub fn plot_fields(samples: Option<SampleExpectation>) -> Vec<Field> {
+    // Each column selector borrows one column of the `RunData`
+    vec![
+        Field {
+            title: "Samples",
+            column: |d| &d.samples,
+            thresholds: None,
+            expected_range: samples.map(|s| s.range()),
+            anomaly: None,
+            precision: Precision::Decimals(0),
+            y_axis: YAxis::AUTO,
+        },
+        Field {
+            title: "Bases",
+            column: |d| &d.bases,
+            thresholds: None,
+            expected_range: None,
+            anomaly: None,
+            precision: Precision::Decimals(0),
+            y_axis: YAxis::AUTO,
+        },
+        Field {
+            title: "Mean Q-score",
+            column: |d| &d.mean_qscore,
+            thresholds: Some(QSCORE_THRESHOLDS),
+            expected_range: None,
+            anomaly: None,
+            precision: Precision::Decimals(2),
+            y_axis: YAxis::AUTO,
+        },
+        Field {
+            title: "Time to Package",
+            column: |d| &d.time_to_package_and_send,
+            // Nanoseconds: 10 ms / 50 ms
+            thresholds: Some(Thresholds {
+                warn: 1e7,
+                fail: 5e7,
+                higher_is_worse: true,
+            }),
+            expected_range: None,
+            anomaly: None,
+            precision: Precision::DEFAULT,
+            y_axis: YAxis::AUTO,
+        },
+        Field {
+            title: "Time in Basecaller",
+            column: |d| &d.time_in_basecaller,
+            // Nanoseconds: 1 s / 2 s
+            thresholds: Some(Thresholds {
+                warn: 1e9,
+                fail: 2e9,
+                higher_is_worse: true,
+            }),
+            expected_range: None,
+            anomaly: None,
+            precision: Precision::DEFAULT,
+            y_axis: YAxis::AUTO,
+        },
+        Field {
+            title: "Batch Interval",
+            column: |d| &d.batch_interval,
+            // Irregular cadence shows up through the rate-of-change fallback
+            thresholds: None,
+            expected_range: None,
+            anomaly: None,
+            precision: Precision::DEFAULT,
+            y_axis: YAxis::AUTO,
+        },
+    ]
+}
+
+/// Short `--panels` names of every metric a figure can show, with the field
+/// title each selects; derived metrics need the option that adds them
+pub const PANEL_NAMES: [(&str, &str); 25] = [
+    ("samples", "Samples"),
+    ("bases", "Bases"),
+    ("qscore", "Mean Q-score"),
+    ("package", "Time to Package"),
+    ("basecaller", "Time in Basecaller"),
+    ("interval", "Batch Interval"),
+    ("cumulative-bases", "Cumulative Bases"),
+    ("cumulative-reads", "Cumulative Reads"),
+    ("bases-per-sec", "Bases/sec"),
+    ("reads-per-sec", "Reads/sec"),
+    ("cost", "Cost per Gb"),
+    ("unclassified", "Unclassified Fraction"),
+    ("split", "Split Read Rate"),
+    ("trimmed", "Trimmed Bases"),
+    ("adapter", "Adapter Detection Rate"),
+    ("polya", "Poly(A) Length"),
+    ("strand", "Forward Strand Share"),
+    ("n50", "Read N50"),
+    ("n50-retention", "N50 Retention"),
+    ("ultralong", "Ultra-long Yield Fraction"),
+    ("temperature", "ASIC Temperature"),
+    ("bias", "Bias Voltage"),
+    ("server-errors", "Server Errors/min"),
+    ("health", "Run Health Score"),
+    ("homopolymer", "Homopolymer Error Rate"),
+];
+
+/// Index of the field a short panel name or title (in any case) refers to
+fn field_position(fields: &[Field], name: &str) -> Result<usize, Box<dyn Error>> {
+    let title = PANEL_NAMES
+        .iter()
+        .find(|(short, _)| short.eq_ignore_ascii_case(name))
+        .map_or(name, |(_, title)| title);
+    fields
+        .iter()
+        .position(|f| f.title.eq_ignore_ascii_case(title))
+        .ok_or_else(|| {
+            // Every field of the run, by short name where it has one
+            let available: Vec<&str> = fields
+                .iter()
+                .map(|f| {
+                    PANEL_NAMES
+                        .iter()
+                        .find(|(_, title)| *title == f.title)
+                        .map_or(f.title, |(short, _)| short)
+                })
+                .collect();
+            format!(
+                "No panel '{}' for this run (available: {})",
+                name,
+                available.join(", ")
+            )
+            .into()
+        })
+}
+
+/// Picks and orders `fields` by `--panels` names (short names, or titles in
+/// any case); `all` stands for every field not named elsewhere, in the
+/// default order, and no names keep every field
+pub fn select_fields(fields: Vec<Field>, names: &[String]) -> Result<Vec<Field>, Box<dyn Error>> {
+    if names.is_empty() {
+        return Ok(fields);
+    }
+    let mut picked = Vec::new();
+    let mut all_at = None;
+    for name in names.iter().map(|n| n.trim()) {
+        if name.eq_ignore_ascii_case("all") {
+            all_at.get_or_insert(picked.len());
+            continue;
+        }
+        let index = field_position(&fields, name)?;
+        if !picked.contains(&index) {
+            picked.push(index);
+        }
+    }
+    // `all` fills in the rest where it was written
+    if let Some(at) = all_at {
+        let rest: Vec<usize> = (0..fields.len()).filter(|i| !picked.contains(i)).collect();
+        picked.splice(at..at, rest);
+    }
+
+    let mut fields: Vec<Option<Field>> = fields.into_iter().map(Some).collect();
+    Ok(picked
+        .into_iter()
+        .filter_map(|index| fields[index].take())
+        .collect())
+}
+
+/// Sets the y axes of `fields`: a log scale on the panels `log_y` names
+/// (every panel when it names none) and the fixed `ranges`
+pub fn set_y_axes(
+    fields: &mut [Field],
+    log_y: Option<&[String]>,
+    ranges: &[YRange],
+) -> Result<(), Box<dyn Error>> {
+    match log_y {
+        Some([]) => fields.iter_mut().for_each(|f| f.y_axis.log = true),
+        Some(names) => {
+            for name in names {
+                let index = field_position(fields, name.trim())?;
+                fields[index].y_axis.log = true;
+            }
+        }
+        None => {}
+    }
+    for range in ranges {
+        let index = field_position(fields, &range.panel)?;
+        let field = &mut fields[index];
+        if field.y_axis.log && range.min <= 0.0 {
+            return Err(format!(
+                "--y-range for '{}' must be positive on a log axis",
+                range.panel
+            )
+            .into());
+        }
+        field.y_axis.range = Some((range.min, range.max));
+    }
+    Ok(())
+}
+
+/// Returns the indices of records whose samples-per-batch fall outside the expectation
+pub fn check_samples_per_batch(data: &RunData, expectation: SampleExpectation) -> Vec<usize> {
+    let (low, high) = expectation.range();
+    data.samples
+        .iter()
+        .enumerate()
+        .filter(|&(_, &samples)| samples < low || samples > high)
+        .map(|(i, _)| i)
+        .collect()
+}
+
+/// Computes mean/min/max of a field over all records
+pub fn summarize_field(data: &RunData, field: &Field) -> ColumnSummary {
+    match data.streamed(field) {
+        Some(stats) => ColumnSummary {
+            sum: stats.mean() * stats.count() as f64,
+            mean: stats.mean(),
+            min: stats.min,
+            max: stats.max,
+        },
+        None => reduce::summarize((field.column)(data)),
+    }
+}
+
+/// Range of a field plus its typical value and spread under the chosen statistic
+pub struct FieldSummary {
+    pub center: f64, // Mean, median or trimmed mean
+    pub spread: f64, // Standard deviation or scaled MAD
+    pub min: f64,    // Smallest value
+    pub max: f64,    // Largest value
+}
+
+impl FieldSummary {
+    /// Summarizes one field; min/max come from the fused pass
+    pub fn new(data: &RunData, field: &Field, center: robust::Center) -> Self {
+        // Streamed runs only support the mean, their rows are gone
+        if let Some(stats) = data.streamed(field) {
+            return FieldSummary {
+                center: stats.mean(),
+                spread: stats.sd(),
+                min: stats.min,
+                max: stats.max,
+            };
+        }
+        // Rows a column has no value for (NaN) are left out
+        let values = (field.column)(data);
+        let values: std::borrow::Cow<[f64]> = if values.iter().any(|v| v.is_nan()) {
+            values.iter().copied().filter(|v| !v.is_nan()).collect()
+        } else {
+            values.into()
+        };
+        let summary = reduce::summarize(&values);
+        FieldSummary {
+            center: center.of(&values),
+            spread: center.spread(&values),
+            min: summary.min,
+            max: summary.max,
+        }
+    }
+}
@@ -0,0 +1,735 @@
+// CSV parsing of the adaptive sampling batch timings into `RunData`
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+
+use crate::alias::AliasMap;
+use crate::model::RunData;
+use crate::sample::{self, Sampling};
+use crate::schema::Schema;
+use crate::stream::{Envelope, StreamStats};
+use crate::timestamp::{TimeFormat, TimeParser};
+use crate::tombstone::{TombstoneReport, Tombstones};
+use crate::validate::Validator;
+use crate::{compression, remote, rna, seqsummary, splitting, trimming};
+
+/// How an input is read
+#[derive(Debug, Clone, Default)]
+pub struct ParseOptions {
+    pub sampling: Option<Sampling>, // Keep a random subset of the rows (see `sample::select`)
+    pub time_format: TimeFormat,    // Format of the batch times (see `timestamp`)
+    pub validate: bool,             // Drop rows breaking `validate::CONSTRAINTS`
+    pub quarantine: Option<String>, // Also write the dropped rows to this CSV
+    pub aliases: AliasMap,          // Renamed columns to read under their usual names
+    pub tombstones: Tombstones,     // Withdrawn reads, whose rows are left out
+}
+
+impl ParseOptions {
+    /// Validator for an input with these `headers`, `None` without validation
+    fn validator(&self, headers: &csv::StringRecord) -> Result<Option<Validator>, Box<dyn Error>> {
+        if !self.validate && self.quarantine.is_none() {
+            return Ok(None);
+        }
+        Ok(Some(Validator::new(
+            headers,
+            self.time_format,
+            self.quarantine.as_deref(),
+        )?))
+    }
+
+    /// Tally of the tombstoned rows, `None` without a tombstone list
+    fn tombstone_report(&self) -> Option<TombstoneReport> {
+        (!self.tombstones.is_empty()).then(|| TombstoneReport::new(self.tombstones.len()))
+    }
+}
+
+/// Reads the CSV input and parses it into a columnar `RunData`.
+///
+/// HTTP(S) and S3 URLs (with the `remote` feature) and `ssh://` locations
+/// are streamed; anything else is read as a local path. Gzip and zstd
+/// input is decompressed on the fly (see `compression::decompress`).
+/// Tab-separated input is accepted too, including MinKNOW's
+/// `sequencing_summary.txt` (see `seqsummary`). See `ParseOptions` for
+/// sampling, timestamp formats and validation.
+pub fn parse_csv(input: &str, options: &ParseOptions) -> Result<RunData, Box<dyn Error>> {
+    parse_csv_reader(open(input)?, options)
+}
+
+/// Reads the CSV input in one constant-memory pass (see `stream`).
+///
+/// The result holds the min/max envelope rows for plotting and, in
+/// `RunData::stream`, statistics over every row. Only the `stream::STREAMED`
+/// columns are kept, and `options.sampling` does not apply.
+pub fn stream_csv(input: &str, options: &ParseOptions) -> Result<RunData, Box<dyn Error>> {
+    stream_csv_reader(open(input)?, options)
+}
+
+/// Opens a local or remote input, decompressing it when needed
+fn open(input: &str) -> Result<Box<dyn Read + Send>, Box<dyn Error>> {
+    if remote::is_remote(input) {
+        compression::decompress(remote::open(input)?)
+    } else {
+        compression::decompress(File::open(Path::new(input))?)
+    }
+}
+
+/// Columns every input must have, in dorado's usual order
+pub const REQUIRED_COLUMNS: [&str; 9] = [
+    "channel",
+    "read_id",
+    "batch_time",
+    "samples",
+    "bases",
+    "barcode_arrangement",
+    "mean_qscore",
+    "time_to_package_and_send",
+    "time_in_basecaller",
+];
+
+/// CSV reader over `reader`, tab-separated when the header line holds a tab,
+/// with `aliases` already applied to the header
+fn csv_reader<R: Read>(
+    reader: R,
+    aliases: &AliasMap,
+) -> Result<csv::Reader<BufReader<R>>, Box<dyn Error>> {
+    let mut reader = BufReader::new(reader);
+    let buffered = reader.fill_buf()?;
+    let header = buffered.split(|&b| b == b'\n').next().unwrap_or_default();
+    let delimiter = if header.contains(&b'\t') { b'\t' } else { b',' };
+    let mut rdr = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .from_reader(reader);
+    let headers = aliases.apply(rdr.headers()?);
+    rdr.set_headers(headers);
+    Ok(rdr)
+}
+
+/// How the rows of an input map onto `RunData`
+#[derive(Debug, Clone, Copy)]
+enum Layout {
+    Batches(Columns),             // Batch timings CSV, one row per batch
+    Summary(seqsummary::Columns), // Sequencing summary, one row per read
+}
+
+impl Layout {
+    /// Tells the input kind from its header and locates its columns
+    fn locate(headers: &csv::StringRecord) -> Result<Self, Box<dyn Error>> {
+        if seqsummary::is_summary(headers) {
+            Ok(Layout::Summary(seqsummary::Columns::locate(headers)?))
+        } else {
+            Ok(Layout::Batches(Columns::locate(headers)?))
+        }
+    }
+
+    /// Parses one row and appends it to the store's columns
+    fn push(
+        self,
+        data: &mut RunData,
+        record: &csv::StringRecord,
+        times: &mut TimeParser,
+    ) -> Result<(), Box<dyn Error>> {
+        match self {
+            Layout::Batches(columns) => push_record(data, record, columns, times),
+            Layout::Summary(columns) => seqsummary::push_record(data, record, columns),
+        }
+    }
+
+    /// Positions of the read ID and of the read's base count
+    fn read_columns(self) -> (usize, usize) {
+        match self {
+            Layout::Batches(columns) => (columns.read_id, columns.bases),
+            Layout::Summary(columns) => (columns.read_id, columns.length),
+        }
+    }
+}
+
+/// Positions of the known columns in the CSV header
+#[derive(Debug, Clone, Copy)]
+struct Columns {
+    channel: usize,
+    read_id: usize,
+    time: usize, // batch_time
+    samples: usize,
+    bases: usize,
+    barcode: usize,          // barcode_arrangement
+    qscore: usize,           // mean_qscore
+    package: usize,          // time_to_package_and_send
+    basecaller: usize,       // time_in_basecaller
+    parent: Option<usize>,   // parent_read_id
+    trimmed: Option<usize>,  // trimmed_bases
+    adapter: Option<usize>,  // adapter_detected
+    identity: Option<usize>, // alignment_identity
+    tail: Option<usize>,     // poly_tail_length
+    strand: Option<usize>,   // strand
+    kit: Option<usize>,      // kit or sequencing_kit
+}
+
+impl Columns {
+    /// Maps column names to their positions in `headers`, failing with the
+    /// full list of required columns the header lacks
+    fn locate(headers: &csv::StringRecord) -> Result<Self, Box<dyn Error>> {
+        let position = |name: &str| headers.iter().position(|h| h.trim() == name);
+        let missing: Vec<&str> = REQUIRED_COLUMNS
+            .into_iter()
+            .filter(|name| position(name).is_none())
+            .collect();
+        if !missing.is_empty() {
+            return Err(format!(
+                "CSV header is missing required column(s): {}",
+                missing.join(", ")
+            )
+            .into());
+        }
+        let required = |name: &str| position(name).unwrap_or_default();
+        Ok(Columns {
+            channel: required("channel"),
+            read_id: required("read_id"),
+            time: required("batch_time"),
+            samples: required("samples"),
+            bases: required("bases"),
+            barcode: required("barcode_arrangement"),
+            qscore: required("mean_qscore"),
+            package: required("time_to_package_and_send"),
+            basecaller: required("time_in_basecaller"),
+            parent: position("parent_read_id"),
+            trimmed: position("trimmed_bases"),
+            adapter: position("adapter_detected"),
+            identity: position("alignment_identity"),
+            tail: position("poly_tail_length").or_else(|| position("polya_length")),
+            strand: position("strand"),
+            kit: position("kit").or_else(|| position("sequencing_kit")),
+        })
+    }
+}
+
+/// Text of the field at `index`, named `name` in errors
+pub(crate) fn text<'r>(
+    record: &'r csv::StringRecord,
+    index: usize,
+    name: &str,
+) -> Result<&'r str, Box<dyn Error>> {
+    record.get(index).ok_or_else(|| {
+        let line = record.position().map_or(0, |p| p.line());
+        format!("Missing {} on line {}", name, line).into()
+    })
+}
+
+/// The field at `index` parsed as a number, named `name` in errors
+pub(crate) fn number<T: std::str::FromStr>(
+    record: &csv::StringRecord,
+    index: usize,
+    name: &str,
+) -> Result<T, Box<dyn Error>> {
+    let value = text(record, index, name)?;
+    value.trim().parse().map_err(|_| {
+        let line = record.position().map_or(0, |p| p.line());
+        format!("Invalid {} '{}' on line {}", name, value, line).into()
+    })
+}
+
+/// The batch time at `index`, in the format `times` resolved for the column
+fn batch_time(
+    record: &csv::StringRecord,
+    index: usize,
+    times: &mut TimeParser,
+) -> Result<f64, Box<dyn Error>> {
+    let value = text(record, index, "batch_time")?;
+    times.parse(value).ok_or_else(|| {
+        let line = record.position().map_or(0, |p| p.line());
+        format!(
+            "Invalid batch_time '{}' on line {} (expected {} timestamps)",
+            value,
+            line,
+            times.format().label()
+        )
+        .into()
+    })
+}
+
+/// Parses CSV rows from any reader into a columnar `RunData`
+pub fn parse_csv_reader<R: Read>(
+    reader: R,
+    options: &ParseOptions,
+) -> Result<RunData, Box<dyn Error>> {
+    // Open the CSV reader over the input
+    let mut rdr = csv_reader(reader, &options.aliases)?;
+    let mut data = RunData::default();
+
+    // Columns are located by name, so inputs may order them freely and
+    // optional ones may be absent (not every dorado version writes them)
+    let layout = Layout::locate(rdr.headers()?)?;
+    data.schema = Schema::from_headers(rdr.headers()?);
+    let mut times = TimeParser::new(options.time_format);
+    let mut validator = options.validator(rdr.headers()?)?;
+    let mut tombstoned = options.tombstone_report();
+    let read_columns = layout.read_columns();
+
+    match options.sampling {
+        // Iterate through each record (row) in the CSV
+        None => {
+            for result in rdr.records() {
+                let record = result?; // Handle CSV parsing errors
+                if withdrawn(options, &mut tombstoned, &record, read_columns)? {
+                    continue;
+                }
+                if accept(&mut validator, &record)? {
+                    layout.push(&mut data, &record, &mut times)?;
+                }
+            }
+        }
+        // Previews draw their rows first and parse only those
+        Some(sampling) => {
+            let (records, total) = sample::select(rdr.records(), sampling)?;
+            for record in &records {
+                if withdrawn(options, &mut tombstoned, record, read_columns)? {
+                    continue;
+                }
+                if accept(&mut validator, record)? {
+                    layout.push(&mut data, record, &mut times)?;
+                }
+            }
+            data.sample = Some(sample::SampleInfo {
+                kept: records.len(),
+                total,
+            });
+        }
+    }
+
+    data.validation = validator.map(Validator::finish).transpose()?;
+    data.tombstones = tombstoned;
+
+    // Sort records chronologically by time and derive the cadence
+    data.sort_by_time();
+
+    Ok(data) // Return the parsed and sorted data
+}
+
+/// Whether a row passes validation (always, without a validator)
+fn accept(
+    validator: &mut Option<Validator>,
+    record: &csv::StringRecord,
+) -> Result<bool, Box<dyn Error>> {
+    match validator {
+        Some(validator) => validator.check(record),
+        None => Ok(true),
+    }
+}
+
+/// Whether a row's read was tombstoned, counting it in `report` if so
+fn withdrawn(
+    options: &ParseOptions,
+    report: &mut Option<TombstoneReport>,
+    record: &csv::StringRecord,
+    (read_id, bases): (usize, usize),
+) -> Result<bool, Box<dyn Error>> {
+    let Some(report) = report else {
+        return Ok(false);
+    };
+    let Some(reason) = options.tombstones.reason(text(record, read_id, "read_id")?) else {
+        return Ok(false);
+    };
+    report.record(reason, number(record, bases, "bases")?);
+    Ok(true)
+}
+
+/// Streams CSV rows from any reader into running statistics and an envelope
+pub fn stream_csv_reader<R: Read>(
+    reader: R,
+    options: &ParseOptions,
+) -> Result<RunData, Box<dyn Error>> {
+    let mut rdr = csv_reader(reader, &options.aliases)?;
+    if seqsummary::is_summary(rdr.headers()?) {
+        return Err("Sequencing summaries have no batch timings to stream; \
+                    use --mode exact or approximate"
+            .into());
+    }
+    let columns = Columns::locate(rdr.headers()?)?;
+    let mut times = TimeParser::new(options.time_format);
+    let mut validator = options.validator(rdr.headers()?)?;
+    let mut tombstoned = options.tombstone_report();
+    let mut stats = StreamStats::default();
+    let mut envelope = Envelope::default();
+
+    // One record buffer is reused, so memory does not grow with the input
+    let mut record = csv::StringRecord::new();
+    while rdr.read_record(&mut record)? {
+        if withdrawn(
+            options,
+            &mut tombstoned,
+            &record,
+            (columns.read_id, columns.bases),
+        )? {
+            continue;
+        }
+        if !accept(&mut validator, &record)? {
+            continue;
+        }
+        let time = batch_time(&record, columns.time, &mut times)?;
+        let values = [
+            number(&record, columns.samples, "samples")?,
+            number(&record, columns.bases, "bases")?,
+            number(&record, columns.qscore, "mean_qscore")?,
+            number(&record, columns.package, "time_to_package_and_send")?,
+            number(&record, columns.basecaller, "time_in_basecaller")?,
+        ];
+        stats.rows += 1;
+        for (column, &value) in stats.columns.iter_mut().zip(&values) {
+            column.push(value);
+        }
+        envelope.push(time, values);
+    }
+    let mut data = envelope.into_run_data(stats);
+    data.schema = Schema::from_headers(rdr.headers()?);
+    data.validation = validator.map(Validator::finish).transpose()?;
+    data.tombstones = tombstoned;
+    Ok(data)
+}
+
+/// Parses one CSV row and appends its values to the store's columns
+fn push_record(
+    data: &mut RunData,
+    record: &csv::StringRecord,
+    columns: Columns,
+    times: &mut TimeParser,
+) -> Result<(), Box<dyn Error>> {
+    let read_id = text(record, columns.read_id, "read_id")?;
+    data.channel
+        .push(number(record, columns.channel, "channel")?);
+    data.read_id.push(read_id.to_string());
+    data.time.push(batch_time(record, columns.time, times)?);
+    data.samples
+        .push(number(record, columns.samples, "samples")?);
+    data.bases.push(number(record, columns.bases, "bases")?);
+    data.barcode
+        .push(text(record, columns.barcode, "barcode_arrangement")?.to_string());
+    data.mean_qscore
+        .push(number(record, columns.qscore, "mean_qscore")?);
+    data.time_to_package_and_send.push(number(
+        record,
+        columns.package,
+        "time_to_package_and_send",
+    )?);
+    data.time_in_basecaller
+        .push(number(record, columns.basecaller, "time_in_basecaller")?);
+    if let Some(column) = columns.parent {
+        let parent = record.get(column).unwrap_or("");
+        data.split
+            .push(f64::from(u8::from(splitting::is_split(read_id, parent))));
+    }
+    if let Some(column) = columns.trimmed {
+        data.trimmed_bases
+            .push(number(record, column, "trimmed_bases")?);
+    }
+    if let Some(column) = columns.adapter {
+        data.adapter_detected.push(trimming::parse_flag(text(
+            record,
+            column,
+            "adapter_detected",
+        )?)?);
+    }
+    if let Some(column) = columns.identity {
+        let identity: f64 = number(record, column, "alignment_identity")?;
+        if !(0.0..=1.0).contains(&identity) {
+            let line = record.position().map_or(0, |p| p.line());
+            return Err(format!(
+                "alignment_identity {} on line {} is outside 0–1",
+                identity, line
+            )
+            .into());
+        }
+        data.alignment_identity.push(identity);
+    }
+    if let Some(column) = columns.tail {
+        data.poly_tail_length.push(rna::parse_tail_length(text(
+            record,
+            column,
+            "poly_tail_length",
+        )?)?);
+    }
+    if let Some(column) = columns.strand {
+        data.strand
+            .push(rna::parse_strand(text(record, column, "strand")?)?);
+    }
+    // The kit is run metadata, so the first row naming one stands for the run
+    if let Some(column) = columns.kit
+        && data.kit.is_none()
+        && let Some(kit) = record.get(column).map(str::trim).filter(|k| !k.is_empty())
+    {
+        data.kit = Some(kit.to_string());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    const HEADER: &str = "channel,read_id,batch_time,samples,bases,barcode_arrangement,mean_qscore,time_to_package_and_send,time_in_basecaller";
+
+    fn parse(body: &str) -> Result<RunData, Box<dyn Error>> {
+        parse_csv_reader(Cursor::new(body.to_string()), &ParseOptions::default())
+    }
+
+    #[test]
+    fn parses_rows_sorted_by_time() {
+        let data = parse(&format!(
+            "{}\n2,r2,20.0,4000,400,barcode02,12.5,0.2,1.5\n1,r1,10.0,2000,200,barcode01,10.0,0.1,1.0\n3,r3,25.0,3000,300,unclassified,11.0,0.3,2.0\n",
+            HEADER
+        ))
+        .unwrap();
+        assert_eq!(data.len(), 3);
+        assert_eq!(data.time, [10.0, 20.0, 25.0]);
+        assert_eq!(data.read_id, ["r1", "r2", "r3"]);
+        assert_eq!(data.channel, [1, 2, 3]);
+        assert_eq!(data.barcode, ["barcode01", "barcode02", "unclassified"]);
+        assert_eq!(data.samples, [2000.0, 4000.0, 3000.0]);
+        assert_eq!(data.mean_qscore, [10.0, 12.5, 11.0]);
+        assert_eq!(data.batch_interval, [0.0, 10.0, 5.0]);
+        assert_eq!(data.time_range(), Some((10.0, 25.0)));
+        assert!(data.sample.is_none());
+    }
+
+    #[test]
+    fn aliases_read_renamed_columns() {
+        let aliases = AliasMap::parse("# dorado-server 8\nmean_qscore = \"avg_qscore\"\n").unwrap();
+        let options = ParseOptions {
+            aliases,
+            ..Default::default()
+        };
+        let body = format!(
+            "{}\n1,r1,10.0,2000,200,none,10.5,0.1,1.0\n",
+            HEADER.replace("mean_qscore", "avg_qscore")
+        );
+        let data = parse_csv_reader(Cursor::new(body.clone()), &options).unwrap();
+        assert_eq!(data.mean_qscore, [10.5]);
+        assert!(parse(&body).is_err());
+    }
+
+    #[test]
+    fn schema_drift_lists_added_and_removed_columns() {
+        let before = parse(&format!("{},strand\n", HEADER)).unwrap().schema;
+        let after = parse(&format!("{},kit\n", HEADER)).unwrap().schema;
+        assert_eq!(before.columns.len(), 10);
+        assert_ne!(before.fingerprint(), after.fingerprint());
+        let drift = before.drift(&after).unwrap();
+        assert_eq!(drift.describe(), "added: kit; removed: strand");
+        assert!(before.drift(&before.clone()).is_none());
+    }
+
+    #[test]
+    fn optional_columns_stay_empty_when_absent() {
+        let data = parse(&format!("{}\n1,r1,1.0,100,10,none,9.0,0.1,0.5\n", HEADER)).unwrap();
+        assert!(data.split.is_empty());
+        assert!(data.trimmed_bases.is_empty());
+        assert!(data.adapter_detected.is_empty());
+        assert!(!data.columns().contains(&"trimmed_bases"));
+        assert_eq!(data.series("bases"), Some(vec![(1.0, 10.0)]));
+        assert_eq!(data.series("trimmed_bases"), None);
+    }
+
+    #[test]
+    fn reads_optional_columns_by_name() {
+        let data = parse(&format!(
+            "{},adapter_detected,parent_read_id,trimmed_bases\n1,r1,1.0,100,10,none,9.0,0.1,0.5,yes,p1,12\n1,r2,2.0,100,10,none,9.0,0.1,0.5,0,,0\n",
+            HEADER
+        ))
+        .unwrap();
+        assert_eq!(data.adapter_detected, [1.0, 0.0]);
+        assert_eq!(data.split, [1.0, 0.0]);
+        assert_eq!(data.trimmed_bases, [12.0, 0.0]);
+        assert!(data.columns().contains(&"trimmed_bases"));
+    }
+
+    #[test]
+    fn detects_the_batch_time_format_from_the_first_row() {
+        let iso = parse(&format!(
+            "{}\n1,r1,2024-05-01T00:00:10Z,100,10,none,9.0,0.1,0.5\n1,r2,2024-05-01 02:00:00+02:00,100,10,none,9.0,0.1,0.5\n",
+            HEADER
+        ))
+        .unwrap();
+        assert_eq!(iso.time, [1714521600.0, 1714521610.0]);
+
+        let millis = parse(&format!(
+            "{}\n1,r1,1714521600000,100,10,none,9.0,0.1,0.5\n1,r2,1714521600500,100,10,none,9.0,0.1,0.5\n",
+            HEADER
+        ))
+        .unwrap();
+        assert_eq!(millis.time, [1714521600.0, 1714521600.5]);
+
+        // Once a column is read as seconds, a value in another format is an error
+        let mixed = parse(&format!(
+            "{}\n1,r1,10.0,100,10,none,9.0,0.1,0.5\n1,r2,2024-05-01T00:00:10Z,100,10,none,9.0,0.1,0.5\n",
+            HEADER
+        ));
+        assert!(
+            mixed
+                .unwrap_err()
+                .to_string()
+                .contains("expected unix timestamps")
+        );
+    }
+
+    #[test]
+    fn validation_drops_impossible_rows_and_keeps_late_ones() {
+        let body = format!(
+            "{}\n1,r1,2.0,100,10,none,9.0,0.1,0.5\n1,r2,3.0,-100,10,none,9.0,0.1,0.5\n1,r3,1.0,100,10,none,75,0.1,0.5\n1,r4,1.5,100,10,none,9.0,0.1,0.5\n",
+            HEADER
+        );
+        let options = ParseOptions {
+            validate: true,
+            ..Default::default()
+        };
+        let data = parse_csv_reader(Cursor::new(body), &options).unwrap();
+        let report = data.validation.as_ref().unwrap();
+        assert_eq!(data.read_id, ["r4", "r1"]);
+        assert_eq!((report.checked, report.invalid, report.suspect), (4, 2, 1));
+    }
+
+    #[test]
+    fn reads_transcript_columns_and_kit() {
+        let data = parse(&format!(
+            "{},poly_tail_length,strand,kit\n1,r1,1.0,100,10,none,9.0,0.1,0.5,85,+,SQK-RNA004\n1,r2,2.0,100,10,none,9.0,0.1,0.5,-1,-,\n",
+            HEADER
+        ))
+        .unwrap();
+        assert_eq!(data.poly_tail_length[0], 85.0);
+        assert!(data.poly_tail_length[1].is_nan());
+        assert_eq!(data.strand, [1.0, 0.0]);
+        assert_eq!(data.kit.as_deref(), Some("SQK-RNA004"));
+    }
+
+    #[test]
+    fn reads_sequencing_summary_reads_as_batches() {
+        let data = parse(
+            "read_id\tchannel\tstart_time\tduration\tsequence_length_template\tmean_qscore_template\n\
+             r1\t7\t10.0\t2.5\t1200\t14.5\n\
+             r2\t3\t4.0\t1.0\t800\t11.0\n",
+        )
+        .unwrap();
+        assert_eq!(data.read_id, ["r2", "r1"]);
+        assert_eq!(data.time, [5.0, 12.5]);
+        assert_eq!(data.bases, [800.0, 1200.0]);
+        assert_eq!(data.mean_qscore, [11.0, 14.5]);
+        assert!(data.time_in_basecaller.iter().all(|v| v.is_nan()));
+        assert!(
+            stream_csv_reader(
+                Cursor::new("read_id\tsequence_length_template\n"),
+                &ParseOptions::default()
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn empty_input_has_no_rows() {
+        let data = parse(&format!("{}\n", HEADER)).unwrap();
+        assert!(data.is_empty());
+        assert_eq!(data.time_range(), None);
+    }
+
+    #[test]
+    fn rejects_non_numeric_values() {
+        assert!(parse(&format!("{}\n1,r1,soon,100,10,none,9.0,0.1,0.5\n", HEADER)).is_err());
+    }
+
+    #[test]
+    fn lists_missing_columns() {
+        let error = parse("channel,read_id,batch_time\n1,r1,1.0\n").unwrap_err();
+        let message = error.to_string();
+        for name in ["samples", "bases", "mean_qscore", "time_in_basecaller"] {
+            assert!(message.contains(name), "{}", message);
+        }
+        assert!(!message.contains("batch_time"), "{}", message);
+    }
+
+    #[test]
+    fn maps_columns_by_header_name() {
+        let data = parse(
+            "time_in_basecaller,mean_qscore,bases,samples,barcode_arrangement,batch_time,read_id,channel,time_to_package_and_send\n\
+             1.5,12.0,300,3000,barcode05,42.0,r1,7,0.25\n",
+        )
+        .unwrap();
+        assert_eq!(data.channel, [7]);
+        assert_eq!(data.read_id, ["r1"]);
+        assert_eq!(data.time, [42.0]);
+        assert_eq!(data.samples, [3000.0]);
+        assert_eq!(data.bases, [300.0]);
+        assert_eq!(data.barcode, ["barcode05"]);
+        assert_eq!(data.mean_qscore, [12.0]);
+        assert_eq!(data.time_to_package_and_send, [0.25]);
+        assert_eq!(data.time_in_basecaller, [1.5]);
+    }
+
+    #[test]
+    fn decompresses_gzip_and_zstd() {
+        use std::io::Write;
+
+        let csv = format!("{}\n1,r1,1.0,100,10,none,9.0,0.1,0.5\n", HEADER);
+        let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gzip.write_all(csv.as_bytes()).unwrap();
+        let gzip = gzip.finish().unwrap();
+        let zstd = zstd::encode_all(csv.as_bytes(), 0).unwrap();
+
+        for (name, bytes) in [("gzip", gzip), ("zstd", zstd), ("plain", csv.into_bytes())] {
+            let reader = compression::decompress(Cursor::new(bytes)).unwrap();
+            let data = parse_csv_reader(reader, &ParseOptions::default()).unwrap();
+            assert_eq!(data.read_id, ["r1"], "{}", name);
+        }
+    }
+
+    #[test]
+    fn streaming_keeps_statistics_and_a_bounded_envelope() {
+        let rows: String = (0..10_000)
+            .map(|i| format!("1,r{},{}.0,{},10,none,9.0,0.1,0.5\n", i, i, i % 7))
+            .collect();
+        let body = format!("{}\n{}", HEADER, rows);
+        let data = stream_csv_reader(Cursor::new(body.clone()), &ParseOptions::default()).unwrap();
+        let exact = parse(&body).unwrap();
+
+        assert_eq!(data.rows(), 10_000);
+        assert!(data.len() <= 2 * crate::stream::BUCKETS);
+        let stats = data.stream.as_ref().unwrap().column("samples").unwrap();
+        let mean = exact.samples.iter().sum::<f64>() / exact.len() as f64;
+        assert!((stats.mean() - mean).abs() < 1e-9);
+        assert_eq!((stats.min, stats.max), (0.0, 6.0));
+        assert_eq!(data.time_range(), exact.time_range());
+    }
+
+    #[test]
+    fn streaming_unsorted_rows_matches_exact_statistics() {
+        let body = format!(
+            "{}\n2,r2,20.0,4000,400,none,12.5,0.2,1.5\n1,r1,10.0,2000,200,none,10.0,0.1,1.0\n3,r3,25.0,3000,300,none,11.0,0.3,2.0\n",
+            HEADER
+        );
+        let data = stream_csv_reader(Cursor::new(body.clone()), &ParseOptions::default()).unwrap();
+        let exact = parse(&body).unwrap();
+        let stream = data.stream.as_ref().unwrap();
+        let bases = stream.column("bases").unwrap();
+        assert_eq!((bases.min, bases.max, bases.mean()), (200.0, 400.0, 300.0));
+        // Intervals need rows in time order, which a stream cannot ensure
+        assert!(stream.column("batch_interval").is_none());
+        assert!(data.batch_interval.is_empty());
+        assert_eq!(data.time_range(), exact.time_range());
+    }
+
+    #[test]
+    fn sampling_keeps_requested_rows() {
+        let rows: String = (0..50)
+            .map(|i| format!("1,r{},{}.0,100,10,none,9.0,0.1,0.5\n", i, i))
+            .collect();
+        let data = parse_csv_reader(
+            Cursor::new(format!("{}\n{}", HEADER, rows)),
+            &ParseOptions {
+                sampling: Some(Sampling::Rows(10)),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(data.len(), 10);
+        let info = data.sample.unwrap();
+        assert_eq!((info.kept, info.total), (10, 50));
+        assert!(data.time.windows(2).all(|w| w[0] <= w[1]));
+    }
+}
@@ -0,0 +1,103 @@
+// Run pauses: stretches without batches, and the mux scans among them
+//
+// MinKNOW stops sequencing for its periodic mux scans, so pauses that recur
+// at a steady period are labelled as mux scans; any other pause is a gap.
+use crate::forensic;
+use crate::model::RunData;
+use crate::robust::Center;
+
+/// Mux scans found in a row before pauses count as periodic
+const MIN_SCANS: usize = 3;
+/// Relative deviation from the typical spacing a mux scan may show
+const PERIOD_TOLERANCE: f64 = 0.1;
+
+/// Why no batches were written
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PauseKind {
+    Gap,     // Any stop, e.g. a paused or stalled run
+    MuxScan, // One of a periodic series of stops
+}
+
+/// An interval without batches, between the batch before and the batch after
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Pause {
+    pub start: f64, // Last batch time before the pause
+    pub end: f64,   // First batch time after it
+    pub kind: PauseKind,
+}
+
+impl PauseKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            PauseKind::Gap => "gap",
+            PauseKind::MuxScan => "mux scan",
+        }
+    }
+}
+
+impl Pause {
+    pub fn seconds(&self) -> f64 {
+        self.end - self.start
+    }
+
+    /// Whether the pause lies between the batch times `a` and `b`
+    pub fn splits(&self, a: f64, b: f64) -> bool {
+        a <= self.start && self.end <= b
+    }
+}
+
+/// Pauses longer than `min_gap` seconds (by default `forensic::gap_limit`)
+/// between consecutive batches, with periodic ones labelled as mux scans
+pub fn detect(data: &RunData, min_gap: Option<f64>) -> Vec<Pause> {
+    let limit = min_gap.unwrap_or_else(|| forensic::gap_limit(data));
+    let mut pauses: Vec<Pause> = data
+        .time
+        .windows(2)
+        .filter(|w| w[1] - w[0] > limit)
+        .map(|w| Pause {
+            start: w[0],
+            end: w[1],
+            kind: PauseKind::Gap,
+        })
+        .collect();
+    label_mux_scans(&mut pauses);
+    pauses
+}
+
+/// Labels the pauses whose spacing to a neighbour matches the typical
+/// spacing, when there are at least `MIN_SCANS` of them
+fn label_mux_scans(pauses: &mut [Pause]) {
+    if pauses.len() < MIN_SCANS {
+        return;
+    }
+    let spacing: Vec<f64> = pauses.windows(2).map(|w| w[1].start - w[0].start).collect();
+    let period = Center::Median.of(&spacing);
+    let regular = |s: f64| (s - period).abs() <= period * PERIOD_TOLERANCE;
+    let periodic: Vec<bool> = (0..pauses.len())
+        .map(|i| (i > 0 && regular(spacing[i - 1])) || spacing.get(i).is_some_and(|&s| regular(s)))
+        .collect();
+    if periodic.iter().filter(|&&p| p).count() >= MIN_SCANS {
+        for (pause, periodic) in pauses.iter_mut().zip(periodic) {
+            if periodic {
+                pause.kind = PauseKind::MuxScan;
+            }
+        }
+    }
+}
+
+/// Prints each pause with its kind and length
+pub fn print_report(pauses: &[Pause]) {
+    if pauses.is_empty() {
+        return;
+    }
+    println!("Pauses: {}", pauses.len());
+    for pause in pauses {
+        println!(
+            "  {:.0} to {:.0}: {} of {:.0} s",
+            pause.start,
+            pause.end,
+            pause.kind.label(),
+            pause.seconds()
+        );
+    }
+}
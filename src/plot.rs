@@ -0,0 +1,1002 @@
+// The standard multi-panel figure: panels, summary strips, thumbnails and legends
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use std::error::Error;
+use std::path::Path;
+
+use crate::manifest::Artifact;
+use crate::metadata::{self, FigureMetadata};
+use crate::model::{
+    Clip, Field, Health, RATE_OF_CHANGE, RunData, Thresholds, YAxis, summarize_field,
+};
+use crate::pauses::{Pause, PauseKind};
+use crate::render::{self, Figure};
+use crate::smooth::{self, Smoothing};
+use crate::theme::{self, Theme};
+use crate::{axisbreak, decimate, layout, locale, robust, threads};
+
+/// Default canvas size of the standard figure
+pub const FIGURE_SIZE: (u32, u32) = (2200, 1800);
+
+/// Title embedded into every saved figure
+pub const FIGURE_TITLE: &str = "Adaptive sampling batch metrics";
+
+/// Number of time bins shown in each summary strip
+const STRIP_BINS: usize = 50;
+/// Width in pixels of the figure thumbnail embedded into the digest
+const THUMBNAIL_WIDTH: u32 = 660;
+/// Size in pixels of a panel written on its own by `--split-panels`
+const SPLIT_PANEL_SIZE: (u32, u32) = (1200, 500);
+/// Height in pixels of the summary strip drawn beneath a panel
+const STRIP_HEIGHT: u32 = 40;
+/// Colour of the markers for values beyond the y range
+const OUTLIER_COLOR: RGBColor = RGBColor(200, 0, 120);
+/// Colour of the reference run's series, drawn faded behind the run's own
+const REFERENCE_COLOR: RGBColor = RGBColor(120, 120, 120);
+/// Opacity of the reference run's series
+const REFERENCE_OPACITY: f64 = 0.35;
+/// Shading of a pause in the batches
+const GAP_COLOR: RGBColor = RGBColor(255, 255, 255);
+/// Shading of a mux scan
+const MUX_SCAN_COLOR: RGBColor = RGBColor(90, 140, 220);
+/// Opacity of the pause shading
+const PAUSE_OPACITY: f64 = 0.35;
+/// Opacity of the raw series beneath a smoothed overlay with `dim_raw`
+const DIMMED_RAW: f64 = 0.3;
+
+/// Rendering switches shared by all panels
+#[derive(Debug, Clone, Copy)]
+pub struct PlotOptions {
+    pub summary_strip: bool,          // Draw the health strip beneath each panel
+    pub text: bool, // Draw captions and axis labels (false when no font is usable)
+    pub center: robust::Center, // Statistic the health checks classify
+    pub language: locale::Language, // Language of titles and axis labels
+    pub axis_break: bool, // Break the y axis of panels with rare extreme spikes
+    pub size: (u32, u32), // Canvas size of the standard figure
+    pub max_points: Option<usize>, // Points each line is decimated to (None draws every point)
+    pub smoothing: Option<Smoothing>, // Rolling overlay drawn over the raw series
+    pub clip: Clip, // Percentiles the automatic y range spans
+    pub outlier_markers: bool, // Mark values beyond the y range at the panel edge
+    pub keep_extremes: bool, // Decimation keeps each bucket's extremes and the flagged values
+    pub theme: Theme, // Background, text and axis colours
+    pub palette: theme::Palette, // Colours of the raw series and its overlay
+    pub dpi: Option<u32>, // Resolution recorded in PNG output
+}
+
+/// Plots a set of subplots showing different variables over time.
+///
+/// The backend is picked from the output extension (see `render::to_file`),
+/// and a title plus summary description is embedded into the saved file. Without text, a label legend listing the
+/// panels is written next to the figure instead. Returns the files written.
+pub fn plot_multi_series(
+    data: &RunData,
+    fields: &[Field],
+    output_path: &str,
+    options: PlotOptions,
+) -> Result<Vec<Artifact>, Box<dyn Error>> {
+    if data.is_empty() {
+        return Err("No batches to plot".into());
+    }
+    let figure = MultiSeries {
+        data,
+        fields,
+        options,
+    };
+    render::to_file(&figure, output_path)?;
+
+    // The backend has flushed the file, so the metadata can be added in place
+    let meta = FigureMetadata {
+        title: options.language.tr(FIGURE_TITLE).to_string(),
+        description: describe_figure(data, fields),
+        dpi: options.dpi,
+    };
+    metadata::embed(output_path, &meta)?;
+    let mut artifacts = vec![Artifact::new("plot", output_path)];
+
+    if !options.text {
+        let legend_path = format!("{}.labels.txt", output_path);
+        write_label_legend(&legend_path, data, fields, options)?;
+        artifacts.push(Artifact::new("label_legend", legend_path));
+    }
+
+    Ok(artifacts)
+}
+
+/// Writes each panel as its own figure in `dir`, named after the field.
+///
+/// Panels use `SPLIT_PANEL_SIZE` and the format of `output_path` (SVG or
+/// bitmap), and carry the same embedded metadata as the combined figure.
+pub fn write_split_panels(
+    data: &RunData,
+    fields: &[Field],
+    dir: &str,
+    output_path: &str,
+    options: PlotOptions,
+) -> Result<Vec<Artifact>, Box<dyn Error>> {
+    std::fs::create_dir_all(dir)?;
+    let extension = Path::new(output_path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("png")
+        .to_ascii_lowercase();
+
+    let mut artifacts = Vec::new();
+    for field in fields {
+        let path = Path::new(dir).join(format!("{}.{}", panel_file_name(field), extension));
+        let path = path.to_string_lossy().to_string();
+
+        let figure = SinglePanel {
+            data,
+            field,
+            options,
+            size: SPLIT_PANEL_SIZE,
+        };
+        render::to_file(&figure, &path)?;
+
+        let meta = FigureMetadata {
+            title: options.language.tr(field.title).to_string(),
+            description: describe_figure(data, std::slice::from_ref(field)),
+            dpi: options.dpi,
+        };
+        metadata::embed(&path, &meta)?;
+        artifacts.push(Artifact::new("panel", path));
+    }
+    Ok(artifacts)
+}
+
+/// File name stem of a field's panel: its title in lower case, with every
+/// other character than letters and digits replaced by `_`
+pub fn panel_file_name(field: &Field) -> String {
+    let name: String = field
+        .title
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    name.trim_matches('_').to_string()
+}
+
+/// Writes the points a field's panel draws to a CSV at `path`, so a figure
+/// can be reproduced from attached data.
+///
+/// Rows are `series,batch_time,value`: the `raw` series after decimation,
+/// then the `smoothed` overlay when there is one. Values are as measured;
+/// the panel clamps those beyond its y range to the edge.
+pub fn write_panel_data(
+    path: &str,
+    data: &RunData,
+    field: &Field,
+    options: PlotOptions,
+) -> Result<(), Box<dyn Error>> {
+    let times = &data.time;
+    let values = (field.column)(data);
+    let mut writer = csv::Writer::from_path(path)?;
+    writer.write_record(["series", "batch_time", "value"])?;
+    for i in drawn_rows(times, values, field, options) {
+        writer.write_record(["raw", &times[i].to_string(), &values[i].to_string()])?;
+    }
+    for (t, v) in smoothed_points(times, values, field, options) {
+        writer.write_record(["smoothed", &t.to_string(), &v.to_string()])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Rows of the raw series a panel draws: decimated to the point budget,
+/// without those a log axis cannot show
+fn drawn_rows(times: &[f64], values: &[f64], field: &Field, options: PlotOptions) -> Vec<usize> {
+    let kept = match options.max_points {
+        Some(max_points) if options.keep_extremes => {
+            decimate::with_extremes(times, values, max_points, &flagged_rows(values, field))
+        }
+        Some(max_points) => decimate::lttb(times, values, max_points),
+        None => (0..times.len().min(values.len())).collect(),
+    };
+    kept.into_iter()
+        .filter(|&i| !field.y_axis.log || field.y_axis.position(values[i]).is_finite())
+        .collect()
+}
+
+/// Rows the panel marks: outside the expected range or flagged by the
+/// field's anomaly detector
+fn flagged_rows(values: &[f64], field: &Field) -> Vec<usize> {
+    let anomalies = field.anomaly.map(|detector| detector.flag(values));
+    values
+        .iter()
+        .enumerate()
+        .filter(|&(i, &v)| {
+            field
+                .expected_range
+                .is_some_and(|(low, high)| v < low || v > high)
+                || anomalies.as_ref().is_some_and(|flags| flags[i])
+        })
+        .map(|(i, _)| i)
+        .collect()
+}
+
+/// Points of the rolling overlay a panel draws (none without smoothing)
+fn smoothed_points(
+    times: &[f64],
+    values: &[f64],
+    field: &Field,
+    options: PlotOptions,
+) -> Vec<(f64, f64)> {
+    let Some(smoothing) = options.smoothing else {
+        return Vec::new();
+    };
+    let rows = smooth::rows(times.len().min(values.len()), options.max_points);
+    smooth::series(times, values, &rows, smoothing)
+        .into_iter()
+        .filter(|&(_, v)| !field.y_axis.log || field.y_axis.position(v).is_finite())
+        .collect()
+}
+
+/// The standard figure: one stacked panel per field, plus the sample watermark
+struct MultiSeries<'a> {
+    data: &'a RunData,
+    fields: &'a [Field],
+    options: PlotOptions,
+}
+
+impl Figure for MultiSeries<'_> {
+    fn size(&self) -> (u32, u32) {
+        self.options.size
+    }
+
+    fn draw<DB: DrawingBackend>(&self, root: &DrawingArea<DB, Shift>) -> Result<(), Box<dyn Error>>
+    where
+        DB::ErrorType: 'static,
+    {
+        draw_panels(root, self.data, self.fields, self.options)?;
+        draw_watermark(root, self.data, self.options)
+    }
+
+    fn draw_raster<DB: DrawingBackend>(
+        &self,
+        root: &DrawingArea<DB, Shift>,
+    ) -> Result<(), Box<dyn Error>>
+    where
+        DB::ErrorType: 'static,
+    {
+        draw_panels_parallel(root, self.data, self.fields, self.options)?;
+        draw_watermark(root, self.data, self.options)
+    }
+}
+
+/// One field's panel on a canvas of its own
+struct SinglePanel<'a> {
+    data: &'a RunData,
+    field: &'a Field,
+    options: PlotOptions,
+    size: (u32, u32),
+}
+
+impl Figure for SinglePanel<'_> {
+    fn size(&self) -> (u32, u32) {
+        self.size
+    }
+
+    fn draw<DB: DrawingBackend>(&self, root: &DrawingArea<DB, Shift>) -> Result<(), Box<dyn Error>>
+    where
+        DB::ErrorType: 'static,
+    {
+        root.fill(&self.options.theme.background())?;
+        draw_panel(root, self.data, self.field, self.options)
+    }
+}
+
+/// Stamps a "sampled" notice across the top of a figure drawn from a sample
+pub fn draw_watermark<DB: DrawingBackend>(
+    root: &DrawingArea<DB, Shift>,
+    data: &RunData,
+    options: PlotOptions,
+) -> Result<(), Box<dyn Error>>
+where
+    DB::ErrorType: 'static,
+{
+    let Some(info) = data.sample else {
+        return Ok(());
+    };
+    if !options.text {
+        return Ok(());
+    }
+    let text = info.describe().to_uppercase();
+    let style = ("sans-serif", 28)
+        .into_font()
+        .color(&Health::Fail.color().mix(0.6));
+    let (width, _) = root.estimate_text_size(&text, &style)?;
+    let x = root.dim_in_pixel().0.saturating_sub(width + 30) as i32;
+    root.draw(&Text::new(text, (x, 6), style))?;
+    Ok(())
+}
+
+/// Draws one panel per field onto the given drawing area
+fn draw_panels<DB: DrawingBackend>(
+    root: &DrawingArea<DB, Shift>,
+    data: &RunData,
+    fields: &[Field],
+    options: PlotOptions,
+) -> Result<(), Box<dyn Error>>
+where
+    DB::ErrorType: 'static,
+{
+    root.fill(&options.theme.background())?;
+
+    // Divide the root area into one stacked horizontal panel per field
+    let split = root.split_evenly((fields.len(), 1));
+
+    // Iterate over each subplot panel and corresponding data field
+    for (i, field) in fields.iter().enumerate() {
+        draw_panel(&split[i], data, field, options)?;
+    }
+
+    Ok(())
+}
+
+/// Draws the panels like `draw_panels`, rendering them on worker threads
+/// (at most `threads::limit()`).
+///
+/// Every panel is drawn into a private RGB buffer sized like its slot, and the
+/// buffers are blitted onto `root` once all threads have finished. Only
+/// worthwhile for raster output, so SVG keeps using `draw_panels`.
+fn draw_panels_parallel<DB: DrawingBackend>(
+    root: &DrawingArea<DB, Shift>,
+    data: &RunData,
+    fields: &[Field],
+    options: PlotOptions,
+) -> Result<(), Box<dyn Error>>
+where
+    DB::ErrorType: 'static,
+{
+    root.fill(&options.theme.background())?;
+
+    // Slot of each panel: offset within the root area and size
+    let base = root.get_base_pixel();
+    let slots: Vec<((i32, i32), (u32, u32))> = root
+        .split_evenly((fields.len(), 1))
+        .iter()
+        .map(|area| {
+            let (x, y) = area.get_base_pixel();
+            ((x - base.0, y - base.1), area.dim_in_pixel())
+        })
+        .collect();
+
+    let panels: Vec<(&Field, (u32, u32))> = fields
+        .iter()
+        .zip(&slots)
+        .map(|(field, &(_, size))| (field, size))
+        .collect();
+    let buffers = threads::map(&panels, |&(field, size)| -> Result<Vec<u8>, String> {
+        let figure = SinglePanel {
+            data,
+            field,
+            options,
+            size,
+        };
+        render::to_rgb(&figure).map_err(|e| e.to_string())
+    });
+
+    // Composite in panel order
+    for (buffer, &(pos, size)) in buffers.into_iter().zip(&slots) {
+        let element = BitMapElement::<(i32, i32)>::with_owned_buffer(pos, size, buffer?)
+            .ok_or("Panel buffer does not match its slot")?;
+        root.draw(&element)?;
+    }
+
+    Ok(())
+}
+
+/// Draws a single field's panel (chart, markers and optional strip) onto `area`
+pub fn draw_panel<DB: DrawingBackend>(
+    area: &DrawingArea<DB, Shift>,
+    data: &RunData,
+    field: &Field,
+    options: PlotOptions,
+) -> Result<(), Box<dyn Error>>
+where
+    DB::ErrorType: 'static,
+{
+    let title = options.language.tr(field.title);
+    let times = &data.time;
+    let values = (field.column)(data);
+
+    // Draw border around the subplot area
+    let x_range = area.get_pixel_range().0.clone();
+    let y_range = area.get_pixel_range().1.clone();
+
+    let x0 = x_range.start;
+    let x1 = x_range.end;
+    let y0 = y_range.start;
+    let y1 = y_range.end;
+
+    area.draw(&Rectangle::new(
+        [(x0, y0), (x1 - 1, y1 - 1)],
+        options.theme.foreground().stroke_width(2),
+    ))?;
+
+    // Determine min/max time for x-axis bounds
+    let (Some(&min_time), Some(&max_time)) = (times.first(), times.last()) else {
+        return Err("No batches to plot".into());
+    };
+
+    // Determine y-axis bounds from the field's column (its clipped range),
+    // unless the range is fixed
+    let (min_val, max_val) = field.y_axis.bounds(values, options.clip);
+
+    // Reserve the bottom of the panel for the summary strip if requested
+    let (chart_area, strip_area) = if options.summary_strip {
+        let height = area.dim_in_pixel().1;
+        let (top, bottom) = area.split_vertically(height.saturating_sub(STRIP_HEIGHT));
+        (top, Some(bottom))
+    } else {
+        (area.clone(), None)
+    };
+
+    // Size text and label areas to what actually fits this panel
+    let layout = if options.text {
+        Some(layout::panel(
+            &chart_area,
+            title,
+            title,
+            (min_time, max_time),
+            (field.y_axis.value(min_val), field.y_axis.value(max_val)),
+            field.precision,
+        )?)
+    } else {
+        None
+    };
+    let y_label_area = layout.as_ref().map_or(100, |l| l.y_label_area);
+    let right_margin = layout.as_ref().map_or(layout::MARGIN, |l| l.right_margin);
+
+    // Spiky series get a broken axis: spikes above, the typical range below
+    let x_label_area = layout.as_ref().map_or(50, |l| l.x_label_area);
+    let margins = ChartMargins {
+        x_label_area,
+        y_label_area,
+        right: right_margin,
+    };
+    let x_range = (min_time, max_time);
+    match (options.axis_break && field.y_axis == YAxis::AUTO)
+        .then(|| axisbreak::find(values))
+        .flatten()
+    {
+        Some(split) => {
+            let height = chart_area.dim_in_pixel().1;
+            let upper_height = (height as f64 * axisbreak::UPPER_SHARE) as u32;
+            let (upper, lower) = chart_area.split_vertically(upper_height);
+            let upper_margins = ChartMargins {
+                x_label_area: 0,
+                ..margins
+            };
+            // The caption goes above the spikes, the description beside the
+            // typical range
+            let upper_layout = layout.as_ref().map(|l| layout::PanelLayout {
+                y_desc: String::new(),
+                ..l.clone()
+            });
+            // Both charts clamp to their own range, so edge markers would
+            // mislabel the spikes drawn above the break
+            let options = PlotOptions {
+                outlier_markers: false,
+                ..options
+            };
+            draw_chart(
+                &upper,
+                data,
+                field,
+                options,
+                upper_layout.as_ref(),
+                upper_margins,
+                x_range,
+                split.upper,
+            )?;
+            let lower_layout = layout.as_ref().map(|l| layout::PanelLayout {
+                caption: String::new(),
+                ..l.clone()
+            });
+            draw_chart(
+                &lower,
+                data,
+                field,
+                options,
+                lower_layout.as_ref(),
+                margins,
+                x_range,
+                split.lower,
+            )?;
+
+            // Break marks across the gap on both ends of the time axis
+            let width = chart_area.dim_in_pixel().0 as i32;
+            let y = upper_height as i32;
+            for x in [
+                (layout::MARGIN + y_label_area) as i32,
+                width - right_margin as i32,
+            ] {
+                for dy in [-4, 4] {
+                    chart_area.draw(&PathElement::new(
+                        vec![(x - 8, y + dy + 4), (x + 8, y + dy - 4)],
+                        options.theme.foreground().stroke_width(2),
+                    ))?;
+                }
+            }
+        }
+        None => draw_chart(
+            &chart_area,
+            data,
+            field,
+            options,
+            layout.as_ref(),
+            margins,
+            x_range,
+            (min_val, max_val),
+        )?,
+    }
+
+    // Draw the health strip aligned to the chart's time axis
+    if let Some(strip_area) = strip_area {
+        let levels = strip_levels(times, values, field.thresholds, options.center, STRIP_BINS);
+        let bin_width = (max_time - min_time) / STRIP_BINS as f64;
+
+        // Same horizontal margins and label area as the chart so bins line up
+        let mut strip = ChartBuilder::on(&strip_area)
+            .margin_left(layout::MARGIN)
+            .margin_right(right_margin)
+            .margin_bottom(10)
+            .y_label_area_size(y_label_area)
+            .build_cartesian_2d(min_time..max_time, 0.0..1.0)?;
+
+        strip.draw_series(levels.iter().enumerate().filter_map(|(bin, level)| {
+            let start = min_time + bin as f64 * bin_width;
+            level.map(|h| {
+                Rectangle::new([(start, 0.0), (start + bin_width, 1.0)], h.color().filled())
+            })
+        }))?;
+    }
+
+    Ok(())
+}
+
+/// Label areas and right margin shared by the charts of one panel
+#[derive(Debug, Clone, Copy)]
+struct ChartMargins {
+    x_label_area: u32, // Height reserved for x tick labels and description
+    y_label_area: u32, // Width reserved for y tick labels and description
+    right: u32,        // Right margin
+}
+
+/// Draws a field's series and markers as one chart spanning `y_range`, in
+/// positions on the field's y axis.
+///
+/// Values outside the range are clamped to its edges, so a spike cut off by
+/// an axis break still reaches the edge of the lower chart. An empty caption
+/// or zero x label area leaves those out.
+#[allow(clippy::too_many_arguments)]
+fn draw_chart<DB: DrawingBackend>(
+    area: &DrawingArea<DB, Shift>,
+    data: &RunData,
+    field: &Field,
+    options: PlotOptions,
+    layout: Option<&layout::PanelLayout>,
+    margins: ChartMargins,
+    (min_time, max_time): (f64, f64),
+    (min_val, max_val): (f64, f64),
+) -> Result<(), Box<dyn Error>>
+where
+    DB::ErrorType: 'static,
+{
+    let times = &data.time;
+    let values = (field.column)(data);
+    let axis = field.y_axis;
+    let within = |v: f64| (min_val..=max_val).contains(&axis.position(v));
+
+    // Create a chart for the current subplot
+    let foreground = options.theme.foreground();
+    let text = |size: u32| ("sans-serif", size).into_font().color(&foreground);
+    let mut builder = ChartBuilder::on(area);
+    if let Some(layout) = layout.filter(|l| !l.caption.is_empty()) {
+        builder.caption(&layout.caption, text(layout.caption_size)); // Title
+    }
+    let mut chart = builder
+        .margin(layout::MARGIN) // Outer margin
+        .margin_right(margins.right)
+        .x_label_area_size(margins.x_label_area) // Space for x-axis labels
+        .y_label_area_size(margins.y_label_area) // Space for y-axis labels
+        .build_cartesian_2d(min_time..max_time, min_val..max_val)?; // Axes ranges
+
+    // Draw chart axes and grid
+    if let Some(layout) = layout {
+        // The chart above a break is short, so it gets fewer y ticks
+        let (x_labels, y_labels) = if margins.x_label_area > 0 {
+            (5, 5)
+        } else {
+            (0, 3)
+        };
+        // A log axis gets one tick per decade, or denser ticks within a few
+        let y_labels = match axis.log {
+            true if max_val - min_val >= 2.0 => ((max_val - min_val).ceil() as usize + 1).min(10),
+            true => y_labels * 2,
+            false => y_labels,
+        };
+        let y_precision = field
+            .precision
+            .fixed_for(axis.value(min_val).abs().max(axis.value(max_val).abs()));
+        let y_label = |v: &f64| y_precision.format(axis.value(*v));
+        let mut mesh = chart.configure_mesh();
+        mesh.x_labels(x_labels)
+            .y_labels(y_labels)
+            // .disable_mesh() // Disable inner grid lines for cleaner look
+            // Adjust label font size
+            .x_label_style(text(20))
+            .y_label_style(text(layout::TICK_SIZE))
+            .y_label_formatter(&y_label)
+            .axis_desc_style(text(layout.desc_size))
+            .axis_style(foreground)
+            .bold_line_style(foreground.mix(0.2))
+            .light_line_style(foreground.mix(0.1))
+            .y_desc(&layout.y_desc);
+        if x_labels > 0 {
+            mesh.x_desc(options.language.tr("Batch Time"));
+        }
+        mesh.draw()?;
+    } else {
+        // No labels means no text is measured or drawn, only the axis lines
+        chart
+            .configure_mesh()
+            .x_labels(0)
+            .y_labels(0)
+            .axis_style(foreground)
+            .bold_line_style(foreground.mix(0.2))
+            .light_line_style(foreground.mix(0.1))
+            .draw()?;
+    }
+
+    // Pauses shaded across the panel, beneath every series
+    chart.draw_series(
+        data.pauses
+            .iter()
+            .filter(|p| p.end > min_time && p.start < max_time)
+            .map(|p| {
+                let color = match p.kind {
+                    PauseKind::Gap => GAP_COLOR,
+                    PauseKind::MuxScan => MUX_SCAN_COLOR,
+                };
+                Rectangle::new(
+                    [
+                        (p.start.max(min_time), min_val),
+                        (p.end.min(max_time), max_val),
+                    ],
+                    color.mix(PAUSE_OPACITY).filled(),
+                )
+            }),
+    )?;
+
+    // The golden run's curve behind everything else, where it has the column
+    if let Some(reference) = data.reference.as_deref() {
+        let reference_values = (field.column)(reference);
+        if !reference_values.is_empty() && reference_values.len() == reference.len() {
+            chart.draw_series(LineSeries::new(
+                drawn_rows(&reference.time, reference_values, field, options)
+                    .into_iter()
+                    .map(|i| (reference.time[i], reference_values[i]))
+                    .filter(|&(t, _)| (min_time..=max_time).contains(&t))
+                    .map(|(t, v)| (t, axis.position(v).clamp(min_val, max_val))),
+                REFERENCE_COLOR.mix(REFERENCE_OPACITY),
+            ))?;
+        }
+    }
+
+    // Plot the data as a line series, decimated to the point budget
+    let raw_color = match options.smoothing {
+        Some(smoothing) if smoothing.dim_raw => options.palette.raw().mix(DIMMED_RAW),
+        _ => options.palette.raw().into(),
+    };
+    let raw_points = drawn_rows(times, values, field, options)
+        .into_iter()
+        .map(|i| (times[i], axis.position(values[i]).clamp(min_val, max_val)));
+    for segment in split_at_pauses(raw_points, &data.pauses) {
+        chart.draw_series(LineSeries::new(segment, raw_color))?;
+    }
+
+    // Rolling mean/median over the raw values
+    if options.smoothing.is_some() {
+        let smoothed = smoothed_points(times, values, field, options)
+            .into_iter()
+            .map(|(t, v)| (t, axis.position(v).clamp(min_val, max_val)));
+        for segment in split_at_pauses(smoothed, &data.pauses) {
+            chart.draw_series(LineSeries::new(
+                segment,
+                options.palette.smoothed().stroke_width(2),
+            ))?;
+        }
+    }
+
+    // Values beyond the y range, as markers on the edge they were clamped to
+    if options.outlier_markers {
+        chart.draw_series(
+            times
+                .iter()
+                .zip(values)
+                .map(|(&t, &v)| (t, axis.position(v)))
+                .filter(|&(_, p)| p < min_val || p > max_val)
+                .map(|(t, p)| {
+                    TriangleMarker::new((t, p.clamp(min_val, max_val)), 6, OUTLIER_COLOR.filled())
+                }),
+        )?;
+    }
+
+    // Mark values outside the expected range
+    if let Some((low, high)) = field.expected_range {
+        chart.draw_series(
+            times
+                .iter()
+                .copied()
+                .zip(values.iter().copied())
+                .filter(|&(_, v)| (v < low || v > high) && within(v))
+                .map(|(t, v)| Circle::new((t, axis.position(v)), 4, Health::Fail.color().filled())),
+        )?;
+    }
+
+    // Mark values the metric's anomaly detector flags
+    if let Some(detector) = field.anomaly {
+        chart.draw_series(
+            times
+                .iter()
+                .zip(values)
+                .zip(detector.flag(values))
+                .filter(|&((_, &v), flagged)| flagged && within(v))
+                .map(|((&t, &v), _)| {
+                    Circle::new((t, axis.position(v)), 4, Health::Warn.color().filled())
+                }),
+        )?;
+    }
+
+    // Server log events as vertical markers across the panel
+    chart.draw_series(
+        data.events
+            .iter()
+            .filter(|e| (min_time..=max_time).contains(&e.time))
+            .map(|e| {
+                PathElement::new(
+                    vec![(e.time, min_val), (e.time, max_val)],
+                    e.kind.color().stroke_width(1),
+                )
+            }),
+    )?;
+
+    Ok(())
+}
+
+/// A line's points cut wherever a pause lies between two of them, so no
+/// straight line is drawn across a stretch without batches
+fn split_at_pauses(
+    points: impl Iterator<Item = (f64, f64)>,
+    pauses: &[Pause],
+) -> Vec<Vec<(f64, f64)>> {
+    let mut segments: Vec<Vec<(f64, f64)>> = vec![Vec::new()];
+    for point in points {
+        let current = segments.last_mut().expect("at least one segment");
+        let paused = current
+            .last()
+            .is_some_and(|&(t, _)| pauses.iter().any(|p| p.splits(t, point.0)));
+        if paused {
+            segments.push(vec![point]);
+        } else {
+            current.push(point);
+        }
+    }
+    segments
+}
+
+/// Computes one health level per time bin for the summary strip.
+///
+/// With thresholds, each bin's typical value (per `center`) is classified
+/// directly. Without them, the relative change against the previous non-empty
+/// bin is used instead. Bins without any records are `None`.
+fn strip_levels(
+    times: &[f64],
+    values: &[f64],
+    thresholds: Option<Thresholds>,
+    center: robust::Center,
+    bins: usize,
+) -> Vec<Option<Health>> {
+    let (min_time, max_time) = match (times.first(), times.last()) {
+        (Some(&first), Some(&last)) => (first, last),
+        _ => return Vec::new(),
+    };
+    let span = max_time - min_time;
+
+    // Collect the values of each bin
+    let mut binned = vec![Vec::new(); bins];
+    for (&time, &value) in times.iter().zip(values) {
+        let bin = if span > 0.0 {
+            (((time - min_time) / span) * bins as f64) as usize
+        } else {
+            0
+        };
+        binned[bin.min(bins - 1)].push(value);
+    }
+
+    // Classify each bin's typical value
+    let mut previous: Option<f64> = None;
+    binned
+        .iter()
+        .map(|bin| {
+            if bin.is_empty() {
+                return None;
+            }
+            let mean = center.of(bin);
+            let level = match thresholds {
+                Some(t) => t.classify(mean),
+                None => {
+                    let change = match previous {
+                        Some(prev) if prev != 0.0 => ((mean - prev) / prev).abs(),
+                        _ => 0.0,
+                    };
+                    RATE_OF_CHANGE.classify(change)
+                }
+            };
+            previous = Some(mean);
+            Some(level)
+        })
+        .collect()
+}
+
+/// Renders the figure into memory and returns a downscaled PNG thumbnail
+pub fn render_thumbnail(
+    data: &RunData,
+    fields: &[Field],
+    options: PlotOptions,
+) -> Result<Vec<u8>, Box<dyn Error>> {
+    let figure = MultiSeries {
+        data,
+        fields,
+        options,
+    };
+    let (width, height) = figure.size();
+    let buffer = render::to_rgb(&figure)?;
+
+    // Render at full size and shrink, so labels keep their proportions
+    let full = image::RgbImage::from_raw(width, height, buffer).ok_or("Invalid render buffer")?;
+    let thumb = image::imageops::resize(
+        &full,
+        THUMBNAIL_WIDTH,
+        THUMBNAIL_WIDTH * height / width,
+        image::imageops::FilterType::Triangle,
+    );
+    encode_png(&thumb)
+}
+
+/// One panel rendered into memory
+#[derive(Debug, Clone)]
+pub struct PanelImage {
+    pub title: String, // Panel title, translated
+    pub png: Vec<u8>,  // PNG file contents
+}
+
+/// Renders each panel on its own into memory, at the size `--split-panels`
+/// writes them
+pub fn render_panels(
+    data: &RunData,
+    fields: &[Field],
+    options: PlotOptions,
+) -> Result<Vec<PanelImage>, Box<dyn Error>> {
+    fields
+        .iter()
+        .map(|field| {
+            let figure = SinglePanel {
+                data,
+                field,
+                options,
+                size: SPLIT_PANEL_SIZE,
+            };
+            let (width, height) = figure.size();
+            let image = image::RgbImage::from_raw(width, height, render::to_rgb(&figure)?)
+                .ok_or("Invalid render buffer")?;
+            Ok(PanelImage {
+                title: options.language.tr(field.title).to_string(),
+                png: encode_png(&image)?,
+            })
+        })
+        .collect()
+}
+
+/// PNG file contents of an RGB image
+fn encode_png(image: &image::RgbImage) -> Result<Vec<u8>, Box<dyn Error>> {
+    use image::ImageEncoder;
+
+    let mut png = Vec::new();
+    image::codecs::png::PngEncoder::new(&mut png).write_image(
+        image,
+        image.width(),
+        image.height(),
+        image::ColorType::Rgb8,
+    )?;
+    Ok(png)
+}
+
+/// Checks whether the default font can be loaded and measured
+pub fn fonts_available() -> bool {
+    ("sans-serif", 20).into_font().box_size("0").is_ok()
+}
+
+/// Writes the external legend used for text-free figures.
+///
+/// Panels are listed top to bottom with the axis ranges they were drawn with,
+/// so the figure can still be read without any labels rendered on it.
+fn write_label_legend(
+    path: &str,
+    data: &RunData,
+    fields: &[Field],
+    options: PlotOptions,
+) -> Result<(), Box<dyn Error>> {
+    let tr = |english| options.language.tr(english);
+    let mut out = String::new();
+    out.push_str(tr(FIGURE_TITLE));
+    out.push('\n');
+    if let Some((first, last)) = data.time_range() {
+        out.push_str(&format!(
+            "{}: {}, {:.3} {} {:.3}\n",
+            tr("x axis (all panels)"),
+            tr("Batch Time"),
+            first,
+            tr("to"),
+            last
+        ));
+    }
+    for (i, field) in fields.iter().enumerate() {
+        // The same (clipped or fixed) bounds the panel was drawn with
+        let (low, high) = field.y_axis.bounds((field.column)(data), options.clip);
+        out.push_str(&format!(
+            "{} {} ({}): {}, {} {} {} {}\n",
+            tr("Panel"),
+            i + 1,
+            tr("top to bottom"),
+            tr(field.title),
+            tr("y axis"),
+            field.precision.format(field.y_axis.value(low)),
+            tr("to"),
+            field.precision.format(field.y_axis.value(high))
+        ));
+    }
+    std::fs::write(path, out)?;
+    Ok(())
+}
+
+/// Builds the alt-text description embedded into a figure.
+///
+/// Lists the number of records, the covered time span and, per plotted
+/// field, the mean/min/max so the figure stays self-describing on its own.
+pub fn describe_figure(data: &RunData, fields: &[Field]) -> String {
+    let (min_time, max_time) = match data.time_range() {
+        Some(range) => range,
+        None => return "No records.".to_string(),
+    };
+
+    let mut parts = vec![format!(
+        "{} stacked time-series panels of {} records from batch time {:.3} to {:.3} ({:.1} s).",
+        fields.len(),
+        data.len(),
+        min_time,
+        max_time,
+        max_time - min_time
+    )];
+
+    for field in fields {
+        let summary = summarize_field(data, field);
+        let fmt = |v| field.precision.format(v);
+        parts.push(format!(
+            "{}: mean {}, min {}, max {}.",
+            field.title,
+            fmt(summary.mean),
+            fmt(summary.min),
+            fmt(summary.max)
+        ));
+    }
+
+    parts.join(" ")
+}
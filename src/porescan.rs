@@ -0,0 +1,169 @@
+// MinKNOW pore (mux) scan ingestion
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use plotters::style::full_palette::GREY_500;
+use std::error::Error;
+
+use crate::render::{self, Figure};
+use crate::timestamp::{TimeFormat, TimeParser};
+
+/// Assessment MinKNOW gives a channel/mux with a usable single pore
+const AVAILABLE: &str = "single_pore";
+
+/// Result of one pore scan
+#[derive(Debug, Clone)]
+pub struct PoreScan {
+    pub time: f64,                   // Scan time (Unix seconds, earliest row of the scan)
+    pub available: usize,            // Channel/mux pairs assessed as a single pore
+    pub yield_per_pore: Option<f64>, // Bases per available pore until the next scan
+}
+
+/// Parses a pore scan CSV into one entry per scan, in time order.
+///
+/// Needs `time` (in `time_format`) and `mux_scan_assessment` columns; rows are
+/// grouped into scans by `repeat` when present, otherwise by identical time.
+pub fn parse(path: &str, time_format: TimeFormat) -> Result<Vec<PoreScan>, Box<dyn Error>> {
+    let mut rdr = csv::Reader::from_path(path)?;
+    let headers = rdr.headers()?.clone();
+    let column = |name: &str| headers.iter().position(|h| h == name);
+    let time_column = column("time").ok_or("Pore scan CSV has no time column")?;
+    let assessment_column =
+        column("mux_scan_assessment").ok_or("Pore scan CSV has no mux_scan_assessment column")?;
+    let repeat_column = column("repeat");
+
+    // (scan key, earliest time, available pores)
+    let mut times = TimeParser::new(time_format);
+    let mut scans: Vec<(String, f64, usize)> = Vec::new();
+    for result in rdr.records() {
+        let record = result?;
+        let time = times.read(record.get(time_column).ok_or("Missing time")?)?;
+        let key = match repeat_column {
+            Some(c) => record.get(c).ok_or("Missing repeat")?.to_string(),
+            None => time.to_string(),
+        };
+        let available = usize::from(record.get(assessment_column) == Some(AVAILABLE));
+
+        match scans.iter_mut().find(|s| s.0 == key) {
+            Some(scan) => {
+                scan.1 = scan.1.min(time);
+                scan.2 += available;
+            }
+            None => scans.push((key, time, available)),
+        }
+    }
+
+    let mut scans: Vec<PoreScan> = scans
+        .into_iter()
+        .map(|(_, time, available)| PoreScan {
+            time,
+            available,
+            yield_per_pore: None,
+        })
+        .collect();
+    scans.sort_by(|a, b| a.time.total_cmp(&b.time));
+    Ok(scans)
+}
+
+/// Fills in the bases per available pore between consecutive scans.
+///
+/// The last scan runs until the end of the batch data. Scans without any
+/// available pore keep `None`.
+pub fn attach_yield(scans: &mut [PoreScan], times: &[f64], bases: &[f64]) {
+    let end = times.last().copied().unwrap_or(f64::NEG_INFINITY);
+    for i in 0..scans.len() {
+        let from = scans[i].time;
+        let until = scans.get(i + 1).map_or(end, |next| next.time);
+        let yielded: f64 = times
+            .iter()
+            .zip(bases)
+            .filter(|&(&t, _)| t >= from && (t < until || (i + 1 == scans.len() && t <= until)))
+            .map(|(_, &b)| b)
+            .sum();
+        scans[i].yield_per_pore =
+            (scans[i].available > 0).then(|| yielded / scans[i].available as f64);
+    }
+}
+
+/// Prints one line per scan
+pub fn print_report(scans: &[PoreScan]) {
+    println!("Pore scans: {}", scans.len());
+    for scan in scans {
+        match scan.yield_per_pore {
+            Some(per_pore) => println!(
+                "  {:.3}: {} available pores, {:.1} bases per pore",
+                scan.time, scan.available, per_pore
+            ),
+            None => println!("  {:.3}: {} available pores", scan.time, scan.available),
+        }
+    }
+}
+
+/// Draws available pores and yield per pore per scan as two stacked panels
+pub fn plot(scans: &[PoreScan], output_path: &str) -> Result<(), Box<dyn Error>> {
+    if scans.is_empty() {
+        return Err("No pore scans to plot".into());
+    }
+    let figure = PoreScanFigure { scans };
+    render::to_file(&figure, output_path)
+}
+
+/// Available pores and yield per pore over the scans
+struct PoreScanFigure<'a> {
+    scans: &'a [PoreScan],
+}
+
+impl Figure for PoreScanFigure<'_> {
+    fn size(&self) -> (u32, u32) {
+        (1600, 900)
+    }
+
+    fn draw<DB: DrawingBackend>(&self, root: &DrawingArea<DB, Shift>) -> Result<(), Box<dyn Error>>
+    where
+        DB::ErrorType: 'static,
+    {
+        let &PoreScanFigure { scans } = self;
+        let (Some(first), Some(last)) = (scans.first(), scans.last()) else {
+            return Err("No pore scans to plot".into());
+        };
+        // Pad a single scan so the time axis has a non-empty range
+        let (min_time, max_time) = (first.time - 1.0, last.time + 1.0);
+
+        root.fill(&GREY_500)?;
+        let panels = root.split_evenly((2, 1));
+
+        let series: [(&str, Vec<(f64, f64)>); 2] = [
+            (
+                "Available Pores",
+                scans.iter().map(|s| (s.time, s.available as f64)).collect(),
+            ),
+            (
+                "Yield per Available Pore",
+                scans
+                    .iter()
+                    .filter_map(|s| s.yield_per_pore.map(|y| (s.time, y)))
+                    .collect(),
+            ),
+        ];
+
+        for (area, (title, points)) in panels.iter().zip(series) {
+            let max_val = points.iter().map(|p| p.1).fold(0.0, f64::max).max(1.0);
+            let mut chart = ChartBuilder::on(area)
+                .caption(title, ("sans-serif", 20))
+                .margin(20)
+                .x_label_area_size(50)
+                .y_label_area_size(100)
+                .build_cartesian_2d(min_time..max_time, 0.0..max_val * 1.05)?;
+            chart
+                .configure_mesh()
+                .x_labels(5)
+                .y_labels(5)
+                .x_desc("Scan Time")
+                .y_desc(title)
+                .draw()?;
+            chart.draw_series(LineSeries::new(points.iter().copied(), &GREEN))?;
+            chart.draw_series(points.iter().map(|&p| Circle::new(p, 4, GREEN.filled())))?;
+        }
+
+        Ok(())
+    }
+}
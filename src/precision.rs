@@ -0,0 +1,64 @@
+// Rounding of reported numbers, configurable per field
+use std::error::Error;
+
+/// How many digits of a value are shown
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Precision {
+    Decimals(usize),    // Fixed digits after the decimal point
+    Significant(usize), // Significant figures, no scientific notation
+}
+
+impl Precision {
+    /// Used by fields without a precision of their own
+    pub const DEFAULT: Precision = Precision::Significant(4);
+
+    /// Parses `2dp` (decimal places) or `3sf` (significant figures)
+    pub fn parse(spec: &str) -> Result<Self, Box<dyn Error>> {
+        let spec = spec.trim().to_ascii_lowercase();
+        let invalid = || format!("Invalid precision '{}' (e.g. 2dp or 3sf)", spec);
+        if let Some(digits) = spec.strip_suffix("dp") {
+            Ok(Precision::Decimals(digits.parse().map_err(|_| invalid())?))
+        } else if let Some(digits) = spec.strip_suffix("sf") {
+            match digits.parse() {
+                Ok(0) | Err(_) => Err(invalid().into()),
+                Ok(n) => Ok(Precision::Significant(n)),
+            }
+        } else {
+            Err(invalid().into())
+        }
+    }
+
+    /// Fixed decimal places giving `self` at the size of `magnitude`, so a
+    /// column of numbers such as an axis shares one format
+    pub fn fixed_for(self, magnitude: f64) -> Precision {
+        match self {
+            Precision::Decimals(_) => self,
+            Precision::Significant(_) if !magnitude.is_normal() => self,
+            Precision::Significant(figures) => {
+                let places = figures as i32 - 1 - magnitude.abs().log10().floor() as i32;
+                Precision::Decimals(places.max(0) as usize)
+            }
+        }
+    }
+
+    /// Formats `value`; digits beyond the precision are rounded to zeros
+    /// rather than shown in scientific notation (4sf of 123456.7 is `123500`)
+    pub fn format(self, value: f64) -> String {
+        if !value.is_finite() {
+            return format!("{}", value);
+        }
+        match self {
+            Precision::Decimals(places) => format!("{:.*}", places, value),
+            Precision::Significant(figures) => {
+                if value == 0.0 {
+                    return "0".to_string();
+                }
+                // Round first: 9.9996 to 4sf becomes 10.00, one magnitude up
+                let places = |v: f64| figures as i32 - 1 - v.abs().log10().floor() as i32;
+                let scale = 10f64.powi(places(value));
+                let rounded = (value * scale).round() / scale;
+                format!("{:.*}", places(rounded).max(0) as usize, rounded)
+            }
+        }
+    }
+}
@@ -0,0 +1,17 @@
+// Named bundles of analyses and thresholds for particular kinds of run
+
+/// Analyses a preset switches on
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Preset {
+    Ultralong, // Ultra-long libraries: N50 over time, >100 kb yield, shearing
+}
+
+impl Preset {
+    /// Parses a `--preset` value
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "ultralong" | "ultra-long" | "ul" => Some(Preset::Ultralong),
+            _ => None,
+        }
+    }
+}
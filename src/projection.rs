@@ -0,0 +1,212 @@
+// Projection of run milestones from the observed yield
+use std::error::Error;
+use std::fs;
+
+/// Fraction of the observed span used to estimate the current yield rate
+const RECENT_FRACTION: f64 = 0.1;
+/// Number of bins used to follow the throughput trend
+const RATE_BINS: usize = 10;
+/// Throughput, relative to its peak, below which a flow cell wash is recommended
+const WASH_FRACTION: f64 = 0.5;
+
+/// A projected (or already reached) point in the run
+#[derive(Debug, Clone)]
+pub struct Milestone {
+    pub name: &'static str,        // Machine-readable milestone name
+    pub description: &'static str, // Human-readable summary used in calendars
+    pub time: f64,                 // Unix time at which it is (or was) reached
+    pub reached: bool,             // Whether it already happened in the observed data
+}
+
+/// Projects the half-target, wash and run-end milestones.
+///
+/// `times` must be sorted. Yield milestones extrapolate the cumulative bases
+/// at the recent rate; the wash milestone extrapolates the linear decline of
+/// binned throughput after its peak. Milestones that cannot be projected
+/// (no data, or no yield at all) are left out.
+pub fn project_milestones(times: &[f64], bases: &[f64], target_bases: f64) -> Vec<Milestone> {
+    let (start, end) = match (times.first(), times.last()) {
+        (Some(&start), Some(&end)) => (start, end),
+        _ => return Vec::new(),
+    };
+    let mut milestones = Vec::new();
+
+    // Recent yield rate in bases per second
+    let recent_from = end - (end - start) * RECENT_FRACTION;
+    let recent_bases: f64 = times
+        .iter()
+        .zip(bases)
+        .filter(|&(&t, _)| t >= recent_from)
+        .map(|(_, &b)| b)
+        .sum();
+    let rate = if end > recent_from {
+        recent_bases / (end - recent_from)
+    } else {
+        0.0
+    };
+
+    for (name, description, fraction) in [
+        ("half_target", "50% of target yield", 0.5),
+        ("run_end", "Target yield reached", 1.0),
+    ] {
+        if let Some(time) = time_to_yield(times, bases, target_bases * fraction, rate) {
+            milestones.push(Milestone {
+                name,
+                description,
+                time,
+                reached: time <= end,
+            });
+        }
+    }
+
+    if let Some((time, reached)) = wash_time(times, bases, start, end) {
+        milestones.push(Milestone {
+            name: "wash",
+            description: "Recommended flow cell wash",
+            time,
+            reached,
+        });
+    }
+
+    milestones.sort_by(|a, b| a.time.total_cmp(&b.time));
+    milestones
+}
+
+/// Time at which cumulative bases reach `goal`, observed or extrapolated at `rate`
+fn time_to_yield(times: &[f64], bases: &[f64], goal: f64, rate: f64) -> Option<f64> {
+    let mut total = 0.0;
+    for (&t, &b) in times.iter().zip(bases) {
+        total += b;
+        if total >= goal {
+            return Some(t);
+        }
+    }
+    if rate > 0.0 {
+        times.last().map(|&end| end + (goal - total) / rate)
+    } else {
+        None
+    }
+}
+
+/// Time at which throughput falls below `WASH_FRACTION` of its peak, and whether it already did
+fn wash_time(times: &[f64], bases: &[f64], start: f64, end: f64) -> Option<(f64, bool)> {
+    let span = end - start;
+    if span <= 0.0 {
+        return None;
+    }
+    let bin_width = span / RATE_BINS as f64;
+
+    // Throughput per bin, in bases per second
+    let mut rates = [0.0; RATE_BINS];
+    for (&t, &b) in times.iter().zip(bases) {
+        let bin = (((t - start) / span) * RATE_BINS as f64) as usize;
+        rates[bin.min(RATE_BINS - 1)] += b / bin_width;
+    }
+    let centre = |bin: usize| start + (bin as f64 + 0.5) * bin_width;
+
+    let (peak_bin, peak) = rates
+        .iter()
+        .copied()
+        .enumerate()
+        .max_by(|a, b| a.1.total_cmp(&b.1))?;
+    let floor = peak * WASH_FRACTION;
+
+    // Already below the floor after the peak
+    if let Some(bin) = (peak_bin..RATE_BINS).find(|&bin| rates[bin] < floor) {
+        return Some((centre(bin), true));
+    }
+
+    // Least-squares line through the post-peak bins
+    let points: Vec<(f64, f64)> = (peak_bin..RATE_BINS)
+        .map(|bin| (centre(bin), rates[bin]))
+        .collect();
+    if points.len() < 2 {
+        return None;
+    }
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|p| p.0).sum::<f64>() / n;
+    let mean_y = points.iter().map(|p| p.1).sum::<f64>() / n;
+    let sxy: f64 = points.iter().map(|p| (p.0 - mean_x) * (p.1 - mean_y)).sum();
+    let sxx: f64 = points.iter().map(|p| (p.0 - mean_x).powi(2)).sum();
+    let slope = sxy / sxx;
+    if slope >= 0.0 || !slope.is_finite() {
+        return None;
+    }
+    Some((mean_x + (floor - mean_y) / slope, false))
+}
+
+/// Writes the milestones as a JSON array
+pub fn write_json(path: &str, milestones: &[Milestone]) -> Result<(), Box<dyn Error>> {
+    let entries: Vec<String> = milestones
+        .iter()
+        .map(|m| {
+            format!(
+                "  {{\"name\": \"{}\", \"description\": \"{}\", \"time\": {:.3}, \"utc\": \"{}\", \"reached\": {}}}",
+                m.name,
+                m.description,
+                m.time,
+                iso8601(m.time),
+                m.reached
+            )
+        })
+        .collect();
+    fs::write(path, format!("[\n{}\n]\n", entries.join(",\n")))?;
+    Ok(())
+}
+
+/// Writes the milestones still ahead as iCalendar events
+pub fn write_ics(path: &str, milestones: &[Milestone]) -> Result<(), Box<dyn Error>> {
+    let mut ics =
+        String::from("BEGIN:VCALENDAR\r\nVERSION:2.0\r\nPRODID:-//as_parser//milestones//EN\r\n");
+    for m in milestones.iter().filter(|m| !m.reached) {
+        let stamp = iso8601_basic(m.time);
+        ics.push_str("BEGIN:VEVENT\r\n");
+        ics.push_str(&format!("UID:{}-{}@as_parser\r\n", m.name, m.time as i64));
+        ics.push_str(&format!("DTSTAMP:{}\r\n", stamp));
+        ics.push_str(&format!("DTSTART:{}\r\n", stamp));
+        ics.push_str(&format!("SUMMARY:{}\r\n", m.description));
+        ics.push_str("END:VEVENT\r\n");
+    }
+    ics.push_str("END:VCALENDAR\r\n");
+    fs::write(path, ics)?;
+    Ok(())
+}
+
+/// Formats a Unix time as `YYYY-MM-DDTHH:MM:SSZ`
+fn iso8601(unix: f64) -> String {
+    let (y, mo, d, h, mi, s) = civil(unix);
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z", y, mo, d, h, mi, s)
+}
+
+/// Formats a Unix time in ISO 8601 basic format (`YYYYMMDDTHHMMSSZ`), as
+/// iCalendar UTC date-times and file names use it
+pub fn iso8601_basic(unix: f64) -> String {
+    let (y, mo, d, h, mi, s) = civil(unix);
+    format!("{:04}{:02}{:02}T{:02}{:02}{:02}Z", y, mo, d, h, mi, s)
+}
+
+/// Splits a Unix time into UTC calendar fields (days-from-civil inverse)
+fn civil(unix: f64) -> (i64, u32, u32, u32, u32, u32) {
+    let secs = unix.floor() as i64;
+    let days = secs.div_euclid(86_400);
+    let rem = secs.rem_euclid(86_400);
+
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    (
+        year,
+        month,
+        day,
+        (rem / 3_600) as u32,
+        (rem % 3_600 / 60) as u32,
+        (rem % 60) as u32,
+    )
+}
@@ -0,0 +1,238 @@
+// Percentile estimation, exact or streaming (P² algorithm)
+//
+// The P² estimator (Jain & Chlamtac, 1985) keeps five markers per quantile and
+// adjusts their heights with a piecewise-parabolic fit as values arrive, so a
+// p95 or p99 can be tracked in constant memory while rows are streamed. Five
+// markers say little about a tail of a few rows, so the first values are kept
+// and answered exactly; the markers are seeded from them once enough arrive.
+
+/// Values kept and answered exactly before switching to P² markers
+pub const EXACT_SAMPLES: usize = 500;
+
+/// Percentiles reported alongside each metric
+pub const REPORTED: [f64; 2] = [0.95, 0.99];
+
+/// How percentiles are computed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Method {
+    Exact, // Sort a copy of the column; exact but holds every value
+    P2,    // Streaming P² markers; constant memory, approximate
+}
+
+impl Method {
+    /// Parses a `--quantiles` value
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "exact" => Some(Method::Exact),
+            "p2" | "streaming" => Some(Method::P2),
+            _ => None,
+        }
+    }
+}
+
+/// Streaming estimator of a single quantile
+#[derive(Debug, Clone)]
+pub struct P2 {
+    p: f64,              // Target quantile in [0, 1]
+    heights: [f64; 5],   // Marker heights
+    positions: [f64; 5], // Actual marker positions
+    desired: [f64; 5],   // Desired marker positions
+    step: [f64; 5],      // Desired position increment per value
+    count: usize,        // Values seen so far
+    exact: Vec<f64>,     // Values kept until the markers are seeded
+}
+
+impl P2 {
+    /// Creates an estimator for quantile `p`
+    pub fn new(p: f64) -> Self {
+        P2 {
+            p,
+            heights: [0.0; 5],
+            positions: [0.0; 5],
+            desired: [0.0; 5],
+            step: [0.0, p / 2.0, p, (1.0 + p) / 2.0, 1.0],
+            count: 0,
+            exact: Vec::new(),
+        }
+    }
+
+    /// Adds one observation
+    pub fn push(&mut self, x: f64) {
+        self.count += 1;
+        if self.count <= EXACT_SAMPLES {
+            self.exact.push(x);
+            if self.count == EXACT_SAMPLES {
+                self.seed();
+            }
+            return;
+        }
+        let (q, n) = (&mut self.heights, &mut self.positions);
+
+        // Cell the value falls into, widening the extremes if needed
+        let k = if x < q[0] {
+            q[0] = x;
+            0
+        } else if x >= q[4] {
+            q[4] = x;
+            3
+        } else {
+            (0..4).rfind(|&i| q[i] <= x).unwrap_or(0)
+        };
+        for pos in n.iter_mut().skip(k + 1) {
+            *pos += 1.0;
+        }
+        for (d, s) in self.desired.iter_mut().zip(self.step) {
+            *d += s;
+        }
+
+        // Move the three middle markers towards their desired positions
+        for i in 1..4 {
+            let offset = self.desired[i] - n[i];
+            if (offset >= 1.0 && n[i + 1] - n[i] > 1.0)
+                || (offset <= -1.0 && n[i - 1] - n[i] < -1.0)
+            {
+                let d = offset.signum();
+                let parabolic = q[i]
+                    + d / (n[i + 1] - n[i - 1])
+                        * ((n[i] - n[i - 1] + d) * (q[i + 1] - q[i]) / (n[i + 1] - n[i])
+                            + (n[i + 1] - n[i] - d) * (q[i] - q[i - 1]) / (n[i] - n[i - 1]));
+                q[i] = if q[i - 1] < parabolic && parabolic < q[i + 1] {
+                    parabolic
+                } else {
+                    let j = if d > 0.0 { i + 1 } else { i - 1 };
+                    q[i] + d * (q[j] - q[i]) / (n[j] - n[i])
+                };
+                n[i] += d;
+            }
+        }
+    }
+
+    /// Places the markers on the kept values and releases them
+    fn seed(&mut self) {
+        let mut sorted = std::mem::take(&mut self.exact);
+        sorted.sort_by(f64::total_cmp);
+        let len = sorted.len() as f64;
+        let mut previous = 0.0;
+        for i in 0..5 {
+            self.desired[i] = 1.0 + self.step[i] * (len - 1.0);
+            // Markers sit on distinct ranks, the outer two on the extremes
+            let rank = self.desired[i]
+                .round()
+                .max(previous + 1.0)
+                .min(len - 4.0 + i as f64);
+            self.positions[i] = rank;
+            self.heights[i] = sorted[rank as usize - 1];
+            previous = rank;
+        }
+    }
+
+    /// Current estimate; exact until [`EXACT_SAMPLES`] values have been seen
+    pub fn estimate(&self) -> f64 {
+        if self.count < EXACT_SAMPLES {
+            let mut seen = self.exact.clone();
+            seen.sort_by(f64::total_cmp);
+            return exact_sorted(&seen, self.p);
+        }
+        self.heights[2]
+    }
+}
+
+/// Linearly interpolated quantile of already sorted values (NaN when empty)
+fn exact_sorted(sorted: &[f64], p: f64) -> f64 {
+    if sorted.is_empty() {
+        return f64::NAN;
+    }
+    let rank = p.clamp(0.0, 1.0) * (sorted.len() - 1) as f64;
+    let (lo, hi) = (rank.floor() as usize, rank.ceil() as usize);
+    sorted[lo] + (sorted[hi] - sorted[lo]) * (rank - lo as f64)
+}
+
+/// Computes each quantile in `ps` of `values` with the chosen method
+pub fn quantiles(values: &[f64], ps: &[f64], method: Method) -> Vec<f64> {
+    match method {
+        Method::Exact => {
+            let mut sorted = values.to_vec();
+            sorted.sort_by(f64::total_cmp);
+            ps.iter().map(|&p| exact_sorted(&sorted, p)).collect()
+        }
+        Method::P2 => ps
+            .iter()
+            .map(|&p| {
+                let mut estimator = P2::new(p);
+                values.iter().for_each(|&v| estimator.push(v));
+                estimator.estimate()
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 1..=1000 in a scrambled but fixed order
+    fn scrambled() -> Vec<f64> {
+        (0..1000).map(|i| ((i * 7919) % 1000 + 1) as f64).collect()
+    }
+
+    #[test]
+    fn exact_quantiles_interpolate() {
+        let values = scrambled();
+        let exact = quantiles(&values, &[0.0, 0.5, 0.95, 1.0], Method::Exact);
+        assert_eq!(exact, [1.0, 500.5, 950.05, 1000.0]);
+        assert!(quantiles(&[], &[0.5], Method::Exact)[0].is_nan());
+    }
+
+    #[test]
+    fn p2_tracks_exact_quantiles() {
+        let values = scrambled();
+        let exact = quantiles(&values, &REPORTED, Method::Exact);
+        let p2 = quantiles(&values, &REPORTED, Method::P2);
+        for (e, p) in exact.iter().zip(&p2) {
+            assert!((e - p).abs() / e < 0.01, "exact {} vs P² {}", e, p);
+        }
+    }
+
+    #[test]
+    fn p2_is_exact_for_small_samples() {
+        let values = [5.0, 3.0, 1.0, 4.0, 2.0];
+        assert_eq!(quantiles(&values, &REPORTED, Method::P2), [4.8, 4.96]);
+        let values: Vec<f64> = scrambled().into_iter().take(EXACT_SAMPLES - 1).collect();
+        assert_eq!(
+            quantiles(&values, &REPORTED, Method::P2),
+            quantiles(&values, &REPORTED, Method::Exact)
+        );
+    }
+
+    #[test]
+    fn p2_seeds_markers_from_kept_values() {
+        let values: Vec<f64> = (1..=EXACT_SAMPLES).map(|v| v as f64).collect();
+        let exact = quantiles(&values, &REPORTED, Method::Exact);
+        let p2 = quantiles(&values, &REPORTED, Method::P2);
+        for (e, p) in exact.iter().zip(&p2) {
+            assert!((e - p).abs() <= 1.0, "exact {} vs P² {}", e, p);
+        }
+    }
+
+    #[test]
+    fn p2_is_exact_below_five_values() {
+        let mut estimator = P2::new(0.5);
+        assert!(estimator.estimate().is_nan());
+        for v in [3.0, 1.0, 2.0] {
+            estimator.push(v);
+        }
+        assert_eq!(estimator.estimate(), 2.0);
+        let p95 = quantiles(&[4.0, 1.0, 3.0, 2.0], &[0.95], Method::P2);
+        assert_eq!(
+            p95,
+            quantiles(&[4.0, 1.0, 3.0, 2.0], &[0.95], Method::Exact)
+        );
+    }
+
+    #[test]
+    fn method_names() {
+        assert_eq!(Method::parse("P2"), Some(Method::P2));
+        assert_eq!(Method::parse("exact"), Some(Method::Exact));
+        assert_eq!(Method::parse("median"), None);
+    }
+}
@@ -0,0 +1,78 @@
+// Throughput rates: bases and reads per second between consecutive batches
+//
+// A batch's count depends on how long it took, so the rate divides it by
+// the time since the previous batch. Batches after a gap or a basecall
+// server restart have no meaningful predecessor and get NaN.
+use crate::forensic;
+use crate::model::RunData;
+use crate::serverlog::EventKind;
+
+/// Bases per second and reads per second at every row; rows of one batch
+/// share its rates
+pub fn per_second(data: &RunData) -> (Vec<f64>, Vec<f64>) {
+    let mut bases = vec![f64::NAN; data.len()];
+    let mut reads = vec![f64::NAN; data.len()];
+    let limit = forensic::gap_limit(data);
+    let starts: Vec<f64> = data
+        .events
+        .iter()
+        .filter(|e| e.kind == EventKind::Start)
+        .map(|e| e.time)
+        .collect();
+
+    let mut previous: Option<f64> = None;
+    let mut row = 0;
+    while row < data.len() {
+        let time = data.time[row];
+        let end = row + data.time[row..].partition_point(|&t| t == time);
+        if let Some(before) = previous {
+            let elapsed = time - before;
+            let restarted = starts.iter().any(|&s| s > before && s <= time);
+            if elapsed > 0.0 && elapsed <= limit && !restarted {
+                let batch_bases: f64 = data.bases[row..end].iter().sum();
+                bases[row..end].fill(batch_bases / elapsed);
+                reads[row..end].fill((end - row) as f64 / elapsed);
+            }
+        }
+        previous = Some(time);
+        row = end;
+    }
+    (bases, reads)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::serverlog::LogEvent;
+
+    #[test]
+    fn rates_divide_each_batch_by_the_time_since_the_previous_one() {
+        let time = vec![0.0, 0.0, 10.0, 10.0, 10.0, 20.0, 500.0, 510.0, 520.0];
+        let data = RunData {
+            bases: vec![100.0, 100.0, 100.0, 200.0, 300.0, 50.0, 80.0, 90.0, 70.0],
+            batch_interval: time
+                .iter()
+                .scan(0.0, |before, &t| Some(t - std::mem::replace(before, t)))
+                .collect(),
+            events: vec![LogEvent {
+                time: 515.0,
+                last: 515.0,
+                count: 1,
+                kind: EventKind::Start,
+                seconds: None,
+                message: "Server started".into(),
+            }],
+            time,
+            ..Default::default()
+        };
+        let (bases, reads) = per_second(&data);
+        let nan = |v: &[f64]| v.iter().map(|x| x.is_nan()).collect::<Vec<_>>();
+        // First batch: no predecessor; 500 s: after a gap; 520 s: after a restart
+        let missing = [true, true, false, false, false, false, true, false, true];
+        assert_eq!(nan(&bases), missing);
+        assert_eq!(nan(&reads), missing);
+        assert_eq!(bases[2..6], [60.0, 60.0, 60.0, 5.0]);
+        assert_eq!(reads[2..6], [0.3, 0.3, 0.3, 0.1]);
+        assert_eq!((bases[7], reads[7]), (9.0, 0.1));
+    }
+}
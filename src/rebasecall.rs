@@ -0,0 +1,98 @@
+// Selection of reads worth re-basecalling with a higher-accuracy model
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+
+use crate::model::RunData;
+
+/// Number of time windows the run is split into when looking for poor stretches
+const WINDOWS: usize = 50;
+
+/// Reads selected for re-basecalling and why
+#[derive(Debug, Default)]
+pub struct Candidates {
+    pub windows: Vec<(f64, f64)>, // Time windows whose mean Q-score is below the threshold
+    pub channels: Vec<u32>,       // Channels whose mean Q-score is below the threshold
+    pub read_ids: Vec<String>,    // Reads in any poor window or channel
+}
+
+/// Finds time windows and channels with a mean Q-score below `min_qscore`
+pub fn find_candidates(data: &RunData, min_qscore: f64) -> Candidates {
+    let Some((start, end)) = data.time_range() else {
+        return Candidates::default();
+    };
+    let span = end - start;
+    let window_of = |t: f64| {
+        if span > 0.0 {
+            ((((t - start) / span) * WINDOWS as f64) as usize).min(WINDOWS - 1)
+        } else {
+            0
+        }
+    };
+
+    // Sum and count of Q-scores per window and per channel
+    let mut per_window = vec![(0.0, 0usize); WINDOWS];
+    let mut per_channel: HashMap<u32, (f64, usize)> = HashMap::new();
+    for ((&t, &q), &channel) in data.time.iter().zip(&data.mean_qscore).zip(&data.channel) {
+        let slot = &mut per_window[window_of(t)];
+        slot.0 += q;
+        slot.1 += 1;
+        let slot = per_channel.entry(channel).or_default();
+        slot.0 += q;
+        slot.1 += 1;
+    }
+    let poor = |&(sum, count): &(f64, usize)| count > 0 && sum / (count as f64) < min_qscore;
+
+    let poor_windows: Vec<usize> = (0..WINDOWS).filter(|&w| poor(&per_window[w])).collect();
+    let mut channels: Vec<u32> = per_channel
+        .iter()
+        .filter(|(_, acc)| poor(acc))
+        .map(|(&channel, _)| channel)
+        .collect();
+    channels.sort_unstable();
+
+    let read_ids = data
+        .read_id
+        .iter()
+        .zip(&data.time)
+        .zip(&data.channel)
+        .filter(|&((_, &t), channel)| {
+            poor_windows.contains(&window_of(t)) || channels.binary_search(channel).is_ok()
+        })
+        .map(|((id, _), _)| id.clone())
+        .collect();
+
+    let width = span / WINDOWS as f64;
+    Candidates {
+        windows: poor_windows
+            .iter()
+            .map(|&w| (start + w as f64 * width, start + (w + 1) as f64 * width))
+            .collect(),
+        channels,
+        read_ids,
+    }
+}
+
+/// Writes one read ID per line, as accepted by `pod5 filter --ids` and `samtools view -N`
+pub fn write_read_ids(path: &str, read_ids: &[String]) -> Result<(), Box<dyn Error>> {
+    let mut out = read_ids.join("\n");
+    out.push('\n');
+    fs::write(path, out)?;
+    Ok(())
+}
+
+/// Writes a shell script that subsets the POD5 files and re-basecalls them
+pub fn write_commands(path: &str, read_ids_path: &str) -> Result<(), Box<dyn Error>> {
+    let script = format!(
+        "#!/bin/sh\n\
+         # Re-basecall reads from poor-quality windows/channels with a higher-accuracy model\n\
+         # Usage: {path} <pod5_dir> <model>   (e.g. sup)\n\
+         set -eu\n\
+         pod5 filter \"$1\" --ids '{ids}' --output rebasecall.pod5 --missing-ok\n\
+         dorado basecaller \"${{2:-sup}}\" rebasecall.pod5 > rebasecall.bam\n",
+        path = path,
+        ids = read_ids_path.replace('\'', "'\\''"),
+    );
+    fs::write(path, script)?;
+    Ok(())
+}
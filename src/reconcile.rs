@@ -0,0 +1,474 @@
+// Reconciliation against MinKNOW's own run report
+//
+// MinKNOW writes a JSON report next to its PDF one. Each acquisition's
+// `yield_summary` holds the headline totals; those of all acquisitions are
+// summed and compared with the totals computed from the batch log. MinKNOW
+// writes 64-bit counts as JSON strings, so numbers are read from either form.
+// Only what this needs of JSON is parsed, into `Json`.
+use std::error::Error;
+
+use crate::model::RunData;
+use crate::tombstone::TombstoneReport;
+
+/// Relative difference, in percent, beyond which a metric is flagged
+pub const DEFAULT_TOLERANCE: f64 = 1.0;
+
+/// Yield summary keys of each headline metric, summed when several are present
+const METRICS: [(&str, &[&str]); 3] = [
+    (
+        "Reads",
+        &["basecalled_pass_read_count", "basecalled_fail_read_count"],
+    ),
+    ("Bases", &["basecalled_pass_bases", "basecalled_fail_bases"]),
+    ("Samples", &["basecalled_samples"]),
+];
+/// Yield summary keys of the metrics split by the pass Q-score
+const PASS_METRICS: [(&str, &str); 2] = [
+    ("Pass reads", "basecalled_pass_read_count"),
+    ("Pass bases", "basecalled_pass_bases"),
+];
+
+/// One headline metric as MinKNOW and this tool count it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Line {
+    pub metric: &'static str, // Metric name
+    pub minknow: f64,         // Total in MinKNOW's report
+    pub computed: f64,        // Total computed from the batch log
+}
+
+impl Line {
+    /// Difference relative to MinKNOW's total, in percent
+    pub fn difference(&self) -> f64 {
+        (self.computed - self.minknow) / self.minknow.abs().max(f64::EPSILON) * 100.0
+    }
+
+    /// Whether the totals agree within `tolerance` percent
+    pub fn agrees(&self, tolerance: f64) -> bool {
+        self.difference().abs() <= tolerance
+    }
+}
+
+/// Compares the report's headline totals with those of `data`; with a
+/// `pass_qscore`, reads at or above it are compared with MinKNOW's pass reads
+pub fn reconcile(
+    path: &str,
+    data: &RunData,
+    pass_qscore: Option<f64>,
+) -> Result<Vec<Line>, Box<dyn Error>> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| format!("Cannot read MinKNOW report {}: {}", path, e))?;
+    let report = Json::parse(&text).map_err(|e| format!("{}: {}", path, e))?;
+    let mut summaries = Vec::new();
+    report.find_all("yield_summary", &mut summaries);
+    if summaries.is_empty() {
+        return Err(format!("{} has no yield_summary (is it a MinKNOW report?)", path).into());
+    }
+    // `None` when no acquisition reports the key
+    let total = |key: &str| -> Option<f64> {
+        let values: Vec<f64> = summaries
+            .iter()
+            .filter_map(|s| s.get(key)?.number())
+            .collect();
+        (!values.is_empty()).then(|| values.iter().sum())
+    };
+
+    let bases: f64 = data.bases.iter().sum();
+    let samples: f64 = data.samples.iter().sum();
+    let computed = [data.len() as f64, bases, samples];
+    let mut lines: Vec<Line> = METRICS
+        .iter()
+        .zip(computed)
+        .filter_map(|(&(metric, keys), computed)| {
+            let found: Vec<f64> = keys.iter().filter_map(|k| total(k)).collect();
+            (!found.is_empty()).then(|| Line {
+                metric,
+                minknow: found.iter().sum(),
+                computed,
+            })
+        })
+        .collect();
+    if let Some(q) = pass_qscore {
+        let pass: Vec<usize> = (0..data.len())
+            .filter(|&i| data.mean_qscore[i] >= q)
+            .collect();
+        let computed = [pass.len() as f64, pass.iter().map(|&i| data.bases[i]).sum()];
+        for (&(metric, key), computed) in PASS_METRICS.iter().zip(computed) {
+            if let Some(minknow) = total(key) {
+                lines.push(Line {
+                    metric,
+                    minknow,
+                    computed,
+                });
+            }
+        }
+    }
+    if lines.is_empty() {
+        return Err(format!("{} has no yield totals to reconcile", path).into());
+    }
+    Ok(lines)
+}
+
+/// Prints the reconciliation table; tombstoned rows are named, since
+/// MinKNOW may or may not have counted them
+pub fn print_report(lines: &[Line], tolerance: f64, tombstones: Option<&TombstoneReport>) {
+    println!(
+        "Reconciliation with MinKNOW's report (tolerance {}%):",
+        tolerance
+    );
+    println!(
+        "  {:<12} {:>16} {:>16} {:>9}",
+        "Metric", "MinKNOW", "Computed", "Diff"
+    );
+    for line in lines {
+        println!(
+            "  {:<12} {:>16.0} {:>16.0} {:>8.2}%  {}",
+            line.metric,
+            line.minknow,
+            line.computed,
+            line.difference(),
+            if line.agrees(tolerance) {
+                "OK"
+            } else {
+                "MISMATCH"
+            }
+        );
+    }
+    if let Some(report) = tombstones {
+        println!(
+            "  Computed totals leave out tombstones: {}",
+            report.describe()
+        );
+    }
+}
+
+/// Writes the table as CSV, one row per metric
+pub fn write_csv(lines: &[Line], tolerance: f64, path: &str) -> Result<(), Box<dyn Error>> {
+    let mut writer = csv::Writer::from_path(path)?;
+    writer.write_record(["metric", "minknow", "computed", "difference_pct", "agrees"])?;
+    for line in lines {
+        writer.write_record([
+            line.metric,
+            &line.minknow.to_string(),
+            &line.computed.to_string(),
+            &format!("{:.4}", line.difference()),
+            &line.agrees(tolerance).to_string(),
+        ])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// A parsed JSON value
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    /// Parses a JSON document
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut parser = JsonParser {
+            bytes: text.as_bytes(),
+            at: 0,
+        };
+        let value = parser.value()?;
+        parser.skip_space();
+        if parser.at != parser.bytes.len() {
+            return Err(parser.error("trailing characters"));
+        }
+        Ok(value)
+    }
+
+    /// Member `key` of an object
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(members) => members.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    /// The value as a number, also from a numeric string
+    pub fn number(&self) -> Option<f64> {
+        match self {
+            Json::Number(n) => Some(*n),
+            Json::String(s) => s.trim().parse().ok(),
+            _ => None,
+        }
+    }
+
+    /// Collects every member named `key`, at any depth, in document order
+    pub fn find_all<'a>(&'a self, key: &str, found: &mut Vec<&'a Json>) {
+        match self {
+            Json::Object(members) => {
+                for (k, v) in members {
+                    if k == key {
+                        found.push(v);
+                    } else {
+                        v.find_all(key, found);
+                    }
+                }
+            }
+            Json::Array(items) => items.iter().for_each(|v| v.find_all(key, found)),
+            _ => {}
+        }
+    }
+}
+
+/// Recursive-descent JSON parser over the document's bytes
+struct JsonParser<'a> {
+    bytes: &'a [u8],
+    at: usize,
+}
+
+impl JsonParser<'_> {
+    fn error(&self, what: &str) -> String {
+        format!("invalid JSON at byte {}: {}", self.at, what)
+    }
+
+    fn skip_space(&mut self) {
+        while self.bytes.get(self.at).is_some_and(u8::is_ascii_whitespace) {
+            self.at += 1;
+        }
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), String> {
+        self.skip_space();
+        if self.bytes.get(self.at) != Some(&byte) {
+            return Err(self.error(&format!("expected '{}'", byte as char)));
+        }
+        self.at += 1;
+        Ok(())
+    }
+
+    fn value(&mut self) -> Result<Json, String> {
+        self.skip_space();
+        match self.bytes.get(self.at) {
+            Some(b'{') => self.object(),
+            Some(b'[') => self.array(),
+            Some(b'"') => self.string().map(Json::String),
+            Some(b't') => self.literal("true", Json::Bool(true)),
+            Some(b'f') => self.literal("false", Json::Bool(false)),
+            Some(b'n') => self.literal("null", Json::Null),
+            Some(_) => self.number(),
+            None => Err(self.error("unexpected end")),
+        }
+    }
+
+    fn literal(&mut self, word: &str, value: Json) -> Result<Json, String> {
+        if !self.bytes[self.at..].starts_with(word.as_bytes()) {
+            return Err(self.error("unknown literal"));
+        }
+        self.at += word.len();
+        Ok(value)
+    }
+
+    fn number(&mut self) -> Result<Json, String> {
+        let start = self.at;
+        while self
+            .bytes
+            .get(self.at)
+            .is_some_and(|b| b.is_ascii_digit() || b"+-.eE".contains(b))
+        {
+            self.at += 1;
+        }
+        std::str::from_utf8(&self.bytes[start..self.at])
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .map(Json::Number)
+            .ok_or_else(|| self.error("invalid number"))
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.expect(b'"')?;
+        let mut out = Vec::new();
+        loop {
+            let byte = *self
+                .bytes
+                .get(self.at)
+                .ok_or_else(|| self.error("unterminated string"))?;
+            self.at += 1;
+            match byte {
+                b'"' => break,
+                b'\\' => {
+                    let escaped = *self
+                        .bytes
+                        .get(self.at)
+                        .ok_or_else(|| self.error("unterminated string"))?;
+                    self.at += 1;
+                    match escaped {
+                        b'n' => out.push(b'\n'),
+                        b't' => out.push(b'\t'),
+                        b'r' => out.push(b'\r'),
+                        b'b' => out.push(0x08),
+                        b'f' => out.push(0x0c),
+                        b'u' => {
+                            let code = self
+                                .bytes
+                                .get(self.at..self.at + 4)
+                                .and_then(|h| std::str::from_utf8(h).ok())
+                                .and_then(|h| u32::from_str_radix(h, 16).ok())
+                                .ok_or_else(|| self.error("invalid \\u escape"))?;
+                            self.at += 4;
+                            // Surrogate pairs are not needed for any key read here
+                            let c = char::from_u32(code).unwrap_or('\u{fffd}');
+                            out.extend_from_slice(c.to_string().as_bytes());
+                        }
+                        other => out.push(other),
+                    }
+                }
+                _ => out.push(byte),
+            }
+        }
+        String::from_utf8(out).map_err(|_| self.error("invalid UTF-8"))
+    }
+
+    fn array(&mut self) -> Result<Json, String> {
+        self.expect(b'[')?;
+        let mut items = Vec::new();
+        self.skip_space();
+        if self.bytes.get(self.at) == Some(&b']') {
+            self.at += 1;
+            return Ok(Json::Array(items));
+        }
+        loop {
+            items.push(self.value()?);
+            self.skip_space();
+            match self.bytes.get(self.at) {
+                Some(b',') => self.at += 1,
+                Some(b']') => {
+                    self.at += 1;
+                    return Ok(Json::Array(items));
+                }
+                _ => return Err(self.error("expected ',' or ']'")),
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<Json, String> {
+        self.expect(b'{')?;
+        let mut members = Vec::new();
+        self.skip_space();
+        if self.bytes.get(self.at) == Some(&b'}') {
+            self.at += 1;
+            return Ok(Json::Object(members));
+        }
+        loop {
+            self.skip_space();
+            let key = self.string()?;
+            self.expect(b':')?;
+            members.push((key, self.value()?));
+            self.skip_space();
+            match self.bytes.get(self.at) {
+                Some(b',') => self.at += 1,
+                Some(b'}') => {
+                    self.at += 1;
+                    return Ok(Json::Object(members));
+                }
+                _ => return Err(self.error("expected ',' or '}'")),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REPORT: &str = r#"{
+        "protocol_run_info": {"device": "MN12345"},
+        "acquisitions": [
+            {"acquisition_run_info": {"yield_summary": {
+                "basecalled_pass_read_count": "2", "basecalled_fail_read_count": "1",
+                "basecalled_pass_bases": "3000", "basecalled_fail_bases": 400,
+                "basecalled_samples": 1.2e4
+            }}},
+            {"acquisition_run_info": {"yield_summary": {"basecalled_pass_read_count": "1"}}}
+        ]
+    }"#;
+
+    fn run() -> RunData {
+        RunData {
+            time: vec![1.0, 2.0, 3.0, 4.0],
+            bases: vec![1000.0, 2000.0, 400.0, 600.0],
+            samples: vec![3000.0, 3000.0, 3000.0, 3000.0],
+            mean_qscore: vec![12.0, 11.0, 6.0, 10.0],
+            ..Default::default()
+        }
+    }
+
+    fn report_file(name: &str, contents: &str) -> String {
+        let path = std::env::temp_dir().join(format!("{}-{}.json", name, std::process::id()));
+        std::fs::write(&path, contents).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn sums_yield_summaries_of_every_acquisition() {
+        let path = report_file("reconcile-report", REPORT);
+        let lines = reconcile(&path, &run(), Some(10.0)).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let totals: Vec<(&str, f64, f64)> = lines
+            .iter()
+            .map(|l| (l.metric, l.minknow, l.computed))
+            .collect();
+        assert_eq!(
+            totals,
+            [
+                ("Reads", 4.0, 4.0),
+                ("Bases", 3400.0, 4000.0),
+                ("Samples", 12000.0, 12000.0),
+                ("Pass reads", 3.0, 3.0),
+                ("Pass bases", 3000.0, 3600.0),
+            ]
+        );
+        assert!(lines[0].agrees(DEFAULT_TOLERANCE));
+        assert!(!lines[1].agrees(DEFAULT_TOLERANCE));
+    }
+
+    #[test]
+    fn reports_without_totals_are_errors() {
+        let path = report_file("reconcile-empty", r#"{"yield_summary": {}}"#);
+        assert!(reconcile(&path, &run(), None).is_err());
+        std::fs::write(&path, r#"{"acquisitions": []}"#).unwrap();
+        assert!(reconcile(&path, &run(), None).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn differences_are_relative_to_minknow() {
+        let line = Line {
+            metric: "Bases",
+            minknow: 200.0,
+            computed: 202.0,
+        };
+        assert_eq!(line.difference(), 1.0);
+        assert!(line.agrees(1.0) && !line.agrees(0.5));
+    }
+
+    #[test]
+    fn parses_json() {
+        let json =
+            Json::parse(r#" {"a": [1, -2.5e1, true, null], "b": "x\"é\n", "c": {}} "#).unwrap();
+        assert_eq!(
+            json.get("a"),
+            Some(&Json::Array(vec![
+                Json::Number(1.0),
+                Json::Number(-25.0),
+                Json::Bool(true),
+                Json::Null
+            ]))
+        );
+        assert_eq!(json.get("b"), Some(&Json::String("x\"é\n".into())));
+        assert_eq!(json.get("c"), Some(&Json::Object(Vec::new())));
+        assert_eq!(Json::String(" 42 ".into()).number(), Some(42.0));
+        assert_eq!(Json::Bool(true).number(), None);
+        for invalid in ["", "{", r#"{"a" 1}"#, "[1,]", "tru", r#""open"#, "1 2"] {
+            assert!(Json::parse(invalid).is_err(), "{}", invalid);
+        }
+    }
+}
@@ -0,0 +1,19 @@
+// Redaction of sample identifiers, paths and hostnames from shareable outputs
+use sha2::{Digest, Sha256};
+
+/// Stable pseudonym for an input: same input, same label, nothing recoverable
+pub fn pseudonym(input: &str) -> String {
+    let hash = Sha256::digest(input.as_bytes());
+    let short: String = hash[..4].iter().map(|b| format!("{:02x}", b)).collect();
+    format!("run-{}", short)
+}
+
+/// Last path segment only, dropping directories, URL schemes and hosts
+pub fn file_name(path: &str) -> String {
+    path.rsplit(['/', '\\']).next().unwrap_or(path).to_string()
+}
+
+/// Generic labels in rank order, replacing barcode names that may be sample aliases
+pub fn sample_label(rank: usize) -> String {
+    format!("sample-{:02}", rank + 1)
+}
@@ -0,0 +1,79 @@
+// Fused, chunked per-column reductions
+//
+// The reduction keeps `LANES` independent accumulators and walks the column in
+// fixed-size chunks, which removes the loop-carried dependency of a plain fold
+// and lets the compiler vectorise the inner loop. Sum, min and max are
+// gathered in the same pass so each column is read from memory only once.
+
+/// Number of independent accumulators (one AVX-512 or two AVX2 registers of f64)
+const LANES: usize = 8;
+
+/// Aggregate statistics of one column
+#[derive(Debug, Clone, Copy)]
+pub struct ColumnSummary {
+    pub sum: f64,  // Total of all values
+    pub mean: f64, // Arithmetic mean (NaN for an empty column)
+    pub min: f64,  // Smallest value (infinity for an empty column)
+    pub max: f64,  // Largest value (negative infinity for an empty column)
+}
+
+/// Computes mean, min and max of a column in a single pass
+pub fn summarize(values: &[f64]) -> ColumnSummary {
+    let mut sum = [0.0; LANES];
+    let mut min = [f64::INFINITY; LANES];
+    let mut max = [f64::NEG_INFINITY; LANES];
+
+    let chunks = values.chunks_exact(LANES);
+    let tail = chunks.remainder();
+    for chunk in chunks {
+        for lane in 0..LANES {
+            sum[lane] += chunk[lane];
+            min[lane] = min[lane].min(chunk[lane]);
+            max[lane] = max[lane].max(chunk[lane]);
+        }
+    }
+
+    // Fold the lanes and the leftover values together
+    let total = sum.iter().sum::<f64>() + tail.iter().sum::<f64>();
+    ColumnSummary {
+        sum: total,
+        mean: total / values.len() as f64,
+        min: min
+            .iter()
+            .chain(tail)
+            .copied()
+            .fold(f64::INFINITY, f64::min),
+        max: max
+            .iter()
+            .chain(tail)
+            .copied()
+            .fold(f64::NEG_INFINITY, f64::max),
+    }
+}
+
+/// Running total of `values`, at every row
+pub fn cumulative_sum(values: &[f64]) -> Vec<f64> {
+    values
+        .iter()
+        .scan(0.0, |sum, &v| {
+            *sum += v;
+            Some(*sum)
+        })
+        .collect()
+}
+
+/// Mean of the last `window` values (fewer at the start), at every row
+pub fn rolling_mean(values: &[f64], window: usize) -> Vec<f64> {
+    let mut in_window = 0.0;
+    values
+        .iter()
+        .enumerate()
+        .map(|(i, &v)| {
+            in_window += v;
+            if i >= window {
+                in_window -= values[i - window];
+            }
+            in_window / (i + 1).min(window) as f64
+        })
+        .collect()
+}
@@ -0,0 +1,223 @@
+// Remote (HTTP(S), S3 and SSH) inputs, streamed into the parser while downloading
+//
+// SSH inputs can also be followed as they grow (see `read_ssh_from`).
+use std::error::Error;
+use std::io::Read;
+use std::process::{Child, ChildStdout, Command, Stdio};
+
+/// URL schemes handled by `open`
+const SCHEMES: &[&str] = &["http://", "https://", "s3://", "ssh://"];
+
+/// Whether the input names a remote object rather than a local path
+pub fn is_remote(input: &str) -> bool {
+    SCHEMES.iter().any(|scheme| input.starts_with(scheme))
+}
+
+/// Opens a remote input as a reader.
+///
+/// `ssh://` inputs are read through the system `ssh` client and need no
+/// feature; the others are HTTP downloads (see `open_http`).
+pub fn open(input: &str) -> Result<Box<dyn Read + Send>, Box<dyn Error>> {
+    match input.strip_prefix("ssh://") {
+        Some(location) => open_ssh(location),
+        None => open_http(input),
+    }
+}
+
+/// Reads a file on another host by running `cat` over `ssh`.
+///
+/// Accepts `host:/path` (scp style, as in `ssh://gridion01:/data/run.csv`)
+/// and `host/path`. `ssh` runs in batch mode, so key-based authentication
+/// (or an agent) must be set up; a password prompt would fail instead.
+fn open_ssh(location: &str) -> Result<Box<dyn Read + Send>, Box<dyn Error>> {
+    let (host, path) = ssh_location(location)?;
+    let mut child = ssh(host, &format!("cat -- {}", shell_quote(path)))
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Cannot run ssh: {}", e))?;
+    let stdout = child.stdout.take().ok_or("ssh has no stdout")?;
+    Ok(Box::new(CommandReader { child, stdout }))
+}
+
+/// Whether the input is a file on another host, read over `ssh`
+pub fn is_ssh(input: &str) -> bool {
+    input.starts_with("ssh://")
+}
+
+/// Size of a file on another host and its bytes from `offset` on, for
+/// following it as it grows; all of its bytes when it is now smaller than
+/// `offset` (rotated or rewritten). Runs one `ssh` command per call.
+pub fn read_ssh_from(input: &str, offset: u64) -> Result<(u64, Vec<u8>), Box<dyn Error>> {
+    let location = input
+        .strip_prefix("ssh://")
+        .ok_or_else(|| format!("Not an ssh:// input: {}", input))?;
+    let (host, path) = ssh_location(location)?;
+    // The size comes first, on a line of its own; `tail -c +N` starts at byte N (1-based)
+    let script = format!(
+        "f={}; size=$(wc -c < \"$f\") || exit 1; echo $size; \
+         if [ \"$size\" -lt {offset} ]; then cat -- \"$f\"; else tail -c +{} -- \"$f\"; fi",
+        shell_quote(path),
+        offset + 1
+    );
+    let output = ssh(host, &script)
+        .stdin(Stdio::null())
+        .stderr(Stdio::inherit())
+        .output()
+        .map_err(|e| format!("Cannot run ssh: {}", e))?;
+    if !output.status.success() {
+        return Err(format!("ssh {} exited with {}", host, output.status).into());
+    }
+    let newline = output.stdout.iter().position(|&b| b == b'\n');
+    let size = newline
+        .and_then(|at| std::str::from_utf8(&output.stdout[..at]).ok())
+        .and_then(|size| size.trim().parse().ok())
+        .ok_or_else(|| format!("Cannot read the size of {} over ssh", input))?;
+    let start = newline.map_or(0, |at| at + 1);
+    Ok((size, output.stdout[start..].to_vec()))
+}
+
+/// `ssh` running `command` on `host` in batch mode
+fn ssh(host: &str, command: &str) -> Command {
+    let mut ssh = Command::new("ssh");
+    // `--` ends ssh's options, so the host cannot add any
+    ssh.args(["-o", "BatchMode=yes", "--", host, command]);
+    ssh
+}
+
+/// Splits an SSH location into host and remote path
+fn ssh_location(location: &str) -> Result<(&str, &str), Box<dyn Error>> {
+    let split = match location.find(":/") {
+        Some(colon) => Some((&location[..colon], &location[colon + 1..])),
+        None => location.find('/').map(|slash| location.split_at(slash)),
+    };
+    match split {
+        Some((host, _)) if host.starts_with('-') => {
+            Err(format!("Invalid ssh host '{}': hosts cannot start with '-'", host).into())
+        }
+        Some((host, path)) if !host.is_empty() && path.len() > 1 => Ok((host, path)),
+        _ => Err(format!("Expected ssh://host:/path, got ssh://{}", location).into()),
+    }
+}
+
+/// Single-quotes a path for the remote shell
+fn shell_quote(path: &str) -> String {
+    format!("'{}'", path.replace('\'', "'\\''"))
+}
+
+/// Reader over a child's stdout that reports a failed exit at end of input
+struct CommandReader {
+    child: Child,
+    stdout: ChildStdout,
+}
+
+impl Read for CommandReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.stdout.read(buf)?;
+        if n == 0 && !buf.is_empty() {
+            // Truncated output must not pass for a complete file
+            let status = self.child.wait()?;
+            if !status.success() {
+                return Err(std::io::Error::other(format!("ssh exited with {}", status)));
+            }
+        }
+        Ok(n)
+    }
+}
+
+/// Opens an HTTP(S) or S3 input as a reader.
+///
+/// The download runs on its own thread and hands chunks to the returned reader
+/// through a bounded channel, so parsing starts with the first chunk instead
+/// of after the whole file has arrived.
+#[cfg(feature = "remote")]
+fn open_http(input: &str) -> Result<Box<dyn Read + Send>, Box<dyn Error>> {
+    use std::sync::mpsc;
+
+    /// Size of each chunk passed from the download thread to the parser
+    const CHUNK_SIZE: usize = 1 << 16;
+    /// Chunks buffered ahead of the parser before the download waits
+    const CHUNKS_AHEAD: usize = 16;
+
+    let url = http_url(input)?;
+    let mut body = ureq::get(&url).call()?.into_reader();
+
+    let (tx, rx) = mpsc::sync_channel(CHUNKS_AHEAD);
+    std::thread::spawn(move || {
+        loop {
+            let mut chunk = vec![0u8; CHUNK_SIZE];
+            let chunk = match body.read(&mut chunk) {
+                Ok(0) => break,
+                Ok(n) => {
+                    chunk.truncate(n);
+                    Ok(chunk)
+                }
+                Err(e) => Err(e),
+            };
+            let failed = chunk.is_err();
+            // The parser hung up (e.g. on a CSV error); stop downloading
+            if tx.send(chunk).is_err() || failed {
+                break;
+            }
+        }
+    });
+
+    Ok(Box::new(ChannelReader {
+        rx,
+        chunk: Vec::new(),
+        pos: 0,
+    }))
+}
+
+/// Stand-in used when the crate is built without the `remote` feature
+#[cfg(not(feature = "remote"))]
+fn open_http(input: &str) -> Result<Box<dyn Read + Send>, Box<dyn Error>> {
+    Err(format!("Cannot read {}: built without the `remote` feature", input).into())
+}
+
+/// Maps `s3://bucket/key` to its HTTPS endpoint and passes HTTP(S) URLs through.
+///
+/// The endpoint defaults to AWS virtual-hosted style; setting `S3_ENDPOINT`
+/// (e.g. a facility MinIO) switches to path style `{endpoint}/{bucket}/{key}`.
+/// Private objects need a presigned HTTPS URL instead.
+#[cfg(feature = "remote")]
+fn http_url(input: &str) -> Result<String, Box<dyn Error>> {
+    let Some(path) = input.strip_prefix("s3://") else {
+        return Ok(input.to_string());
+    };
+    let (bucket, key) = path
+        .split_once('/')
+        .ok_or_else(|| format!("S3 URL without an object key: {}", input))?;
+    Ok(match std::env::var("S3_ENDPOINT") {
+        Ok(endpoint) => format!("{}/{}/{}", endpoint.trim_end_matches('/'), bucket, key),
+        Err(_) => format!("https://{}.s3.amazonaws.com/{}", bucket, key),
+    })
+}
+
+/// Reader over the chunks received from the download thread
+#[cfg(feature = "remote")]
+struct ChannelReader {
+    rx: std::sync::mpsc::Receiver<std::io::Result<Vec<u8>>>,
+    chunk: Vec<u8>, // Chunk currently being consumed
+    pos: usize,     // Read position within `chunk`
+}
+
+#[cfg(feature = "remote")]
+impl Read for ChannelReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while self.pos == self.chunk.len() {
+            match self.rx.recv() {
+                Ok(chunk) => {
+                    self.chunk = chunk?;
+                    self.pos = 0;
+                }
+                // Sender gone: the download finished
+                Err(_) => return Ok(0),
+            }
+        }
+        let n = buf.len().min(self.chunk.len() - self.pos);
+        buf[..n].copy_from_slice(&self.chunk[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
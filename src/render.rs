@@ -0,0 +1,135 @@
+// Backend-independent rendering of figures
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use std::error::Error;
+use std::path::Path;
+
+/// A chart that knows how to draw itself onto any plotters drawing area.
+///
+/// Analysis code describes *what* to draw by implementing this trait; the
+/// `Renderer` decides *where* (bitmap file, SVG file, memory buffer), so a new
+/// output backend only needs a new `Renderer`.
+pub trait Figure {
+    /// Canvas size in pixels
+    fn size(&self) -> (u32, u32);
+
+    /// Draws the whole figure, background included, onto `root`
+    fn draw<DB: DrawingBackend>(&self, root: &DrawingArea<DB, Shift>) -> Result<(), Box<dyn Error>>
+    where
+        DB::ErrorType: 'static;
+
+    /// Draws onto a raster area; figures that can render faster on pixels override this
+    fn draw_raster<DB: DrawingBackend>(
+        &self,
+        root: &DrawingArea<DB, Shift>,
+    ) -> Result<(), Box<dyn Error>>
+    where
+        DB::ErrorType: 'static,
+    {
+        self.draw(root)
+    }
+}
+
+/// An output backend that turns a `Figure` into a file
+pub trait Renderer {
+    /// Renders `figure` and writes it to `path`
+    fn render<F: Figure>(&self, figure: &F, path: &str) -> Result<(), Box<dyn Error>>;
+}
+
+/// Raster output (PNG, or any other format the bitmap backend infers from the extension)
+pub struct Bitmap;
+
+impl Renderer for Bitmap {
+    fn render<F: Figure>(&self, figure: &F, path: &str) -> Result<(), Box<dyn Error>> {
+        let root = BitMapBackend::new(path, figure.size()).into_drawing_area();
+        figure.draw_raster(&root)?;
+        root.present()?;
+        Ok(())
+    }
+}
+
+/// Vector output as SVG
+pub struct Svg;
+
+impl Renderer for Svg {
+    fn render<F: Figure>(&self, figure: &F, path: &str) -> Result<(), Box<dyn Error>> {
+        let root = SVGBackend::new(path, figure.size()).into_drawing_area();
+        figure.draw(&root)?;
+        root.present()?;
+        Ok(())
+    }
+}
+
+/// Output file format
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Png, // Raster
+    Svg, // Vector
+}
+
+impl Format {
+    /// Parses a `--format` value
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "png" => Some(Format::Png),
+            "svg" => Some(Format::Svg),
+            _ => None,
+        }
+    }
+
+    /// Format implied by the extension of `path`; anything but `.svg` is a bitmap
+    pub fn from_path(path: &str) -> Self {
+        if path.to_ascii_lowercase().ends_with(".svg") {
+            Format::Svg
+        } else {
+            Format::Png
+        }
+    }
+
+    /// File extension, without the dot
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Png => "png",
+            Format::Svg => "svg",
+        }
+    }
+
+    /// Adds this format's extension to an output path without one, and
+    /// rejects a path whose extension names another format
+    pub fn apply(self, path: &str) -> Result<String, Box<dyn Error>> {
+        match Path::new(path).extension().and_then(|e| e.to_str()) {
+            None => Ok(format!("{}.{}", path, self.extension())),
+            Some(ext) if ext.eq_ignore_ascii_case(self.extension()) => Ok(path.to_string()),
+            Some(ext) => Err(format!(
+                "Output {} has extension .{} but --format is {}",
+                path,
+                ext,
+                self.extension()
+            )
+            .into()),
+        }
+    }
+}
+
+/// Renders `figure` with the backend matching the extension of `path` (`.svg` or bitmap).
+///
+/// Validate inputs before calling: plotters backends write their file when
+/// dropped, so a figure failing mid-draw still leaves a partial image behind.
+pub fn to_file<F: Figure>(figure: &F, path: &str) -> Result<(), Box<dyn Error>> {
+    match Format::from_path(path) {
+        Format::Svg => Svg.render(figure, path),
+        Format::Png => Bitmap.render(figure, path),
+    }
+}
+
+/// Renders `figure` into an RGB buffer of `figure.size()` pixels
+pub fn to_rgb<F: Figure>(figure: &F) -> Result<Vec<u8>, Box<dyn Error>> {
+    let (width, height) = figure.size();
+    let mut buffer = vec![0u8; (width * height * 3) as usize];
+    {
+        let root = BitMapBackend::with_buffer(&mut buffer, (width, height)).into_drawing_area();
+        figure.draw_raster(&root)?;
+        root.present()?;
+    }
+    Ok(buffer)
+}
@@ -0,0 +1,145 @@
+// Retention policy for the timestamped snapshots written in watch mode
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+/// Calendar period of which one snapshot is kept
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Period {
+    Hour,
+    Day,
+}
+
+impl Period {
+    /// Parses a `--keep-per` value
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "hour" | "hourly" => Some(Period::Hour),
+            "day" | "daily" => Some(Period::Day),
+            _ => None,
+        }
+    }
+
+    /// Length of the `YYYYMMDDTHH` / `YYYYMMDD` prefix shared within a period
+    fn prefix_len(self) -> usize {
+        match self {
+            Period::Hour => 11,
+            Period::Day => 8,
+        }
+    }
+}
+
+/// Which snapshots survive pruning; with neither limit set, all do. The
+/// newest snapshot is always kept, even with `keep_last` 0 or unset.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Retention {
+    pub keep_last: Option<usize>, // Newest snapshots always kept (at least one)
+    pub per: Option<Period>,      // Additionally keep the first snapshot of each period
+}
+
+impl Retention {
+    /// Names to delete from a list of `YYYYMMDDTHHMMSSZ` snapshot names
+    fn expired<'a>(&self, names: &[&'a str]) -> Vec<&'a str> {
+        if self.keep_last.is_none() && self.per.is_none() {
+            return Vec::new();
+        }
+        let mut sorted = names.to_vec();
+        sorted.sort_unstable();
+        let recent = sorted
+            .len()
+            .saturating_sub(self.keep_last.unwrap_or(0).max(1));
+
+        sorted
+            .iter()
+            .enumerate()
+            .filter(|&(i, name)| {
+                let first_of_period = self.per.is_some_and(|p| {
+                    i == 0 || sorted[i - 1][..p.prefix_len()] != name[..p.prefix_len()]
+                });
+                i < recent && !first_of_period
+            })
+            .map(|(_, name)| *name)
+            .collect()
+    }
+}
+
+/// Whether a directory name looks like a snapshot time (`YYYYMMDDTHHMMSSZ`)
+fn is_snapshot_name(name: &str) -> bool {
+    name.len() == 16
+        && name.ends_with('Z')
+        && name.as_bytes()[8] == b'T'
+        && name[..8]
+            .bytes()
+            .chain(name[9..15].bytes())
+            .all(|b| b.is_ascii_digit())
+}
+
+/// Deletes the snapshot folders in `dir` the policy no longer keeps.
+///
+/// Only folders named like a snapshot are considered, so anything else an
+/// operator stores next to them is left alone. Returns how many were removed.
+pub fn prune(dir: &Path, policy: &Retention) -> Result<usize, Box<dyn Error>> {
+    let mut names = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir()
+            && let Some(name) = entry.file_name().to_str()
+            && is_snapshot_name(name)
+        {
+            names.push(name.to_string());
+        }
+    }
+
+    let refs: Vec<&str> = names.iter().map(String::as_str).collect();
+    let expired = policy.expired(&refs);
+    for name in &expired {
+        fs::remove_dir_all(dir.join(name))?;
+    }
+    Ok(expired.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NAMES: [&str; 5] = [
+        "20250301T230000Z",
+        "20250302T080000Z",
+        "20250302T083000Z",
+        "20250302T090000Z",
+        "20250302T091500Z",
+    ];
+
+    fn expired(keep_last: Option<usize>, per: Option<Period>) -> Vec<&'static str> {
+        let mut names = NAMES;
+        names.reverse(); // Order on disk does not matter
+        Retention { keep_last, per }.expired(&names)
+    }
+
+    #[test]
+    fn without_limits_nothing_expires() {
+        assert!(expired(None, None).is_empty());
+    }
+
+    #[test]
+    fn keep_last_keeps_the_newest() {
+        assert_eq!(expired(Some(2), None), NAMES[..3]);
+        assert_eq!(expired(Some(10), None), Vec::<&str>::new());
+        // 0 still keeps the newest snapshot
+        assert_eq!(expired(Some(0), None), NAMES[..4]);
+    }
+
+    #[test]
+    fn keep_per_keeps_the_first_of_each_period_and_the_newest() {
+        assert_eq!(expired(None, Some(Period::Day)), [NAMES[2], NAMES[3]]);
+        assert_eq!(expired(None, Some(Period::Hour)), [NAMES[2]]);
+        assert_eq!(expired(Some(2), Some(Period::Day)), [NAMES[2]]);
+    }
+
+    #[test]
+    fn snapshot_names() {
+        assert!(is_snapshot_name("20250302T091500Z"));
+        assert!(!is_snapshot_name("20250302-091500Z"));
+        assert!(!is_snapshot_name("notes"));
+    }
+}
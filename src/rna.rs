@@ -0,0 +1,151 @@
+// Direct RNA and cDNA runs: poly(A) tail lengths and strand balance
+use std::error::Error;
+
+use crate::model::{RunData, Thresholds};
+
+/// Number of most recent estimates the poly(A) length and strand balance are averaged over
+pub const WINDOW: usize = 1000;
+/// Share of forward-strand reads that turns the strand balance of a cDNA run
+/// amber/red, as a distance from an even split
+pub const STRAND_IMBALANCE: Thresholds = Thresholds {
+    warn: 0.1,
+    fail: 0.2,
+    higher_is_worse: true,
+};
+
+/// Kind of library, recognized from the sequencing kit
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Library {
+    Dna,       // Genomic DNA (ligation, rapid, ...)
+    Cdna,      // cDNA, both strands expected in equal shares
+    DirectRna, // Native RNA, every read from the same strand
+}
+
+impl Library {
+    /// Library of a kit name such as `SQK-RNA004` or `SQK-PCS114`
+    pub fn from_kit(kit: &str) -> Self {
+        let kit = kit.to_ascii_uppercase();
+        if kit.contains("RNA") {
+            Library::DirectRna
+        } else if ["PCS", "PCB", "DCS"].iter().any(|k| kit.contains(k)) {
+            Library::Cdna
+        } else {
+            Library::Dna
+        }
+    }
+
+    /// Parses a `--library` value
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "dna" => Some(Library::Dna),
+            "cdna" => Some(Library::Cdna),
+            "rna" | "direct-rna" | "drna" => Some(Library::DirectRna),
+            _ => None,
+        }
+    }
+
+    /// Name used in reports
+    pub fn label(self) -> &'static str {
+        match self {
+            Library::Dna => "DNA",
+            Library::Cdna => "cDNA",
+            Library::DirectRna => "direct RNA",
+        }
+    }
+
+    /// Whether the library carries poly(A) tails and strand information
+    pub fn is_transcript(self) -> bool {
+        self != Library::Dna
+    }
+}
+
+/// Parses a poly(A) length cell; dorado writes -1 (or nothing) for reads
+/// without an estimate, which become NaN
+pub fn parse_tail_length(value: &str) -> Result<f64, Box<dyn Error>> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Ok(f64::NAN);
+    }
+    let length: f64 = value
+        .parse()
+        .map_err(|_| format!("Invalid poly_tail_length '{}'", value))?;
+    Ok(if length < 0.0 { f64::NAN } else { length })
+}
+
+/// Parses a strand cell (`+`/`-`, `1`/`0`, `forward`/`reverse`) as 1 for forward
+pub fn parse_strand(value: &str) -> Result<f64, Box<dyn Error>> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "+" | "1" | "forward" | "fwd" => Ok(1.0),
+        "-" | "0" | "reverse" | "rev" => Ok(0.0),
+        other => Err(format!("Invalid strand value '{}'", other).into()),
+    }
+}
+
+/// Mean of the last `window` poly(A) estimates at every row; rows before the
+/// first estimate hold 0 and rows without one repeat the latest mean
+pub fn rolling_tail_length(lengths: &[f64], window: usize) -> Vec<f64> {
+    let mut recent = std::collections::VecDeque::with_capacity(window);
+    let mut sum = 0.0;
+    lengths
+        .iter()
+        .map(|&length| {
+            if length.is_finite() {
+                recent.push_back(length);
+                sum += length;
+                if recent.len() > window {
+                    sum -= recent.pop_front().unwrap_or(0.0);
+                }
+            }
+            if recent.is_empty() {
+                0.0
+            } else {
+                sum / recent.len() as f64
+            }
+        })
+        .collect()
+}
+
+/// Prints the library, poly(A) and strand summaries
+pub fn print_report(data: &RunData, library: Library) {
+    let kit = data
+        .kit
+        .as_deref()
+        .map(|k| format!(" ({})", k))
+        .unwrap_or_default();
+    println!("Library: {}{}", library.label(), kit);
+
+    let mut estimated: Vec<f64> = data
+        .poly_tail_length
+        .iter()
+        .copied()
+        .filter(|l| l.is_finite())
+        .collect();
+    if !estimated.is_empty() {
+        estimated.sort_by(f64::total_cmp);
+        println!(
+            "  Poly(A) length: median {:.0} nt, IQR {:.0}–{:.0} nt ({} of {} reads estimated)",
+            estimated[estimated.len() / 2],
+            estimated[estimated.len() / 4],
+            estimated[estimated.len() * 3 / 4],
+            estimated.len(),
+            data.len()
+        );
+    }
+
+    if !data.strand.is_empty() {
+        let forward = data.strand.iter().sum::<f64>() / data.strand.len() as f64;
+        let verdict = match library {
+            Library::Cdna => format!(
+                " [{}]",
+                STRAND_IMBALANCE.classify((forward - 0.5).abs()).label()
+            ),
+            _ => String::new(),
+        };
+        println!(
+            "  Strand balance: {:.1}% forward, {:.1}% reverse{}",
+            forward * 100.0,
+            (1.0 - forward) * 100.0,
+            verdict
+        );
+    }
+}
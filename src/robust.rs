@@ -0,0 +1,116 @@
+// Central tendency and dispersion, classical or robust to outliers
+//
+// Latency columns carry a few extreme batches that drag the mean and standard
+// deviation far from the typical value; the median with the MAD, or a trimmed
+// mean, describe the bulk of the run instead.
+
+/// Scale that makes the MAD estimate σ for normally distributed data
+const MAD_TO_SIGMA: f64 = 1.4826;
+/// Share cut from each end by `trimmed` without an explicit fraction
+const DEFAULT_TRIM: f64 = 0.1;
+
+/// Statistic used for "typical value" summaries and checks
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Center {
+    Mean,         // Arithmetic mean, with the standard deviation
+    Median,       // Median, with the scaled median absolute deviation
+    Trimmed(f64), // Mean after cutting this share from each end, with the MAD
+}
+
+impl Center {
+    /// Parses a `--stat` value: `mean`, `median`, `trimmed` or `trimmed:F`
+    pub fn parse(name: &str) -> Option<Self> {
+        let lower = name.to_ascii_lowercase();
+        match lower.split_once(':') {
+            Some(("trimmed", fraction)) => fraction
+                .parse()
+                .ok()
+                .filter(|f: &f64| (0.0..0.5).contains(f))
+                .map(Center::Trimmed),
+            Some(_) => None,
+            None => match lower.as_str() {
+                "mean" => Some(Center::Mean),
+                "median" => Some(Center::Median),
+                "trimmed" => Some(Center::Trimmed(DEFAULT_TRIM)),
+                _ => None,
+            },
+        }
+    }
+
+    /// Short name of the central statistic, for report labels
+    pub fn label(&self) -> &'static str {
+        match self {
+            Center::Mean => "mean",
+            Center::Median => "median",
+            Center::Trimmed(_) => "trimmed mean",
+        }
+    }
+
+    /// Capitalised name, for table headers
+    pub fn header(&self) -> &'static str {
+        match self {
+            Center::Mean => "Mean",
+            Center::Median => "Median",
+            Center::Trimmed(_) => "Trimmed mean",
+        }
+    }
+
+    /// Short name of the matching dispersion statistic
+    pub fn spread_label(&self) -> &'static str {
+        match self {
+            Center::Mean => "sd",
+            _ => "MAD",
+        }
+    }
+
+    /// Typical value of `values` (NaN when empty)
+    pub fn of(&self, values: &[f64]) -> f64 {
+        if values.is_empty() {
+            return f64::NAN;
+        }
+        match self {
+            Center::Mean => values.iter().sum::<f64>() / values.len() as f64,
+            Center::Median => median(&mut values.to_vec()),
+            Center::Trimmed(fraction) => {
+                let mut sorted = values.to_vec();
+                sorted.sort_by(f64::total_cmp);
+                let cut = (sorted.len() as f64 * fraction) as usize;
+                let kept = &sorted[cut..sorted.len() - cut];
+                kept.iter().sum::<f64>() / kept.len() as f64
+            }
+        }
+    }
+
+    /// Dispersion of `values`: standard deviation for the mean, scaled MAD otherwise
+    pub fn spread(&self, values: &[f64]) -> f64 {
+        if values.is_empty() {
+            return f64::NAN;
+        }
+        match self {
+            Center::Mean => {
+                let mean = self.of(values);
+                let n = values.len() as f64;
+                (values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n).sqrt()
+            }
+            _ => {
+                let mid = median(&mut values.to_vec());
+                let mut deviations: Vec<f64> = values.iter().map(|v| (v - mid).abs()).collect();
+                MAD_TO_SIGMA * median(&mut deviations)
+            }
+        }
+    }
+}
+
+/// Median of a non-empty slice, reordering it in place
+fn median(values: &mut [f64]) -> f64 {
+    let mid = values.len() / 2;
+    let (_, &mut upper, _) = values.select_nth_unstable_by(mid, f64::total_cmp);
+    if values.len() % 2 == 1 {
+        return upper;
+    }
+    let lower = values[..mid]
+        .iter()
+        .copied()
+        .fold(f64::NEG_INFINITY, f64::max);
+    (lower + upper) / 2.0
+}
@@ -0,0 +1,274 @@
+// Declarative QC rules evaluated against the run's columns
+//
+// One rule per line, `#` starts a comment:
+//
+//   rule "latency": p95(time_in_basecaller) < 2e9 severity=fail
+//   rule "quality": median(mean_qscore) >= 9 severity=warn
+//
+// Values are in the units of the input columns (timings in nanoseconds).
+use std::error::Error;
+use std::fs;
+
+use crate::model::Health;
+use crate::model::RunData;
+use crate::quantile;
+use crate::robust::Center;
+
+/// Statistic a rule computes over a column
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Aggregate {
+    Mean,
+    Median,
+    Min,
+    Max,
+    Sum,
+    Count,
+    Sd,
+    Percentile(f64), // Quantile in [0, 1], written pNN
+}
+
+impl Aggregate {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "mean" => Some(Aggregate::Mean),
+            "median" => Some(Aggregate::Median),
+            "min" => Some(Aggregate::Min),
+            "max" => Some(Aggregate::Max),
+            "sum" => Some(Aggregate::Sum),
+            "count" => Some(Aggregate::Count),
+            "sd" => Some(Aggregate::Sd),
+            _ => name
+                .strip_prefix('p')
+                .and_then(|p| p.parse::<f64>().ok())
+                .filter(|p| (0.0..=100.0).contains(p))
+                .map(|p| Aggregate::Percentile(p / 100.0)),
+        }
+    }
+
+    fn apply(self, values: &[f64]) -> f64 {
+        match self {
+            Aggregate::Mean => Center::Mean.of(values),
+            Aggregate::Median => Center::Median.of(values),
+            Aggregate::Min => values.iter().copied().fold(f64::INFINITY, f64::min),
+            Aggregate::Max => values.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+            Aggregate::Sum => values.iter().sum(),
+            Aggregate::Count => values.len() as f64,
+            Aggregate::Sd => Center::Mean.spread(values),
+            Aggregate::Percentile(p) => {
+                quantile::quantiles(values, &[p], quantile::Method::Exact)[0]
+            }
+        }
+    }
+}
+
+/// Comparison a rule's value must satisfy, longest symbols first
+const COMPARISONS: [&str; 6] = ["<=", ">=", "==", "!=", "<", ">"];
+
+/// One parsed QC rule
+#[derive(Debug, Clone)]
+pub struct Rule {
+    pub name: String,         // Label in quotes
+    pub expression: String,   // `aggregate(column)` as written
+    aggregate: Aggregate,     // Statistic to compute
+    column: String,           // Store column it reads
+    comparison: &'static str, // Operator the value must satisfy
+    limit: f64,               // Right-hand side
+    severity: Health,         // Outcome when the rule does not hold
+}
+
+/// Result of evaluating a rule
+#[derive(Debug, Clone)]
+pub struct Outcome {
+    pub rule: Rule,     // Rule that was evaluated
+    pub value: f64,     // Computed left-hand side
+    pub health: Health, // Ok when satisfied, the rule's severity otherwise
+}
+
+/// Parses a single `rule "name": agg(column) OP number [severity=warn|fail]` line
+pub fn parse_rule(line: &str) -> Result<Rule, Box<dyn Error>> {
+    let bad = |why: &str| format!("Rule '{}': {}", line, why);
+    let rest = line
+        .strip_prefix("rule")
+        .ok_or_else(|| bad("must start with `rule`"))?
+        .trim_start();
+    let rest = rest
+        .strip_prefix('"')
+        .ok_or_else(|| bad("missing quoted name"))?;
+    let (name, rest) = rest
+        .split_once('"')
+        .ok_or_else(|| bad("unterminated name"))?;
+    let rest = rest
+        .trim_start()
+        .strip_prefix(':')
+        .ok_or_else(|| bad("missing `:` after the name"))?;
+
+    // Optional trailing severity
+    let (condition, severity) = match rest.rsplit_once("severity=") {
+        Some((condition, level)) => (
+            condition,
+            match level.trim() {
+                "warn" => Health::Warn,
+                "fail" => Health::Fail,
+                other => return Err(bad(&format!("unknown severity '{}'", other)).into()),
+            },
+        ),
+        None => (rest, Health::Fail),
+    };
+
+    let comparison = COMPARISONS
+        .into_iter()
+        .find(|op| condition.contains(op))
+        .ok_or_else(|| bad("no comparison operator"))?;
+    let (expression, limit) = condition.split_once(comparison).unwrap_or_default();
+    let expression = expression.trim();
+    let (aggregate, column_name) = expression
+        .strip_suffix(')')
+        .and_then(|e| e.split_once('('))
+        .ok_or_else(|| bad("expected aggregate(column)"))?;
+
+    Ok(Rule {
+        name: name.to_string(),
+        expression: expression.to_string(),
+        aggregate: Aggregate::parse(aggregate.trim())
+            .ok_or_else(|| bad(&format!("unknown aggregate '{}'", aggregate)))?,
+        column: column_name.trim().to_string(),
+        comparison,
+        limit: limit
+            .trim()
+            .parse()
+            .map_err(|_| bad(&format!("'{}' is not a number", limit.trim())))?,
+        severity,
+    })
+}
+
+/// Reads a rules file, skipping blank lines and `#` comments
+pub fn load(path: &str) -> Result<Vec<Rule>, Box<dyn Error>> {
+    fs::read_to_string(path)?
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or_default().trim())
+        .filter(|line| !line.is_empty())
+        .map(parse_rule)
+        .collect()
+}
+
+/// Evaluates every rule; a rule on a column the input lacks is an error
+pub fn evaluate(data: &RunData, rules: &[Rule]) -> Result<Vec<Outcome>, Box<dyn Error>> {
+    rules
+        .iter()
+        .map(|rule| {
+            let values = data
+                .column(&rule.column)
+                .filter(|v| !v.is_empty())
+                .ok_or_else(|| format!("Rule \"{}\": no column '{}'", rule.name, rule.column))?;
+            let value = rule.aggregate.apply(values);
+            let holds = match rule.comparison {
+                "<" => value < rule.limit,
+                "<=" => value <= rule.limit,
+                ">" => value > rule.limit,
+                ">=" => value >= rule.limit,
+                "==" => value == rule.limit,
+                _ => value != rule.limit,
+            };
+            Ok(Outcome {
+                rule: rule.clone(),
+                value,
+                health: if holds { Health::Ok } else { rule.severity },
+            })
+        })
+        .collect()
+}
+
+/// Prints one line per rule with its value and outcome
+pub fn print_report(outcomes: &[Outcome]) {
+    for o in outcomes {
+        println!(
+            "Rule \"{}\": {} = {:.3}, expected {} {} [{}]",
+            o.rule.name,
+            o.rule.expression,
+            o.value,
+            o.rule.comparison,
+            o.rule.limit,
+            o.health.label()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run() -> RunData {
+        RunData {
+            time: vec![0.0, 1.0, 2.0, 3.0],
+            mean_qscore: vec![8.0, 10.0, 12.0, 14.0],
+            time_in_basecaller: vec![1e9, 1e9, 2e9, 4e9],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn parses_rules() {
+        let rule = parse_rule(r#"rule "quality": median(mean_qscore) >= 9 severity=warn"#).unwrap();
+        assert_eq!(rule.name, "quality");
+        assert_eq!(rule.expression, "median(mean_qscore)");
+        assert_eq!(rule.aggregate, Aggregate::Median);
+        assert_eq!(rule.column, "mean_qscore");
+        assert_eq!((rule.comparison, rule.limit), (">=", 9.0));
+        assert_eq!(rule.severity, Health::Warn);
+
+        let rule = parse_rule(r#"rule "latency": p95(time_in_basecaller) < 2e9"#).unwrap();
+        assert_eq!(rule.aggregate, Aggregate::Percentile(0.95));
+        assert_eq!((rule.comparison, rule.limit), ("<", 2e9));
+        assert_eq!(rule.severity, Health::Fail);
+    }
+
+    #[test]
+    fn rejects_malformed_rules() {
+        for line in [
+            r#"check "q": mean(mean_qscore) > 9"#,
+            r#"rule q: mean(mean_qscore) > 9"#,
+            r#"rule "q" mean(mean_qscore) > 9"#,
+            r#"rule "q": mean(mean_qscore) 9"#,
+            r#"rule "q": mean mean_qscore > 9"#,
+            r#"rule "q": mode(mean_qscore) > 9"#,
+            r#"rule "q": p101(mean_qscore) > 9"#,
+            r#"rule "q": mean(mean_qscore) > nine"#,
+            r#"rule "q": mean(mean_qscore) > 9 severity=info"#,
+        ] {
+            assert!(parse_rule(line).is_err(), "{}", line);
+        }
+    }
+
+    #[test]
+    fn evaluates_aggregates_against_limits() {
+        let rules: Vec<Rule> = [
+            r#"rule "median": median(mean_qscore) == 11"#,
+            r#"rule "count": count(mean_qscore) != 4 severity=warn"#,
+            r#"rule "latency": p50(time_in_basecaller) <= 1.5e9"#,
+        ]
+        .iter()
+        .map(|line| parse_rule(line).unwrap())
+        .collect();
+        let outcomes = evaluate(&run(), &rules).unwrap();
+        let health: Vec<Health> = outcomes.iter().map(|o| o.health).collect();
+        assert_eq!(health, [Health::Ok, Health::Warn, Health::Ok]);
+        assert_eq!(outcomes[0].value, 11.0);
+        assert_eq!(outcomes[2].value, 1.5e9);
+
+        let missing = parse_rule(r#"rule "n50": mean(read_n50) > 1000"#).unwrap();
+        assert!(evaluate(&run(), &[missing]).is_err());
+    }
+
+    #[test]
+    fn other_aggregates() {
+        let values = [2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0];
+        assert_eq!(Aggregate::Sum.apply(&values), 40.0);
+        assert_eq!(Aggregate::Mean.apply(&values), 5.0);
+        assert_eq!(
+            (Aggregate::Min.apply(&values), Aggregate::Max.apply(&values)),
+            (2.0, 9.0)
+        );
+        assert!(Aggregate::Sd.apply(&values) > 0.0);
+        assert_eq!(Aggregate::parse("p99"), Some(Aggregate::Percentile(0.99)));
+    }
+}
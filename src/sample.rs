@@ -0,0 +1,101 @@
+// Random row subsampling during parsing, for fast previews of huge inputs
+use csv::StringRecord;
+
+/// Seed of the row sampler, fixed so repeated previews show the same rows
+const SEED: u64 = 0x5EED_0000_A5A5_0001;
+
+/// How many rows a preview keeps
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Sampling {
+    Fraction(f64), // Keep each row with this probability
+    Rows(usize),   // Keep exactly this many rows (reservoir sampling)
+}
+
+impl Sampling {
+    /// Parses a `--sample` value: `1%`, `0.5%` or a row count such as `10000`
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().strip_suffix('%') {
+            Some(percent) => percent
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|p| *p > 0.0 && *p <= 100.0)
+                .map(|p| Sampling::Fraction(p / 100.0)),
+            None => value
+                .trim()
+                .parse()
+                .ok()
+                .filter(|&n| n > 0)
+                .map(Sampling::Rows),
+        }
+    }
+}
+
+/// How a sampled store relates to its input
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SampleInfo {
+    pub kept: usize,  // Rows in the store
+    pub total: usize, // Rows in the input
+}
+
+impl SampleInfo {
+    /// Watermark and report text, e.g. `Sampled preview: 1000 of 100000 rows`
+    pub fn describe(&self) -> String {
+        format!("Sampled preview: {} of {} rows", self.kept, self.total)
+    }
+}
+
+/// SplitMix64, enough randomness for picking rows
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform value in `[0, 1)`
+    fn unit(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Uniform index in `0..=upper`
+    fn index(&mut self, upper: usize) -> usize {
+        (self.next() % (upper as u64 + 1)) as usize
+    }
+}
+
+/// Draws the sampled records from `records` in one pass.
+///
+/// Fractions keep each row independently; row counts use reservoir
+/// sampling (algorithm R), so the input length need not be known up front.
+pub fn select<I>(records: I, sampling: Sampling) -> Result<(Vec<StringRecord>, usize), csv::Error>
+where
+    I: Iterator<Item = Result<StringRecord, csv::Error>>,
+{
+    let mut rng = SplitMix64(SEED);
+    let mut kept = Vec::new();
+    let mut total = 0;
+    for record in records {
+        let record = record?;
+        match sampling {
+            Sampling::Fraction(p) => {
+                if rng.unit() < p {
+                    kept.push(record);
+                }
+            }
+            Sampling::Rows(n) if kept.len() < n => kept.push(record),
+            Sampling::Rows(n) => {
+                let slot = rng.index(total);
+                if slot < n {
+                    kept[slot] = record;
+                }
+            }
+        }
+        total += 1;
+    }
+    Ok((kept, total))
+}
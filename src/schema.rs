@@ -0,0 +1,94 @@
+// Input schema fingerprints and drift between files of the same run
+use sha2::{Digest, Sha256};
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+/// Hex digits of the SHA-256 shown as the fingerprint
+const FINGERPRINT_LENGTH: usize = 16;
+
+/// Column names of an input, in file order (after `--aliases`)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Schema {
+    pub columns: Vec<String>,
+}
+
+/// Columns that appeared or disappeared between two schemas
+#[derive(Debug, Clone, PartialEq)]
+pub struct Drift {
+    pub added: Vec<String>,   // In the later file only
+    pub removed: Vec<String>, // In the earlier file only
+}
+
+impl Schema {
+    pub fn from_headers(headers: &csv::StringRecord) -> Self {
+        Schema {
+            columns: headers.iter().map(|h| h.trim().to_string()).collect(),
+        }
+    }
+
+    /// Short SHA-256 over the column names; equal for equal column lists
+    pub fn fingerprint(&self) -> String {
+        let hash = Sha256::digest(self.columns.join("\n").as_bytes());
+        hash.iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<String>()
+            .chars()
+            .take(FINGERPRINT_LENGTH)
+            .collect()
+    }
+
+    /// Columns added and removed in `later`; `None` when the sets match
+    /// (a reordering alone is no drift, columns are located by name)
+    pub fn drift(&self, later: &Schema) -> Option<Drift> {
+        let missing_from = |schema: &Schema, other: &Schema| -> Vec<String> {
+            other
+                .columns
+                .iter()
+                .filter(|c| !schema.columns.contains(c))
+                .cloned()
+                .collect()
+        };
+        let drift = Drift {
+            added: missing_from(self, later),
+            removed: missing_from(later, self),
+        };
+        (!drift.added.is_empty() || !drift.removed.is_empty()).then_some(drift)
+    }
+
+    /// Reads a schema saved by `save`; `None` when there is none yet
+    pub fn load(path: &Path) -> Option<Self> {
+        let text = fs::read_to_string(path).ok()?;
+        Some(Schema {
+            columns: text
+                .lines()
+                .skip(1)
+                .map(|l| l.trim().to_string())
+                .filter(|l| !l.is_empty())
+                .collect(),
+        })
+    }
+
+    /// Saves the fingerprint, then one column name per line
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        fs::write(
+            path,
+            format!("{}\n{}\n", self.fingerprint(), self.columns.join("\n")),
+        )?;
+        Ok(())
+    }
+}
+
+impl Drift {
+    /// One-line summary, e.g. `added: kit; removed: strand`
+    pub fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        if !self.added.is_empty() {
+            parts.push(format!("added: {}", self.added.join(", ")));
+        }
+        if !self.removed.is_empty() {
+            parts.push(format!("removed: {}", self.removed.join(", ")));
+        }
+        parts.join("; ")
+    }
+}
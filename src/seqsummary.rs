@@ -0,0 +1,98 @@
+// MinKNOW/dorado sequencing_summary.txt: one row per read instead of one per batch
+//
+// The summary carries no batch timings, so reads stand in for batches: each
+// read's row is timed at the end of the read (start_time + duration, seconds
+// since the run started) and the signal and basecaller timing columns are
+// left NaN, which drops their panels.
+use std::error::Error;
+
+use crate::model::RunData;
+use crate::parser::{number, text};
+
+/// Column that tells a sequencing summary apart from a batch timings CSV
+pub const MARKER_COLUMN: &str = "sequence_length_template";
+
+/// Columns every sequencing summary must have
+pub const REQUIRED_COLUMNS: [&str; 5] = [
+    "read_id",
+    "channel",
+    "start_time",
+    "sequence_length_template",
+    "mean_qscore_template",
+];
+
+/// Whether `headers` are those of a sequencing summary
+pub fn is_summary(headers: &csv::StringRecord) -> bool {
+    headers.iter().any(|h| h.trim() == MARKER_COLUMN)
+}
+
+/// Positions of the used columns in the summary header
+#[derive(Debug, Clone, Copy)]
+pub struct Columns {
+    pub(crate) read_id: usize,
+    channel: usize,
+    start: usize,             // start_time
+    duration: Option<usize>,  // duration
+    pub(crate) length: usize, // sequence_length_template
+    qscore: usize,            // mean_qscore_template
+    barcode: Option<usize>,   // barcode_arrangement (barcoded runs only)
+}
+
+impl Columns {
+    /// Maps column names to their positions in `headers`, failing with the
+    /// full list of required columns the header lacks
+    pub fn locate(headers: &csv::StringRecord) -> Result<Self, Box<dyn Error>> {
+        let position = |name: &str| headers.iter().position(|h| h.trim() == name);
+        let missing: Vec<&str> = REQUIRED_COLUMNS
+            .into_iter()
+            .filter(|name| position(name).is_none())
+            .collect();
+        if !missing.is_empty() {
+            return Err(format!(
+                "Sequencing summary is missing required column(s): {}",
+                missing.join(", ")
+            )
+            .into());
+        }
+        let required = |name: &str| position(name).unwrap_or_default();
+        Ok(Columns {
+            read_id: required("read_id"),
+            channel: required("channel"),
+            start: required("start_time"),
+            duration: position("duration"),
+            length: required("sequence_length_template"),
+            qscore: required("mean_qscore_template"),
+            barcode: position("barcode_arrangement"),
+        })
+    }
+}
+
+/// Parses one summary row and appends it to the store's columns as one batch
+pub fn push_record(
+    data: &mut RunData,
+    record: &csv::StringRecord,
+    columns: Columns,
+) -> Result<(), Box<dyn Error>> {
+    let start: f64 = number(record, columns.start, "start_time")?;
+    let duration: f64 = match columns.duration {
+        Some(column) => number(record, column, "duration")?,
+        None => 0.0,
+    };
+    data.channel
+        .push(number(record, columns.channel, "channel")?);
+    data.read_id
+        .push(text(record, columns.read_id, "read_id")?.to_string());
+    data.time.push(start + duration);
+    data.samples.push(f64::NAN);
+    data.bases
+        .push(number(record, columns.length, "sequence_length_template")?);
+    data.barcode.push(match columns.barcode {
+        Some(column) => text(record, column, "barcode_arrangement")?.to_string(),
+        None => String::new(),
+    });
+    data.mean_qscore
+        .push(number(record, columns.qscore, "mean_qscore_template")?);
+    data.time_to_package_and_send.push(f64::NAN);
+    data.time_in_basecaller.push(f64::NAN);
+    Ok(())
+}
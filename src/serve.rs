@@ -0,0 +1,509 @@
+// Prometheus exporter: the latest metrics of a followed run on `/metrics`
+//
+// A minimal HTTP/1.1 responder on std's TcpListener: every request gets the
+// current exposition text, a JSON view of the run under `/runs/{id}/`, or a
+// 404, and the connection is closed after it.
+// Each connection is answered on its own thread, so a client that is slow to
+// send its request line does not hold up the scrapes behind it. With a token,
+// requests without the matching bearer `Authorization` header get a 401; TLS
+// is left to a reverse proxy in front of the exporter.
+use std::error::Error;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::decisions::DecisionReport;
+use crate::model::RunData;
+use crate::robust::Center;
+use crate::smooth::Window;
+use crate::summary;
+
+/// Default address the exporter listens on
+pub const DEFAULT_LISTEN: &str = "127.0.0.1:9184";
+/// How long a client may take to send its request line
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+/// Request header lines read before giving up on finding the blank line
+const MAX_HEADERS: usize = 100;
+/// Content types of the responses
+const PROMETHEUS: &str = "text/plain; version=0.0.4; charset=utf-8";
+const JSON: &str = "application/json";
+const TEXT: &str = "text/plain; charset=utf-8";
+
+/// What the HTTP threads answer from, replaced by the refresh loop each poll
+#[derive(Debug, Default)]
+pub struct Published {
+    pub metrics: String,    // Prometheus exposition text
+    pub data: Arc<RunData>, // Latest batches, for the JSON endpoints
+}
+
+/// State shared between the refresh loop and the HTTP threads
+pub type Shared = Arc<Mutex<Published>>;
+
+/// A response: status line, content type and body
+type Response = (&'static str, &'static str, String);
+
+/// Id of the run in the JSON paths: its pseudonym when redacting, otherwise
+/// the CSV's file name without its extension
+pub fn run_id(input: &str, redact: bool) -> String {
+    if redact {
+        return crate::redact::pseudonym(input);
+    }
+    let name = crate::redact::file_name(input);
+    match name.rsplit_once('.') {
+        Some((stem, _)) if !stem.is_empty() => stem.to_string(),
+        _ => name,
+    }
+}
+
+/// Serves `shared` for run `run_id` on `listen` from a background thread, to
+/// clients sending `token` as a bearer token when one is given
+pub fn spawn(
+    listen: &str,
+    run_id: String,
+    shared: Shared,
+    token: Option<String>,
+) -> Result<(), Box<dyn Error>> {
+    let listener =
+        TcpListener::bind(listen).map_err(|e| format!("Cannot listen on {}: {}", listen, e))?;
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let (run_id, shared) = (run_id.clone(), Arc::clone(&shared));
+            let token = token.clone();
+            std::thread::spawn(move || {
+                // One bad client must not stop the exporter
+                if let Err(e) = respond(stream, &run_id, &shared, token.as_deref()) {
+                    eprintln!("Metrics request failed: {}", e);
+                }
+            });
+        }
+    });
+    Ok(())
+}
+
+/// Answers one request as [`route`] does, or with a 401 when the bearer
+/// `token` is required but not sent
+fn respond(
+    mut stream: TcpStream,
+    run_id: &str,
+    shared: &Shared,
+    token: Option<&str>,
+) -> Result<(), Box<dyn Error>> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let authorization = read_authorization(&mut reader)?;
+    let mut parts = request_line.split_whitespace();
+    let (method, target) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+
+    let authorized = token.is_none_or(|token| {
+        authorization
+            .as_deref()
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .is_some_and(|sent| same_token(sent.trim(), token))
+    });
+    let (status, content_type, body) = if !authorized {
+        (
+            "401 Unauthorized",
+            TEXT,
+            "A bearer token is required\n".to_string(),
+        )
+    } else {
+        route(method, target, run_id, shared)?
+    };
+    let challenge = if authorized {
+        ""
+    } else {
+        "WWW-Authenticate: Bearer\r\n"
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\n{}Content-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        challenge,
+        content_type,
+        body.len(),
+        body
+    )?;
+    Ok(())
+}
+
+/// Response to an authorized request:
+///
+/// - `GET /metrics`: the Prometheus exposition text
+/// - `GET /runs/{id}/summary`: the run summary `stats --json` writes
+/// - `GET /runs/{id}/series/{field}?bin=5m`: a column's batches, or their
+///   means over bins of batch time
+fn route(
+    method: &str,
+    target: &str,
+    run_id: &str,
+    shared: &Shared,
+) -> Result<Response, Box<dyn Error>> {
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let not_found = || {
+        let body = format!(
+            "Served: /metrics, /runs/{0}/summary and /runs/{0}/series/{{field}}\n",
+            run_id
+        );
+        ("404 Not Found", TEXT, body)
+    };
+    if method != "GET" {
+        return Ok(not_found());
+    }
+    let published = shared.lock().map_err(|_| "Metrics lock poisoned")?;
+    if path == "/metrics" {
+        return Ok(("200 OK", PROMETHEUS, published.metrics.clone()));
+    }
+    // The JSON is built outside the lock, so a slow request does not hold up a poll
+    let data = Arc::clone(&published.data);
+    drop(published);
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    Ok(match segments[..] {
+        ["runs", id, "summary"] if id == run_id => {
+            let summary = summary::summarize(&data, summary::DEFAULT_WINDOW);
+            ("200 OK", JSON, summary::to_json(&summary))
+        }
+        ["runs", id, "series", field] if id == run_id => series(&data, field, query),
+        _ => not_found(),
+    })
+}
+
+/// `field`'s `(batch time, value)` points as JSON, averaged over bins of
+/// the `bin` query parameter's duration when it is given
+fn series(data: &RunData, field: &str, query: &str) -> Response {
+    let bin = match query.split('&').find_map(|pair| pair.strip_prefix("bin=")) {
+        None => None,
+        Some(spec) => match Window::parse(spec) {
+            Some(Window::Seconds(seconds)) => Some(seconds),
+            _ => {
+                let body = format!("Invalid bin '{}' (a duration: 30s, 5m, 1h)\n", spec);
+                return ("400 Bad Request", TEXT, body);
+            }
+        },
+    };
+    let Some(points) = data.series(field) else {
+        let body = format!(
+            "Unknown field '{}' (one of {})\n",
+            field,
+            data.columns().join(", ")
+        );
+        return ("404 Not Found", TEXT, body);
+    };
+    let json = |(time, value, batches): (f64, f64, usize)| {
+        format!(
+            "    {{\"time\": {}, \"value\": {}, \"batches\": {}}}",
+            summary::json_number(time),
+            summary::json_number(value),
+            batches
+        )
+    };
+    let points: Vec<String> = match bin {
+        Some(seconds) => bin_means(&points, seconds).into_iter().map(json).collect(),
+        None => points.into_iter().map(|(t, v)| json((t, v, 1))).collect(),
+    };
+    let body = format!(
+        "{{\n  \"field\": \"{}\",\n  \"bin_seconds\": {},\n  \"points\": [\n{}\n  ]\n}}\n",
+        field,
+        summary::json_number(bin.unwrap_or(f64::NAN)),
+        points.join(",\n")
+    );
+    ("200 OK", JSON, body)
+}
+
+/// Mean value per bin of `seconds` from the first batch time, as
+/// `(bin start, mean, batches)` for the bins holding any batch
+fn bin_means(points: &[(f64, f64)], seconds: f64) -> Vec<(f64, f64, usize)> {
+    let first = points.iter().map(|&(t, _)| t).fold(f64::INFINITY, f64::min);
+    let mut bins: std::collections::BTreeMap<u64, Vec<f64>> = Default::default();
+    for &(time, value) in points {
+        bins.entry(((time - first) / seconds) as u64)
+            .or_default()
+            .push(value);
+    }
+    bins.into_iter()
+        .map(|(i, values)| {
+            let start = first + i as f64 * seconds;
+            (start, Center::Mean.of(&values), values.len())
+        })
+        .collect()
+}
+
+/// Value of the `Authorization` header, reading up to the blank line that
+/// ends the request headers
+fn read_authorization(reader: &mut impl BufRead) -> Result<Option<String>, Box<dyn Error>> {
+    let mut authorization = None;
+    for _ in 0..MAX_HEADERS {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':')
+            && name.trim().eq_ignore_ascii_case("authorization")
+        {
+            authorization = Some(value.trim().to_string());
+        }
+    }
+    Ok(authorization)
+}
+
+/// Compares tokens without stopping at the first differing byte
+fn same_token(sent: &str, token: &str) -> bool {
+    sent.len() == token.len()
+        && sent
+            .bytes()
+            .zip(token.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// Prometheus text exposition of `data`'s last `window` seconds, labelled
+/// with `run`; the unblock rate needs the run's decision log
+pub fn exposition(
+    data: &RunData,
+    run: &str,
+    window: f64,
+    decisions: Option<&DecisionReport>,
+) -> String {
+    let label = format!("{{run=\"{}\"}}", escape_label(run));
+    let mut out = String::new();
+    let mut metric = |name: &str, kind: &str, help: &str, value: f64| {
+        out.push_str(&format!(
+            "# HELP {0} {1}\n# TYPE {0} {2}\n{0}{3} {4}\n",
+            name,
+            help,
+            kind,
+            label,
+            prometheus_value(value)
+        ));
+    };
+
+    metric(
+        "as_parser_batches_total",
+        "counter",
+        "Batches read from the batch log",
+        data.len() as f64,
+    );
+    metric(
+        "as_parser_bases_total",
+        "counter",
+        "Bases called over the run",
+        data.bases.iter().sum(),
+    );
+
+    // Rate and averages over the batches of the last `window` seconds
+    let last = data.time_range().map_or(0.0, |(_, last)| last);
+    let recent: Vec<usize> = (0..data.len())
+        .filter(|&i| data.time[i] > last - window)
+        .collect();
+    let mean = |column: &[f64]| {
+        let values: Vec<f64> = recent.iter().map(|&i| column[i]).collect();
+        Center::Mean.of(&values)
+    };
+    let per_second = |column: &[f64]| recent.iter().map(|&i| column[i]).sum::<f64>() / window;
+    metric(
+        "as_parser_bases_per_second",
+        "gauge",
+        "Bases called per second over the recent window",
+        per_second(&data.bases),
+    );
+    metric(
+        "as_parser_samples_per_second",
+        "gauge",
+        "Signal samples per second over the recent window",
+        per_second(&data.samples),
+    );
+    metric(
+        "as_parser_mean_qscore",
+        "gauge",
+        "Mean Q-score of the batches in the recent window",
+        mean(&data.mean_qscore),
+    );
+    metric(
+        "as_parser_basecaller_latency_seconds",
+        "gauge",
+        "Mean time a batch spent in the basecaller over the recent window",
+        mean(&data.time_in_basecaller) / 1e9,
+    );
+    metric(
+        "as_parser_package_latency_seconds",
+        "gauge",
+        "Mean time to package and send a batch over the recent window",
+        mean(&data.time_to_package_and_send) / 1e9,
+    );
+    metric(
+        "as_parser_last_batch_timestamp_seconds",
+        "gauge",
+        "Batch time of the latest batch",
+        last,
+    );
+    if let Some(report) = decisions {
+        metric(
+            "as_parser_unblock_rate",
+            "gauge",
+            "Share of adaptive sampling decisions that were unblocks",
+            report.unblock_rate,
+        );
+    }
+    out
+}
+
+/// Sample value as Prometheus writes it (`NaN`, `+Inf`, `-Inf` or a number)
+fn prometheus_value(value: f64) -> String {
+    if value.is_nan() {
+        "NaN".to_string()
+    } else if value.is_infinite() {
+        if value > 0.0 { "+Inf" } else { "-Inf" }.to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+/// Escapes a label value (backslash, double quote and newline)
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    fn run() -> RunData {
+        RunData {
+            time: vec![0.0, 50.0, 100.0],
+            bases: vec![1000.0, 2000.0, 3000.0],
+            samples: vec![4000.0, 4000.0, 4000.0],
+            mean_qscore: vec![8.0, 10.0, 12.0],
+            time_in_basecaller: vec![1e9, 2e9, 3e9],
+            time_to_package_and_send: vec![1e6, 1e6, 1e6],
+            ..Default::default()
+        }
+    }
+
+    /// Value of the sample line of metric `name`
+    fn sample(text: &str, name: &str) -> f64 {
+        text.lines()
+            .find(|line| line.starts_with(&format!("{}{{", name)))
+            .and_then(|line| line.rsplit(' ').next())
+            .and_then(|value| value.parse().ok())
+            .unwrap_or_else(|| panic!("no sample for {}", name))
+    }
+
+    #[test]
+    fn exposition_covers_the_recent_window() {
+        let text = exposition(&run(), "run_1", 60.0, None);
+        assert_eq!(sample(&text, "as_parser_batches_total"), 3.0);
+        assert_eq!(sample(&text, "as_parser_bases_total"), 6000.0);
+        // Only the batches at 50 and 100 s fall within the last minute
+        assert_eq!(sample(&text, "as_parser_bases_per_second"), 5000.0 / 60.0);
+        assert_eq!(sample(&text, "as_parser_mean_qscore"), 11.0);
+        assert_eq!(sample(&text, "as_parser_basecaller_latency_seconds"), 2.5);
+        assert_eq!(
+            sample(&text, "as_parser_last_batch_timestamp_seconds"),
+            100.0
+        );
+        assert!(text.contains("# TYPE as_parser_batches_total counter\n"));
+        assert!(!text.contains("as_parser_unblock_rate"));
+    }
+
+    #[test]
+    fn exposition_of_an_empty_run_is_nan() {
+        let text = exposition(&RunData::default(), "run_1", 60.0, None);
+        assert_eq!(sample(&text, "as_parser_batches_total"), 0.0);
+        assert!(text.contains("as_parser_mean_qscore{run=\"run_1\"} NaN\n"));
+    }
+
+    #[test]
+    fn labels_and_values_are_escaped() {
+        assert_eq!(escape_label("a\"b\\c\nd"), "a\\\"b\\\\c\\nd");
+        assert_eq!(prometheus_value(f64::INFINITY), "+Inf");
+        assert_eq!(prometheus_value(f64::NEG_INFINITY), "-Inf");
+        assert_eq!(prometheus_value(2.5), "2.5");
+    }
+
+    /// Sends `request` to a fresh responder and returns the whole response
+    fn exchange(request: &str, token: Option<&str>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (server, _) = listener.accept().unwrap();
+        client.write_all(request.as_bytes()).unwrap();
+        let shared = Shared::new(Mutex::new(Published {
+            metrics: "up 1\n".to_string(),
+            data: Arc::new(run()),
+        }));
+        respond(server, "run_1", &shared, token).unwrap();
+        let mut response = String::new();
+        client.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn serves_metrics_and_nothing_else() {
+        let response = exchange("GET /metrics?x=1 HTTP/1.1\r\n\r\n", None);
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.ends_with("\r\n\r\nup 1\n"));
+        let response = exchange("GET / HTTP/1.1\r\n\r\n", None);
+        assert!(response.starts_with("HTTP/1.1 404 Not Found\r\n"));
+    }
+
+    #[test]
+    fn serves_the_run_as_json() {
+        let response = exchange("GET /runs/run_1/summary HTTP/1.1\r\n\r\n", None);
+        assert!(response.contains("Content-Type: application/json\r\n"));
+        assert!(response.contains("  \"total_bases\": 6000,\n"));
+        let response = exchange("GET /runs/run_2/summary HTTP/1.1\r\n\r\n", None);
+        assert!(response.starts_with("HTTP/1.1 404 "));
+        let response = exchange("GET /runs/run_1/series/color HTTP/1.1\r\n\r\n", None);
+        assert!(response.starts_with("HTTP/1.1 404 "));
+        let response = exchange("GET /runs/run_1/series/bases?bin=5 HTTP/1.1\r\n\r\n", None);
+        assert!(response.starts_with("HTTP/1.1 400 "));
+    }
+
+    #[test]
+    fn series_are_binned_by_batch_time() {
+        let response = series(&run(), "bases", "bin=1m");
+        assert_eq!(response.0, "200 OK");
+        assert!(response.2.contains("\"bin_seconds\": 60,"));
+        assert!(
+            response
+                .2
+                .contains("{\"time\": 0, \"value\": 1500, \"batches\": 2},\n")
+        );
+        assert!(
+            response
+                .2
+                .contains("{\"time\": 60, \"value\": 3000, \"batches\": 1}\n")
+        );
+        let response = series(&run(), "bases", "");
+        assert!(response.2.contains("\"bin_seconds\": null,"));
+        assert_eq!(response.2.matches("\"batches\": 1").count(), 3);
+    }
+
+    #[test]
+    fn run_ids_are_file_stems_or_pseudonyms() {
+        assert_eq!(
+            run_id("/data/adaptive_sampling_PAQ1.csv", false),
+            "adaptive_sampling_PAQ1"
+        );
+        assert_eq!(run_id("ssh://host/runs/as.log.csv", false), "as.log");
+        assert!(run_id("/data/PATIENT_1/as.csv", true).starts_with("run-"));
+    }
+
+    #[test]
+    fn requires_the_bearer_token_when_set() {
+        let request = |authorization: &str| {
+            format!("GET /metrics HTTP/1.1\r\nHost: x\r\n{}\r\n", authorization)
+        };
+        let response = exchange(&request(""), Some("s3cret"));
+        assert!(response.starts_with("HTTP/1.1 401 Unauthorized\r\nWWW-Authenticate: Bearer\r\n"));
+        let response = exchange(&request("Authorization: Bearer wrong\r\n"), Some("s3cret"));
+        assert!(response.starts_with("HTTP/1.1 401 "));
+        let response = exchange(&request("authorization: Bearer s3cret\r\n"), Some("s3cret"));
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    }
+}
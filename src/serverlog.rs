@@ -0,0 +1,246 @@
+// Events parsed from dorado basecall server logs (stderr or log files)
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+
+use plotters::style::RGBColor;
+
+use crate::timestamp::{TimeFormat, TimeParser};
+
+/// Seconds of log history counted into each row's error rate
+pub const ERROR_WINDOW: f64 = 60.0;
+/// Seconds within which a repeat of the same message joins the previous event
+pub const REPEAT_GAP: f64 = ERROR_WINDOW;
+/// Repeated messages listed in the report
+const MAX_REPEATS_LISTED: usize = 5;
+
+/// Kind of a recognised server log line
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EventKind {
+    Start,      // Server (re)started
+    ModelLoad,  // Basecalling model loaded
+    CudaError,  // CUDA/GPU failure
+    BatchRetry, // Batch resubmitted after a failure
+}
+
+impl EventKind {
+    /// Classifies a log message, `None` for lines of no interest
+    fn classify(message: &str) -> Option<Self> {
+        let m = message.to_ascii_lowercase();
+        if m.contains("cuda") && (m.contains("error") || m.contains("fail")) {
+            Some(EventKind::CudaError)
+        } else if m.contains("retry") || m.contains("retrying") {
+            Some(EventKind::BatchRetry)
+        } else if m.contains("model") && m.contains("load") {
+            Some(EventKind::ModelLoad)
+        } else if m.contains("starting") && (m.contains("server") || m.contains("dorado")) {
+            Some(EventKind::Start)
+        } else {
+            None
+        }
+    }
+
+    /// Whether the event counts towards the error rate series
+    pub fn is_error(self) -> bool {
+        matches!(self, EventKind::CudaError | EventKind::BatchRetry)
+    }
+
+    /// Lower-case name used in reports
+    pub fn label(self) -> &'static str {
+        match self {
+            EventKind::Start => "server start",
+            EventKind::ModelLoad => "model load",
+            EventKind::CudaError => "CUDA error",
+            EventKind::BatchRetry => "batch retry",
+        }
+    }
+
+    /// Colour of the event's marker line on the panels
+    pub fn color(self) -> RGBColor {
+        match self {
+            EventKind::Start => RGBColor(0, 0, 0),
+            EventKind::ModelLoad => RGBColor(38, 139, 210),
+            EventKind::CudaError => RGBColor(220, 50, 47),
+            EventKind::BatchRetry => RGBColor(255, 176, 0),
+        }
+    }
+}
+
+/// One recognised log line, or a run of repeats of it (see `collapse`)
+#[derive(Debug, Clone)]
+pub struct LogEvent {
+    pub time: f64,            // Unix seconds (log timestamps are taken as UTC)
+    pub last: f64,            // Time of the last repeat, `time` for a single line
+    pub count: usize,         // Lines collapsed into the event
+    pub kind: EventKind,      // What happened
+    pub seconds: Option<f64>, // Reported duration, e.g. of a model load
+    pub message: String,      // Log message without timestamp and level
+}
+
+impl LogEvent {
+    /// The message with every number masked, so repeats differing only in
+    /// counters, batch ids or addresses compare equal
+    fn signature(&self) -> String {
+        let mut signature = String::with_capacity(self.message.len());
+        let mut in_number = false;
+        for c in self.message.chars() {
+            if c.is_ascii_digit() {
+                if !in_number {
+                    signature.push('#');
+                }
+                in_number = true;
+            } else {
+                signature.push(c);
+                in_number = false;
+            }
+        }
+        signature
+    }
+}
+
+/// Parses a dorado log, keeping lines whose message is recognised.
+///
+/// Lines look like `[2024-05-01 12:00:03.123] [info] message`; lines without
+/// a leading timestamp in `time_format` (stack traces, banners) are skipped.
+/// Events come back sorted by time.
+pub fn parse(path: &str, time_format: TimeFormat) -> Result<Vec<LogEvent>, Box<dyn Error>> {
+    let text = fs::read_to_string(path)?;
+    let mut times = TimeParser::new(time_format);
+    let mut events: Vec<LogEvent> = text
+        .lines()
+        .filter_map(|line| {
+            let rest = line.trim().strip_prefix('[')?;
+            let (stamp, rest) = rest.split_once(']')?;
+            let time = times.parse(stamp)?;
+            // Skip an optional `[level]` tag
+            let rest = rest.trim_start();
+            let message = match rest.strip_prefix('[').and_then(|r| r.split_once(']')) {
+                Some((_, message)) => message.trim(),
+                None => rest,
+            };
+            let kind = EventKind::classify(message)?;
+            Some(LogEvent {
+                time,
+                last: time,
+                count: 1,
+                kind,
+                seconds: duration(message),
+                message: message.to_string(),
+            })
+        })
+        .collect();
+    events.sort_by(|a, b| a.time.total_cmp(&b.time));
+    Ok(events)
+}
+
+/// Collapses repeats of the same message into one counted event.
+///
+/// An event of the same kind and message (numbers aside) no more than
+/// `REPEAT_GAP` seconds after the previous occurrence extends that event
+/// instead of adding a new one, so a warning logged thousands of times in a
+/// row becomes a single marker. `events` must be sorted by time; the result
+/// is sorted by each event's first occurrence.
+pub fn collapse(events: Vec<LogEvent>) -> Vec<LogEvent> {
+    let mut collapsed: Vec<LogEvent> = Vec::new();
+    let mut open: HashMap<(EventKind, String), usize> = HashMap::new();
+    for event in events {
+        let key = (event.kind, event.signature());
+        match open.get(&key).map(|&i| &mut collapsed[i]) {
+            Some(previous) if event.time - previous.last <= REPEAT_GAP => {
+                previous.last = event.time;
+                previous.count += event.count;
+            }
+            _ => {
+                open.insert(key, collapsed.len());
+                collapsed.push(event);
+            }
+        }
+    }
+    collapsed
+}
+
+/// Extracts a duration written as `in 1234 ms` or `in 1.2 s`, in seconds
+fn duration(message: &str) -> Option<f64> {
+    let (_, rest) = message.rsplit_once(" in ")?;
+    let mut parts = rest.split_whitespace();
+    let number = parts.next()?;
+    let (value, unit) = match number.find(|c: char| c.is_ascii_alphabetic()) {
+        Some(split) => (&number[..split], &number[split..]),
+        None => (number, parts.next().unwrap_or("s")),
+    };
+    let value: f64 = value.parse().ok()?;
+    match unit.trim_end_matches(['.', ',']) {
+        "ms" => Some(value / 1000.0),
+        "s" | "sec" | "seconds" => Some(value),
+        _ => None,
+    }
+}
+
+/// Counts error events in the `ERROR_WINDOW` seconds up to each target time
+pub fn error_rate(events: &[LogEvent], targets: &[f64]) -> Vec<f64> {
+    let errors: Vec<f64> = events
+        .iter()
+        .filter(|e| e.kind.is_error())
+        .map(|e| e.time)
+        .collect();
+    targets
+        .iter()
+        .map(|&t| {
+            let end = errors.partition_point(|&e| e <= t);
+            let start = errors.partition_point(|&e| e <= t - ERROR_WINDOW);
+            (end - start) as f64
+        })
+        .collect()
+}
+
+/// Prints event counts and the mean model load time
+pub fn print_report(events: &[LogEvent]) {
+    let kinds = [
+        EventKind::Start,
+        EventKind::ModelLoad,
+        EventKind::CudaError,
+        EventKind::BatchRetry,
+    ];
+    let counts: Vec<String> = kinds
+        .iter()
+        .map(|&kind| {
+            let n: usize = events
+                .iter()
+                .filter(|e| e.kind == kind)
+                .map(|e| e.count)
+                .sum();
+            format!("{} {}", n, kind.label())
+        })
+        .collect();
+    println!("Server log: {}", counts.join(", "));
+
+    if let Some(first) = events.iter().find(|e| e.kind == EventKind::CudaError) {
+        println!("First CUDA error at {:.0}: {}", first.time, first.message);
+    }
+
+    let mut repeated: Vec<&LogEvent> = events.iter().filter(|e| e.count > 1).collect();
+    repeated.sort_by_key(|e| std::cmp::Reverse(e.count));
+    for event in repeated.iter().take(MAX_REPEATS_LISTED) {
+        println!(
+            "  Repeated {}× from {:.0} to {:.0}: {}",
+            event.count, event.time, event.last, event.message
+        );
+    }
+    if repeated.len() > MAX_REPEATS_LISTED {
+        println!(
+            "  ... and {} more repeated messages",
+            repeated.len() - MAX_REPEATS_LISTED
+        );
+    }
+
+    let loads: Vec<f64> = events
+        .iter()
+        .filter(|e| e.kind == EventKind::ModelLoad)
+        .filter_map(|e| e.seconds)
+        .collect();
+    if !loads.is_empty() {
+        let mean = loads.iter().sum::<f64>() / loads.len() as f64;
+        let max = loads.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        println!("Model load time: mean {:.2} s, max {:.2} s", mean, max);
+    }
+}
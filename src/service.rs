@@ -0,0 +1,103 @@
+// Running watch as a systemd service: PID file, readiness notification, journal levels
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Journal priority of a log line (syslog levels, as `<N>` line prefixes)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Level {
+    Info,    // Routine progress (priority 6)
+    Warning, // A run or snapshot failed; the daemon carries on (priority 4)
+}
+
+impl Level {
+    /// Priority prefix journald reads off stdout/stderr lines
+    fn prefix(self) -> &'static str {
+        match self {
+            Level::Info => "<6>",
+            Level::Warning => "<4>",
+        }
+    }
+}
+
+/// Writes daemon messages to stdout (info) and stderr (warnings), with
+/// priority prefixes when stdout and stderr go to the journal
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Logger {
+    pub journal: bool, // Prefix lines with their syslog priority
+}
+
+impl Logger {
+    pub fn log(self, level: Level, message: &str) {
+        let prefix = if self.journal { level.prefix() } else { "" };
+        match level {
+            Level::Info => println!("{}{}", prefix, message),
+            Level::Warning => eprintln!("{}{}", prefix, message),
+        }
+    }
+
+    pub fn info(self, message: &str) {
+        self.log(Level::Info, message)
+    }
+
+    pub fn warn(self, message: &str) {
+        self.log(Level::Warning, message)
+    }
+}
+
+/// A PID file that is removed again when dropped
+pub struct PidFile {
+    path: PathBuf, // File holding the process ID
+}
+
+impl PidFile {
+    /// Writes the current process ID to `path`
+    pub fn create(path: &Path) -> Result<Self, Box<dyn Error>> {
+        fs::write(path, format!("{}\n", std::process::id()))
+            .map_err(|e| format!("Cannot write PID file {}: {}", path.display(), e))?;
+        Ok(PidFile {
+            path: path.to_path_buf(),
+        })
+    }
+}
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Sends `state` (e.g. `READY=1`, `STATUS=...`, `STOPPING=1`) to the service
+/// manager over `$NOTIFY_SOCKET`, as `sd_notify` does; without the variable
+/// (not started by systemd with `Type=notify`) nothing is sent
+pub fn notify(state: &str) {
+    #[cfg(unix)]
+    if let Some(socket) = std::env::var_os("NOTIFY_SOCKET") {
+        // Notification is best effort; the service manager times out on its own
+        let _ = unix::send(&socket.to_string_lossy(), state);
+    }
+    #[cfg(not(unix))]
+    let _ = state;
+}
+
+#[cfg(unix)]
+mod unix {
+    use std::error::Error;
+    use std::os::unix::net::{SocketAddr, UnixDatagram};
+
+    /// Sends one datagram to a filesystem or (Linux) abstract `@` socket
+    pub fn send(socket: &str, state: &str) -> Result<(), Box<dyn Error>> {
+        let address = match socket.strip_prefix('@') {
+            #[cfg(target_os = "linux")]
+            Some(name) => {
+                use std::os::linux::net::SocketAddrExt;
+                SocketAddr::from_abstract_name(name)?
+            }
+            #[cfg(not(target_os = "linux"))]
+            Some(_) => return Err("Abstract notify sockets need Linux".into()),
+            None => SocketAddr::from_pathname(socket)?,
+        };
+        UnixDatagram::unbound()?.send_to_addr(state.as_bytes(), &address)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,51 @@
+// SIGINT/SIGTERM handling for the long-running modes
+//
+// The handler only records the request; the watch loop checks it between
+// runs and while waiting, so a figure or digest being written is finished
+// before the daemon stops. A second signal exits at once.
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Set once SIGINT or SIGTERM arrived
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// Whether a shutdown was requested
+pub fn requested() -> bool {
+    REQUESTED.load(Ordering::SeqCst)
+}
+
+/// Routes SIGINT and SIGTERM to the shutdown flag (a no-op off Unix, where
+/// Ctrl-C keeps terminating the process)
+pub fn install() {
+    #[cfg(unix)]
+    unix::install();
+}
+
+#[cfg(unix)]
+mod unix {
+    use super::REQUESTED;
+    use std::sync::atomic::Ordering;
+
+    const SIGINT: i32 = 2;
+    const SIGTERM: i32 = 15;
+    /// Exit status of a process stopped by a second signal (128 + SIGINT)
+    const FORCED_EXIT: i32 = 130;
+
+    unsafe extern "C" {
+        fn signal(signum: i32, handler: extern "C" fn(i32)) -> usize;
+        fn _exit(status: i32) -> !;
+    }
+
+    /// Async-signal-safe: only touches an atomic, or exits without unwinding
+    extern "C" fn handle(_signum: i32) {
+        if REQUESTED.swap(true, Ordering::SeqCst) {
+            unsafe { _exit(FORCED_EXIT) }
+        }
+    }
+
+    pub fn install() {
+        unsafe {
+            signal(SIGINT, handle);
+            signal(SIGTERM, handle);
+        }
+    }
+}
@@ -0,0 +1,128 @@
+// Replay of a run through hypothetical adaptive sampling rules
+use std::error::Error;
+
+use crate::filter::Predicate;
+use crate::model::RunData;
+
+/// Samples read before an off-target read is unblocked when a rule does not say
+const DEFAULT_UNBLOCK_SAMPLES: f64 = 4000.0;
+/// Pore time lost per unblock (ejection and recapture), in samples
+const UNBLOCK_OVERHEAD_SAMPLES: f64 = 400.0;
+
+/// A hypothetical decision rule: reads matching `targets` are sequenced, the rest unblocked
+#[derive(Debug, Clone)]
+pub struct Rule {
+    pub name: String,        // Label used in the report
+    targets: Vec<Predicate>, // On-target when all of these hold
+    unblock_samples: f64,    // Samples read before an off-target read is ejected
+}
+
+impl Rule {
+    /// Parses `name:expr,expr,...,unblock=N`, where each `expr` is a read filter.
+    ///
+    /// A rule without target filters treats every read as on-target.
+    pub fn parse(spec: &str) -> Result<Self, Box<dyn Error>> {
+        let (name, body) = spec
+            .split_once(':')
+            .ok_or_else(|| format!("Rule '{}' must look like name:filters", spec))?;
+        let mut targets = Vec::new();
+        let mut unblock_samples = DEFAULT_UNBLOCK_SAMPLES;
+        for item in body.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            match item.strip_prefix("unblock=") {
+                Some(value) => {
+                    unblock_samples = value
+                        .parse()
+                        .map_err(|_| format!("Rule '{}': bad unblock '{}'", name, value))?
+                }
+                None => targets.push(Predicate::parse(item)?),
+            }
+        }
+        Ok(Rule {
+            name: name.to_string(),
+            targets,
+            unblock_samples,
+        })
+    }
+}
+
+/// Estimated outcome of replaying the run under one rule
+#[derive(Debug, Clone)]
+pub struct Outcome {
+    pub rule: String,      // Rule name
+    pub on_target: usize,  // Reads the rule would sequence in full
+    pub target_bases: f64, // On-target bases projected over the same pore time
+    pub total_bases: f64,  // All bases projected over the same pore time
+    pub enrichment: f64,   // On-target base fraction relative to no rule
+}
+
+/// Replays every read under `rule`, scaling yield to the pore time the run actually used.
+///
+/// Pore time is measured in samples: an on-target read costs its full length, an
+/// off-target read costs the unblock point plus a fixed ejection overhead.
+pub fn simulate(data: &RunData, rule: &Rule) -> Outcome {
+    let mut on_target = 0;
+    let (mut target_bases, mut total_bases, mut consumed) = (0.0, 0.0, 0.0);
+    let (mut baseline_target, mut baseline_total, mut budget) = (0.0, 0.0, 0.0);
+
+    for i in 0..data.len() {
+        let (samples, bases) = (data.samples[i], data.bases[i]);
+        let hit = rule.targets.iter().all(|p| p.matches(data, i));
+        budget += samples;
+        baseline_total += bases;
+        if hit {
+            on_target += 1;
+            baseline_target += bases;
+            target_bases += bases;
+            total_bases += bases;
+            consumed += samples;
+        } else {
+            let read = samples.min(rule.unblock_samples);
+            if samples > 0.0 {
+                total_bases += bases * read / samples;
+            }
+            consumed += read + UNBLOCK_OVERHEAD_SAMPLES;
+        }
+    }
+
+    let scale = if consumed > 0.0 {
+        budget / consumed
+    } else {
+        0.0
+    };
+    let fraction = |t: f64, all: f64| if all > 0.0 { t / all } else { 0.0 };
+    let baseline = fraction(baseline_target, baseline_total);
+    Outcome {
+        rule: rule.name.clone(),
+        on_target,
+        target_bases: target_bases * scale,
+        total_bases: total_bases * scale,
+        enrichment: if baseline > 0.0 {
+            fraction(target_bases, total_bases) / baseline
+        } else {
+            0.0
+        },
+    }
+}
+
+/// Prints one row per rule, after the observed run as a reference
+pub fn print_report(data: &RunData, outcomes: &[Outcome]) {
+    let observed: f64 = data.bases.iter().sum();
+    println!(
+        "{:<16} {:>10} {:>16} {:>16} {:>11}",
+        "Rule", "On-target", "Target bases", "Total bases", "Enrichment"
+    );
+    println!(
+        "{:<16} {:>10} {:>16} {:>16.0} {:>11}",
+        "observed",
+        data.len(),
+        "-",
+        observed,
+        "-"
+    );
+    for o in outcomes {
+        println!(
+            "{:<16} {:>10} {:>16.0} {:>16.0} {:>10.2}x",
+            o.rule, o.on_target, o.target_bases, o.total_bases, o.enrichment
+        );
+    }
+}
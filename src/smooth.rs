@@ -0,0 +1,141 @@
+// Rolling smoothing of a panel's series, drawn over the raw per-batch values
+use crate::robust::Center;
+
+/// Span each smoothed value summarizes, ending at its own row
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Window {
+    Batches(usize), // The last N batches
+    Seconds(f64),   // The batches of the last N seconds of batch time
+}
+
+impl Window {
+    /// Parses a `--smooth` value: a batch count (`100`) or a duration (`30s`, `5m`, `1h`)
+    pub fn parse(spec: &str) -> Option<Self> {
+        let spec = spec.trim().to_ascii_lowercase();
+        if let Ok(batches) = spec.parse::<usize>() {
+            return (batches > 0).then_some(Window::Batches(batches));
+        }
+        let (number, scale) = match spec.chars().last()? {
+            's' => (&spec[..spec.len() - 1], 1.0),
+            'm' => (&spec[..spec.len() - 1], 60.0),
+            'h' => (&spec[..spec.len() - 1], 3600.0),
+            _ => return None,
+        };
+        let seconds = number.trim().parse::<f64>().ok()? * scale;
+        (seconds.is_finite() && seconds > 0.0).then_some(Window::Seconds(seconds))
+    }
+
+    /// First row of the window ending at `row`
+    fn start(self, times: &[f64], row: usize) -> usize {
+        match self {
+            Window::Batches(n) => (row + 1).saturating_sub(n),
+            Window::Seconds(s) => times[..=row].partition_point(|&t| t <= times[row] - s),
+        }
+    }
+}
+
+/// Smoothing overlay drawn on every panel
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Smoothing {
+    pub window: Window, // Span of each smoothed value
+    pub center: Center, // Statistic over the window (mean, median or trimmed mean)
+    pub dim_raw: bool,  // Draw the raw series faintly underneath
+}
+
+/// Smoothed `(time, value)` points at each of `rows`; windows without a
+/// finite value are left out
+pub fn series(
+    times: &[f64],
+    values: &[f64],
+    rows: &[usize],
+    smoothing: Smoothing,
+) -> Vec<(f64, f64)> {
+    let mut window = Vec::new();
+    rows.iter()
+        .filter_map(|&row| {
+            let start = smoothing.window.start(times, row);
+            window.clear();
+            window.extend(
+                values[start..=row]
+                    .iter()
+                    .copied()
+                    .filter(|v| v.is_finite()),
+            );
+            let value = smoothing.center.of(&window);
+            value.is_finite().then_some((times[row], value))
+        })
+        .collect()
+}
+
+/// Rows a smoothed series is evaluated at: every row, or `max_points` evenly
+/// spread ones (the smoothed line has no spikes for decimation to keep)
+pub fn rows(len: usize, max_points: Option<usize>) -> Vec<usize> {
+    match max_points {
+        Some(max_points) if max_points > 1 && len > max_points => {
+            let step = (len - 1) as f64 / (max_points - 1) as f64;
+            (0..max_points)
+                .map(|i| ((i as f64 * step).round() as usize).min(len - 1))
+                .collect()
+        }
+        _ => (0..len).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mean_over(window: Window) -> Smoothing {
+        Smoothing {
+            window,
+            center: Center::Mean,
+            dim_raw: false,
+        }
+    }
+
+    #[test]
+    fn window_specs() {
+        assert_eq!(Window::parse("100"), Some(Window::Batches(100)));
+        assert_eq!(Window::parse("30s"), Some(Window::Seconds(30.0)));
+        assert_eq!(Window::parse("5M"), Some(Window::Seconds(300.0)));
+        assert_eq!(Window::parse("1.5h"), Some(Window::Seconds(5400.0)));
+        for invalid in ["0", "0s", "-5m", "5d", "", "m"] {
+            assert_eq!(Window::parse(invalid), None, "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn batch_windows_end_at_their_row() {
+        let times = [0.0, 1.0, 2.0, 3.0, 4.0];
+        let values = [1.0, 2.0, 3.0, f64::NAN, 5.0];
+        let smoothed = series(
+            &times,
+            &values,
+            &[0, 1, 2, 3, 4],
+            mean_over(Window::Batches(2)),
+        );
+        // The NaN row is skipped within windows, not propagated
+        assert_eq!(
+            smoothed,
+            [(0.0, 1.0), (1.0, 1.5), (2.0, 2.5), (3.0, 3.0), (4.0, 5.0)]
+        );
+    }
+
+    #[test]
+    fn time_windows_follow_batch_time() {
+        let times = [0.0, 10.0, 11.0, 12.0, 60.0];
+        let values = [10.0, 20.0, 30.0, 40.0, 50.0];
+        let smoothed = series(&times, &values, &[3, 4], mean_over(Window::Seconds(5.0)));
+        assert_eq!(smoothed, [(12.0, 30.0), (60.0, 50.0)]);
+        // A window holding no finite value is left out
+        let gaps = [f64::NAN; 5];
+        assert!(series(&times, &gaps, &[4], mean_over(Window::Batches(3))).is_empty());
+    }
+
+    #[test]
+    fn evaluated_rows_are_spread_evenly() {
+        assert_eq!(rows(4, None), [0, 1, 2, 3]);
+        assert_eq!(rows(4, Some(10)), [0, 1, 2, 3]);
+        assert_eq!(rows(101, Some(5)), [0, 25, 50, 75, 100]);
+    }
+}
@@ -0,0 +1,20 @@
+// Read-splitting (chimera) rate tracking
+
+use crate::model::Thresholds;
+
+/// Number of most recent reads the split rate is computed over
+pub const WINDOW: usize = 1000;
+/// Share of split reads that turns the panel amber/red
+pub const SPLIT_RATE: Thresholds = Thresholds {
+    warn: 0.05,
+    fail: 0.15,
+    higher_is_worse: true,
+};
+
+/// Whether a read is a split product, given its own and its parent's read ID.
+///
+/// Dorado records the original read as `parent_read_id`; unsplit reads
+/// either leave it empty or repeat their own ID.
+pub fn is_split(read_id: &str, parent_read_id: &str) -> bool {
+    !parent_read_id.is_empty() && parent_read_id != read_id
+}
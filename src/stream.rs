@@ -0,0 +1,198 @@
+// Constant-memory streaming of very large inputs: running statistics and min/max envelopes
+//
+// A streamed run never holds its rows. Every value updates running moments
+// and P² tail estimators, and a time-bucketed envelope keeps each bucket's
+// minimum and maximum per column for the figure. Buckets start narrow and
+// are merged pairwise whenever there are too many, so the axis range need not
+// be known in advance and memory stays bounded however long the input is.
+use std::collections::BTreeMap;
+
+use crate::model::RunData;
+use crate::quantile::{self, P2};
+
+/// Columns computed while streaming, in the order `Envelope::push` takes them.
+/// Batch intervals are not among them: they need the rows in time order,
+/// and batch logs are not always written in it.
+pub const STREAMED: [&str; 5] = [
+    "samples",
+    "bases",
+    "mean_qscore",
+    "time_to_package_and_send",
+    "time_in_basecaller",
+];
+/// Time buckets the envelope is kept below; each is drawn as two rows
+pub const BUCKETS: usize = 2000;
+/// Bucket width in seconds before the first merge
+const INITIAL_WIDTH: f64 = 1.0;
+
+/// Mean, variance (Welford), range and tail percentiles of a streamed column
+#[derive(Debug, Clone)]
+pub struct RunningStats {
+    count: usize,   // Values seen
+    mean: f64,      // Running mean
+    m2: f64,        // Sum of squared deviations from the running mean
+    pub min: f64,   // Smallest value
+    pub max: f64,   // Largest value
+    tails: Vec<P2>, // Estimators of `quantile::REPORTED`
+}
+
+impl Default for RunningStats {
+    fn default() -> Self {
+        RunningStats {
+            count: 0,
+            mean: 0.0,
+            m2: 0.0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+            tails: quantile::REPORTED.iter().map(|&p| P2::new(p)).collect(),
+        }
+    }
+}
+
+impl RunningStats {
+    /// Adds one value
+    pub fn push(&mut self, value: f64) {
+        self.count += 1;
+        let delta = value - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (value - self.mean);
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.tails.iter_mut().for_each(|t| t.push(value));
+    }
+
+    /// Number of values seen
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Mean (NaN without values)
+    pub fn mean(&self) -> f64 {
+        if self.count == 0 { f64::NAN } else { self.mean }
+    }
+
+    /// Population standard deviation, as `robust::Center::Mean` reports it
+    pub fn sd(&self) -> f64 {
+        if self.count == 0 {
+            f64::NAN
+        } else {
+            (self.m2 / self.count as f64).sqrt()
+        }
+    }
+
+    /// Estimates of the `quantile::REPORTED` percentiles
+    pub fn tails(&self) -> Vec<f64> {
+        self.tails.iter().map(P2::estimate).collect()
+    }
+}
+
+/// Statistics over every row of a streamed input
+#[derive(Debug, Clone, Default)]
+pub struct StreamStats {
+    pub rows: usize,                // Rows read
+    pub columns: [RunningStats; 5], // One per `STREAMED` column
+}
+
+impl StreamStats {
+    /// Statistics of a `STREAMED` column by name
+    pub fn column(&self, name: &str) -> Option<&RunningStats> {
+        STREAMED
+            .iter()
+            .position(|&n| n == name)
+            .map(|i| &self.columns[i])
+    }
+}
+
+/// Extremes of each streamed column within one time bucket
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    start: f64,    // Earliest batch time seen in the bucket
+    end: f64,      // Latest batch time seen in the bucket
+    min: [f64; 5], // Per-column minimum
+    max: [f64; 5], // Per-column maximum
+}
+
+impl Bucket {
+    fn merge(&mut self, other: &Bucket) {
+        self.start = self.start.min(other.start);
+        self.end = self.end.max(other.end);
+        for i in 0..STREAMED.len() {
+            self.min[i] = self.min[i].min(other.min[i]);
+            self.max[i] = self.max[i].max(other.max[i]);
+        }
+    }
+}
+
+/// Min/max envelope of the streamed columns over time, at most `BUCKETS` buckets
+#[derive(Debug, Clone)]
+pub struct Envelope {
+    origin: Option<f64>,            // Batch time bucket 0 starts at
+    width: f64,                     // Current bucket width in seconds
+    buckets: BTreeMap<i64, Bucket>, // Buckets by index from the origin
+}
+
+impl Default for Envelope {
+    fn default() -> Self {
+        Envelope {
+            origin: None,
+            width: INITIAL_WIDTH,
+            buckets: BTreeMap::new(),
+        }
+    }
+}
+
+impl Envelope {
+    /// Adds one row's values (in `STREAMED` order) at batch time `time`
+    pub fn push(&mut self, time: f64, values: [f64; 5]) {
+        let origin = *self.origin.get_or_insert(time);
+        let key = ((time - origin) / self.width).floor() as i64;
+        let row = Bucket {
+            start: time,
+            end: time,
+            min: values,
+            max: values,
+        };
+        self.buckets
+            .entry(key)
+            .and_modify(|b| b.merge(&row))
+            .or_insert(row);
+        while self.buckets.len() > BUCKETS {
+            self.coarsen();
+        }
+    }
+
+    /// Doubles the bucket width, merging neighbouring buckets
+    fn coarsen(&mut self) {
+        self.width *= 2.0;
+        let mut merged: BTreeMap<i64, Bucket> = BTreeMap::new();
+        for (key, bucket) in std::mem::take(&mut self.buckets) {
+            merged
+                .entry(key.div_euclid(2))
+                .and_modify(|b| b.merge(&bucket))
+                .or_insert(bucket);
+        }
+        self.buckets = merged;
+    }
+
+    /// Turns the envelope into a small `RunData`: each bucket becomes a row of
+    /// minima at its first batch time and a row of maxima at its last, so line
+    /// plots trace the full range of the values
+    pub fn into_run_data(self, stats: StreamStats) -> RunData {
+        let mut data = RunData::default();
+        for bucket in self.buckets.values() {
+            for (time, values) in [(bucket.start, &bucket.min), (bucket.end, &bucket.max)] {
+                data.time.push(time);
+                data.samples.push(values[0]);
+                data.bases.push(values[1]);
+                data.mean_qscore.push(values[2]);
+                data.time_to_package_and_send.push(values[3]);
+                data.time_in_basecaller.push(values[4]);
+                data.channel.push(0);
+                data.read_id.push(String::new());
+                data.barcode.push(String::new());
+            }
+        }
+        data.stream = Some(stats);
+        data
+    }
+}
@@ -0,0 +1,198 @@
+// Per-run aggregate metrics as JSON or TSV for downstream pipelines
+use std::error::Error;
+use std::fs;
+
+use crate::model::RunData;
+use crate::robust::Center;
+
+/// Width in seconds of the throughput windows unless `--throughput-window` says otherwise
+pub const DEFAULT_WINDOW: f64 = 3600.0;
+
+/// Bases called within one window of batch time
+#[derive(Debug, Clone, Copy)]
+pub struct ThroughputWindow {
+    pub start: f64,            // Window start (batch time)
+    pub end: f64,              // Window end, the last batch for the final window
+    pub bases: f64,            // Bases called in the window
+    pub bases_per_second: f64, // `bases` over the window's length
+}
+
+/// Aggregate metrics of one run
+#[derive(Debug, Clone)]
+pub struct RunSummary {
+    pub rows: usize,                       // Batch rows
+    pub duration: f64,                     // Seconds from first to last batch
+    pub total_bases: f64,                  // Bases called
+    pub total_samples: f64,                // Signal samples
+    pub mean_qscore: f64,                  // Mean of the per-read Q-scores
+    pub median_qscore: f64,                // Median of the per-read Q-scores (NaN when streamed)
+    pub batches_per_minute: f64,           // Batch rate over the whole run
+    pub mean_time_in_basecaller: f64,      // Mean time in the basecaller
+    pub throughput: Vec<ThroughputWindow>, // Bases per second over time (empty when streamed)
+}
+
+/// Aggregates `data`, with throughput in windows of `window` seconds.
+///
+/// Streamed runs keep no rows, so their totals come from the running
+/// statistics and the median and throughput windows are left out.
+pub fn summarize(data: &RunData, window: f64) -> RunSummary {
+    let (first, last) = data.time_range().unwrap_or((0.0, 0.0));
+    let duration = last - first;
+    let rows = data.rows();
+    let batches_per_minute = if duration > 0.0 {
+        rows as f64 / duration * 60.0
+    } else {
+        f64::NAN
+    };
+
+    if let Some(stream) = &data.stream {
+        let total = |name: &str| {
+            stream
+                .column(name)
+                .map_or(f64::NAN, |s| s.mean() * s.count() as f64)
+        };
+        let mean = |name: &str| stream.column(name).map_or(f64::NAN, |s| s.mean());
+        return RunSummary {
+            rows,
+            duration,
+            total_bases: total("bases"),
+            total_samples: total("samples"),
+            mean_qscore: mean("mean_qscore"),
+            median_qscore: f64::NAN,
+            batches_per_minute,
+            mean_time_in_basecaller: mean("time_in_basecaller"),
+            throughput: Vec::new(),
+        };
+    }
+
+    RunSummary {
+        rows,
+        duration,
+        total_bases: data.bases.iter().sum(),
+        total_samples: data.samples.iter().sum(),
+        mean_qscore: Center::Mean.of(&data.mean_qscore),
+        median_qscore: Center::Median.of(&data.mean_qscore),
+        batches_per_minute,
+        mean_time_in_basecaller: Center::Mean.of(&data.time_in_basecaller),
+        throughput: throughput(&data.time, &data.bases, first, last, window),
+    }
+}
+
+/// Bases per second in consecutive windows of `window` seconds from `first`
+fn throughput(
+    times: &[f64],
+    bases: &[f64],
+    first: f64,
+    last: f64,
+    window: f64,
+) -> Vec<ThroughputWindow> {
+    if times.is_empty() || window <= 0.0 {
+        return Vec::new();
+    }
+    let count = (((last - first) / window) as usize + 1).max(1);
+    let mut sums = vec![0.0; count];
+    for (&t, &b) in times.iter().zip(bases) {
+        let index = (((t - first) / window) as usize).min(count - 1);
+        sums[index] += b;
+    }
+    sums.into_iter()
+        .enumerate()
+        .map(|(i, bases)| {
+            let start = first + i as f64 * window;
+            let end = (start + window).min(last);
+            let bases_per_second = if end > start {
+                bases / (end - start)
+            } else {
+                f64::NAN
+            };
+            ThroughputWindow {
+                start,
+                end,
+                bases,
+                bases_per_second,
+            }
+        })
+        .collect()
+}
+
+/// A number for JSON, `null` when it is not finite
+pub fn json_number(value: f64) -> String {
+    if value.is_finite() {
+        format!("{}", value)
+    } else {
+        "null".to_string()
+    }
+}
+
+/// Scalar metrics in output order: (name, value)
+fn metrics(summary: &RunSummary) -> [(&'static str, f64); 8] {
+    [
+        ("rows", summary.rows as f64),
+        ("duration_seconds", summary.duration),
+        ("total_bases", summary.total_bases),
+        ("total_samples", summary.total_samples),
+        ("mean_qscore", summary.mean_qscore),
+        ("median_qscore", summary.median_qscore),
+        ("batches_per_minute", summary.batches_per_minute),
+        ("mean_time_in_basecaller", summary.mean_time_in_basecaller),
+    ]
+}
+
+/// Writes the summary as JSON, see [`to_json`]
+pub fn write_json(path: &str, summary: &RunSummary) -> Result<(), Box<dyn Error>> {
+    fs::write(path, to_json(summary))?;
+    Ok(())
+}
+
+/// The summary as one JSON object, the throughput windows as an array
+pub fn to_json(summary: &RunSummary) -> String {
+    let mut lines: Vec<String> = metrics(summary)
+        .iter()
+        .map(|(name, value)| format!("  \"{}\": {}", name, json_number(*value)))
+        .collect();
+    let windows: Vec<String> = summary
+        .throughput
+        .iter()
+        .map(|w| {
+            format!(
+                "    {{\"start\": {}, \"end\": {}, \"bases\": {}, \"bases_per_second\": {}}}",
+                json_number(w.start),
+                json_number(w.end),
+                json_number(w.bases),
+                json_number(w.bases_per_second)
+            )
+        })
+        .collect();
+    lines.push(if windows.is_empty() {
+        "  \"throughput\": []".to_string()
+    } else {
+        format!("  \"throughput\": [\n{}\n  ]", windows.join(",\n"))
+    });
+    format!("{{\n{}\n}}\n", lines.join(",\n"))
+}
+
+/// Writes the summary as long-format TSV: `metric`, `start`, `end`, `value`,
+/// with the window bounds empty for run-level metrics
+pub fn write_tsv(path: &str, summary: &RunSummary) -> Result<(), Box<dyn Error>> {
+    let value = |v: f64| {
+        if v.is_finite() {
+            format!("{}", v)
+        } else {
+            "NA".to_string()
+        }
+    };
+    let mut tsv = String::from("metric\tstart\tend\tvalue\n");
+    for (name, v) in metrics(summary) {
+        tsv.push_str(&format!("{}\t\t\t{}\n", name, value(v)));
+    }
+    for w in &summary.throughput {
+        tsv.push_str(&format!(
+            "bases_per_second\t{}\t{}\t{}\n",
+            w.start,
+            w.end,
+            value(w.bases_per_second)
+        ));
+    }
+    fs::write(path, tsv)?;
+    Ok(())
+}
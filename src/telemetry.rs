@@ -0,0 +1,71 @@
+// MinKNOW device telemetry (temperature, bias voltage) aligned to the batch rows
+use std::error::Error;
+
+use crate::timestamp::{TimeFormat, TimeParser};
+
+/// Column names accepted for the ASIC temperature
+const TEMPERATURE_COLUMNS: &[&str] = &["asic_temp", "asic_temperature"];
+/// Column names accepted for the bias voltage
+const VOLTAGE_COLUMNS: &[&str] = &["bias_voltage"];
+
+/// Device telemetry samples, sorted by time
+#[derive(Debug, Default)]
+pub struct Telemetry {
+    pub time: Vec<f64>,                 // Sample time (Unix seconds)
+    pub temperature: Option<Vec<f64>>,  // ASIC temperature, if logged
+    pub bias_voltage: Option<Vec<f64>>, // Bias voltage, if logged
+}
+
+/// Parses a telemetry CSV with a `time` column and any of the known series
+pub fn parse(path: &str, time_format: TimeFormat) -> Result<Telemetry, Box<dyn Error>> {
+    let mut rdr = csv::Reader::from_path(path)?;
+    let headers = rdr.headers()?.clone();
+    let find = |names: &[&str]| headers.iter().position(|h| names.contains(&h));
+    let time_column = find(&["time"]).ok_or("Telemetry CSV has no time column")?;
+    let temperature_column = find(TEMPERATURE_COLUMNS);
+    let voltage_column = find(VOLTAGE_COLUMNS);
+    if temperature_column.is_none() && voltage_column.is_none() {
+        return Err("Telemetry CSV has neither temperature nor bias voltage columns".into());
+    }
+
+    let mut times = TimeParser::new(time_format);
+    let mut rows: Vec<(f64, f64, f64)> = Vec::new();
+    for result in rdr.records() {
+        let record = result?;
+        let value = |column: Option<usize>| -> Result<f64, Box<dyn Error>> {
+            match column {
+                Some(c) => Ok(record.get(c).ok_or("Missing telemetry value")?.parse()?),
+                None => Ok(f64::NAN),
+            }
+        };
+        rows.push((
+            times.read(record.get(time_column).ok_or("Missing time")?)?,
+            value(temperature_column)?,
+            value(voltage_column)?,
+        ));
+    }
+    rows.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    Ok(Telemetry {
+        time: rows.iter().map(|r| r.0).collect(),
+        temperature: temperature_column.map(|_| rows.iter().map(|r| r.1).collect()),
+        bias_voltage: voltage_column.map(|_| rows.iter().map(|r| r.2).collect()),
+    })
+}
+
+/// Resamples a telemetry series onto `targets` by holding the last sample.
+///
+/// Target times before the first sample take the first value, so every
+/// batch row gets a value and the panels share the batch time axis.
+pub fn resample(times: &[f64], values: &[f64], targets: &[f64]) -> Vec<f64> {
+    if values.is_empty() {
+        return Vec::new();
+    }
+    targets
+        .iter()
+        .map(|&t| {
+            let after = times.partition_point(|&s| s <= t);
+            values[after.saturating_sub(1)]
+        })
+        .collect()
+}
@@ -0,0 +1,78 @@
+// Figure themes and series palettes
+use plotters::style::RGBColor;
+use plotters::style::full_palette::GREY_500;
+
+/// Background, text and axis colours of a figure
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Theme {
+    #[default]
+    Grey, // Mid-grey background, the classic look
+    Light, // White background for slides and print
+    Dark,  // Near-black background for dark-mode dashboards
+}
+
+/// Colours of the raw series and its rolling overlay
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Palette {
+    #[default]
+    Classic, // Green raw series, dark blue overlay
+    OkabeIto, // Blue and vermillion, distinguishable with colour blindness
+    Tableau,  // Tableau blue and orange
+}
+
+impl Theme {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name.to_ascii_lowercase().as_str() {
+            "grey" | "gray" => Ok(Theme::Grey),
+            "light" => Ok(Theme::Light),
+            "dark" => Ok(Theme::Dark),
+            _ => Err(format!("Unknown theme '{}' (grey, light or dark)", name)),
+        }
+    }
+
+    pub fn background(self) -> RGBColor {
+        match self {
+            Theme::Grey => GREY_500,
+            Theme::Light => RGBColor(255, 255, 255),
+            Theme::Dark => RGBColor(24, 24, 28),
+        }
+    }
+
+    /// Colour of text, axes and borders; grid lines are drawn in it faintly
+    pub fn foreground(self) -> RGBColor {
+        match self {
+            Theme::Grey | Theme::Light => RGBColor(0, 0, 0),
+            Theme::Dark => RGBColor(225, 225, 225),
+        }
+    }
+}
+
+impl Palette {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name.to_ascii_lowercase().as_str() {
+            "classic" => Ok(Palette::Classic),
+            "okabe-ito" => Ok(Palette::OkabeIto),
+            "tableau" => Ok(Palette::Tableau),
+            _ => Err(format!(
+                "Unknown palette '{}' (classic, okabe-ito or tableau)",
+                name
+            )),
+        }
+    }
+
+    pub fn raw(self) -> RGBColor {
+        match self {
+            Palette::Classic => RGBColor(0, 255, 0),
+            Palette::OkabeIto => RGBColor(0, 114, 178),
+            Palette::Tableau => RGBColor(31, 119, 180),
+        }
+    }
+
+    pub fn smoothed(self) -> RGBColor {
+        match self {
+            Palette::Classic => RGBColor(0, 60, 140),
+            Palette::OkabeIto => RGBColor(213, 94, 0),
+            Palette::Tableau => RGBColor(255, 127, 14),
+        }
+    }
+}
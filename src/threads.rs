@@ -0,0 +1,51 @@
+// Worker threads: one process-wide limit shared by every parallel step
+//
+// The limit defaults to the CPUs this process may use, which std derives
+// from the affinity mask and cgroup CPU quotas, so a job on a shared node
+// starts no more busy threads than its allocation. Work is spread over
+// scoped threads of at most `limit()` at a time.
+use std::num::NonZeroUsize;
+use std::sync::OnceLock;
+
+/// Limit set by `configure`
+static LIMIT: OnceLock<usize> = OnceLock::new();
+
+/// Sets the process-wide limit: `threads`, or the usable CPUs for `None`
+/// or 0. Only the first call has an effect; returns the limit in force.
+pub fn configure(threads: Option<usize>) -> usize {
+    *LIMIT.get_or_init(|| threads.filter(|&n| n > 0).unwrap_or_else(usable_cpus))
+}
+
+/// Worker threads parallel steps may use
+pub fn limit() -> usize {
+    configure(None)
+}
+
+/// CPUs available to this process, respecting affinity and cgroup quotas
+pub fn usable_cpus() -> usize {
+    std::thread::available_parallelism().map_or(1, NonZeroUsize::get)
+}
+
+/// `f` applied to every item, in order, on up to `limit()` threads
+pub fn map<T: Sync, R: Send>(items: &[T], f: impl Fn(&T) -> R + Sync) -> Vec<R> {
+    let threads = limit().min(items.len());
+    if threads <= 1 {
+        return items.iter().map(f).collect();
+    }
+    let chunk = items.len().div_ceil(threads);
+    let f = &f;
+    std::thread::scope(|scope| {
+        let workers: Vec<_> = items
+            .chunks(chunk)
+            .map(|part| scope.spawn(move || part.iter().map(f).collect::<Vec<R>>()))
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|worker| {
+                worker
+                    .join()
+                    .unwrap_or_else(|e| std::panic::resume_unwind(e))
+            })
+            .collect()
+    })
+}
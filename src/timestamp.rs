@@ -0,0 +1,293 @@
+// Timestamps of every input source: Unix seconds or milliseconds, ISO 8601 and MinKNOW names
+//
+// All times are converted to seconds (Unix seconds for absolute timestamps),
+// so batch rows, server logs, pore scans and telemetry share one axis. In
+// auto mode the first value of a column decides its format, and every later
+// value must then be written the same way; a column silently switching
+// between seconds and milliseconds would otherwise shift rows by decades.
+use std::error::Error;
+
+/// Numeric timestamps at least this large are taken as milliseconds in auto
+/// mode (1e11 seconds lie in the year 5138, 1e11 ms in 1973)
+const MILLIS_FROM: f64 = 1e11;
+
+/// How the timestamps of an input are written
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum TimeFormat {
+    #[default]
+    Auto, // Decided by the first value of each column
+    UnixSeconds, // Seconds, fractional allowed (also relative times such as batch_time)
+    UnixMillis,  // Milliseconds since the epoch
+    Iso8601,     // `2024-05-01T12:00:03.123Z`, space separator and UTC offsets allowed
+    Minknow,     // MinKNOW run and file names: `20240501_1200` or `20240501_120003`
+}
+
+impl TimeFormat {
+    /// Parses a `--time-format` value
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "auto" => Some(TimeFormat::Auto),
+            "unix" | "unix-s" | "seconds" => Some(TimeFormat::UnixSeconds),
+            "unix-ms" | "millis" | "milliseconds" => Some(TimeFormat::UnixMillis),
+            "iso8601" | "iso" => Some(TimeFormat::Iso8601),
+            "minknow" => Some(TimeFormat::Minknow),
+            _ => None,
+        }
+    }
+
+    /// Name used in messages
+    pub fn label(self) -> &'static str {
+        match self {
+            TimeFormat::Auto => "auto",
+            TimeFormat::UnixSeconds => "unix",
+            TimeFormat::UnixMillis => "unix-ms",
+            TimeFormat::Iso8601 => "iso8601",
+            TimeFormat::Minknow => "minknow",
+        }
+    }
+
+    /// Format a single value is written in, `None` when none fits
+    pub fn detect(text: &str) -> Option<Self> {
+        let text = text.trim();
+        if let Ok(value) = text.parse::<f64>() {
+            return Some(if value.abs() >= MILLIS_FROM {
+                TimeFormat::UnixMillis
+            } else {
+                TimeFormat::UnixSeconds
+            });
+        }
+        [TimeFormat::Iso8601, TimeFormat::Minknow]
+            .into_iter()
+            .find(|format| format.read(text).is_some())
+    }
+
+    /// Seconds of a value written in this format; `Auto` detects the format
+    /// of this value alone (see `TimeParser` for per-column detection)
+    pub fn read(self, text: &str) -> Option<f64> {
+        let text = text.trim();
+        match self {
+            TimeFormat::Auto => Self::detect(text)?.read(text),
+            TimeFormat::UnixSeconds => text.parse().ok().filter(|v: &f64| v.is_finite()),
+            TimeFormat::UnixMillis => text
+                .parse::<f64>()
+                .ok()
+                .filter(|v| v.is_finite())
+                .map(|v| v / 1000.0),
+            TimeFormat::Iso8601 => iso8601(text),
+            TimeFormat::Minknow => minknow(text),
+        }
+    }
+}
+
+/// Reads one column's timestamps, fixing the format on the first value in auto mode
+#[derive(Debug, Clone, Copy)]
+pub struct TimeParser {
+    format: TimeFormat, // Requested format, then the resolved one
+}
+
+impl TimeParser {
+    pub fn new(format: TimeFormat) -> Self {
+        TimeParser { format }
+    }
+
+    /// Seconds of `text`, `None` when it is not written in the column's format
+    pub fn parse(&mut self, text: &str) -> Option<f64> {
+        if self.format == TimeFormat::Auto {
+            self.format = TimeFormat::detect(text)?;
+        }
+        self.format.read(text)
+    }
+
+    /// Like `parse`, with an error naming the expected format
+    pub fn read(&mut self, text: &str) -> Result<f64, Box<dyn Error>> {
+        self.parse(text).ok_or_else(|| {
+            format!(
+                "Invalid time '{}' (expected {} timestamps)",
+                text.trim(),
+                self.format.label()
+            )
+            .into()
+        })
+    }
+
+    /// Format the column was found (or told) to use
+    pub fn format(&self) -> TimeFormat {
+        self.format
+    }
+}
+
+/// Seconds since the epoch of a UTC civil date and time
+fn unix_seconds(
+    year: i64,
+    month: i64,
+    day: i64,
+    hour: i64,
+    minute: i64,
+    second: f64,
+) -> Option<f64> {
+    if !(1..=12).contains(&month)
+        || !(1..=31).contains(&day)
+        || !(0..24).contains(&hour)
+        || !(0..60).contains(&minute)
+        || !(0.0..61.0).contains(&second)
+    {
+        return None;
+    }
+    // Days from civil (proleptic Gregorian)
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+
+    Some((days * 86_400 + hour * 3_600 + minute * 60) as f64 + second)
+}
+
+/// Parses `YYYY-MM-DD[T ]HH:MM:SS[.fff][Z|±HH:MM|±HHMM]`; times without an
+/// offset are taken as UTC
+fn iso8601(text: &str) -> Option<f64> {
+    let (date, time) = text.split_once(['T', ' '])?;
+    let mut date = date.splitn(3, '-').map(|p| p.parse::<i64>().ok());
+    let (year, month, day) = (date.next()??, date.next()??, date.next()??);
+
+    // A trailing offset shifts the local time back to UTC
+    let (time, offset) = match time.strip_suffix('Z') {
+        Some(time) => (time, 0.0),
+        None => match time.rfind(['+', '-']) {
+            Some(sign) => {
+                let digits: String = time[sign + 1..].chars().filter(|c| *c != ':').collect();
+                if digits.len() != 4 || !digits.chars().all(|c| c.is_ascii_digit()) {
+                    return None;
+                }
+                let hours: f64 = digits[..2].parse().ok()?;
+                let minutes: f64 = digits[2..].parse().ok()?;
+                let offset = hours * 3600.0 + minutes * 60.0;
+                let offset = if time[sign..].starts_with('-') {
+                    -offset
+                } else {
+                    offset
+                };
+                (&time[..sign], offset)
+            }
+            None => (time, 0.0),
+        },
+    };
+    let mut time = time.splitn(3, ':');
+    let hour: i64 = time.next()?.parse().ok()?;
+    let minute: i64 = time.next()?.parse().ok()?;
+    let second: f64 = time.next()?.parse().ok()?;
+    Some(unix_seconds(year, month, day, hour, minute, second)? - offset)
+}
+
+/// Parses MinKNOW's `YYYYMMDD_HHMM[SS]` as UTC
+fn minknow(text: &str) -> Option<f64> {
+    let (date, time) = text.split_once('_')?;
+    if date.len() != 8
+        || !matches!(time.len(), 4 | 6)
+        || !date.chars().chain(time.chars()).all(|c| c.is_ascii_digit())
+    {
+        return None;
+    }
+    let field = |s: &str| s.parse::<i64>().ok();
+    let second = if time.len() == 6 {
+        field(&time[4..6])?
+    } else {
+        0
+    };
+    unix_seconds(
+        field(&date[..4])?,
+        field(&date[4..6])?,
+        field(&date[6..8])?,
+        field(&time[..2])?,
+        field(&time[2..4])?,
+        second as f64,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 2024-05-01 12:00:03 UTC
+    const NOON: f64 = 1_714_564_803.0;
+
+    #[test]
+    fn iso8601_with_offsets() {
+        let read = |text| TimeFormat::Iso8601.read(text);
+        assert_eq!(read("2024-05-01T12:00:03Z"), Some(NOON));
+        assert_eq!(read("2024-05-01 12:00:03"), Some(NOON));
+        assert_eq!(read("2024-05-01T12:00:03.250Z"), Some(NOON + 0.25));
+        assert_eq!(read("2024-05-01T14:00:03+02:00"), Some(NOON));
+        assert_eq!(read("2024-05-01T07:30:03-0430"), Some(NOON));
+        assert_eq!(read("2000-02-29T00:00:00Z"), Some(951_782_400.0));
+        for invalid in [
+            "2024-13-01T00:00:00Z",
+            "2024-05-01T25:00:00Z",
+            "2024-05-01",
+            "noon",
+        ] {
+            assert_eq!(read(invalid), None, "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn minknow_names() {
+        assert_eq!(TimeFormat::Minknow.read("20240501_120003"), Some(NOON));
+        assert_eq!(TimeFormat::Minknow.read("20240501_1200"), Some(NOON - 3.0));
+        assert_eq!(TimeFormat::Minknow.read("2024051_1200"), None);
+        assert_eq!(TimeFormat::Minknow.read("20240501_12000"), None);
+    }
+
+    #[test]
+    fn detects_formats() {
+        assert_eq!(
+            TimeFormat::detect("1714564803.5"),
+            Some(TimeFormat::UnixSeconds)
+        );
+        assert_eq!(
+            TimeFormat::detect("1714564803000"),
+            Some(TimeFormat::UnixMillis)
+        );
+        assert_eq!(TimeFormat::detect("12.5"), Some(TimeFormat::UnixSeconds));
+        assert_eq!(
+            TimeFormat::detect("2024-05-01T12:00:03Z"),
+            Some(TimeFormat::Iso8601)
+        );
+        assert_eq!(
+            TimeFormat::detect("20240501_1200"),
+            Some(TimeFormat::Minknow)
+        );
+        assert_eq!(TimeFormat::detect("yesterday"), None);
+        assert_eq!(TimeFormat::Auto.read("1714564803000"), Some(NOON));
+    }
+
+    #[test]
+    fn columns_keep_their_first_format() {
+        let mut parser = TimeParser::new(TimeFormat::Auto);
+        assert_eq!(parser.parse("1714564803000"), Some(NOON));
+        assert_eq!(parser.format(), TimeFormat::UnixMillis);
+        // Milliseconds throughout: a later small value is not read as seconds
+        assert_eq!(parser.parse("5000"), Some(5.0));
+        assert!(parser.read("2024-05-01T12:00:03Z").is_err());
+
+        let mut parser = TimeParser::new(TimeFormat::UnixSeconds);
+        assert_eq!(parser.parse("1714564803000"), Some(1_714_564_803_000.0));
+        assert!(parser.read("inf").is_err());
+    }
+
+    #[test]
+    fn format_names() {
+        for format in [
+            TimeFormat::Auto,
+            TimeFormat::UnixSeconds,
+            TimeFormat::UnixMillis,
+            TimeFormat::Iso8601,
+            TimeFormat::Minknow,
+        ] {
+            assert_eq!(TimeFormat::parse(format.label()), Some(format));
+        }
+        assert_eq!(TimeFormat::parse("epoch"), None);
+    }
+}
@@ -0,0 +1,227 @@
+// Tombstones: reads withdrawn after they were logged or lost mid-transfer
+//
+// A read is logged when its batch is sent, so a read MinKNOW later withdraws,
+// or one whose transfer fails, still has a batch row and possibly a decision.
+// A tombstone list names those reads: a CSV or tab-separated file with a
+// `read_id` column and an optional `reason` column. Their rows and decisions
+// are left out of every statistic and counted, so yields reconcile with
+// MinKNOW's own report.
+use std::collections::HashMap;
+use std::error::Error;
+
+/// Why a read was withdrawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reason {
+    Withdrawn,      // Withdrawn by MinKNOW after it was logged
+    TransferFailed, // Failed mid-transfer, never fully received
+}
+
+impl Reason {
+    /// Every reason, in report order
+    pub const ALL: [Reason; 2] = [Reason::Withdrawn, Reason::TransferFailed];
+
+    /// Parses a reason as the logs write it; an empty field means withdrawn
+    pub fn parse(text: &str) -> Option<Self> {
+        match text.trim().to_ascii_lowercase().as_str() {
+            "" | "withdrawn" | "removed" => Some(Reason::Withdrawn),
+            "failed" | "transfer_failed" | "failed_transfer" | "incomplete" => {
+                Some(Reason::TransferFailed)
+            }
+            _ => None,
+        }
+    }
+
+    /// Description in reports
+    pub fn label(self) -> &'static str {
+        match self {
+            Reason::Withdrawn => "withdrawn",
+            Reason::TransferFailed => "failed mid-transfer",
+        }
+    }
+}
+
+/// Tombstoned reads by read ID
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Tombstones {
+    reads: HashMap<String, Reason>,
+}
+
+impl Tombstones {
+    /// Reads a tombstone list
+    pub fn load(path: &str) -> Result<Self, Box<dyn Error>> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("Cannot read tombstone list {}: {}", path, e))?;
+        let delimiter = match text.lines().next() {
+            Some(header) if header.contains('\t') => b'\t',
+            _ => b',',
+        };
+        let mut rdr = csv::ReaderBuilder::new()
+            .delimiter(delimiter)
+            .from_reader(text.as_bytes());
+        let headers = rdr.headers()?.clone();
+        let column = |name: &str| headers.iter().position(|h| h.trim() == name);
+        let read_column = column("read_id")
+            .ok_or_else(|| format!("Tombstone list {} has no read_id column", path))?;
+        let reason_column = column("reason");
+
+        let mut reads = HashMap::new();
+        for result in rdr.records() {
+            let record = result?;
+            let line = record.position().map_or(0, |p| p.line());
+            let read_id = record.get(read_column).unwrap_or("").trim();
+            if read_id.is_empty() {
+                continue;
+            }
+            let reason = reason_column.and_then(|c| record.get(c)).unwrap_or("");
+            let reason = Reason::parse(reason).ok_or_else(|| {
+                format!(
+                    "Unknown tombstone reason '{}' on line {} (withdrawn or failed)",
+                    reason, line
+                )
+            })?;
+            reads.insert(read_id.to_string(), reason);
+        }
+        Ok(Tombstones { reads })
+    }
+
+    /// Why `read_id` was withdrawn, if it was
+    pub fn reason(&self, read_id: &str) -> Option<Reason> {
+        self.reads.get(read_id.trim()).copied()
+    }
+
+    /// Number of reads listed
+    pub fn len(&self) -> usize {
+        self.reads.len()
+    }
+
+    /// Whether no read is listed
+    pub fn is_empty(&self) -> bool {
+        self.reads.is_empty()
+    }
+}
+
+/// Rows left out of an input because their read was tombstoned
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TombstoneReport {
+    pub listed: usize,    // Reads in the tombstone list
+    pub rows: [usize; 2], // Rows excluded, per reason in `Reason::ALL` order
+    pub bases: [f64; 2],  // Bases of those rows, per reason
+}
+
+impl TombstoneReport {
+    /// Report for a list of `listed` reads, before any row is read
+    pub fn new(listed: usize) -> Self {
+        TombstoneReport {
+            listed,
+            ..Default::default()
+        }
+    }
+
+    /// Counts one excluded row
+    pub fn record(&mut self, reason: Reason, bases: f64) {
+        self.rows[reason as usize] += 1;
+        self.bases[reason as usize] += bases;
+    }
+
+    /// Adds the rows excluded from more of the same input
+    pub fn merge(&mut self, more: TombstoneReport) {
+        for i in 0..Reason::ALL.len() {
+            self.rows[i] += more.rows[i];
+            self.bases[i] += more.bases[i];
+        }
+    }
+
+    /// Rows excluded for any reason
+    pub fn excluded(&self) -> usize {
+        self.rows.iter().sum()
+    }
+
+    /// One-line summary, e.g. for digests
+    pub fn describe(&self) -> String {
+        format!(
+            "{} rows excluded ({:.0} bases) of {} tombstoned reads",
+            self.excluded(),
+            self.bases.iter().sum::<f64>(),
+            self.listed
+        )
+    }
+
+    /// Prints the excluded rows and bases per reason
+    pub fn print(&self) {
+        println!("Tombstones: {}", self.describe());
+        for (i, reason) in Reason::ALL.iter().enumerate() {
+            println!(
+                "  {}: {} rows, {:.0} bases",
+                reason.label(),
+                self.rows[i],
+                self.bases[i]
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn list(name: &str, contents: &str) -> Result<Tombstones, Box<dyn Error>> {
+        let path = std::env::temp_dir().join(format!("{}-{}", name, std::process::id()));
+        std::fs::write(&path, contents).unwrap();
+        let tombstones = Tombstones::load(&path.to_string_lossy());
+        std::fs::remove_file(&path).unwrap();
+        tombstones
+    }
+
+    #[test]
+    fn reasons() {
+        assert_eq!(Reason::parse(""), Some(Reason::Withdrawn));
+        assert_eq!(Reason::parse(" Removed "), Some(Reason::Withdrawn));
+        assert_eq!(
+            Reason::parse("transfer_failed"),
+            Some(Reason::TransferFailed)
+        );
+        assert_eq!(Reason::parse("INCOMPLETE"), Some(Reason::TransferFailed));
+        assert_eq!(Reason::parse("lost"), None);
+    }
+
+    #[test]
+    fn loads_csv_and_tab_separated_lists() {
+        let csv = list(
+            "tombstones.csv",
+            "channel,read_id,reason\n3,r1,\n4,r2,failed\n5,,failed\n",
+        )
+        .unwrap();
+        assert_eq!(csv.len(), 2);
+        assert_eq!(csv.reason("r1"), Some(Reason::Withdrawn));
+        assert_eq!(csv.reason(" r2 "), Some(Reason::TransferFailed));
+        assert_eq!(csv.reason("r3"), None);
+
+        let tsv = list("tombstones.tsv", "read_id\treason\nr1\t\nr2\tfailed\n").unwrap();
+        assert_eq!(tsv.reason("r2"), Some(Reason::TransferFailed));
+        assert!(!tsv.is_empty());
+    }
+
+    #[test]
+    fn rejects_malformed_lists() {
+        assert!(list("tombstones-noid.csv", "read,reason\nr1,\n").is_err());
+        let err = list("tombstones-reason.csv", "read_id,reason\nr1,lost\n").unwrap_err();
+        assert!(err.to_string().contains("'lost' on line 2"), "{}", err);
+    }
+
+    #[test]
+    fn reports_count_rows_and_bases_per_reason() {
+        let mut report = TombstoneReport::new(3);
+        report.record(Reason::Withdrawn, 100.0);
+        let mut more = TombstoneReport::new(3);
+        more.record(Reason::TransferFailed, 50.0);
+        more.record(Reason::Withdrawn, 25.0);
+        report.merge(more);
+        assert_eq!(report.rows, [2, 1]);
+        assert_eq!(report.bases, [125.0, 50.0]);
+        assert_eq!(report.excluded(), 3);
+        assert_eq!(
+            report.describe(),
+            "3 rows excluded (175 bases) of 3 tombstoned reads"
+        );
+    }
+}
@@ -0,0 +1,24 @@
+// Adapter/primer trimming statistics
+
+use std::error::Error;
+
+use crate::model::Thresholds;
+
+/// Number of most recent reads the adapter detection rate is computed over
+pub const WINDOW: usize = 1000;
+/// Adapter detection rate that turns the panel amber/red; a drop usually
+/// means a chemistry or configuration problem
+pub const ADAPTER_DETECTION: Thresholds = Thresholds {
+    warn: 0.8,
+    fail: 0.6,
+    higher_is_worse: false,
+};
+
+/// Parses a boolean CSV cell (`1`/`0`, `true`/`false`, `yes`/`no`) as 1 or 0
+pub fn parse_flag(value: &str) -> Result<f64, Box<dyn Error>> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" => Ok(1.0),
+        "0" | "false" | "no" | "" => Ok(0.0),
+        other => Err(format!("Invalid adapter_detected value '{}'", other).into()),
+    }
+}
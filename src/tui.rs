@@ -0,0 +1,137 @@
+// Terminal dashboard: sparklines and current values drawn with plain ANSI text
+use crate::model::{Health, QSCORE_THRESHOLDS, RunData};
+use crate::robust::Center;
+
+/// Sparkline levels, lowest first
+const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+/// Columns taken by a row's label and current value around its sparkline
+const ROW_TEXT: usize = 44;
+
+/// One dashboard row: a per-bin series and how to show it
+struct Metric {
+    label: &'static str,    // Row label
+    unit: &'static str,     // Unit after the current value
+    values: Vec<f64>,       // One value per time bin, NaN for empty bins
+    health: Option<Health>, // Health of the current value, if it has thresholds
+}
+
+/// Renders the dashboard for `data` as `width` columns of text; with
+/// `color`, current values are coloured by their health
+pub fn render(data: &RunData, title: &str, width: usize, color: bool) -> String {
+    let bins = width.saturating_sub(ROW_TEXT).max(10);
+    let mut out = format!("{}\n", title);
+    let Some((first, last)) = data.time_range() else {
+        out.push_str("No batches yet\n");
+        return out;
+    };
+    out.push_str(&format!(
+        "{} batches over {:.1} min, {} bins of {:.0} s\n\n",
+        data.len(),
+        (last - first) / 60.0,
+        bins,
+        ((last - first) / bins as f64).max(1.0)
+    ));
+
+    for metric in metrics(data, first, last, bins) {
+        let current = metric.values.iter().rev().find(|v| v.is_finite());
+        let value = match current {
+            Some(v) => format!("{:>10.2} {:<5}", v, metric.unit),
+            None => format!("{:>10} {:<5}", "-", metric.unit),
+        };
+        let value = match (color, metric.health) {
+            (true, Some(health)) => format!("{}{}\x1b[0m", ansi(health), value),
+            _ => value,
+        };
+        out.push_str(&format!(
+            "{:<24} {} {}\n",
+            metric.label,
+            sparkline(&metric.values),
+            value
+        ));
+    }
+    out
+}
+
+/// Throughput, quality and latency per time bin between `first` and `last`
+fn metrics(data: &RunData, first: f64, last: f64, bins: usize) -> Vec<Metric> {
+    let width = ((last - first) / bins as f64).max(f64::EPSILON);
+    let mut binned: Vec<Vec<usize>> = vec![Vec::new(); bins];
+    for (row, &t) in data.time.iter().enumerate() {
+        binned[(((t - first) / width) as usize).min(bins - 1)].push(row);
+    }
+    let per_bin = |reduce: &dyn Fn(&[usize]) -> f64| -> Vec<f64> {
+        binned
+            .iter()
+            .map(|rows| {
+                if rows.is_empty() {
+                    f64::NAN
+                } else {
+                    reduce(rows)
+                }
+            })
+            .collect()
+    };
+    let sum = |column: &[f64], rows: &[usize]| rows.iter().map(|&r| column[r]).sum::<f64>();
+    let mean = |column: &[f64], rows: &[usize]| {
+        let values: Vec<f64> = rows.iter().map(|&r| column[r]).collect();
+        Center::Mean.of(&values)
+    };
+
+    let qscore = per_bin(&|rows| mean(&data.mean_qscore, rows));
+    let current_qscore = qscore.iter().rev().find(|v| v.is_finite()).copied();
+    vec![
+        Metric {
+            label: "Samples/sec",
+            unit: "/s",
+            values: per_bin(&|rows| sum(&data.samples, rows) / width),
+            health: None,
+        },
+        Metric {
+            label: "Bases/sec",
+            unit: "/s",
+            values: per_bin(&|rows| sum(&data.bases, rows) / width),
+            health: None,
+        },
+        Metric {
+            label: "Mean Q-score",
+            unit: "",
+            values: qscore,
+            health: current_qscore.map(|q| QSCORE_THRESHOLDS.classify(q)),
+        },
+        Metric {
+            label: "Basecaller latency",
+            unit: "ms",
+            // Nanoseconds in the batch log
+            values: per_bin(&|rows| mean(&data.time_in_basecaller, rows) / 1e6),
+            health: None,
+        },
+    ]
+}
+
+/// One block character per value, scaled between the finite min and max;
+/// empty bins stay blank
+fn sparkline(values: &[f64]) -> String {
+    let finite = values.iter().filter(|v| v.is_finite());
+    let min = finite.clone().copied().fold(f64::INFINITY, f64::min);
+    let max = finite.copied().fold(f64::NEG_INFINITY, f64::max);
+    let span = (max - min).max(f64::EPSILON);
+    values
+        .iter()
+        .map(|&v| {
+            if !v.is_finite() {
+                return ' ';
+            }
+            let level = ((v - min) / span * (BARS.len() - 1) as f64).round() as usize;
+            BARS[level.min(BARS.len() - 1)]
+        })
+        .collect()
+}
+
+/// Escape sequence colouring text by health
+fn ansi(health: Health) -> &'static str {
+    match health {
+        Health::Ok => "\x1b[32m",
+        Health::Warn => "\x1b[33m",
+        Health::Fail => "\x1b[31m",
+    }
+}
@@ -0,0 +1,167 @@
+// Ultra-long library QC: read N50 over time, >100 kb yield and shearing indicators
+use crate::model::{Health, RunData, Thresholds};
+
+/// Reads of at least this many bases count as ultra-long
+pub const ULTRALONG_BASES: f64 = 100_000.0;
+/// Reads below this many bases count as short fragments
+pub const SHORT_BASES: f64 = 10_000.0;
+/// Length class boundaries the preset reports by default
+pub const LENGTH_CLASSES: &str = "10kb,100kb";
+/// Consecutive reads the N50 over time is computed on
+pub const WINDOW: usize = 1000;
+/// Share of the run's first reads whose N50 is the reference for the retention
+const REFERENCE_SHARE: f64 = 0.1;
+/// Window N50 relative to the start of the run; a falling N50 points to
+/// shearing during loading or DNA degrading on the flow cell
+pub const N50_RETENTION: Thresholds = Thresholds {
+    warn: 0.7,
+    fail: 0.5,
+    higher_is_worse: false,
+};
+/// Share of the yield from ultra-long reads expected of a UL library
+pub const ULTRALONG_YIELD: Thresholds = Thresholds {
+    warn: 0.3,
+    fail: 0.1,
+    higher_is_worse: false,
+};
+/// Rise of the short-fragment share from the first to the last quarter of the
+/// run; fragments accumulating late in a run indicate degradation
+pub const SHORT_FRACTION_RISE: Thresholds = Thresholds {
+    warn: 0.1,
+    fail: 0.2,
+    higher_is_worse: true,
+};
+
+/// Length such that reads at least this long hold half of all bases
+pub fn n50(lengths: &[f64]) -> f64 {
+    let mut sorted: Vec<f64> = lengths.to_vec();
+    sorted.sort_by(|a, b| b.total_cmp(a));
+    let half = sorted.iter().sum::<f64>() / 2.0;
+    let mut covered = 0.0;
+    for length in sorted {
+        covered += length;
+        if covered >= half {
+            return length;
+        }
+    }
+    0.0
+}
+
+/// N50 of each block of `window` consecutive reads, at every row of the block
+pub fn windowed_n50(bases: &[f64], window: usize) -> Vec<f64> {
+    bases
+        .chunks(window.max(1))
+        .flat_map(|block| std::iter::repeat_n(n50(block), block.len()))
+        .collect()
+}
+
+/// Windowed N50 relative to the N50 of the first reads of the run
+pub fn n50_retention(bases: &[f64], windowed: &[f64]) -> Vec<f64> {
+    let reference_reads = ((bases.len() as f64 * REFERENCE_SHARE) as usize).max(WINDOW);
+    let reference = n50(&bases[..reference_reads.min(bases.len())]);
+    if reference <= 0.0 {
+        return vec![f64::NAN; bases.len()];
+    }
+    windowed.iter().map(|n| n / reference).collect()
+}
+
+/// Running share of the bases so far that come from ultra-long reads
+pub fn cumulative_ultralong_fraction(bases: &[f64]) -> Vec<f64> {
+    let (mut long, mut total) = (0.0, 0.0);
+    bases
+        .iter()
+        .map(|&b| {
+            total += b;
+            if b >= ULTRALONG_BASES {
+                long += b;
+            }
+            if total > 0.0 { long / total } else { 0.0 }
+        })
+        .collect()
+}
+
+/// Run-level ultra-long indicators
+#[derive(Debug, Clone)]
+pub struct UltralongReport {
+    pub n50: f64,             // Read N50 of the whole run
+    pub first_n50: f64,       // Read N50 of the first quarter
+    pub last_n50: f64,        // Read N50 of the last quarter
+    pub ultralong_yield: f64, // Share of bases from reads ≥ `ULTRALONG_BASES`
+    pub first_short: f64,     // Share of reads < `SHORT_BASES`, first quarter
+    pub last_short: f64,      // Share of reads < `SHORT_BASES`, last quarter
+}
+
+impl UltralongReport {
+    /// Last-quarter N50 relative to the first quarter
+    pub fn retention(&self) -> f64 {
+        if self.first_n50 > 0.0 {
+            self.last_n50 / self.first_n50
+        } else {
+            f64::NAN
+        }
+    }
+
+    /// Worst of the retention, ultra-long yield and fragmentation checks
+    pub fn health(&self) -> Health {
+        N50_RETENTION
+            .classify(self.retention())
+            .max(ULTRALONG_YIELD.classify(self.ultralong_yield))
+            .max(SHORT_FRACTION_RISE.classify(self.last_short - self.first_short))
+    }
+}
+
+/// Computes the run-level indicators, `None` without reads
+pub fn analyze(data: &RunData) -> Option<UltralongReport> {
+    let bases = &data.bases;
+    if bases.is_empty() {
+        return None;
+    }
+    let quarter = bases.len().div_ceil(4);
+    let (first, last) = (&bases[..quarter], &bases[bases.len() - quarter..]);
+    let short = |reads: &[f64]| {
+        reads.iter().filter(|&&b| b < SHORT_BASES).count() as f64 / reads.len() as f64
+    };
+    let total: f64 = bases.iter().sum();
+    let long: f64 = bases.iter().filter(|&&b| b >= ULTRALONG_BASES).sum();
+
+    Some(UltralongReport {
+        n50: n50(bases),
+        first_n50: n50(first),
+        last_n50: n50(last),
+        ultralong_yield: if total > 0.0 { long / total } else { 0.0 },
+        first_short: short(first),
+        last_short: short(last),
+    })
+}
+
+/// Prints the ultra-long indicators with their verdicts
+pub fn print_report(report: &UltralongReport) {
+    println!("Ultra-long QC [{}]:", report.health().label());
+    println!("  Read N50: {:.0} bases", report.n50);
+    println!(
+        "  Yield from reads ≥{:.0} kb: {:.1}% [{}]",
+        ULTRALONG_BASES / 1e3,
+        report.ultralong_yield * 100.0,
+        ULTRALONG_YIELD.classify(report.ultralong_yield).label()
+    );
+    println!(
+        "  N50 first to last quarter: {:.0} → {:.0} ({:.0}% retained) [{}]",
+        report.first_n50,
+        report.last_n50,
+        report.retention() * 100.0,
+        N50_RETENTION.classify(report.retention()).label()
+    );
+    let rise = report.last_short - report.first_short;
+    println!(
+        "  Reads <{:.0} kb, first to last quarter: {:.1}% → {:.1}% [{}]",
+        SHORT_BASES / 1e3,
+        report.first_short * 100.0,
+        report.last_short * 100.0,
+        SHORT_FRACTION_RISE.classify(rise).label()
+    );
+    if N50_RETENTION.classify(report.retention()) != Health::Ok
+        || SHORT_FRACTION_RISE.classify(rise) != Health::Ok
+    {
+        println!("  Read lengths shorten over the run: check for shearing or DNA degradation");
+    }
+}
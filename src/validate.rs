@@ -0,0 +1,217 @@
+// Typed constraints on input rows, with a report and an optional quarantine CSV
+use std::error::Error;
+use std::fs::File;
+
+use crate::timestamp::{TimeFormat, TimeParser};
+
+/// Violations listed individually in the report
+const MAX_EXAMPLES: usize = 5;
+/// Column appended to quarantined rows, naming the constraints they broke
+pub const REASON_COLUMN: &str = "validation_errors";
+
+/// What a broken constraint means for the row
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Invalid, // The row is dropped (and quarantined)
+    Suspect, // The row is kept, but counted in the report
+}
+
+/// Constraint on one column of every row
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Constraint {
+    NonNegative(&'static str),     // A number ≥ 0
+    Range(&'static str, f64, f64), // A number within the bounds
+    Monotone(&'static str),        // A timestamp no earlier than any before it
+}
+
+/// Constraints of the batch timings; optional columns are checked when present
+pub const CONSTRAINTS: [Constraint; 8] = [
+    Constraint::NonNegative("samples"),
+    Constraint::NonNegative("bases"),
+    Constraint::Range("mean_qscore", 0.0, 60.0),
+    Constraint::NonNegative("time_to_package_and_send"),
+    Constraint::NonNegative("time_in_basecaller"),
+    Constraint::NonNegative("trimmed_bases"),
+    Constraint::Range("alignment_identity", 0.0, 1.0),
+    Constraint::Monotone("batch_time"),
+];
+
+impl Constraint {
+    /// Column the constraint applies to
+    pub fn column(self) -> &'static str {
+        match self {
+            Constraint::NonNegative(c) | Constraint::Range(c, ..) | Constraint::Monotone(c) => c,
+        }
+    }
+
+    /// Description used in the report, e.g. `mean_qscore in 0–60`
+    pub fn describe(self) -> String {
+        match self {
+            Constraint::NonNegative(c) => format!("{} ≥ 0", c),
+            Constraint::Range(c, low, high) => format!("{} in {}–{}", c, low, high),
+            Constraint::Monotone(c) => format!("{} not decreasing", c),
+        }
+    }
+
+    /// Whether a violation drops the row; out-of-order rows are kept, since
+    /// the parser sorts them
+    pub fn severity(self) -> Severity {
+        match self {
+            Constraint::Monotone(_) => Severity::Suspect,
+            _ => Severity::Invalid,
+        }
+    }
+}
+
+/// Outcome of validating an input
+#[derive(Debug, Clone, Default)]
+pub struct ValidationReport {
+    pub checked: usize,                   // Rows checked
+    pub invalid: usize,                   // Rows dropped
+    pub suspect: usize,                   // Rows kept despite a suspect value
+    pub violations: Vec<(String, usize)>, // Rows breaking each constraint
+    pub examples: Vec<String>,            // First violations, with line numbers
+    pub quarantine: Option<String>,       // CSV the dropped rows were written to
+}
+
+impl ValidationReport {
+    /// Prints the counts per constraint and the first violations
+    pub fn print(&self) {
+        println!(
+            "Validation: {} rows checked, {} invalid (dropped), {} suspect",
+            self.checked, self.invalid, self.suspect
+        );
+        for (constraint, count) in self.violations.iter().filter(|(_, n)| *n > 0) {
+            println!("  {}: {} rows", constraint, count);
+        }
+        for example in &self.examples {
+            println!("  {}", example);
+        }
+        if let Some(path) = &self.quarantine {
+            println!("  Invalid rows saved to {}", path);
+        }
+    }
+}
+
+/// Checks rows against `CONSTRAINTS` as they are read
+pub struct Validator {
+    checks: Vec<(Constraint, usize)>, // Constraints of the columns present, with their positions
+    times: TimeParser,                // Reads the timestamps of monotone columns
+    latest: Option<f64>,              // Latest timestamp seen so far
+    counts: Vec<usize>,               // Violations per entry of `checks`
+    report: ValidationReport,         // Totals so far
+    quarantine: Option<csv::Writer<File>>, // Receives the invalid rows
+}
+
+impl Validator {
+    /// Resolves the constraints against `headers`; with `quarantine`, invalid
+    /// rows are copied there with a `REASON_COLUMN` column added
+    pub fn new(
+        headers: &csv::StringRecord,
+        time_format: TimeFormat,
+        quarantine_path: Option<&str>,
+    ) -> Result<Self, Box<dyn Error>> {
+        let checks: Vec<(Constraint, usize)> = CONSTRAINTS
+            .iter()
+            .filter_map(|&c| {
+                let index = headers.iter().position(|h| h.trim() == c.column())?;
+                Some((c, index))
+            })
+            .collect();
+        let quarantine = match quarantine_path {
+            Some(path) => {
+                let mut writer = csv::Writer::from_path(path)?;
+                let mut header = headers.clone();
+                header.push_field(REASON_COLUMN);
+                writer.write_record(&header)?;
+                Some(writer)
+            }
+            None => None,
+        };
+        Ok(Validator {
+            counts: vec![0; checks.len()],
+            checks,
+            times: TimeParser::new(time_format),
+            latest: None,
+            report: ValidationReport {
+                quarantine: quarantine_path.map(str::to_string),
+                ..Default::default()
+            },
+            quarantine,
+        })
+    }
+
+    /// Checks one row; `false` when it is invalid and must be skipped
+    pub fn check(&mut self, record: &csv::StringRecord) -> Result<bool, Box<dyn Error>> {
+        self.report.checked += 1;
+        let line = record.position().map_or(0, |p| p.line());
+        let mut broken = Vec::new();
+        let mut severity = None;
+        for (i, &(constraint, index)) in self.checks.iter().enumerate() {
+            let value = record.get(index).unwrap_or("").trim();
+            let ok = match constraint {
+                Constraint::Monotone(_) => match self.times.parse(value) {
+                    Some(time) => {
+                        let ordered = self.latest.is_none_or(|l| time >= l);
+                        self.latest = Some(self.latest.map_or(time, |l| l.max(time)));
+                        ordered
+                    }
+                    // Unreadable timestamps are the parser's to report
+                    None => true,
+                },
+                Constraint::NonNegative(_) => value.parse::<f64>().is_ok_and(|v| v >= 0.0),
+                Constraint::Range(_, low, high) => value
+                    .parse::<f64>()
+                    .is_ok_and(|v| (low..=high).contains(&v)),
+            };
+            if ok {
+                continue;
+            }
+            self.counts[i] += 1;
+            broken.push(constraint.describe());
+            if self.report.examples.len() < MAX_EXAMPLES {
+                self.report.examples.push(format!(
+                    "line {}: '{}' breaks {}",
+                    line,
+                    value,
+                    constraint.describe()
+                ));
+            }
+            severity = match (severity, constraint.severity()) {
+                (Some(Severity::Invalid), _) | (_, Severity::Invalid) => Some(Severity::Invalid),
+                _ => Some(Severity::Suspect),
+            };
+        }
+
+        match severity {
+            Some(Severity::Invalid) => {
+                self.report.invalid += 1;
+                if let Some(writer) = self.quarantine.as_mut() {
+                    let mut row = record.clone();
+                    row.push_field(&broken.join("; "));
+                    writer.write_record(&row)?;
+                }
+                Ok(false)
+            }
+            Some(Severity::Suspect) => {
+                self.report.suspect += 1;
+                Ok(true)
+            }
+            None => Ok(true),
+        }
+    }
+
+    /// Flushes the quarantine file and returns the report
+    pub fn finish(mut self) -> Result<ValidationReport, Box<dyn Error>> {
+        if let Some(writer) = self.quarantine.as_mut() {
+            writer.flush()?;
+        }
+        self.report.violations = self
+            .checks
+            .iter()
+            .zip(&self.counts)
+            .map(|(&(constraint, _), &count)| (constraint.describe(), count))
+            .collect();
+        Ok(self.report)
+    }
+}